@@ -201,6 +201,7 @@ pub struct Xdp {
     item: ItemFn,
     name: Option<String>,
     frags: bool,
+    map: Option<String>,
 }
 
 impl Xdp {
@@ -217,16 +218,33 @@ impl Xdp {
                 ));
             }
         }
+        let map = pop_arg(&mut args, "map");
+        if let Some(m) = &map {
+            if m != "cpumap" && m != "devmap" {
+                return Err(Error::new_spanned(
+                    "map",
+                    "invalid value. should be 'cpumap' or 'devmap'",
+                ));
+            }
+        }
         err_on_unknown_args(&args)?;
-        Ok(Xdp { item, name, frags })
+        Ok(Xdp {
+            item,
+            name,
+            frags,
+            map,
+        })
     }
 
     pub fn expand(&self) -> Result<TokenStream> {
         let section_prefix = if self.frags { "xdp.frags" } else { "xdp" };
-        let section_name = if let Some(name) = &self.name {
-            format!("{section_prefix}/{name}")
-        } else {
-            section_prefix.to_string()
+        let section_name = match (&self.map, &self.name) {
+            (Some(map), Some(name)) => format!("{section_prefix}/{map}/{name}"),
+            // fall back to the function name, otherwise e.g. `xdp/devmap` would be parsed as a
+            // regular XDP program called `devmap`
+            (Some(map), None) => format!("{section_prefix}/{map}/{}", self.item.sig.ident),
+            (None, Some(name)) => format!("{section_prefix}/{name}"),
+            (None, None) => section_prefix.to_string(),
         };
         let fn_vis = &self.item.vis;
         let fn_name = &self.item.sig.ident;
@@ -505,8 +523,12 @@ fn name_arg(args: &mut Args) -> Result<Option<String>> {
 pub enum ProbeKind {
     KProbe,
     KRetProbe,
+    KProbeMulti,
+    KRetProbeMulti,
     UProbe,
     URetProbe,
+    UProbeMulti,
+    URetProbeMulti,
 }
 
 impl std::fmt::Display for ProbeKind {
@@ -515,8 +537,12 @@ impl std::fmt::Display for ProbeKind {
         match self {
             KProbe => write!(f, "kprobe"),
             KRetProbe => write!(f, "kretprobe"),
+            KProbeMulti => write!(f, "kprobe.multi"),
+            KRetProbeMulti => write!(f, "kretprobe.multi"),
             UProbe => write!(f, "uprobe"),
             URetProbe => write!(f, "uretprobe"),
+            UProbeMulti => write!(f, "uprobe.multi"),
+            URetProbeMulti => write!(f, "uretprobe.multi"),
         }
     }
 }
@@ -885,12 +911,175 @@ impl CgroupDevice {
         })
     }
 }
+
+pub struct StructOps {
+    item: ItemFn,
+    name: String,
+    sleepable: bool,
+}
+
+impl StructOps {
+    pub fn from_syn(mut args: Args, item: ItemFn) -> Result<StructOps> {
+        let name = pop_arg(&mut args, "name").unwrap_or_else(|| item.sig.ident.to_string());
+        let mut sleepable = false;
+        if let Some(s) = pop_arg(&mut args, "sleepable") {
+            if let Ok(m) = s.parse() {
+                sleepable = m
+            } else {
+                return Err(Error::new_spanned(
+                    "sleepable",
+                    "invalid value. should be 'true' or 'false'",
+                ));
+            }
+        }
+        err_on_unknown_args(&args)?;
+
+        Ok(StructOps {
+            item,
+            name,
+            sleepable,
+        })
+    }
+
+    pub fn expand(&self) -> Result<TokenStream> {
+        let section_prefix = if self.sleepable {
+            "struct_ops.s"
+        } else {
+            "struct_ops"
+        };
+        let section_name = format!("{section_prefix}/{}", self.name);
+        let fn_vis = &self.item.vis;
+        let fn_name = &self.item.sig.ident;
+        // the value returned to the kernel depends on the implemented struct member
+        let fn_output = &self.item.sig.output;
+        let item = &self.item;
+        Ok(quote! {
+            #[no_mangle]
+            #[link_section = #section_name]
+            #fn_vis fn #fn_name(ctx: *mut ::core::ffi::c_void) #fn_output {
+                return #fn_name(::aya_bpf::programs::StructOpsContext::new(ctx));
+
+                #item
+            }
+        })
+    }
+}
+
+pub struct Usdt {
+    item: ItemFn,
+    name: String,
+}
+
+impl Usdt {
+    pub fn from_syn(mut args: Args, item: ItemFn) -> Result<Usdt> {
+        let name = pop_arg(&mut args, "name").unwrap_or_else(|| item.sig.ident.to_string());
+        err_on_unknown_args(&args)?;
+
+        Ok(Usdt { item, name })
+    }
+
+    pub fn expand(&self) -> Result<TokenStream> {
+        let section_name = format!("usdt/{}", self.name);
+        let fn_vis = &self.item.vis;
+        let fn_name = &self.item.sig.ident;
+        let item = &self.item;
+        Ok(quote! {
+            #[no_mangle]
+            #[link_section = #section_name]
+            #fn_vis fn #fn_name(ctx: *mut ::core::ffi::c_void) -> u32 {
+                let _ = #fn_name(::aya_bpf::programs::UsdtContext::new(ctx));
+                return 0;
+
+                #item
+            }
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use syn::parse_quote;
 
     use super::*;
 
+    #[test]
+    fn kprobe_multi() {
+        let prog = Probe::from_syn(
+            ProbeKind::KRetProbeMulti,
+            parse_quote!(),
+            parse_quote!(
+                fn trace_tcp(ctx: ProbeContext) -> u32 {
+                    0
+                }
+            ),
+        )
+        .unwrap();
+        let stream = prog.expand().unwrap();
+        assert!(stream
+            .to_string()
+            .contains("[link_section = \"kretprobe.multi/trace_tcp\"]"));
+    }
+
+    #[test]
+    fn usdt_with_name() {
+        let prog = Usdt::from_syn(
+            parse_quote!(name = "query_start"),
+            parse_quote!(
+                fn foo(ctx: UsdtContext) -> u32 {
+                    0
+                }
+            ),
+        )
+        .unwrap();
+        let stream = prog.expand().unwrap();
+        assert!(stream
+            .to_string()
+            .contains("[link_section = \"usdt/query_start\"]"));
+    }
+
+    #[test]
+    fn usdt_unknown_arg() {
+        assert!(Usdt::from_syn(
+            parse_quote!(nmae = "query_start"),
+            parse_quote!(
+                fn foo(ctx: UsdtContext) -> u32 {
+                    0
+                }
+            ),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn xdp_devmap_with_name() {
+        let prog = Xdp::from_syn(
+            parse_quote!(name = "foo", map = "devmap"),
+            parse_quote!(
+                fn foo(ctx: XdpContext) -> u32 {
+                    0
+                }
+            ),
+        )
+        .unwrap();
+        let stream = prog.expand().unwrap();
+        assert!(stream
+            .to_string()
+            .contains("[link_section = \"xdp/devmap/foo\"]"));
+    }
+
+    #[test]
+    fn xdp_invalid_map() {
+        assert!(Xdp::from_syn(
+            parse_quote!(map = "sockmap"),
+            parse_quote!(
+                fn foo(ctx: XdpContext) -> u32 {
+                    0
+                }
+            ),
+        )
+        .is_err());
+    }
+
     #[test]
     fn cgroup_skb_with_attach_and_name() {
         let prog = CgroupSkb::from_syn(
@@ -1020,4 +1209,20 @@ mod tests {
         let stream = prog.expand().unwrap();
         assert!(stream.to_string().contains("] pub (crate) fn foo ("));
     }
+
+    #[test]
+    fn struct_ops_sleepable() {
+        let prog = StructOps::from_syn(
+            parse_quote!(sleepable = "true"),
+            parse_quote!(
+                fn foo(ctx: StructOpsContext) -> u32 {
+                    0
+                }
+            ),
+        )
+        .unwrap();
+        let stream = prog.expand().unwrap().to_string();
+        assert!(stream.contains("[link_section = \"struct_ops.s/foo\"]"));
+        assert!(stream.contains("fn foo (ctx : * mut :: core :: ffi :: c_void) -> u32"));
+    }
 }
@@ -4,7 +4,7 @@ use expand::{
     Args, BtfTracePoint, CgroupDevice, CgroupSkb, CgroupSock, CgroupSockAddr, CgroupSockopt,
     CgroupSysctl, FEntry, FExit, Lsm, Map, PerfEvent, Probe, ProbeKind, RawTracePoint,
     SchedClassifier, SkLookup, SkMsg, SkSkb, SkSkbKind, SockAddrArgs, SockOps, SocketFilter,
-    SockoptArgs, TracePoint, Xdp,
+    SockoptArgs, StructOps, TracePoint, Usdt, Xdp,
 };
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, ItemFn, ItemStatic};
@@ -30,6 +30,39 @@ pub fn kretprobe(attrs: TokenStream, item: TokenStream) -> TokenStream {
     probe(ProbeKind::KRetProbe, attrs, item)
 }
 
+/// Marks a function as a kprobe that can be attached to many kernel functions at once.
+///
+/// The program is attached with `KProbe::attach_multi` or `KProbe::attach_multi_glob`, which
+/// create a single `BPF_TRACE_KPROBE_MULTI` link for all the target functions. The cookie
+/// set for the function being probed can be read with `bpf_get_attach_cookie`.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.18.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::kprobe_multi, programs::ProbeContext};
+///
+/// #[kprobe_multi(name = "trace_tcp")]
+/// pub fn trace_tcp(ctx: ProbeContext) -> u32 {
+///     0
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn kprobe_multi(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    probe(ProbeKind::KProbeMulti, attrs, item)
+}
+
+/// Marks a function as a kretprobe that can be attached to many kernel functions at once.
+///
+/// See [`macro@kprobe_multi`].
+#[proc_macro_attribute]
+pub fn kretprobe_multi(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    probe(ProbeKind::KRetProbeMulti, attrs, item)
+}
+
 #[proc_macro_attribute]
 pub fn uprobe(attrs: TokenStream, item: TokenStream) -> TokenStream {
     probe(ProbeKind::UProbe, attrs, item)
@@ -40,6 +73,40 @@ pub fn uretprobe(attrs: TokenStream, item: TokenStream) -> TokenStream {
     probe(ProbeKind::URetProbe, attrs, item)
 }
 
+/// Marks a function as a uprobe that can be attached to many locations of a binary at once.
+///
+/// The program is attached with `UProbe::attach_multi`, which creates a single
+/// `BPF_TRACE_UPROBE_MULTI` link for all the locations. The cookie set for the location being
+/// probed can be read with `bpf_get_attach_cookie`.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 6.6. On older kernels,
+/// `UProbe::attach_multi` falls back to one perf event per location.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::uprobe_multi, programs::ProbeContext};
+///
+/// #[uprobe_multi(name = "alloc")]
+/// pub fn alloc(ctx: ProbeContext) -> u32 {
+///     0
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn uprobe_multi(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    probe(ProbeKind::UProbeMulti, attrs, item)
+}
+
+/// Marks a function as a uretprobe that can be attached to many locations of a binary at once.
+///
+/// See [`macro@uprobe_multi`].
+#[proc_macro_attribute]
+pub fn uretprobe_multi(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    probe(ProbeKind::URetProbeMulti, attrs, item)
+}
+
 #[proc_macro_attribute]
 pub fn sock_ops(attrs: TokenStream, item: TokenStream) -> TokenStream {
     let args = parse_macro_input!(attrs as Args);
@@ -540,3 +607,68 @@ pub fn cgroup_device(attrs: TokenStream, item: TokenStream) -> TokenStream {
         .unwrap_or_else(|err| err.to_compile_error())
         .into()
 }
+
+/// Marks a function as a struct_ops eBPF program, implementing a function pointer member of a
+/// kernel struct such as `tcp_congestion_ops`.
+///
+/// The struct is declared as a static in a `.struct_ops.link` section, with the member set to
+/// the program. Pass `sleepable = "true"` for members the kernel calls from a sleepable context.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 6.4.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::struct_ops, programs::StructOpsContext};
+///
+/// #[struct_ops(name = "cubic_init")]
+/// pub fn cubic_init(ctx: StructOpsContext) {
+///     let _sk: *const u8 = unsafe { ctx.arg(0) };
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn struct_ops(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attrs as Args);
+    let item = parse_macro_input!(item as ItemFn);
+
+    StructOps::from_syn(args, item)
+        .and_then(|u| u.expand())
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Marks a function as an eBPF program that can be attached to a USDT probe.
+///
+/// USDT (user statically-defined tracing) probes are tracing points compiled into user space
+/// binaries, eg with the `DTRACE_PROBE` macros of `sys/sdt.h`. The arguments of the probe can be
+/// read with `UsdtContext::arg`.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.15.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::usdt, programs::UsdtContext};
+///
+/// #[usdt(name = "query_start")]
+/// pub fn query_start(ctx: UsdtContext) -> u32 {
+///     match ctx.arg(0) {
+///         Ok(_query) => 0,
+///         Err(_) => 1,
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn usdt(attrs: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attrs as Args);
+    let item = parse_macro_input!(item as ItemFn);
+
+    Usdt::from_syn(args, item)
+        .and_then(|u| u.expand())
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
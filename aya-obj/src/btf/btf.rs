@@ -126,6 +126,13 @@ pub enum BtfError {
         type_id: u32,
     },
 
+    /// the data is too short for its BTF type
+    #[error("data is too short for BTF type `{type_id}`")]
+    InvalidDataSize {
+        /// type id
+        type_id: u32,
+    },
+
     #[cfg(not(feature = "no_std"))]
     /// Loading the btf failed
     #[error("the BPF_BTF_LOAD syscall failed. Verifier output: {verifier_log}")]
@@ -137,6 +144,17 @@ pub enum BtfError {
         verifier_log: String,
     },
 
+    #[cfg(not(feature = "no_std"))]
+    /// A syscall failed while reading BTF from the kernel
+    #[error("the `{call}` syscall failed")]
+    SyscallError {
+        /// The name of the syscall which failed.
+        call: String,
+        /// The [`std::io::Error`] returned by the syscall.
+        #[source]
+        io_error: std::io::Error,
+    },
+
     /// offset not found for symbol
     #[error("Offset not found for symbol `{symbol_name}`")]
     SymbolOffsetNotFound {
@@ -280,6 +298,33 @@ impl Btf {
         })
     }
 
+    /// Parses split BTF, like the BTF of a kernel module, on top of the `base` BTF it extends.
+    ///
+    /// The type ids and string offsets of split BTF continue those of the base, usually the
+    /// vmlinux BTF. The returned instance contains the types and strings of both.
+    pub fn parse_split(data: &[u8], base: &Btf, endianness: Endianness) -> Result<Btf, BtfError> {
+        let split = Btf::parse(data, endianness)?;
+
+        let mut strings = base.strings.clone();
+        strings.extend(&split.strings);
+        let mut types = base.types.clone();
+        // skip the BtfType::Unknown added by parse(), split BTF has no void type
+        types.types.extend(split.types.types.into_iter().skip(1));
+
+        let mut header = base.header;
+        header.type_off = 0;
+        header.type_len = base.header.type_len + split.header.type_len;
+        header.str_off = header.type_len;
+        header.str_len = strings.len() as u32;
+
+        Ok(Btf {
+            header,
+            strings,
+            types,
+            _endianness: endianness,
+        })
+    }
+
     fn read_type_info(
         header: &btf_header,
         data: &[u8],
@@ -372,7 +417,7 @@ impl Btf {
             let ty = self.types.type_by_id(type_id)?;
             let size = match ty {
                 BtfType::Array(Array { array, .. }) => {
-                    n_elems = array.len;
+                    n_elems *= array.len;
                     type_id = array.element_type;
                     continue;
                 }
@@ -1031,6 +1076,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_split() {
+        let mut base = Btf::new();
+        let name_offset = base.add_string("int".to_string());
+        let int_type_id = base.add_type(BtfType::Int(Int::new(
+            name_offset,
+            4,
+            IntEncoding::Signed,
+            0,
+        )));
+        let base = Btf::parse(&base.to_bytes(), Endianness::default()).unwrap();
+
+        // the strings and types of split BTF are numbered after the base ones
+        let mut split = Btf::new();
+        let name_offset = base.strings.len() as u32 + split.add_string("myint".to_string());
+        split.add_type(BtfType::Typedef(Typedef::new(name_offset, int_type_id)));
+
+        let btf = Btf::parse_split(&split.to_bytes(), &base, Endianness::default()).unwrap();
+        let typedef_type_id = btf.id_by_type_name_kind("myint", BtfKind::Typedef).unwrap();
+        assert_eq!(typedef_type_id, int_type_id + 1);
+        assert_eq!(
+            btf.type_name(btf.type_by_id(int_type_id).unwrap()).unwrap(),
+            "int"
+        );
+    }
+
     #[test]
     fn test_fixup_ptr() {
         let mut btf = Btf::new();
@@ -5,8 +5,10 @@ mod btf;
 mod info;
 mod relocation;
 mod types;
+mod value;
 
 pub use btf::*;
 pub use info::*;
 pub use relocation::BtfRelocationError;
 pub use types::*;
+pub use value::*;
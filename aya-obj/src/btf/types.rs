@@ -636,8 +636,7 @@ impl Array {
         mem::size_of::<Self>()
     }
 
-    #[cfg(test)]
-    pub(crate) fn new(name_offset: u32, element_type: u32, index_type: u32, len: u32) -> Self {
+    pub fn new(name_offset: u32, element_type: u32, index_type: u32, len: u32) -> Self {
         let info = (BtfKind::Array as u32) << 24;
         Array {
             name_offset,
@@ -0,0 +1,499 @@
+//! Decoding of raw data with BTF.
+use core::fmt::{self, Write};
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::btf::{Btf, BtfError, BtfMember, BtfType, IntEncoding, MAX_RESOLVE_DEPTH};
+
+/// A value decoded with its BTF type, see [`Btf::decode`].
+///
+/// The value can be rendered as JSON with [`BtfValue::to_json`], in the style of
+/// `bpftool map dump`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BtfValue {
+    /// A signed integer, or a signed bitfield.
+    Signed(i128),
+    /// An unsigned integer, or an unsigned bitfield.
+    Unsigned(u128),
+    /// A boolean.
+    Bool(bool),
+    /// A floating point number.
+    Float(f64),
+    /// A pointer.
+    Pointer(u64),
+    /// The name of an enum variant. Enum values without a variant are decoded as integers.
+    Enum(String),
+    /// A NUL terminated array of chars.
+    String(String),
+    /// An array.
+    Array(Vec<BtfValue>),
+    /// The members of a struct or a union, or the variables of a data section, in order.
+    Struct(Vec<(String, BtfValue)>),
+    /// Data of a type that can't be decoded, like a forward declaration.
+    Bytes(Vec<u8>),
+}
+
+impl BtfValue {
+    /// Renders the value as JSON.
+    ///
+    /// Pointers and undecodable bytes are rendered as hex strings and non finite floats as
+    /// strings, like `bpftool` does.
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        // writing to a String can't fail
+        let _ = self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, w: &mut String) -> fmt::Result {
+        match self {
+            BtfValue::Signed(v) => write!(w, "{v}"),
+            BtfValue::Unsigned(v) => write!(w, "{v}"),
+            BtfValue::Bool(v) => write!(w, "{v}"),
+            BtfValue::Float(v) if v.is_finite() => write!(w, "{v}"),
+            BtfValue::Float(v) => write_json_string(w, &v.to_string()),
+            BtfValue::Pointer(v) => write_json_string(w, &format!("{v:#x}")),
+            BtfValue::Enum(s) | BtfValue::String(s) => write_json_string(w, s),
+            BtfValue::Array(values) => {
+                w.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        w.push(',');
+                    }
+                    value.write_json(w)?;
+                }
+                w.push(']');
+                Ok(())
+            }
+            BtfValue::Struct(members) => {
+                w.push('{');
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        w.push(',');
+                    }
+                    write_json_string(w, name)?;
+                    w.push(':');
+                    value.write_json(w)?;
+                }
+                w.push('}');
+                Ok(())
+            }
+            BtfValue::Bytes(bytes) => {
+                w.push('[');
+                for (i, b) in bytes.iter().enumerate() {
+                    if i > 0 {
+                        w.push(',');
+                    }
+                    write!(w, "\"{b:#04x}\"")?;
+                }
+                w.push(']');
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_json_string(w: &mut String, s: &str) -> fmt::Result {
+    w.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => w.push_str("\\\""),
+            '\\' => w.push_str("\\\\"),
+            '\n' => w.push_str("\\n"),
+            '\r' => w.push_str("\\r"),
+            '\t' => w.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.push(c),
+        }
+    }
+    w.push('"');
+    Ok(())
+}
+
+impl Btf {
+    /// Decodes `data` as a value of the type `type_id`.
+    ///
+    /// `data` must be in the native byte order, e.g. a map key or value read from the kernel.
+    pub fn decode(&self, type_id: u32, data: &[u8]) -> Result<BtfValue, BtfError> {
+        self.decode_type(type_id, data, 0)
+    }
+
+    fn decode_type(&self, type_id: u32, data: &[u8], depth: u8) -> Result<BtfValue, BtfError> {
+        if depth >= MAX_RESOLVE_DEPTH {
+            return Err(BtfError::MaximumTypeDepthReached { type_id });
+        }
+
+        let ty = self.type_by_id(type_id)?;
+        let value = match ty {
+            BtfType::Int(int) => {
+                let bits = (int.data & 0xff) as usize;
+                let signed = int.encoding() == IntEncoding::Signed;
+                if int.offset() != 0 || bits != int.size as usize * 8 {
+                    decode_bitfield(type_id, data, int.offset() as usize, bits, signed)?
+                } else {
+                    let bytes = field(type_id, data, 0, int.size as usize)?;
+                    match int.encoding() {
+                        IntEncoding::Bool => BtfValue::Bool(read_uint(bytes) != 0),
+                        IntEncoding::Signed => BtfValue::Signed(read_int(bytes)),
+                        _ => BtfValue::Unsigned(read_uint(bytes)),
+                    }
+                }
+            }
+            BtfType::Float(float) => {
+                let bytes = field(type_id, data, 0, float.size as usize)?;
+                match *bytes {
+                    [a, b, c, d] => BtfValue::Float(f32::from_ne_bytes([a, b, c, d]) as f64),
+                    [a, b, c, d, e, f, g, h] => {
+                        BtfValue::Float(f64::from_ne_bytes([a, b, c, d, e, f, g, h]))
+                    }
+                    _ => BtfValue::Bytes(bytes.to_vec()),
+                }
+            }
+            BtfType::Ptr(_) => {
+                let bytes = field(type_id, data, 0, core::mem::size_of::<usize>())?;
+                BtfValue::Pointer(read_uint(bytes) as u64)
+            }
+            BtfType::Enum(e) => {
+                let bytes = field(type_id, data, 0, e.size as usize)?;
+                let value = if e.is_signed() {
+                    read_int(bytes) as u64
+                } else {
+                    read_uint(bytes) as u64
+                };
+                match e
+                    .variants
+                    .iter()
+                    .find(|v| v.value as u64 & 0xffffffff == value & 0xffffffff)
+                {
+                    Some(v) => BtfValue::Enum(self.string_at(v.name_offset)?.into_owned()),
+                    None if e.is_signed() => BtfValue::Signed(read_int(bytes)),
+                    None => BtfValue::Unsigned(read_uint(bytes)),
+                }
+            }
+            BtfType::Enum64(e) => {
+                let bytes = field(type_id, data, 0, e.size as usize)?;
+                let value = read_uint(bytes) as u64;
+                match e
+                    .variants
+                    .iter()
+                    .find(|v| ((v.value_high as u64) << 32 | v.value_low as u64) == value)
+                {
+                    Some(v) => BtfValue::Enum(self.string_at(v.name_offset)?.into_owned()),
+                    None if e.is_signed() => BtfValue::Signed(read_int(bytes)),
+                    None => BtfValue::Unsigned(read_uint(bytes)),
+                }
+            }
+            BtfType::Array(array) => {
+                let element_type = array.array.element_type;
+                let element_size = self.type_size(element_type)?;
+                let len = array.array.len as usize;
+                let bytes = field(type_id, data, 0, element_size * len)?;
+                if self.is_char(element_type)? {
+                    let end = bytes.iter().position(|c| *c == 0).unwrap_or(bytes.len());
+                    if bytes[..end]
+                        .iter()
+                        .all(|c| c.is_ascii_graphic() || *c == b' ')
+                    {
+                        return Ok(BtfValue::String(
+                            String::from_utf8_lossy(&bytes[..end]).into_owned(),
+                        ));
+                    }
+                }
+                let mut values = Vec::with_capacity(len);
+                for i in 0..len {
+                    let element = &bytes[i * element_size..(i + 1) * element_size];
+                    values.push(self.decode_type(element_type, element, depth + 1)?);
+                }
+                BtfValue::Array(values)
+            }
+            BtfType::Struct(s) => self.decode_members(
+                &s.members,
+                data,
+                depth,
+                |m| s.member_bit_offset(m),
+                |m| s.member_bit_field_size(m),
+            )?,
+            BtfType::Union(u) => self.decode_members(
+                &u.members,
+                data,
+                depth,
+                |m| u.member_bit_offset(m),
+                |m| u.member_bit_field_size(m),
+            )?,
+            BtfType::DataSec(d) => {
+                let mut members = Vec::with_capacity(d.entries.len());
+                for entry in &d.entries {
+                    let bytes = field(type_id, data, entry.offset as usize, entry.size as usize)?;
+                    let var = self.type_by_id(entry.btf_type)?;
+                    members.push((
+                        self.type_name(var)?.into_owned(),
+                        self.decode_type(entry.btf_type, bytes, depth + 1)?,
+                    ));
+                }
+                BtfValue::Struct(members)
+            }
+            BtfType::Const(_)
+            | BtfType::Volatile(_)
+            | BtfType::Restrict(_)
+            | BtfType::Typedef(_)
+            | BtfType::Var(_)
+            | BtfType::DeclTag(_)
+            | BtfType::TypeTag(_) => {
+                // unwrap is safe, all these types reference another type
+                self.decode_type(ty.btf_type().unwrap(), data, depth + 1)?
+            }
+            BtfType::Unknown | BtfType::Fwd(_) | BtfType::Func(_) | BtfType::FuncProto(_) => {
+                BtfValue::Bytes(data.to_vec())
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn decode_members(
+        &self,
+        members: &[BtfMember],
+        data: &[u8],
+        depth: u8,
+        bit_offset: impl Fn(&BtfMember) -> usize,
+        bit_field_size: impl Fn(&BtfMember) -> usize,
+    ) -> Result<BtfValue, BtfError> {
+        let mut values = Vec::with_capacity(members.len());
+        for member in members {
+            let name = self.string_at(member.name_offset)?.into_owned();
+            let offset = bit_offset(member);
+            let bits = bit_field_size(member);
+            let value = if bits > 0 {
+                let signed = self.is_signed(member.btf_type)?;
+                decode_bitfield(member.btf_type, data, offset, bits, signed)?
+            } else {
+                let size = self.type_size(member.btf_type)?;
+                let bytes = field(member.btf_type, data, offset / 8, size)?;
+                self.decode_type(member.btf_type, bytes, depth + 1)?
+            };
+            values.push((name, value));
+        }
+        Ok(BtfValue::Struct(values))
+    }
+
+    fn is_char(&self, type_id: u32) -> Result<bool, BtfError> {
+        let ty = self.type_by_id(self.resolve_type(type_id)?)?;
+        Ok(match ty {
+            BtfType::Int(int) => {
+                int.size == 1
+                    && (int.encoding() == IntEncoding::Char || self.type_name(ty)?.contains("char"))
+            }
+            _ => false,
+        })
+    }
+
+    fn is_signed(&self, type_id: u32) -> Result<bool, BtfError> {
+        let ty = self.type_by_id(self.resolve_type(type_id)?)?;
+        Ok(match ty {
+            BtfType::Int(int) => int.encoding() == IntEncoding::Signed,
+            BtfType::Enum(e) => e.is_signed(),
+            BtfType::Enum64(e) => e.is_signed(),
+            _ => false,
+        })
+    }
+}
+
+fn field(type_id: u32, data: &[u8], offset: usize, size: usize) -> Result<&[u8], BtfError> {
+    data.get(offset..offset + size)
+        .ok_or(BtfError::InvalidDataSize { type_id })
+}
+
+fn read_uint(bytes: &[u8]) -> u128 {
+    let mut buf = [0u8; 16];
+    let len = bytes.len().min(16);
+    if cfg!(target_endian = "little") {
+        buf[..len].copy_from_slice(&bytes[..len]);
+    } else {
+        buf[16 - len..].copy_from_slice(&bytes[..len]);
+    }
+    u128::from_ne_bytes(buf)
+}
+
+fn read_int(bytes: &[u8]) -> i128 {
+    let bits = bytes.len().min(16) * 8;
+    sign_extend(read_uint(bytes), bits)
+}
+
+fn sign_extend(value: u128, bits: usize) -> i128 {
+    if bits == 0 || bits >= 128 {
+        return value as i128;
+    }
+    let shift = 128 - bits;
+    ((value << shift) as i128) >> shift
+}
+
+fn decode_bitfield(
+    type_id: u32,
+    data: &[u8],
+    bit_offset: usize,
+    bits: usize,
+    signed: bool,
+) -> Result<BtfValue, BtfError> {
+    if bits == 0 || bits > 64 {
+        return Err(BtfError::InvalidDataSize { type_id });
+    }
+    let shift = bit_offset % 8;
+    let len = (shift + bits).div_ceil(8);
+    let bytes = field(type_id, data, bit_offset / 8, len)?;
+
+    let mut value = 0u128;
+    if cfg!(target_endian = "little") {
+        for b in bytes.iter().rev() {
+            value = value << 8 | *b as u128;
+        }
+        value >>= shift;
+    } else {
+        for b in bytes {
+            value = value << 8 | *b as u128;
+        }
+        value >>= len * 8 - shift - bits;
+    }
+    value &= (1u128 << bits) - 1;
+
+    Ok(if signed {
+        BtfValue::Signed(sign_extend(value, bits))
+    } else {
+        BtfValue::Unsigned(value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use object::Endianness;
+
+    use super::*;
+    use crate::btf::{Array, BtfEnum, BtfKind, Enum, Int, Struct};
+
+    #[test]
+    fn test_decode_struct() {
+        let mut btf = Btf::new();
+        let u32_name = btf.add_string("u32".to_string());
+        let u32_type = btf.add_type(BtfType::Int(Int::new(u32_name, 4, IntEncoding::None, 0)));
+        let char_name = btf.add_string("char".to_string());
+        let char_type = btf.add_type(BtfType::Int(Int::new(char_name, 1, IntEncoding::Signed, 0)));
+        let comm_type = btf.add_type(BtfType::Array(Array::new(0, char_type, u32_type, 4)));
+        let a = btf.add_string("A".to_string());
+        let b = btf.add_string("B".to_string());
+        let enum_type = btf.add_type(BtfType::Enum(Enum::new(
+            0,
+            vec![
+                BtfEnum {
+                    name_offset: a,
+                    value: 0,
+                },
+                BtfEnum {
+                    name_offset: b,
+                    value: 1,
+                },
+            ],
+        )));
+        let pid = btf.add_string("pid".to_string());
+        let comm = btf.add_string("comm".to_string());
+        let kind = btf.add_string("kind".to_string());
+        let struct_type = btf.add_type(BtfType::Struct(Struct::new(
+            0,
+            vec![
+                BtfMember {
+                    name_offset: pid,
+                    btf_type: u32_type,
+                    offset: 0,
+                },
+                BtfMember {
+                    name_offset: comm,
+                    btf_type: comm_type,
+                    offset: 32,
+                },
+                BtfMember {
+                    name_offset: kind,
+                    btf_type: enum_type,
+                    offset: 64,
+                },
+            ],
+            12,
+        )));
+
+        let mut data = vec![];
+        data.extend_from_slice(&42u32.to_ne_bytes());
+        data.extend_from_slice(b"ls\0\0");
+        data.extend_from_slice(&1u32.to_ne_bytes());
+
+        let value = btf.decode(struct_type, &data).unwrap();
+        assert_eq!(
+            value,
+            BtfValue::Struct(vec![
+                ("pid".to_string(), BtfValue::Unsigned(42)),
+                ("comm".to_string(), BtfValue::String("ls".to_string())),
+                ("kind".to_string(), BtfValue::Enum("B".to_string())),
+            ])
+        );
+        assert_eq!(value.to_json(), r#"{"pid":42,"comm":"ls","kind":"B"}"#);
+
+        assert!(matches!(
+            btf.decode(struct_type, &data[..8]),
+            Err(BtfError::InvalidDataSize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_bitfields() {
+        let mut btf = Btf::new();
+        let int_name = btf.add_string("int".to_string());
+        let int_type = btf.add_type(BtfType::Int(Int::new(int_name, 4, IntEncoding::Signed, 0)));
+        let a = btf.add_string("a".to_string());
+        let b = btf.add_string("b".to_string());
+
+        // struct { int a:3; int b:5; } with kind_flag set
+        let mut raw = vec![];
+        for v in [
+            0u32,
+            (BtfKind::Struct as u32) << 24 | 1 << 31 | 2,
+            4,
+            a,
+            int_type,
+            3 << 24,
+            b,
+            int_type,
+            5 << 24 | 3,
+        ] {
+            raw.extend_from_slice(&v.to_ne_bytes());
+        }
+        let ty = unsafe { BtfType::read(&raw, Endianness::default()) }.unwrap();
+        let struct_type = btf.add_type(ty);
+
+        // a = -1, b = 13
+        let packed: u32 = 0b111 | 13 << 3;
+        let value = btf.decode(struct_type, &packed.to_ne_bytes()).unwrap();
+        assert_eq!(
+            value,
+            BtfValue::Struct(vec![
+                ("a".to_string(), BtfValue::Signed(-1)),
+                ("b".to_string(), BtfValue::Signed(13)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let value = BtfValue::Array(vec![
+            BtfValue::Pointer(0xff),
+            BtfValue::Bool(true),
+            BtfValue::Float(f64::NAN),
+            BtfValue::String("a\"b\n".to_string()),
+            BtfValue::Bytes(vec![0, 0xab]),
+        ]);
+        assert_eq!(
+            value.to_json(),
+            r#"["0xff",true,"NaN","a\"b\n",["0x00","0xab"]]"#
+        );
+    }
+}
@@ -79,6 +79,7 @@ pub mod maps;
 pub mod obj;
 pub mod programs;
 pub mod relocation;
+pub mod struct_ops;
 mod util;
 
 pub use maps::Map;
@@ -2,8 +2,11 @@
 
 use core::mem;
 
-use crate::thiserror::{self, Error};
-use alloc::vec::Vec;
+use crate::{
+    struct_ops::StructOps,
+    thiserror::{self, Error},
+};
+use alloc::{string::String, vec::Vec};
 
 /// Invalid map type encontered
 pub struct InvalidMapTypeError {
@@ -148,6 +151,8 @@ pub enum MapKind {
     Data,
     /// A map holding `.rodata` section data
     Rodata,
+    /// A map holding a `struct_ops` value, from a `.struct_ops` section
+    StructOps,
     /// Other maps
     Other,
 }
@@ -224,6 +229,14 @@ impl Map {
         }
     }
 
+    /// Sets the map flags
+    pub fn set_map_flags(&mut self, v: u32) {
+        match self {
+            Map::Legacy(m) => m.def.map_flags = v,
+            Map::Btf(m) => m.def.map_flags = v,
+        }
+    }
+
     /// Returns the pinning type of the map
     pub fn pinning(&self) -> PinningType {
         match self {
@@ -271,6 +284,50 @@ impl Map {
             Map::Btf(m) => m.symbol_index,
         }
     }
+
+    /// Returns the map used as a template for the inner maps of a map-in-map, if one is declared
+    pub fn inner(&self) -> Option<Map> {
+        match self {
+            Map::Legacy(_) => None,
+            Map::Btf(m) => m.inner_def.map(|def| {
+                Map::Btf(BtfMap {
+                    def,
+                    section_index: m.section_index,
+                    symbol_index: m.symbol_index,
+                    kind: MapKind::Other,
+                    data: Vec::new(),
+                    inner_def: None,
+                    initial_slots: Vec::new(),
+                    struct_ops: None,
+                })
+            }),
+        }
+    }
+
+    /// Returns the `struct_ops` definition of a map from a `.struct_ops` section
+    pub fn struct_ops(&self) -> Option<&StructOps> {
+        match self {
+            Map::Legacy(_) => None,
+            Map::Btf(m) => m.struct_ops.as_ref(),
+        }
+    }
+
+    /// Returns the `struct_ops` definition of a map as mutable
+    pub fn struct_ops_mut(&mut self) -> Option<&mut StructOps> {
+        match self {
+            Map::Legacy(_) => None,
+            Map::Btf(m) => m.struct_ops.as_mut(),
+        }
+    }
+
+    /// Returns the inner maps a map-in-map is statically initialised with, as
+    /// `(index, inner map name)` pairs
+    pub fn initial_slots(&self) -> &[(u32, String)] {
+        match self {
+            Map::Legacy(_) => &[],
+            Map::Btf(m) => &m.initial_slots,
+        }
+    }
 }
 
 /// A map declared with legacy BPF map declaration style, most likely from a `maps` section.
@@ -300,4 +357,10 @@ pub struct BtfMap {
     pub(crate) symbol_index: usize,
     pub(crate) kind: MapKind,
     pub(crate) data: Vec<u8>,
+    /// The definition of the inner maps of a map-in-map, declared with `__array(values, ...)`
+    pub(crate) inner_def: Option<BtfMapDef>,
+    /// The static initialisers of a map-in-map, as `(index, inner map name)` pairs
+    pub(crate) initial_slots: Vec<(u32, String)>,
+    /// The `struct_ops` definition of a map from a `.struct_ops` section
+    pub(crate) struct_ops: Option<StructOps>,
 }
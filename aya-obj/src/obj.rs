@@ -22,14 +22,21 @@ use crate::{
 };
 
 use crate::{
-    btf::{Btf, BtfError, BtfExt, BtfType},
-    generated::{bpf_insn, bpf_map_info, bpf_map_type::BPF_MAP_TYPE_ARRAY, BPF_F_RDONLY_PROG},
+    btf::{Btf, BtfError, BtfExt, BtfType, IntEncoding},
+    generated::{
+        bpf_insn, bpf_map_info,
+        bpf_map_type::{BPF_MAP_TYPE_ARRAY, BPF_MAP_TYPE_STRUCT_OPS},
+        BPF_F_RDONLY_PROG,
+    },
     maps::{bpf_map_def, BtfMapDef, PinningType},
-    programs::{CgroupSockAddrAttachType, CgroupSockAttachType, CgroupSockoptAttachType},
+    programs::{
+        CgroupSockAddrAttachType, CgroupSockAttachType, CgroupSockoptAttachType, XdpAttachType,
+    },
+    struct_ops::{StructOps, BPF_F_LINK},
 };
 use core::slice::from_raw_parts_mut;
 
-use crate::btf::{Array, DataSecEntry, FuncSecInfo, LineSecInfo};
+use crate::btf::{Array, DataSecEntry, FuncSecInfo, LineSecInfo, Struct};
 
 const KERNEL_VERSION_ANY: u32 = 0xFFFF_FFFE;
 
@@ -146,34 +153,33 @@ pub struct Function {
 /// - `flow_dissector`: `BPF_PROG_TYPE_FLOW_DISSECTOR`
 /// - `ksyscall+` or `kretsyscall+`
 /// - `uprobe.s+` or `uretprobe.s+`
-/// - `usdt+`
-/// - `kprobe.multi+` or `kretprobe.multi+`: `BPF_TRACE_KPROBE_MULTI`
 /// - `lsm_cgroup+` or `lsm.s+`
 /// - `lwt_in`, `lwt_out`, `lwt_seg6local`, `lwt_xmit`
 /// - `raw_tp.w+`, `raw_tracepoint.w+`
 /// - `action`
 /// - `sk_reuseport/migrate`, `sk_reuseport`
 /// - `syscall`
-/// - `struct_ops+`
 /// - `fmod_ret+`, `fmod_ret.s+`
 /// - `fentry.s+`, `fexit.s+`
 /// - `iter+`, `iter.s+`
-/// - `xdp.frags/cpumap`, `xdp/cpumap`
-/// - `xdp.frags/devmap`, `xdp/devmap`
 #[derive(Debug, Clone)]
 #[allow(missing_docs)]
 pub enum ProgramSection {
     KRetProbe {
         name: String,
+        multi: bool,
     },
     KProbe {
         name: String,
+        multi: bool,
     },
     UProbe {
         name: String,
+        multi: bool,
     },
     URetProbe {
         name: String,
+        multi: bool,
     },
     TracePoint {
         name: String,
@@ -184,6 +190,7 @@ pub enum ProgramSection {
     Xdp {
         name: String,
         frags_supported: bool,
+        attach_type: XdpAttachType,
     },
     SkMsg {
         name: String,
@@ -254,16 +261,23 @@ pub enum ProgramSection {
     CgroupDevice {
         name: String,
     },
+    StructOps {
+        name: String,
+        sleepable: bool,
+    },
+    Usdt {
+        name: String,
+    },
 }
 
 impl ProgramSection {
     /// Returns the program name
     pub fn name(&self) -> &str {
         match self {
-            ProgramSection::KRetProbe { name } => name,
-            ProgramSection::KProbe { name } => name,
-            ProgramSection::UProbe { name } => name,
-            ProgramSection::URetProbe { name } => name,
+            ProgramSection::KRetProbe { name, .. } => name,
+            ProgramSection::KProbe { name, .. } => name,
+            ProgramSection::UProbe { name, .. } => name,
+            ProgramSection::URetProbe { name, .. } => name,
             ProgramSection::TracePoint { name } => name,
             ProgramSection::SocketFilter { name } => name,
             ProgramSection::Xdp { name, .. } => name,
@@ -289,6 +303,8 @@ impl ProgramSection {
             ProgramSection::SkLookup { name } => name,
             ProgramSection::CgroupSock { name, .. } => name,
             ProgramSection::CgroupDevice { name } => name,
+            ProgramSection::StructOps { name, .. } => name,
+            ProgramSection::Usdt { name } => name,
         }
     }
 }
@@ -305,21 +321,53 @@ impl FromStr for ProgramSection {
         if parts.len() == 1 {
             parts.push(parts[0]);
         }
-        let kind = parts[1];
+        // libbpf-style objects use bare `xdp/devmap` and `xdp/cpumap` sections, which would
+        // otherwise be parsed as interface programs named `devmap` and `cpumap`
+        let kind = match section {
+            "xdp/devmap" | "xdp.frags/devmap" | "xdp/cpumap" | "xdp.frags/cpumap" => section,
+            _ => parts[1],
+        };
         let name = parts[0].to_owned();
 
         Ok(match kind {
-            "kprobe" => KProbe { name },
-            "kretprobe" => KRetProbe { name },
-            "uprobe" => UProbe { name },
-            "uretprobe" => URetProbe { name },
+            "kprobe" => KProbe { name, multi: false },
+            "kretprobe" => KRetProbe { name, multi: false },
+            "kprobe.multi" => KProbe { name, multi: true },
+            "kretprobe.multi" => KRetProbe { name, multi: true },
+            "uprobe" => UProbe { name, multi: false },
+            "uretprobe" => URetProbe { name, multi: false },
+            "uprobe.multi" => UProbe { name, multi: true },
+            "uretprobe.multi" => URetProbe { name, multi: true },
+            "usdt" => Usdt { name },
             "xdp" => Xdp {
                 name,
                 frags_supported: false,
+                attach_type: XdpAttachType::Interface,
             },
             "xdp.frags" => Xdp {
                 name,
                 frags_supported: true,
+                attach_type: XdpAttachType::Interface,
+            },
+            "xdp/cpumap" => Xdp {
+                name,
+                frags_supported: false,
+                attach_type: XdpAttachType::CpuMap,
+            },
+            "xdp.frags/cpumap" => Xdp {
+                name,
+                frags_supported: true,
+                attach_type: XdpAttachType::CpuMap,
+            },
+            "xdp/devmap" => Xdp {
+                name,
+                frags_supported: false,
+                attach_type: XdpAttachType::DevMap,
+            },
+            "xdp.frags/devmap" => Xdp {
+                name,
+                frags_supported: true,
+                attach_type: XdpAttachType::DevMap,
             },
             "tp_btf" => BtfTracePoint { name },
             _ if kind.starts_with("tracepoint") || kind.starts_with("tp") => {
@@ -477,6 +525,14 @@ impl FromStr for ProgramSection {
             "fexit" => FExit { name },
             "freplace" => Extension { name },
             "sk_lookup" => SkLookup { name },
+            "struct_ops" => StructOps {
+                name,
+                sleepable: false,
+            },
+            "struct_ops.s" => StructOps {
+                name,
+                sleepable: true,
+            },
             _ => {
                 return Err(ParseError::InvalidProgramSection {
                     section: section.to_owned(),
@@ -540,15 +596,25 @@ impl Object {
             }
         }
 
+        // .struct_ops sections reference the programs implementing their function pointers,
+        // so they're parsed once all the program sections are
+        let mut struct_ops_sections = Vec::new();
         for s in obj.sections() {
             if let Ok(name) = s.name() {
                 if name == ".BTF" || name == ".BTF.ext" {
                     continue;
                 }
+                if name.starts_with(".struct_ops") {
+                    struct_ops_sections.push(s);
+                    continue;
+                }
             }
 
             bpf_obj.parse_section(Section::try_from(&s)?)?;
         }
+        for s in &struct_ops_sections {
+            bpf_obj.parse_section(Section::try_from(s)?)?;
+        }
 
         Ok(bpf_obj)
     }
@@ -571,6 +637,40 @@ impl Object {
         }
     }
 
+    /// Returns the global variables stored in the `.bss`, `.data` and `.rodata` maps, keyed by
+    /// name
+    pub fn globals(&self) -> HashMap<String, GlobalVariable> {
+        let mut globals = HashMap::new();
+        for symbol in self.symbols_by_index.values() {
+            let name = match &symbol.name {
+                Some(name) if symbol.kind == SymbolKind::Data && symbol.is_definition => name,
+                _ => continue,
+            };
+            let map_name = match self.maps.iter().find(|(_, m)| {
+                matches!(m.kind(), MapKind::Bss | MapKind::Data | MapKind::Rodata)
+                    && symbol.section_index == Some(m.section_index())
+            }) {
+                Some((map_name, _)) => map_name,
+                None => continue,
+            };
+            let btf_type = self
+                .btf
+                .as_ref()
+                .and_then(|btf| global_btf_type(btf, map_name, name));
+            globals.insert(
+                name.clone(),
+                GlobalVariable {
+                    map_name: map_name.clone(),
+                    offset: symbol.address,
+                    size: symbol.size,
+                    btf_size: btf_type.map(|(size, _)| size),
+                    btf_kind: btf_type.map(|(_, kind)| kind),
+                },
+            );
+        }
+        globals
+    }
+
     /// Patches map data
     pub fn patch_map_data(&mut self, globals: HashMap<&str, &[u8]>) -> Result<(), ParseError> {
         let symbols: HashMap<String, &Symbol> = self
@@ -812,21 +912,31 @@ impl Object {
                 if type_name == section.name {
                     // each btf_var_secinfo contains a map
                     for info in &datasec.entries {
-                        let (map_name, def) = parse_btf_map_def(btf, info)?;
-                        let symbol_index = symbols
-                            .get(&map_name)
-                            .ok_or_else(|| ParseError::SymbolNotFound {
-                                name: map_name.to_string(),
-                            })?
-                            .index;
+                        let (map_name, def, inner) = parse_btf_map_def(btf, info)?;
+                        let symbol =
+                            symbols
+                                .get(&map_name)
+                                .ok_or_else(|| ParseError::SymbolNotFound {
+                                    name: map_name.to_string(),
+                                })?;
+                        let (inner_def, initial_slots) = match inner {
+                            Some((inner_def, values_offset)) => (
+                                Some(inner_def),
+                                self.parse_initial_slots(section, symbol, values_offset)?,
+                            ),
+                            None => (None, Vec::new()),
+                        };
                         self.maps.insert(
                             map_name,
                             Map::Btf(BtfMap {
                                 def,
                                 section_index: section.index.0,
-                                symbol_index,
+                                symbol_index: symbol.index,
                                 kind: MapKind::Other,
                                 data: Vec::new(),
+                                inner_def,
+                                initial_slots,
+                                struct_ops: None,
                             }),
                         );
                     }
@@ -836,6 +946,138 @@ impl Object {
         Ok(())
     }
 
+    // Each variable of a .struct_ops section is a struct_ops map. Its function pointer members
+    // are relocations against the programs implementing them.
+    fn parse_struct_ops(
+        &mut self,
+        section: &Section,
+        symbols: HashMap<String, Symbol>,
+    ) -> Result<(), ParseError> {
+        let btf = self.btf.as_ref().ok_or(ParseError::NoBTF)?;
+        let datasec = btf
+            .types()
+            .find_map(|t| match t {
+                BtfType::DataSec(datasec)
+                    if btf.type_name(t).map(|n| n == section.name).unwrap_or(false) =>
+                {
+                    Some(datasec)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| BtfError::UnknownBtfTypeName {
+                type_name: section.name.to_owned(),
+            })?;
+
+        for info in &datasec.entries {
+            let var = match btf.type_by_id(info.btf_type)? {
+                BtfType::Var(var) => var,
+                _ => return Err(BtfError::InvalidDatasec.into()),
+            };
+            let name = btf.string_at(var.name_offset)?.to_string();
+            let type_id = btf.resolve_type(var.btf_type)?;
+            let s = match btf.type_by_id(type_id)? {
+                BtfType::Struct(s) => s,
+                _ => return Err(BtfError::UnexpectedBtfType { type_id }.into()),
+            };
+            let type_name = btf.string_at(s.name_offset)?.to_string();
+            let symbol = symbols
+                .get(&name)
+                .ok_or_else(|| ParseError::SymbolNotFound { name: name.clone() })?;
+            let start = symbol.address;
+            let end = start + s.size as u64;
+            let data = section
+                .data
+                .get(start as usize..end as usize)
+                .ok_or_else(|| ParseError::InvalidMapDefinition { name: name.clone() })?;
+
+            let mut programs = Vec::new();
+            for rel in &section.relocations {
+                if rel.offset < start || rel.offset >= end {
+                    continue;
+                }
+                let member = s
+                    .members
+                    .iter()
+                    .find(|m| (s.member_bit_offset(m) / 8) as u64 == rel.offset - start)
+                    .ok_or(ParseError::UnknownSymbol {
+                        section_index: section.index.0,
+                        address: rel.offset,
+                    })?;
+                let member = btf.string_at(member.name_offset)?.to_string();
+                let program = self
+                    .symbols_by_index
+                    .get(&rel.symbol_index)
+                    .and_then(|sym| {
+                        self.programs
+                            .values()
+                            .find(|p| sym.section_index == Some(p.function.section_index.0))
+                    })
+                    .ok_or_else(|| ParseError::StructOpsProgramNotFound {
+                        name: name.clone(),
+                        member: member.clone(),
+                    })?;
+                programs.push((member, program.section.name().to_owned()));
+            }
+
+            self.maps.insert(
+                name,
+                Map::Btf(BtfMap {
+                    def: BtfMapDef {
+                        map_type: BPF_MAP_TYPE_STRUCT_OPS as u32,
+                        key_size: mem::size_of::<u32>() as u32,
+                        value_size: s.size,
+                        max_entries: 1,
+                        map_flags: BPF_F_LINK,
+                        ..Default::default()
+                    },
+                    section_index: section.index.0,
+                    symbol_index: symbol.index,
+                    kind: MapKind::StructOps,
+                    data: data.to_vec(),
+                    inner_def: None,
+                    initial_slots: Vec::new(),
+                    struct_ops: Some(StructOps {
+                        type_name,
+                        programs,
+                        layout: None,
+                        type_id,
+                    }),
+                }),
+            );
+        }
+        Ok(())
+    }
+
+    // The static initialisers of a map-in-map are relocations against the inner maps, at the
+    // offsets of the slots of the `values` array.
+    fn parse_initial_slots(
+        &self,
+        section: &Section,
+        symbol: &Symbol,
+        values_offset: u32,
+    ) -> Result<Vec<(u32, String)>, ParseError> {
+        let start = symbol.address + values_offset as u64;
+        let end = symbol.address + symbol.size;
+        let mut slots = Vec::new();
+        for rel in &section.relocations {
+            if rel.offset < start || rel.offset >= end {
+                continue;
+            }
+            let inner = self
+                .symbols_by_index
+                .get(&rel.symbol_index)
+                .and_then(|sym| sym.name.as_ref())
+                .ok_or(ParseError::UnknownSymbol {
+                    section_index: section.index.0,
+                    address: rel.offset,
+                })?;
+            let index = (rel.offset - start) / mem::size_of::<u64>() as u64;
+            slots.push((index as u32, inner.clone()));
+        }
+        slots.sort();
+        Ok(slots)
+    }
+
     fn parse_section(&mut self, mut section: Section) -> Result<(), ParseError> {
         let mut parts = section.name.rsplitn(2, '/').collect::<Vec<_>>();
         parts.reverse();
@@ -874,6 +1116,16 @@ impl Object {
                     .collect();
                 self.parse_btf_maps(&section, symbols)?
             }
+            BpfSectionKind::StructOps => {
+                let symbols: HashMap<String, Symbol> = self
+                    .symbols_by_index
+                    .values()
+                    .filter(|s| s.section_index == Some(section.index.0) && s.name.is_some())
+                    .cloned()
+                    .map(|s| (s.name.as_ref().unwrap().to_string(), s))
+                    .collect();
+                self.parse_struct_ops(&section, symbols)?
+            }
             BpfSectionKind::Maps => {
                 let symbols: Vec<Symbol> = self
                     .symbols_by_index
@@ -980,6 +1232,9 @@ pub enum ParseError {
     /// No BTF parsed for object
     #[error("no BTF parsed for object")]
     NoBTF,
+
+    #[error("struct_ops map `{name}`: member `{member}` doesn't point to a struct_ops program")]
+    StructOpsProgramNotFound { name: String, member: String },
 }
 
 #[derive(Debug)]
@@ -987,6 +1242,7 @@ enum BpfSectionKind {
     Undefined,
     Maps,
     BtfMaps,
+    StructOps,
     Program,
     Data,
     Text,
@@ -1006,6 +1262,8 @@ impl BpfSectionKind {
             BpfSectionKind::Maps
         } else if name.starts_with(".maps") {
             BpfSectionKind::BtfMaps
+        } else if name.starts_with(".struct_ops") {
+            BpfSectionKind::StructOps
         } else if name.starts_with(".text") {
             BpfSectionKind::Text
         } else if name.starts_with(".bss")
@@ -1159,6 +1417,84 @@ impl From<KernelVersion> for u32 {
     }
 }
 
+/// A global variable of an object
+#[derive(Debug, Clone)]
+pub struct GlobalVariable {
+    /// The name of the map holding the variable
+    pub map_name: String,
+    /// The offset of the variable in the value of the map
+    pub offset: u64,
+    /// The size of the variable
+    pub size: u64,
+    /// The size of the BTF type of the variable, if the object has BTF for its section
+    pub btf_size: Option<usize>,
+    /// The kind of the BTF type of the variable, if the object has BTF for its section
+    pub btf_kind: Option<GlobalKind>,
+}
+
+/// The kind of the BTF type of a global variable, once typedefs and qualifiers are resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalKind {
+    /// An integer or an enum
+    Int {
+        /// Whether the integer is signed
+        signed: bool,
+    },
+    /// An array
+    Array,
+    /// A struct or a union
+    Struct,
+    /// Any other kind, eg a pointer or a float
+    Other,
+}
+
+impl GlobalKind {
+    fn from_btf(btf: &Btf, type_id: u32) -> Result<GlobalKind, BtfError> {
+        let kind = match btf.type_by_id(btf.resolve_type(type_id)?)? {
+            BtfType::Int(int) => GlobalKind::Int {
+                signed: int.encoding() == IntEncoding::Signed,
+            },
+            BtfType::Enum(e) => GlobalKind::Int {
+                signed: e.is_signed(),
+            },
+            BtfType::Enum64(e) => GlobalKind::Int {
+                signed: e.is_signed(),
+            },
+            BtfType::Array(_) => GlobalKind::Array,
+            BtfType::Struct(_) | BtfType::Union(_) => GlobalKind::Struct,
+            _ => GlobalKind::Other,
+        };
+        Ok(kind)
+    }
+}
+
+// Looks up the variable in the DATASEC of its section to get the size and kind of its type.
+fn global_btf_type(btf: &Btf, section: &str, name: &str) -> Option<(usize, GlobalKind)> {
+    for ty in btf.types() {
+        let datasec = match ty {
+            BtfType::DataSec(datasec) => datasec,
+            _ => continue,
+        };
+        if btf.type_name(ty).ok().as_deref() != Some(section) {
+            continue;
+        }
+        for entry in &datasec.entries {
+            if let Ok(var @ BtfType::Var(v)) = btf.type_by_id(entry.btf_type) {
+                let var_name = match btf.type_name(var) {
+                    Ok(var_name) => var_name,
+                    Err(_) => continue,
+                };
+                if var_name == name {
+                    let size = btf.type_size(v.btf_type).ok()?;
+                    let kind = GlobalKind::from_btf(btf, v.btf_type).ok()?;
+                    return Some((size, kind));
+                }
+            }
+        }
+    }
+    None
+}
+
 fn parse_map(section: &Section, name: &str) -> Result<Map, ParseError> {
     let kind = MapKind::from(name);
     let (def, data) = match kind {
@@ -1180,6 +1516,9 @@ fn parse_map(section: &Section, name: &str) -> Result<Map, ParseError> {
             (def, section.data.to_vec())
         }
         MapKind::Other => (parse_map_def(name, section.data)?, Vec::new()),
+        // `MapKind::from` never returns StructOps, struct_ops maps are created from their
+        // `.struct_ops` section by `Object::parse_struct_ops`
+        MapKind::StructOps => unreachable!("struct_ops maps aren't parsed from a map section"),
     };
     Ok(Map::Legacy(LegacyMap {
         section_index: section.index.0,
@@ -1210,7 +1549,14 @@ fn parse_map_def(name: &str, data: &[u8]) -> Result<bpf_map_def, ParseError> {
     }
 }
 
-fn parse_btf_map_def(btf: &Btf, info: &DataSecEntry) -> Result<(String, BtfMapDef), BtfError> {
+// The definition of the inner maps of a map-in-map, and the byte offset of the `values` member
+// holding the static initialisers of the map.
+type InnerMapDef = (BtfMapDef, u32);
+
+fn parse_btf_map_def(
+    btf: &Btf,
+    info: &DataSecEntry,
+) -> Result<(String, BtfMapDef, Option<InnerMapDef>), BtfError> {
     let ty = match btf.type_by_id(info.btf_type)? {
         BtfType::Var(var) => var,
         other => {
@@ -1220,7 +1566,6 @@ fn parse_btf_map_def(btf: &Btf, info: &DataSecEntry) -> Result<(String, BtfMapDe
         }
     };
     let map_name = btf.string_at(ty.name_offset)?;
-    let mut map_def = BtfMapDef::default();
 
     // Safety: union
     let root_type = btf.resolve_type(ty.btf_type)?;
@@ -1233,6 +1578,17 @@ fn parse_btf_map_def(btf: &Btf, info: &DataSecEntry) -> Result<(String, BtfMapDe
         }
     };
 
+    let (map_def, inner) = parse_btf_map_struct(btf, s)?;
+    Ok((map_name.to_string(), map_def, inner))
+}
+
+fn parse_btf_map_struct(
+    btf: &Btf,
+    s: &Struct,
+) -> Result<(BtfMapDef, Option<InnerMapDef>), BtfError> {
+    let mut map_def = BtfMapDef::default();
+    let mut inner = None;
+
     for m in &s.members {
         match btf.string_at(m.name_offset)?.as_ref() {
             "type" => {
@@ -1267,6 +1623,40 @@ fn parse_btf_map_def(btf: &Btf, info: &DataSecEntry) -> Result<(String, BtfMapDe
             "value_size" => {
                 map_def.value_size = get_map_field(btf, m.btf_type)?;
             }
+            "values" => {
+                // `__array(values, struct inner_map)` is an array of pointers to the definition
+                // of the inner maps
+                let arr = match btf.type_by_id(m.btf_type)? {
+                    BtfType::Array(Array { array, .. }) => array,
+                    _ => {
+                        return Err(BtfError::UnexpectedBtfType {
+                            type_id: m.btf_type,
+                        })
+                    }
+                };
+                let pty = match btf.type_by_id(arr.element_type)? {
+                    BtfType::Ptr(pty) => pty,
+                    _ => {
+                        return Err(BtfError::UnexpectedBtfType {
+                            type_id: arr.element_type,
+                        })
+                    }
+                };
+                let inner_type = btf.resolve_type(pty.btf_type)?;
+                match btf.type_by_id(inner_type)? {
+                    BtfType::Struct(inner_struct) => {
+                        let (inner_def, _) = parse_btf_map_struct(btf, inner_struct)?;
+                        let offset = (s.member_bit_offset(m) / 8) as u32;
+                        inner = Some((inner_def, offset));
+                    }
+                    _ => {
+                        debug!("skipping values of unsupported type {}", inner_type);
+                        continue;
+                    }
+                }
+                // the values of a map-in-map are inner map fds
+                map_def.value_size = mem::size_of::<u32>() as u32;
+            }
             "max_entries" => {
                 map_def.max_entries = get_map_field(btf, m.btf_type)?;
             }
@@ -1286,7 +1676,7 @@ fn parse_btf_map_def(btf: &Btf, info: &DataSecEntry) -> Result<(String, BtfMapDe
             }
         }
     }
-    Ok((map_name.to_string(), map_def))
+    Ok((map_def, inner))
 }
 
 /// Parses a [bpf_map_info] into a [Map].
@@ -1308,6 +1698,9 @@ pub fn parse_map_info(info: bpf_map_info, pinned: PinningType) -> Map {
             data: Vec::new(),
             // We should never be loading the .bss or .data or .rodata FDs
             kind: MapKind::Other,
+            inner_def: None,
+            initial_slots: Vec::new(),
+            struct_ops: None,
         })
     } else {
         Map::Legacy(LegacyMap {
@@ -1744,6 +2137,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_section_kprobe_multi() {
+        let mut obj = fake_obj();
+
+        assert_matches!(
+            obj.parse_section(fake_section(
+                BpfSectionKind::Program,
+                "kretprobe.multi/foo",
+                bytes_of(&fake_ins())
+            )),
+            Ok(())
+        );
+        assert_matches!(
+            obj.programs.get("foo"),
+            Some(Program {
+                section: ProgramSection::KRetProbe { multi: true, .. },
+                ..
+            })
+        );
+    }
+
     #[test]
     fn test_parse_section_uprobe() {
         let mut obj = fake_obj();
@@ -1765,6 +2179,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_section_uprobe_multi() {
+        let mut obj = fake_obj();
+
+        assert_matches!(
+            obj.parse_section(fake_section(
+                BpfSectionKind::Program,
+                "uprobe.multi/foo",
+                bytes_of(&fake_ins())
+            )),
+            Ok(())
+        );
+        assert_matches!(
+            obj.programs.get("foo"),
+            Some(Program {
+                section: ProgramSection::UProbe { multi: true, .. },
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_section_usdt() {
+        let mut obj = fake_obj();
+
+        assert_matches!(
+            obj.parse_section(fake_section(
+                BpfSectionKind::Program,
+                "usdt/foo",
+                bytes_of(&fake_ins())
+            )),
+            Ok(())
+        );
+        assert_matches!(
+            obj.programs.get("foo"),
+            Some(Program {
+                section: ProgramSection::Usdt { .. },
+                ..
+            })
+        );
+    }
+
     #[test]
     fn test_parse_section_trace_point() {
         let mut obj = fake_obj();
@@ -1868,6 +2324,110 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_section_xdp_devmap() {
+        let mut obj = fake_obj();
+
+        assert_matches!(
+            obj.parse_section(fake_section(
+                BpfSectionKind::Program,
+                "xdp/devmap/foo",
+                bytes_of(&fake_ins())
+            )),
+            Ok(())
+        );
+        assert_matches!(
+            obj.programs.get("foo"),
+            Some(Program {
+                section: ProgramSection::Xdp {
+                    frags_supported: false,
+                    attach_type: XdpAttachType::DevMap,
+                    ..
+                },
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_section_xdp_devmap_bare() {
+        let mut obj = fake_obj();
+
+        for (section, frags) in [("xdp/devmap", false), ("xdp.frags/devmap", true)] {
+            assert_matches!(
+                obj.parse_section(fake_section(
+                    BpfSectionKind::Program,
+                    section,
+                    bytes_of(&fake_ins())
+                )),
+                Ok(())
+            );
+            assert_matches!(
+                obj.programs.get("devmap"),
+                Some(Program {
+                    section: ProgramSection::Xdp {
+                        frags_supported,
+                        attach_type: XdpAttachType::DevMap,
+                        ..
+                    },
+                    ..
+                }) if *frags_supported == frags
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_section_xdp_frags_cpumap() {
+        let mut obj = fake_obj();
+
+        assert_matches!(
+            obj.parse_section(fake_section(
+                BpfSectionKind::Program,
+                "xdp.frags/cpumap/foo",
+                bytes_of(&fake_ins())
+            )),
+            Ok(())
+        );
+        assert_matches!(
+            obj.programs.get("foo"),
+            Some(Program {
+                section: ProgramSection::Xdp {
+                    frags_supported: true,
+                    attach_type: XdpAttachType::CpuMap,
+                    ..
+                },
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_section_xdp_cpumap_bare() {
+        let mut obj = fake_obj();
+
+        for (section, frags) in [("xdp/cpumap", false), ("xdp.frags/cpumap", true)] {
+            assert_matches!(
+                obj.parse_section(fake_section(
+                    BpfSectionKind::Program,
+                    section,
+                    bytes_of(&fake_ins())
+                )),
+                Ok(())
+            );
+            assert_matches!(
+                obj.programs.get("cpumap"),
+                Some(Program {
+                    section: ProgramSection::Xdp {
+                        frags_supported,
+                        attach_type: XdpAttachType::CpuMap,
+                        ..
+                    },
+                    ..
+                }) if *frags_supported == frags
+            );
+        }
+    }
+
     #[test]
     fn test_parse_section_raw_tp() {
         let mut obj = fake_obj();
@@ -1926,6 +2486,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_section_struct_ops() {
+        let mut obj = fake_obj();
+
+        assert_matches!(
+            obj.parse_section(fake_section(
+                BpfSectionKind::Program,
+                "struct_ops.s/foo",
+                bytes_of(&fake_ins())
+            )),
+            Ok(())
+        );
+        assert_matches!(
+            obj.programs.get("foo"),
+            Some(Program {
+                section: ProgramSection::StructOps {
+                    sleepable: true,
+                    ..
+                },
+                ..
+            })
+        );
+    }
+
     #[test]
     fn test_parse_section_btf_tracepoint() {
         let mut obj = fake_obj();
@@ -2253,6 +2837,115 @@ mod tests {
         assert_eq!(test_data, map.data());
     }
 
+    #[test]
+    fn test_globals() {
+        use crate::btf::{DataSec, Int, IntEncoding, Typedef, Var, VarLinkage};
+
+        let mut obj = fake_obj();
+        obj.maps.insert(
+            ".data".to_string(),
+            Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_ARRAY as u32,
+                    key_size: mem::size_of::<u32>() as u32,
+                    value_size: 16,
+                    max_entries: 1,
+                    ..Default::default()
+                },
+                section_index: 1,
+                symbol_index: 0,
+                data: vec![0; 16],
+                kind: MapKind::Data,
+            }),
+        );
+        for (index, name, address) in [(1, "counter", 8), (2, "bytes", 0)] {
+            obj.symbols_by_index.insert(
+                index,
+                Symbol {
+                    index,
+                    section_index: Some(1),
+                    name: Some(name.to_string()),
+                    address,
+                    size: 8,
+                    is_definition: true,
+                    kind: SymbolKind::Data,
+                },
+            );
+        }
+        // not in a global data section
+        fake_sym(&mut obj, 0, 0, "map_1", 0);
+
+        let globals = obj.globals();
+        assert_eq!(globals.len(), 2);
+        assert_matches!(
+            globals.get("counter"),
+            Some(GlobalVariable {
+                map_name,
+                offset: 8,
+                size: 8,
+                btf_size: None,
+                btf_kind: None,
+            }) if map_name == ".data"
+        );
+
+        // typedef long long s64; s64 counter; unsigned char bytes[8];
+        let mut btf = Btf::new();
+        let name_offset = btf.add_string("long long".to_string());
+        let s64 = btf.add_type(BtfType::Int(Int::new(
+            name_offset,
+            8,
+            IntEncoding::Signed,
+            0,
+        )));
+        let name_offset = btf.add_string("s64".to_string());
+        let s64 = btf.add_type(BtfType::Typedef(Typedef::new(name_offset, s64)));
+        let name_offset = btf.add_string("unsigned char".to_string());
+        let u8_type = btf.add_type(BtfType::Int(Int::new(name_offset, 1, IntEncoding::None, 0)));
+        let bytes = btf.add_type(BtfType::Array(Array::new(0, u8_type, u8_type, 8)));
+        let name_offset = btf.add_string("counter".to_string());
+        let counter_var =
+            btf.add_type(BtfType::Var(Var::new(name_offset, s64, VarLinkage::Global)));
+        let name_offset = btf.add_string("bytes".to_string());
+        let bytes_var = btf.add_type(BtfType::Var(Var::new(
+            name_offset,
+            bytes,
+            VarLinkage::Global,
+        )));
+        let name_offset = btf.add_string(".data".to_string());
+        let entries = vec![
+            DataSecEntry {
+                btf_type: bytes_var,
+                offset: 0,
+                size: 8,
+            },
+            DataSecEntry {
+                btf_type: counter_var,
+                offset: 8,
+                size: 8,
+            },
+        ];
+        btf.add_type(BtfType::DataSec(DataSec::new(name_offset, entries, 16)));
+        obj.btf = Some(btf);
+
+        let globals = obj.globals();
+        assert_matches!(
+            globals.get("counter"),
+            Some(GlobalVariable {
+                btf_size: Some(8),
+                btf_kind: Some(GlobalKind::Int { signed: true }),
+                ..
+            })
+        );
+        assert_matches!(
+            globals.get("bytes"),
+            Some(GlobalVariable {
+                btf_size: Some(8),
+                btf_kind: Some(GlobalKind::Array),
+                ..
+            })
+        );
+    }
+
     #[test]
     fn test_parse_btf_map_section() {
         let mut obj = fake_obj();
@@ -2349,4 +3042,196 @@ mod tests {
             panic!("expected a BTF map")
         }
     }
+
+    #[test]
+    fn test_parse_btf_map_in_map_section() {
+        use crate::{
+            btf::{BtfMember, DataSec, Int, IntEncoding, Ptr, Var, VarLinkage},
+            generated::bpf_map_type::BPF_MAP_TYPE_ARRAY_OF_MAPS,
+        };
+
+        let mut obj = fake_obj();
+        // struct inner { __uint(type, BPF_MAP_TYPE_ARRAY); __type(key, int); __type(value, int);
+        //                __uint(max_entries, 1); } inner SEC(".maps");
+        // struct { __uint(type, BPF_MAP_TYPE_ARRAY_OF_MAPS); __type(key, int);
+        //          __uint(max_entries, 4); __array(values, struct inner); } outer SEC(".maps") = {
+        //     .values = { [1] = &inner },
+        // };
+        let mut btf = Btf::new();
+        let name_offset = btf.add_string("int".to_string());
+        let int_type = btf.add_type(BtfType::Int(Int::new(
+            name_offset,
+            4,
+            IntEncoding::Signed,
+            0,
+        )));
+        let int_ptr = btf.add_type(BtfType::Ptr(Ptr::new(0, int_type)));
+        let field = |btf: &mut Btf, value: u32| {
+            let array = btf.add_type(BtfType::Array(Array::new(0, int_type, int_type, value)));
+            btf.add_type(BtfType::Ptr(Ptr::new(0, array)))
+        };
+        let member = |btf: &mut Btf, name: &str, btf_type: u32, offset: u32| BtfMember {
+            name_offset: btf.add_string(name.to_string()),
+            btf_type,
+            offset,
+        };
+
+        let map_type = field(&mut btf, BPF_MAP_TYPE_ARRAY as u32);
+        let max_entries = field(&mut btf, 1);
+        let members = vec![
+            member(&mut btf, "type", map_type, 0),
+            member(&mut btf, "key", int_ptr, 64),
+            member(&mut btf, "value", int_ptr, 128),
+            member(&mut btf, "max_entries", max_entries, 192),
+        ];
+        let name_offset = btf.add_string("inner".to_string());
+        let inner_struct = btf.add_type(BtfType::Struct(Struct::new(name_offset, members, 32)));
+        let inner_ptr = btf.add_type(BtfType::Ptr(Ptr::new(0, inner_struct)));
+        let values = btf.add_type(BtfType::Array(Array::new(0, inner_ptr, int_type, 0)));
+        let map_type = field(&mut btf, BPF_MAP_TYPE_ARRAY_OF_MAPS as u32);
+        let max_entries = field(&mut btf, 4);
+        let members = vec![
+            member(&mut btf, "type", map_type, 0),
+            member(&mut btf, "key", int_ptr, 64),
+            member(&mut btf, "max_entries", max_entries, 128),
+            member(&mut btf, "values", values, 192),
+        ];
+        let outer_struct = btf.add_type(BtfType::Struct(Struct::new(0, members, 24)));
+
+        let name_offset = btf.add_string("inner".to_string());
+        let inner_var = btf.add_type(BtfType::Var(Var::new(
+            name_offset,
+            inner_struct,
+            VarLinkage::Global,
+        )));
+        let name_offset = btf.add_string("outer".to_string());
+        let outer_var = btf.add_type(BtfType::Var(Var::new(
+            name_offset,
+            outer_struct,
+            VarLinkage::Global,
+        )));
+        let name_offset = btf.add_string(".maps".to_string());
+        let entries = vec![
+            DataSecEntry {
+                btf_type: inner_var,
+                offset: 0,
+                size: 32,
+            },
+            DataSecEntry {
+                btf_type: outer_var,
+                offset: 32,
+                size: 40,
+            },
+        ];
+        btf.add_type(BtfType::DataSec(DataSec::new(name_offset, entries, 72)));
+        obj.btf = Some(btf);
+
+        fake_sym(&mut obj, 0, 0, "inner", 32);
+        fake_sym(&mut obj, 0, 32, "outer", 40);
+        let mut map_section = fake_section(BpfSectionKind::BtfMaps, ".maps", &[]);
+        // the relocation of values[1]
+        map_section.relocations = vec![Relocation {
+            offset: 32 + 24 + 8,
+            symbol_index: 1,
+        }];
+        obj.parse_section(map_section).unwrap();
+
+        let outer = obj.maps.get("outer").unwrap();
+        assert_eq!(outer.map_type(), BPF_MAP_TYPE_ARRAY_OF_MAPS as u32);
+        assert_eq!(outer.key_size(), 4);
+        assert_eq!(outer.value_size(), 4);
+        assert_eq!(outer.max_entries(), 4);
+        assert_eq!(outer.initial_slots(), &[(1, "inner".to_string())]);
+
+        let inner = outer.inner().unwrap();
+        assert_eq!(inner.map_type(), BPF_MAP_TYPE_ARRAY as u32);
+        assert_eq!(inner.key_size(), 4);
+        assert_eq!(inner.value_size(), 4);
+        assert_eq!(inner.max_entries(), 1);
+
+        let inner = obj.maps.get("inner").unwrap();
+        assert!(inner.inner().is_none());
+        assert!(inner.initial_slots().is_empty());
+    }
+
+    #[test]
+    fn test_parse_struct_ops_section() {
+        use crate::{
+            btf::{BtfMember, DataSec, FuncProto, Int, IntEncoding, Ptr, Var, VarLinkage},
+            generated::bpf_map_type::BPF_MAP_TYPE_STRUCT_OPS,
+        };
+
+        let mut obj = fake_obj();
+        // struct foo_ops { int (*init)(void); int flags; };
+        // SEC(".struct_ops.link") struct foo_ops ops = { .init = (void *)init, .flags = 1 };
+        let mut btf = Btf::new();
+        let name_offset = btf.add_string("int".to_string());
+        let int_type = btf.add_type(BtfType::Int(Int::new(
+            name_offset,
+            4,
+            IntEncoding::Signed,
+            0,
+        )));
+        let proto = btf.add_type(BtfType::FuncProto(FuncProto::new(Vec::new(), int_type)));
+        let func_ptr = btf.add_type(BtfType::Ptr(Ptr::new(0, proto)));
+        let members = vec![
+            BtfMember {
+                name_offset: btf.add_string("init".to_string()),
+                btf_type: func_ptr,
+                offset: 0,
+            },
+            BtfMember {
+                name_offset: btf.add_string("flags".to_string()),
+                btf_type: int_type,
+                offset: 64,
+            },
+        ];
+        let name_offset = btf.add_string("foo_ops".to_string());
+        let ops_struct = btf.add_type(BtfType::Struct(Struct::new(name_offset, members, 16)));
+        let name_offset = btf.add_string("ops".to_string());
+        let ops_var = btf.add_type(BtfType::Var(Var::new(
+            name_offset,
+            ops_struct,
+            VarLinkage::Global,
+        )));
+        let name_offset = btf.add_string(".struct_ops.link".to_string());
+        let entries = vec![DataSecEntry {
+            btf_type: ops_var,
+            offset: 0,
+            size: 16,
+        }];
+        btf.add_type(BtfType::DataSec(DataSec::new(name_offset, entries, 16)));
+        obj.btf = Some(btf);
+
+        let ins = fake_ins();
+        let mut program_section =
+            fake_section(BpfSectionKind::Program, "struct_ops/init", bytes_of(&ins));
+        program_section.index = SectionIndex(1);
+        obj.parse_section(program_section).unwrap();
+
+        fake_sym(&mut obj, 1, 0, "init", 8);
+        fake_sym(&mut obj, 2, 0, "ops", 16);
+        let data = [0u8, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+        let mut ops_section = fake_section(BpfSectionKind::StructOps, ".struct_ops.link", &data);
+        ops_section.index = SectionIndex(2);
+        ops_section.relocations = vec![Relocation {
+            offset: 0,
+            symbol_index: 1,
+        }];
+        obj.parse_section(ops_section).unwrap();
+
+        let map = obj.maps.get("ops").unwrap();
+        assert_eq!(map.map_type(), BPF_MAP_TYPE_STRUCT_OPS as u32);
+        assert_eq!(map.kind(), MapKind::StructOps);
+        assert_eq!(map.key_size(), 4);
+        assert_eq!(map.value_size(), 16);
+        assert_eq!(map.map_flags(), BPF_F_LINK);
+        assert_eq!(map.data(), &data);
+        let struct_ops = map.struct_ops().unwrap();
+        assert_eq!(struct_ops.type_name, "foo_ops");
+        assert_eq!(
+            struct_ops.programs,
+            vec![("init".to_string(), "init".to_string())]
+        );
+    }
 }
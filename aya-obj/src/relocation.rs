@@ -517,6 +517,9 @@ mod test {
             symbol_index,
             data: Vec::new(),
             kind: MapKind::Other,
+            inner_def: None,
+            initial_slots: Vec::new(),
+            struct_ops: None,
         })
     }
 
@@ -0,0 +1,386 @@
+//! `struct_ops` maps handling.
+//!
+//! A `struct_ops` map holds an implementation of a kernel struct of function pointers, eg
+//! `tcp_congestion_ops`. The struct is declared as a global variable in a `.struct_ops` section,
+//! and its function pointer members point to `struct_ops` programs.
+//!
+//! The layout of the struct in the object file can differ from the layout the kernel expects, so
+//! before the map can be created the value has to be rebuilt against the kernel BTF with
+//! [`Object::relocate_struct_ops`].
+
+use alloc::{
+    borrow::ToOwned,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::mem;
+
+use crate::{
+    btf::{Btf, BtfError, BtfKind, BtfType, Struct},
+    maps::Map,
+    thiserror::{self, Error},
+    Object,
+};
+
+/// The `BPF_F_LINK` map flag, missing from the generated bindings. `struct_ops` maps created
+/// with it are registered by creating a link instead of updating their value.
+pub(crate) const BPF_F_LINK: u32 = 1 << 13;
+
+// The kernel value of a `struct_ops` map is a `struct bpf_struct_ops_<name>` wrapping the
+// implemented struct in its `data` member.
+const STRUCT_OPS_VALUE_PREFIX: &str = "bpf_struct_ops_";
+const STRUCT_OPS_VALUE_DATA: &str = "data";
+
+/// The error type returned by [`Object::relocate_struct_ops`]
+#[derive(Error, Debug)]
+pub enum StructOpsRelocationError {
+    /// The kernel doesn't support the struct implemented by the map
+    #[error("struct `{type_name}` is not a struct_ops type of the running kernel")]
+    UnknownType {
+        /// The name of the struct
+        type_name: String,
+    },
+
+    /// A member set by the map doesn't exist in the kernel struct
+    #[error("map `{map}`: member `{member}` is not a member of the kernel `{type_name}`")]
+    UnknownMember {
+        /// The map name
+        map: String,
+        /// The name of the struct
+        type_name: String,
+        /// The member name
+        member: String,
+    },
+
+    /// A member of the map doesn't match the type of the kernel member
+    #[error("map `{map}`: member `{member}` is incompatible with the kernel definition")]
+    IncompatibleMember {
+        /// The map name
+        map: String,
+        /// The member name
+        member: String,
+    },
+
+    /// Bitfield members are not supported
+    #[error("map `{map}`: bitfield member `{member}` is not supported")]
+    BitfieldMember {
+        /// The map name
+        map: String,
+        /// The member name
+        member: String,
+    },
+
+    /// Error reading the kernel BTF
+    #[error(transparent)]
+    BtfError(#[from] BtfError),
+}
+
+/// The `struct_ops` definition of a map declared in a `.struct_ops` section
+#[derive(Debug, Clone)]
+pub struct StructOps {
+    /// The name of the implemented kernel struct, eg `tcp_congestion_ops`
+    pub type_name: String,
+    /// The programs set as function pointer members, as `(member name, program name)` pairs
+    pub programs: Vec<(String, String)>,
+    /// The layout of the map value in the running kernel, set by
+    /// [`Object::relocate_struct_ops`]
+    pub layout: Option<StructOpsLayout>,
+    /// The BTF type id of the struct in the object BTF
+    pub(crate) type_id: u32,
+}
+
+/// The layout of the value of a `struct_ops` map in the running kernel
+#[derive(Debug, Clone)]
+pub struct StructOpsLayout {
+    /// The kernel BTF type id of the implemented struct
+    pub type_id: u32,
+    /// The kernel BTF type id of the `struct bpf_struct_ops_<name>` map value
+    pub value_type_id: u32,
+    /// The programs to set in the value
+    pub programs: Vec<StructOpsProgram>,
+}
+
+/// A program set as a function pointer member of a `struct_ops` map value
+#[derive(Debug, Clone)]
+pub struct StructOpsProgram {
+    /// The program name
+    pub name: String,
+    /// The index of the member in the kernel struct, used as the expected attach type of the
+    /// program
+    pub member_index: u32,
+    /// The byte offset of the member in the map value, where the program fd is written
+    pub offset: usize,
+}
+
+impl Object {
+    /// Rebuilds the values of the `struct_ops` maps against the kernel BTF
+    ///
+    /// The members are matched by name. Function pointer members are left zeroed, and their
+    /// programs are recorded in the [`StructOpsLayout`] of the map so that their fds can be
+    /// written when the map is registered.
+    pub fn relocate_struct_ops(
+        &mut self,
+        target_btf: &Btf,
+    ) -> Result<(), StructOpsRelocationError> {
+        let local_btf = match &self.btf {
+            Some(btf) => btf,
+            None => return Ok(()),
+        };
+
+        for (name, map) in self.maps.iter_mut() {
+            let map = match map {
+                Map::Btf(map) => map,
+                Map::Legacy(_) => continue,
+            };
+            let struct_ops = match &mut map.struct_ops {
+                Some(struct_ops) if struct_ops.layout.is_none() => struct_ops,
+                _ => continue,
+            };
+            let (value, layout) =
+                struct_ops_value(local_btf, target_btf, name, struct_ops, &map.data)?;
+            map.def.value_size = value.len() as u32;
+            map.data = value;
+            struct_ops.layout = Some(layout);
+        }
+
+        Ok(())
+    }
+}
+
+fn struct_ops_value(
+    local_btf: &Btf,
+    target_btf: &Btf,
+    map: &str,
+    struct_ops: &StructOps,
+    data: &[u8],
+) -> Result<(Vec<u8>, StructOpsLayout), StructOpsRelocationError> {
+    let local = btf_struct(local_btf, struct_ops.type_id)?;
+
+    let unknown_type = || StructOpsRelocationError::UnknownType {
+        type_name: struct_ops.type_name.clone(),
+    };
+    let value_type_id = target_btf
+        .id_by_type_name_kind(
+            &format!("{STRUCT_OPS_VALUE_PREFIX}{}", struct_ops.type_name),
+            BtfKind::Struct,
+        )
+        .map_err(|_| unknown_type())?;
+    let wrapper = btf_struct(target_btf, value_type_id)?;
+    let data_member = wrapper
+        .members
+        .iter()
+        .find(|m| {
+            target_btf
+                .string_at(m.name_offset)
+                .map(|name| name == STRUCT_OPS_VALUE_DATA)
+                .unwrap_or(false)
+        })
+        .ok_or_else(unknown_type)?;
+    let data_offset = wrapper.member_bit_offset(data_member) / 8;
+    let type_id = target_btf.resolve_type(data_member.btf_type)?;
+    let kernel = btf_struct(target_btf, type_id)?;
+
+    let mut value = vec![0u8; wrapper.size as usize];
+    let mut programs = Vec::new();
+    for member in &local.members {
+        let member_name = local_btf.string_at(member.name_offset)?;
+        let program = struct_ops
+            .programs
+            .iter()
+            .find(|(m, _)| *m == member_name)
+            .map(|(_, program)| program);
+        let local_offset = local.member_bit_offset(member) / 8;
+        let local_size = if program.is_some() {
+            mem::size_of::<u64>()
+        } else {
+            local_btf.type_size(member.btf_type)?
+        };
+        let local_data = data
+            .get(local_offset..local_offset + local_size)
+            .ok_or_else(|| StructOpsRelocationError::IncompatibleMember {
+                map: map.to_owned(),
+                member: member_name.to_string(),
+            })?;
+
+        let kernel_member = kernel.members.iter().enumerate().find(|(_, m)| {
+            target_btf
+                .string_at(m.name_offset)
+                .map(|name| name == member_name)
+                .unwrap_or(false)
+        });
+        let (member_index, kernel_member) = match kernel_member {
+            Some(kernel_member) => kernel_member,
+            // members the kernel doesn't know about can be left out as long as they're unset
+            None if program.is_none() && local_data.iter().all(|b| *b == 0) => continue,
+            None => {
+                return Err(StructOpsRelocationError::UnknownMember {
+                    map: map.to_owned(),
+                    type_name: struct_ops.type_name.clone(),
+                    member: member_name.to_string(),
+                })
+            }
+        };
+
+        if local.member_bit_field_size(member) != 0
+            || kernel.member_bit_field_size(kernel_member) != 0
+        {
+            return Err(StructOpsRelocationError::BitfieldMember {
+                map: map.to_owned(),
+                member: member_name.to_string(),
+            });
+        }
+
+        let offset = data_offset + kernel.member_bit_offset(kernel_member) / 8;
+        let incompatible = || StructOpsRelocationError::IncompatibleMember {
+            map: map.to_owned(),
+            member: member_name.to_string(),
+        };
+        if is_func_ptr(target_btf, kernel_member.btf_type)? {
+            // the program fds are written when the map is registered
+            if let Some(program) = program {
+                programs.push(StructOpsProgram {
+                    name: program.clone(),
+                    member_index: member_index as u32,
+                    offset,
+                });
+            } else if local_data.iter().any(|b| *b != 0) {
+                return Err(incompatible());
+            }
+            continue;
+        }
+        if program.is_some() || target_btf.type_size(kernel_member.btf_type)? != local_size {
+            return Err(incompatible());
+        }
+        value
+            .get_mut(offset..offset + local_size)
+            .ok_or_else(incompatible)?
+            .copy_from_slice(local_data);
+    }
+
+    Ok((
+        value,
+        StructOpsLayout {
+            type_id,
+            value_type_id,
+            programs,
+        },
+    ))
+}
+
+fn btf_struct(btf: &Btf, type_id: u32) -> Result<&Struct, BtfError> {
+    match btf.type_by_id(btf.resolve_type(type_id)?)? {
+        BtfType::Struct(s) => Ok(s),
+        _ => Err(BtfError::UnexpectedBtfType { type_id }),
+    }
+}
+
+fn is_func_ptr(btf: &Btf, type_id: u32) -> Result<bool, BtfError> {
+    match btf.type_by_id(btf.resolve_type(type_id)?)? {
+        BtfType::Ptr(ptr) => Ok(matches!(
+            btf.type_by_id(btf.resolve_type(ptr.btf_type)?)?,
+            BtfType::FuncProto(_)
+        )),
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    use super::*;
+    use crate::btf::{BtfMember, FuncProto, Int, IntEncoding, Ptr};
+
+    fn member(btf: &mut Btf, name: &str, btf_type: u32, offset: u32) -> BtfMember {
+        BtfMember {
+            name_offset: btf.add_string(name.to_string()),
+            btf_type,
+            offset,
+        }
+    }
+
+    // Adds the `int` and `int (*)(void)` types
+    fn base_types(btf: &mut Btf) -> (u32, u32) {
+        let name_offset = btf.add_string("int".to_string());
+        let int_type = btf.add_type(BtfType::Int(Int::new(
+            name_offset,
+            4,
+            IntEncoding::Signed,
+            0,
+        )));
+        let proto = btf.add_type(BtfType::FuncProto(FuncProto::new(Vec::new(), int_type)));
+        let func_ptr = btf.add_type(BtfType::Ptr(Ptr::new(0, proto)));
+        (int_type, func_ptr)
+    }
+
+    #[test]
+    fn test_struct_ops_value() {
+        // struct foo_ops { int (*init)(void); int flags; int unused; };
+        let mut local_btf = Btf::new();
+        let (int_type, func_ptr) = base_types(&mut local_btf);
+        let members = vec![
+            member(&mut local_btf, "init", func_ptr, 0),
+            member(&mut local_btf, "flags", int_type, 64),
+            member(&mut local_btf, "unused", int_type, 96),
+        ];
+        let name_offset = local_btf.add_string("foo_ops".to_string());
+        let local_type = local_btf.add_type(BtfType::Struct(Struct::new(name_offset, members, 16)));
+
+        // struct foo_ops { int flags; int (*release)(void); int (*init)(void); };
+        // struct bpf_struct_ops_foo_ops { int refcnt; int state; struct foo_ops data; };
+        let mut target_btf = Btf::new();
+        let (int_type, func_ptr) = base_types(&mut target_btf);
+        let members = vec![
+            member(&mut target_btf, "flags", int_type, 0),
+            member(&mut target_btf, "release", func_ptr, 64),
+            member(&mut target_btf, "init", func_ptr, 128),
+        ];
+        let name_offset = target_btf.add_string("foo_ops".to_string());
+        let kernel_type =
+            target_btf.add_type(BtfType::Struct(Struct::new(name_offset, members, 24)));
+        let members = vec![
+            member(&mut target_btf, "refcnt", int_type, 0),
+            member(&mut target_btf, "state", int_type, 32),
+            member(&mut target_btf, "data", kernel_type, 64),
+        ];
+        let name_offset = target_btf.add_string("bpf_struct_ops_foo_ops".to_string());
+        let value_type =
+            target_btf.add_type(BtfType::Struct(Struct::new(name_offset, members, 32)));
+
+        let mut struct_ops = StructOps {
+            type_name: "foo_ops".to_string(),
+            programs: vec![("init".to_string(), "my_init".to_string())],
+            layout: None,
+            type_id: local_type,
+        };
+        let data = [0u8, 0, 0, 0, 0, 0, 0, 0, 42, 0, 0, 0, 0, 0, 0, 0];
+        let (value, layout) =
+            struct_ops_value(&local_btf, &target_btf, "ops", &struct_ops, &data).unwrap();
+
+        let mut expected = vec![0u8; 32];
+        expected[8] = 42;
+        assert_eq!(value, expected);
+        assert_eq!(layout.type_id, kernel_type);
+        assert_eq!(layout.value_type_id, value_type);
+        assert_eq!(layout.programs.len(), 1);
+        assert_eq!(layout.programs[0].name, "my_init");
+        assert_eq!(layout.programs[0].member_index, 2);
+        assert_eq!(layout.programs[0].offset, 24);
+
+        // setting a member the kernel doesn't know about fails
+        let mut data = data;
+        data[12] = 1;
+        assert!(matches!(
+            struct_ops_value(&local_btf, &target_btf, "ops", &struct_ops, &data),
+            Err(StructOpsRelocationError::UnknownMember { member, .. }) if member == "unused"
+        ));
+
+        struct_ops.type_name = "bar_ops".to_string();
+        assert!(matches!(
+            struct_ops_value(&local_btf, &target_btf, "ops", &struct_ops, &data),
+            Err(StructOpsRelocationError::UnknownType { type_name }) if type_name == "bar_ops"
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+//! AF_XDP definitions from `linux/if_xdp.h`.
+//!
+//! libc only provides them in recent versions, and only for glibc targets.
+#![allow(non_camel_case_types)]
+
+use libc::{c_int, c_ulonglong, off_t};
+
+pub(crate) const AF_XDP: c_int = 44;
+pub(crate) const SOL_XDP: c_int = 283;
+
+// sxdp_flags
+pub(crate) const XDP_COPY: u16 = 1 << 1;
+pub(crate) const XDP_ZEROCOPY: u16 = 1 << 2;
+pub(crate) const XDP_USE_NEED_WAKEUP: u16 = 1 << 3;
+
+// xdp_ring_offset flags
+pub(crate) const XDP_RING_NEED_WAKEUP: u32 = 1 << 0;
+
+// socket options
+pub(crate) const XDP_MMAP_OFFSETS: c_int = 1;
+pub(crate) const XDP_RX_RING: c_int = 2;
+pub(crate) const XDP_TX_RING: c_int = 3;
+pub(crate) const XDP_UMEM_REG: c_int = 4;
+pub(crate) const XDP_UMEM_FILL_RING: c_int = 5;
+pub(crate) const XDP_UMEM_COMPLETION_RING: c_int = 6;
+
+// mmap offsets of the rings
+pub(crate) const XDP_PGOFF_RX_RING: off_t = 0;
+pub(crate) const XDP_PGOFF_TX_RING: off_t = 0x80000000u32 as off_t;
+pub(crate) const XDP_UMEM_PGOFF_FILL_RING: c_ulonglong = 0x100000000;
+pub(crate) const XDP_UMEM_PGOFF_COMPLETION_RING: c_ulonglong = 0x180000000;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct sockaddr_xdp {
+    pub sxdp_family: u16,
+    pub sxdp_flags: u16,
+    pub sxdp_ifindex: u32,
+    pub sxdp_queue_id: u32,
+    pub sxdp_shared_umem_fd: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct xdp_ring_offset {
+    pub producer: u64,
+    pub consumer: u64,
+    pub desc: u64,
+    pub flags: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct xdp_mmap_offsets {
+    pub rx: xdp_ring_offset,
+    pub tx: xdp_ring_offset,
+    pub fr: xdp_ring_offset,
+    pub cr: xdp_ring_offset,
+}
+
+// Before 5.4, the rings had no flags.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct xdp_ring_offset_v1 {
+    pub producer: u64,
+    pub consumer: u64,
+    pub desc: u64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct xdp_mmap_offsets_v1 {
+    pub rx: xdp_ring_offset_v1,
+    pub tx: xdp_ring_offset_v1,
+    pub fr: xdp_ring_offset_v1,
+    pub cr: xdp_ring_offset_v1,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct xdp_umem_reg {
+    pub addr: u64,
+    pub len: u64,
+    pub chunk_size: u32,
+    pub headroom: u32,
+    pub flags: u32,
+    pub tx_metadata_len: u32,
+}
@@ -0,0 +1,73 @@
+//! AF_XDP sockets.
+//!
+//! AF_XDP sockets receive packets redirected by XDP programs straight into a memory area shared
+//! with user space, the [`Umem`], bypassing the rest of the network stack. An AF_XDP socket can
+//! also transmit packets written into the [`Umem`].
+//!
+//! Packets are exchanged with the kernel through four rings:
+//!
+//! - the [`FillQueue`] hands free frames over to the kernel to receive packets into;
+//! - the [`RxQueue`] hands the received packets back to user space;
+//! - the [`TxQueue`] hands packets to transmit over to the kernel;
+//! - the [`CompletionQueue`] hands the frames of the transmitted packets back to user space.
+//!
+//! To receive packets, an [`Xdp`](crate::programs::Xdp) program is attached to the interface and
+//! redirects packets to the socket bound to their receive queue, through an
+//! [`XskMap`](crate::maps::XskMap). [`Xdp::attach_to_socket`](crate::programs::Xdp::attach_to_socket)
+//! does both. See [`XskSocket`] for an example.
+mod if_xdp;
+mod ring;
+mod socket;
+mod umem;
+
+use std::io;
+
+use thiserror::Error;
+
+pub use ring::{CompletionQueue, FillQueue, RxQueue, TxQueue, XdpDesc};
+pub use socket::{XskBindFlags, XskSocket, XskSocketConfig};
+pub use umem::{Umem, UmemConfig, DEFAULT_FRAME_COUNT, DEFAULT_FRAME_SIZE, DEFAULT_RING_SIZE};
+
+/// Errors occuring from working with AF_XDP sockets.
+#[derive(Error, Debug)]
+pub enum XskError {
+    /// The frame size is invalid.
+    #[error("invalid frame size {size}, it must be a power of two between 2048 and the page size")]
+    InvalidFrameSize {
+        /// The frame size.
+        size: u32,
+    },
+
+    /// The frame count is invalid.
+    #[error("invalid frame count {count}")]
+    InvalidFrameCount {
+        /// The frame count.
+        count: u32,
+    },
+
+    /// The size of a ring is invalid.
+    #[error("invalid {name} ring size {size}, it must be a power of two")]
+    InvalidRingSize {
+        /// The name of the ring.
+        name: &'static str,
+        /// The ring size.
+        size: u32,
+    },
+
+    /// The network interface does not exist.
+    #[error("unknown network interface {name}")]
+    UnknownInterface {
+        /// The interface name.
+        name: String,
+    },
+
+    /// A syscall failed.
+    #[error("`{call}` failed")]
+    SyscallError {
+        /// The name of the syscall which failed.
+        call: String,
+        /// The [`io::Error`] returned by the syscall.
+        #[source]
+        io_error: io::Error,
+    },
+}
@@ -0,0 +1,327 @@
+//! Single producer, single consumer rings shared with the kernel.
+
+use std::{
+    cmp, mem,
+    os::unix::io::RawFd,
+    ptr::NonNull,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use libc::{off_t, MAP_POPULATE, MAP_SHARED, PROT_READ, PROT_WRITE};
+
+use crate::{
+    af_xdp::{
+        if_xdp::{xdp_ring_offset, XDP_RING_NEED_WAKEUP},
+        XskError,
+    },
+    maps::MMap,
+};
+
+/// A descriptor of a packet in the UMEM, as found in the RX and TX rings.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct XdpDesc {
+    /// Offset of the packet data in the UMEM.
+    pub addr: u64,
+    /// Length of the packet data.
+    pub len: u32,
+    /// Descriptor options.
+    pub options: u32,
+}
+
+// A ring is made of a producer index, a consumer index, a flags word and an array of descriptors,
+// at the offsets returned by the kernel. The indices wrap around and are masked to get the
+// position in the array.
+struct Ring<T> {
+    producer: NonNull<AtomicU32>,
+    consumer: NonNull<AtomicU32>,
+    flags: NonNull<AtomicU32>,
+    descs: NonNull<T>,
+    mask: u32,
+    size: u32,
+    // local copies of the producer and consumer indices, so that the shared ones are only read
+    // when needed. On producer rings `cached_cons` is offset by `size`, so that the number of free
+    // entries is `cached_cons - cached_prod`.
+    cached_prod: u32,
+    cached_cons: u32,
+    // keeps the ring mapped, `None` when the ring memory is not owned
+    _mmap: Option<MMap>,
+}
+
+impl<T: Copy> Ring<T> {
+    fn map(fd: RawFd, size: u32, off: &xdp_ring_offset, pgoff: off_t) -> Result<Self, XskError> {
+        let len = off.desc as usize + size as usize * mem::size_of::<T>();
+        let mmap = MMap::new(
+            fd,
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED | MAP_POPULATE,
+            pgoff,
+        )
+        .map_err(|io_error| XskError::SyscallError {
+            call: "mmap".to_owned(),
+            io_error,
+        })?;
+        let base = mmap.ptr().cast::<u8>();
+        Ok(unsafe { Ring::from_raw(base, off, size, Some(mmap)) })
+    }
+
+    // Safety: `base` must point to a ring laid out according to `off`, with room for `size`
+    // descriptors, that lives as long as the returned value.
+    unsafe fn from_raw(
+        base: NonNull<u8>,
+        off: &xdp_ring_offset,
+        size: u32,
+        mmap: Option<MMap>,
+    ) -> Self {
+        let at = |offset: u64| NonNull::new_unchecked(base.as_ptr().add(offset as usize));
+        let producer: NonNull<AtomicU32> = at(off.producer).cast();
+        let consumer: NonNull<AtomicU32> = at(off.consumer).cast();
+        Ring {
+            producer,
+            consumer,
+            flags: at(off.flags).cast(),
+            descs: at(off.desc).cast(),
+            mask: size - 1,
+            size,
+            cached_prod: producer.as_ref().load(Ordering::Relaxed),
+            cached_cons: consumer.as_ref().load(Ordering::Relaxed),
+            _mmap: mmap,
+        }
+    }
+
+    fn producer(&self) -> &AtomicU32 {
+        unsafe { self.producer.as_ref() }
+    }
+
+    fn consumer(&self) -> &AtomicU32 {
+        unsafe { self.consumer.as_ref() }
+    }
+
+    fn needs_wakeup(&self) -> bool {
+        unsafe { self.flags.as_ref() }.load(Ordering::Relaxed) & XDP_RING_NEED_WAKEUP != 0
+    }
+
+    fn produce(&mut self, items: &[T]) -> usize {
+        let wanted = cmp::min(items.len(), self.size as usize) as u32;
+        let mut free = self.cached_cons.wrapping_sub(self.cached_prod);
+        if free < wanted {
+            // pairs with the kernel releasing the entries it consumed
+            self.cached_cons = self
+                .consumer()
+                .load(Ordering::Acquire)
+                .wrapping_add(self.size);
+            free = self.cached_cons.wrapping_sub(self.cached_prod);
+        }
+        let n = cmp::min(free, wanted);
+        for (i, item) in items[..n as usize].iter().enumerate() {
+            let idx = self.cached_prod.wrapping_add(i as u32) & self.mask;
+            unsafe { self.descs.as_ptr().add(idx as usize).write_volatile(*item) };
+        }
+        self.cached_prod = self.cached_prod.wrapping_add(n);
+        // make the entries visible to the kernel before publishing the new producer index
+        self.producer().store(self.cached_prod, Ordering::Release);
+        n as usize
+    }
+
+    fn consume(&mut self, items: &mut [T]) -> usize {
+        let wanted = cmp::min(items.len(), self.size as usize) as u32;
+        let mut available = self.cached_prod.wrapping_sub(self.cached_cons);
+        if available < wanted {
+            // pairs with the kernel releasing the entries it produced
+            self.cached_prod = self.producer().load(Ordering::Acquire);
+            available = self.cached_prod.wrapping_sub(self.cached_cons);
+        }
+        let n = cmp::min(available, wanted);
+        for (i, item) in items[..n as usize].iter_mut().enumerate() {
+            let idx = self.cached_cons.wrapping_add(i as u32) & self.mask;
+            *item = unsafe { self.descs.as_ptr().add(idx as usize).read_volatile() };
+        }
+        self.cached_cons = self.cached_cons.wrapping_add(n);
+        // the entries have been copied out, hand them back to the kernel
+        self.consumer().store(self.cached_cons, Ordering::Release);
+        n as usize
+    }
+}
+
+macro_rules! producer_ring {
+    ($(#[$doc:meta])* $name:ident, $item:ty) => {
+        $(#[$doc])*
+        pub struct $name(Ring<$item>);
+
+        impl $name {
+            pub(crate) fn map(
+                fd: RawFd,
+                size: u32,
+                off: &xdp_ring_offset,
+                pgoff: off_t,
+            ) -> Result<Self, XskError> {
+                let mut ring = Ring::map(fd, size, off, pgoff)?;
+                ring.cached_cons = ring.cached_cons.wrapping_add(size);
+                Ok($name(ring))
+            }
+
+            /// Returns the number of entries in the ring.
+            pub fn size(&self) -> u32 {
+                self.0.size
+            }
+
+            /// Returns `true` if the kernel must be woken up to process the ring.
+            ///
+            /// This is only meaningful if the socket was bound with
+            /// [`XskBindFlags::USE_NEED_WAKEUP`](crate::af_xdp::XskBindFlags::USE_NEED_WAKEUP).
+            pub fn needs_wakeup(&self) -> bool {
+                self.0.needs_wakeup()
+            }
+
+            /// Hands entries over to the kernel.
+            ///
+            /// Returns the number of entries that were produced, which is lower than
+            /// `items.len()` if the ring is full.
+            pub fn produce(&mut self, items: &[$item]) -> usize {
+                self.0.produce(items)
+            }
+        }
+    };
+}
+
+macro_rules! consumer_ring {
+    ($(#[$doc:meta])* $name:ident, $item:ty) => {
+        $(#[$doc])*
+        pub struct $name(Ring<$item>);
+
+        impl $name {
+            pub(crate) fn map(
+                fd: RawFd,
+                size: u32,
+                off: &xdp_ring_offset,
+                pgoff: off_t,
+            ) -> Result<Self, XskError> {
+                Ok($name(Ring::map(fd, size, off, pgoff)?))
+            }
+
+            /// Returns the number of entries in the ring.
+            pub fn size(&self) -> u32 {
+                self.0.size
+            }
+
+            /// Takes entries produced by the kernel out of the ring.
+            ///
+            /// Returns the number of entries copied into `items`.
+            pub fn consume(&mut self, items: &mut [$item]) -> usize {
+                self.0.consume(items)
+            }
+        }
+    };
+}
+
+producer_ring!(
+    /// The fill ring, used to hand UMEM frames over to the kernel to receive packets into.
+    ///
+    /// The entries are the addresses of the frames in the UMEM.
+    FillQueue,
+    u64
+);
+
+consumer_ring!(
+    /// The completion ring, through which the kernel hands back the UMEM frames of the packets that
+    /// were transmitted.
+    ///
+    /// The entries are the addresses of the frames in the UMEM.
+    CompletionQueue,
+    u64
+);
+
+consumer_ring!(
+    /// The RX ring, through which the kernel hands over the received packets.
+    RxQueue,
+    XdpDesc
+);
+
+producer_ring!(
+    /// The TX ring, used to hand packets over to the kernel for transmission.
+    TxQueue,
+    XdpDesc
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: u32 = 4;
+
+    #[repr(C, align(8))]
+    struct FakeRing {
+        producer: u32,
+        consumer: u32,
+        flags: u32,
+        pad: u32,
+        descs: [u64; SIZE as usize],
+    }
+
+    fn fake_ring() -> Box<FakeRing> {
+        Box::new(FakeRing {
+            producer: 0,
+            consumer: 0,
+            flags: 0,
+            pad: 0,
+            descs: [0; SIZE as usize],
+        })
+    }
+
+    fn ring(buf: &mut FakeRing) -> Ring<u64> {
+        let off = xdp_ring_offset {
+            producer: 0,
+            consumer: 4,
+            flags: 8,
+            desc: 16,
+        };
+        unsafe { Ring::from_raw(NonNull::from(buf).cast(), &off, SIZE, None) }
+    }
+
+    #[test]
+    fn test_produce() {
+        let mut buf = fake_ring();
+        let mut ring = ring(&mut buf);
+        ring.cached_cons = ring.cached_cons.wrapping_add(SIZE);
+
+        assert_eq!(ring.produce(&[1, 2, 3]), 3);
+        // only one free entry left
+        assert_eq!(ring.produce(&[4, 5]), 1);
+        assert_eq!(ring.produce(&[6]), 0);
+        assert_eq!(ring.producer().load(Ordering::Relaxed), 4);
+
+        // the kernel consumed two entries
+        ring.consumer().store(2, Ordering::Relaxed);
+        assert_eq!(ring.produce(&[6, 7, 8]), 2);
+        assert_eq!(ring.producer().load(Ordering::Relaxed), 6);
+        drop(ring);
+        assert_eq!(buf.descs, [6, 7, 3, 4]);
+    }
+
+    #[test]
+    fn test_consume() {
+        let mut buf = fake_ring();
+        buf.descs = [10, 20, 30, 40];
+        let mut ring = ring(&mut buf);
+        let mut items = [0u64; 8];
+
+        assert_eq!(ring.consume(&mut items), 0);
+
+        // the kernel produced three entries
+        ring.producer().store(3, Ordering::Relaxed);
+        assert_eq!(ring.consume(&mut items[..2]), 2);
+        assert_eq!(items[..2], [10, 20]);
+        assert_eq!(ring.consume(&mut items), 1);
+        assert_eq!(items[0], 30);
+        assert_eq!(ring.consumer().load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_needs_wakeup() {
+        let mut buf = fake_ring();
+        buf.flags = XDP_RING_NEED_WAKEUP;
+        let ring = ring(&mut buf);
+        assert!(ring.needs_wakeup());
+    }
+}
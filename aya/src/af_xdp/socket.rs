@@ -0,0 +1,386 @@
+//! AF_XDP sockets.
+
+use std::{
+    ffi::CString,
+    io, mem,
+    os::unix::io::{AsRawFd, RawFd},
+    ptr,
+};
+
+use libc::{
+    bind, c_void, close, getsockopt, if_nametoindex, off_t, recvfrom, sendto, setsockopt, sockaddr,
+    socket, socklen_t, EAGAIN, EBUSY, ENETDOWN, ENOBUFS, MSG_DONTWAIT, SOCK_CLOEXEC, SOCK_RAW,
+};
+
+use crate::af_xdp::{
+    if_xdp::{
+        sockaddr_xdp, xdp_mmap_offsets, xdp_mmap_offsets_v1, xdp_ring_offset, xdp_ring_offset_v1,
+        xdp_umem_reg, AF_XDP, SOL_XDP, XDP_COPY, XDP_MMAP_OFFSETS, XDP_PGOFF_RX_RING,
+        XDP_PGOFF_TX_RING, XDP_RX_RING, XDP_TX_RING, XDP_UMEM_COMPLETION_RING, XDP_UMEM_FILL_RING,
+        XDP_UMEM_PGOFF_COMPLETION_RING, XDP_UMEM_PGOFF_FILL_RING, XDP_UMEM_REG,
+        XDP_USE_NEED_WAKEUP, XDP_ZEROCOPY,
+    },
+    umem::{check_ring_size, DEFAULT_RING_SIZE},
+    CompletionQueue, FillQueue, RxQueue, TxQueue, Umem, XskError,
+};
+
+bitflags! {
+    /// Flags passed when binding an [`XskSocket`].
+    #[derive(Default)]
+    pub struct XskBindFlags: u16 {
+        /// Force copy mode.
+        const COPY = XDP_COPY;
+        /// Force zero-copy mode.
+        const ZEROCOPY = XDP_ZEROCOPY;
+        /// Only wake up the kernel when it asks for it, see
+        /// [`FillQueue::needs_wakeup`] and [`TxQueue::needs_wakeup`].
+        const USE_NEED_WAKEUP = XDP_USE_NEED_WAKEUP;
+    }
+}
+
+/// The configuration of an [`XskSocket`].
+#[derive(Copy, Clone, Debug)]
+pub struct XskSocketConfig {
+    /// Number of entries in the RX ring. Must be a power of two.
+    pub rx_size: u32,
+    /// Number of entries in the TX ring. Must be a power of two.
+    pub tx_size: u32,
+    /// Flags used to bind the socket.
+    pub bind_flags: XskBindFlags,
+}
+
+impl Default for XskSocketConfig {
+    fn default() -> Self {
+        XskSocketConfig {
+            rx_size: DEFAULT_RING_SIZE,
+            tx_size: DEFAULT_RING_SIZE,
+            bind_flags: XskBindFlags::default(),
+        }
+    }
+}
+
+/// An AF_XDP socket.
+///
+/// The socket owns its [`Umem`] and the four rings used to exchange frames with the kernel. It
+/// is bound to a single queue of a network interface, and receives the packets that an XDP
+/// program attached to the interface redirects to it through an
+/// [`XskMap`](crate::maps::XskMap).
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.18.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::{
+///     af_xdp::{Umem, UmemConfig, XdpDesc, XskSocket, XskSocketConfig},
+///     maps::XskMap,
+///     programs::{Xdp, XdpFlags},
+/// };
+///
+/// let mut sockets = XskMap::try_from(bpf.take_map("SOCKETS").unwrap())?;
+/// let umem = Umem::new(UmemConfig::default())?;
+/// let mut socket = XskSocket::new(umem, "eth0", 0, XskSocketConfig::default())?;
+///
+/// let program: &mut Xdp = bpf.program_mut("redirect_sock").unwrap().try_into()?;
+/// program.load()?;
+/// program.attach_to_socket(&socket, &mut sockets, XdpFlags::default())?;
+///
+/// // give all the frames to the kernel to receive packets into
+/// let frames = socket.umem().frame_addrs().collect::<Vec<_>>();
+/// socket.fill_queue().produce(&frames);
+///
+/// let mut descs = vec![XdpDesc::default(); 64];
+/// loop {
+///     let n = socket.rx_queue().consume(&mut descs);
+///     for desc in &descs[..n] {
+///         println!("received {} bytes", socket.umem().data(desc).len());
+///     }
+///     // recycle the frames
+///     let addrs = descs[..n].iter().map(|desc| desc.addr).collect::<Vec<_>>();
+///     socket.fill_queue().produce(&addrs);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct XskSocket {
+    // the rings are declared before the UMEM so that they're unmapped first
+    fill: FillQueue,
+    completion: CompletionQueue,
+    rx: RxQueue,
+    tx: TxQueue,
+    umem: Umem,
+    fd: RawFd,
+    if_index: u32,
+    queue_id: u32,
+}
+
+// Safety: the rings are only accessed through `&mut self`, and the kernel side of the rings is
+// synchronized with atomics.
+unsafe impl Send for XskSocket {}
+
+impl XskSocket {
+    /// Creates a socket bound to the queue `queue_id` of `interface`, using `umem` to receive and
+    /// transmit packets.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`XskError::UnknownInterface`] if `interface` does not exist, and
+    /// [`XskError::SyscallError`] if creating, configuring or binding the socket fails.
+    pub fn new(
+        umem: Umem,
+        interface: &str,
+        queue_id: u32,
+        config: XskSocketConfig,
+    ) -> Result<XskSocket, XskError> {
+        check_ring_size("rx", config.rx_size)?;
+        check_ring_size("tx", config.tx_size)?;
+
+        let c_interface = CString::new(interface).unwrap();
+        let if_index = unsafe { if_nametoindex(c_interface.as_ptr()) };
+        if if_index == 0 {
+            return Err(XskError::UnknownInterface {
+                name: interface.to_owned(),
+            });
+        }
+
+        let fd = unsafe { socket(AF_XDP, SOCK_RAW | SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(XskError::SyscallError {
+                call: "socket".to_owned(),
+                io_error: io::Error::last_os_error(),
+            });
+        }
+        // close the socket if anything below fails
+        let guard = FdGuard(fd);
+
+        let umem_config = umem.config();
+        let reg = xdp_umem_reg {
+            addr: umem.addr(),
+            len: umem.len() as u64,
+            chunk_size: umem_config.frame_size,
+            headroom: umem_config.frame_headroom,
+            ..unsafe { mem::zeroed() }
+        };
+        set_sockopt(fd, XDP_UMEM_REG, &reg)?;
+        set_sockopt(fd, XDP_UMEM_FILL_RING, &umem_config.fill_size)?;
+        set_sockopt(fd, XDP_UMEM_COMPLETION_RING, &umem_config.completion_size)?;
+        set_sockopt(fd, XDP_RX_RING, &config.rx_size)?;
+        set_sockopt(fd, XDP_TX_RING, &config.tx_size)?;
+
+        let off = mmap_offsets(fd)?;
+        let fill = FillQueue::map(
+            fd,
+            umem_config.fill_size,
+            &off.fr,
+            XDP_UMEM_PGOFF_FILL_RING as off_t,
+        )?;
+        let completion = CompletionQueue::map(
+            fd,
+            umem_config.completion_size,
+            &off.cr,
+            XDP_UMEM_PGOFF_COMPLETION_RING as off_t,
+        )?;
+        let rx = RxQueue::map(fd, config.rx_size, &off.rx, XDP_PGOFF_RX_RING)?;
+        let tx = TxQueue::map(fd, config.tx_size, &off.tx, XDP_PGOFF_TX_RING)?;
+
+        let addr = sockaddr_xdp {
+            sxdp_family: AF_XDP as u16,
+            sxdp_flags: config.bind_flags.bits,
+            sxdp_ifindex: if_index,
+            sxdp_queue_id: queue_id,
+            sxdp_shared_umem_fd: 0,
+        };
+        let ret = unsafe {
+            bind(
+                fd,
+                &addr as *const _ as *const sockaddr,
+                mem::size_of::<sockaddr_xdp>() as socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(XskError::SyscallError {
+                call: "bind".to_owned(),
+                io_error: io::Error::last_os_error(),
+            });
+        }
+        mem::forget(guard);
+
+        Ok(XskSocket {
+            fill,
+            completion,
+            rx,
+            tx,
+            umem,
+            fd,
+            if_index,
+            queue_id,
+        })
+    }
+
+    /// Returns the index of the interface the socket is bound to.
+    pub fn if_index(&self) -> u32 {
+        self.if_index
+    }
+
+    /// Returns the queue the socket is bound to.
+    pub fn queue_id(&self) -> u32 {
+        self.queue_id
+    }
+
+    /// Returns the UMEM of the socket.
+    pub fn umem(&self) -> &Umem {
+        &self.umem
+    }
+
+    /// Returns the UMEM of the socket, to write packets to transmit.
+    pub fn umem_mut(&mut self) -> &mut Umem {
+        &mut self.umem
+    }
+
+    /// Returns the fill ring.
+    pub fn fill_queue(&mut self) -> &mut FillQueue {
+        &mut self.fill
+    }
+
+    /// Returns the completion ring.
+    pub fn completion_queue(&mut self) -> &mut CompletionQueue {
+        &mut self.completion
+    }
+
+    /// Returns the RX ring.
+    pub fn rx_queue(&mut self) -> &mut RxQueue {
+        &mut self.rx
+    }
+
+    /// Returns the TX ring.
+    pub fn tx_queue(&mut self) -> &mut TxQueue {
+        &mut self.tx
+    }
+
+    /// Wakes up the kernel to transmit the packets in the TX ring.
+    ///
+    /// When the socket is bound with [`XskBindFlags::USE_NEED_WAKEUP`], this only needs to be
+    /// called if [`TxQueue::needs_wakeup`] returns `true`.
+    pub fn wakeup_tx(&self) -> Result<(), XskError> {
+        let ret = unsafe { sendto(self.fd, ptr::null(), 0, MSG_DONTWAIT, ptr::null(), 0) };
+        check_wakeup("sendto", ret)
+    }
+
+    /// Wakes up the kernel to receive packets into the frames of the fill ring.
+    ///
+    /// When the socket is bound with [`XskBindFlags::USE_NEED_WAKEUP`], this only needs to be
+    /// called if [`FillQueue::needs_wakeup`] returns `true`.
+    pub fn wakeup_rx(&self) -> Result<(), XskError> {
+        let ret = unsafe {
+            recvfrom(
+                self.fd,
+                ptr::null_mut(),
+                0,
+                MSG_DONTWAIT,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        check_wakeup("recvfrom", ret)
+    }
+}
+
+impl AsRawFd for XskSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl AsRawFd for &XskSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+impl Drop for XskSocket {
+    fn drop(&mut self) {
+        unsafe { close(self.fd) };
+    }
+}
+
+struct FdGuard(RawFd);
+
+impl Drop for FdGuard {
+    fn drop(&mut self) {
+        unsafe { close(self.0) };
+    }
+}
+
+fn set_sockopt<T>(fd: RawFd, name: i32, value: &T) -> Result<(), XskError> {
+    let ret = unsafe {
+        setsockopt(
+            fd,
+            SOL_XDP,
+            name,
+            value as *const T as *const c_void,
+            mem::size_of::<T>() as socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(XskError::SyscallError {
+            call: "setsockopt".to_owned(),
+            io_error: io::Error::last_os_error(),
+        });
+    }
+    Ok(())
+}
+
+fn mmap_offsets(fd: RawFd) -> Result<xdp_mmap_offsets, XskError> {
+    let mut off = unsafe { mem::zeroed::<xdp_mmap_offsets>() };
+    let mut len = mem::size_of::<xdp_mmap_offsets>() as socklen_t;
+    let ret = unsafe {
+        getsockopt(
+            fd,
+            SOL_XDP,
+            XDP_MMAP_OFFSETS,
+            &mut off as *mut _ as *mut c_void,
+            &mut len,
+        )
+    };
+    if ret < 0 {
+        return Err(XskError::SyscallError {
+            call: "getsockopt".to_owned(),
+            io_error: io::Error::last_os_error(),
+        });
+    }
+    if len as usize == mem::size_of::<xdp_mmap_offsets_v1>() {
+        // kernels older than 5.4 don't report the offset of the flags, which are then located
+        // right after the consumer index
+        let off_v1 = unsafe { ptr::read(&off as *const _ as *const xdp_mmap_offsets_v1) };
+        let from_v1 = |off: xdp_ring_offset_v1| xdp_ring_offset {
+            producer: off.producer,
+            consumer: off.consumer,
+            desc: off.desc,
+            flags: off.consumer + mem::size_of::<u32>() as u64,
+        };
+        off = xdp_mmap_offsets {
+            rx: from_v1(off_v1.rx),
+            tx: from_v1(off_v1.tx),
+            fr: from_v1(off_v1.fr),
+            cr: from_v1(off_v1.cr),
+        };
+    }
+    Ok(off)
+}
+
+fn check_wakeup(call: &str, ret: isize) -> Result<(), XskError> {
+    if ret >= 0 {
+        return Ok(());
+    }
+    let io_error = io::Error::last_os_error();
+    match io_error.raw_os_error() {
+        // the kernel is already processing the rings, or will do so later
+        Some(EAGAIN | EBUSY | ENOBUFS | ENETDOWN) => Ok(()),
+        _ => Err(XskError::SyscallError {
+            call: call.to_owned(),
+            io_error,
+        }),
+    }
+}
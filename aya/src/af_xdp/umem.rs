@@ -0,0 +1,176 @@
+//! The memory area packets are received into and transmitted from.
+
+use std::ops::Range;
+
+use libc::{MAP_ANONYMOUS, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+
+use crate::{
+    af_xdp::{XdpDesc, XskError},
+    maps::MMap,
+    util::page_size,
+};
+
+/// The default number of frames in a [`Umem`].
+pub const DEFAULT_FRAME_COUNT: u32 = 4096;
+/// The default size of the frames of a [`Umem`].
+pub const DEFAULT_FRAME_SIZE: u32 = 4096;
+/// The default number of entries of the AF_XDP rings.
+pub const DEFAULT_RING_SIZE: u32 = 2048;
+
+/// The configuration of a [`Umem`].
+#[derive(Copy, Clone, Debug)]
+pub struct UmemConfig {
+    /// Number of frames in the UMEM.
+    pub frame_count: u32,
+    /// Size of each frame. Must be a power of two between 2048 and the page size.
+    pub frame_size: u32,
+    /// Space reserved by the kernel at the start of each frame, before the packet data.
+    pub frame_headroom: u32,
+    /// Number of entries in the fill ring. Must be a power of two.
+    pub fill_size: u32,
+    /// Number of entries in the completion ring. Must be a power of two.
+    pub completion_size: u32,
+}
+
+impl Default for UmemConfig {
+    fn default() -> Self {
+        UmemConfig {
+            frame_count: DEFAULT_FRAME_COUNT,
+            frame_size: DEFAULT_FRAME_SIZE,
+            frame_headroom: 0,
+            fill_size: DEFAULT_RING_SIZE,
+            completion_size: DEFAULT_RING_SIZE,
+        }
+    }
+}
+
+/// A UMEM, the memory area shared by an AF_XDP socket and the kernel.
+///
+/// The area is split into fixed size frames. Frames are handed over to the kernel through the
+/// [`FillQueue`](crate::af_xdp::FillQueue) to receive packets and through the
+/// [`TxQueue`](crate::af_xdp::TxQueue) to transmit them, and come back through the
+/// [`RxQueue`](crate::af_xdp::RxQueue) and the
+/// [`CompletionQueue`](crate::af_xdp::CompletionQueue) respectively.
+#[derive(Debug)]
+pub struct Umem {
+    area: MMap,
+    config: UmemConfig,
+}
+
+impl Umem {
+    /// Allocates a new UMEM.
+    ///
+    /// The UMEM is registered with the kernel when it's used to create an
+    /// [`XskSocket`](crate::af_xdp::XskSocket).
+    pub fn new(config: UmemConfig) -> Result<Umem, XskError> {
+        if !config.frame_size.is_power_of_two()
+            || config.frame_size < 2048
+            || config.frame_size as usize > page_size()
+        {
+            return Err(XskError::InvalidFrameSize {
+                size: config.frame_size,
+            });
+        }
+        if config.frame_count == 0 {
+            return Err(XskError::InvalidFrameCount {
+                count: config.frame_count,
+            });
+        }
+        check_ring_size("fill", config.fill_size)?;
+        check_ring_size("completion", config.completion_size)?;
+
+        let len = config.frame_count as usize * config.frame_size as usize;
+        let area = MMap::new(
+            -1,
+            len,
+            PROT_READ | PROT_WRITE,
+            MAP_PRIVATE | MAP_ANONYMOUS,
+            0,
+        )
+        .map_err(|io_error| XskError::SyscallError {
+            call: "mmap".to_owned(),
+            io_error,
+        })?;
+
+        Ok(Umem { area, config })
+    }
+
+    /// Returns the configuration of the UMEM.
+    pub fn config(&self) -> &UmemConfig {
+        &self.config
+    }
+
+    /// Returns the size of the UMEM in bytes.
+    pub fn len(&self) -> usize {
+        self.config.frame_count as usize * self.config.frame_size as usize
+    }
+
+    /// An iterator over the addresses of all the frames, suitable to populate the
+    /// [`FillQueue`](crate::af_xdp::FillQueue).
+    pub fn frame_addrs(&self) -> impl Iterator<Item = u64> {
+        let frame_size = self.config.frame_size as u64;
+        (0..self.config.frame_count as u64).map(move |i| i * frame_size)
+    }
+
+    /// Returns the packet data described by `desc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the descriptor points outside of the UMEM.
+    pub fn data(&self, desc: &XdpDesc) -> &[u8] {
+        &self.area.as_ref()[self.range(desc)]
+    }
+
+    /// Returns the packet data described by `desc`, to fill in a packet to transmit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the descriptor points outside of the UMEM.
+    pub fn data_mut(&mut self, desc: &XdpDesc) -> &mut [u8] {
+        let range = self.range(desc);
+        &mut self.area.as_mut()[range]
+    }
+
+    pub(crate) fn addr(&self) -> u64 {
+        self.area.ptr().as_ptr() as u64
+    }
+
+    fn range(&self, desc: &XdpDesc) -> Range<usize> {
+        let start = desc.addr as usize;
+        start..start + desc.len as usize
+    }
+}
+
+pub(crate) fn check_ring_size(name: &'static str, size: u32) -> Result<(), XskError> {
+    if size.is_power_of_two() {
+        Ok(())
+    } else {
+        Err(XskError::InvalidRingSize { name, size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(matches!(
+            Umem::new(UmemConfig {
+                frame_size: 3000,
+                ..Default::default()
+            }),
+            Err(XskError::InvalidFrameSize { size: 3000 })
+        ));
+        assert!(matches!(
+            Umem::new(UmemConfig {
+                fill_size: 1000,
+                ..Default::default()
+            }),
+            Err(XskError::InvalidRingSize {
+                name: "fill",
+                size: 1000
+            })
+        ));
+    }
+}
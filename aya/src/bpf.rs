@@ -2,41 +2,43 @@ use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
     ffi::CString,
-    fs, io,
+    fs, io, mem,
     os::{raw::c_int, unix::io::RawFd},
     path::{Path, PathBuf},
 };
 
 use aya_obj::{
     btf::{BtfFeatures, BtfRelocationError},
-    generated::BPF_F_XDP_HAS_FRAGS,
+    generated::{BPF_F_SLEEPABLE, BPF_F_XDP_HAS_FRAGS},
     relocation::BpfRelocationError,
+    struct_ops::StructOpsRelocationError,
 };
-use log::debug;
+use log::{debug, warn};
 use thiserror::Error;
 
 use crate::{
     generated::{
         bpf_map_type, bpf_map_type::*, AYA_PERF_EVENT_IOC_DISABLE, AYA_PERF_EVENT_IOC_ENABLE,
-        AYA_PERF_EVENT_IOC_SET_BPF,
+        AYA_PERF_EVENT_IOC_SET_BPF, BPF_F_MMAPABLE, BPF_F_NO_PREALLOC,
     },
-    maps::{Map, MapData, MapError},
+    maps::{Global, GlobalError, GlobalKind, Map, MapData, MapError},
     obj::{
         btf::{Btf, BtfError},
         maps::MapKind,
-        Object, ParseError, ProgramSection,
+        GlobalVariable, Object, ParseError, ProgramSection,
     },
     programs::{
         BtfTracePoint, CgroupDevice, CgroupSkb, CgroupSkbAttachType, CgroupSock, CgroupSockAddr,
         CgroupSockopt, CgroupSysctl, Extension, FEntry, FExit, KProbe, LircMode2, Lsm, PerfEvent,
         ProbeKind, Program, ProgramData, ProgramError, RawTracePoint, SchedClassifier, SkLookup,
-        SkMsg, SkSkb, SkSkbKind, SockOps, SocketFilter, TracePoint, UProbe, Xdp,
+        SkMsg, SkSkb, SkSkbKind, SockOps, SocketFilter, StructOps, TracePoint, UProbe, Usdt, Xdp,
     },
     sys::{
-        bpf_load_btf, bpf_map_freeze, bpf_map_update_elem_ptr, is_btf_datasec_supported,
-        is_btf_decl_tag_supported, is_btf_float_supported, is_btf_func_global_supported,
-        is_btf_func_supported, is_btf_supported, is_btf_type_tag_supported, is_perf_link_supported,
-        is_prog_name_supported, retry_with_verifier_logs,
+        bpf_load_btf, bpf_map_freeze, bpf_map_update_elem, bpf_map_update_elem_ptr,
+        is_btf_datasec_supported, is_btf_decl_tag_supported, is_btf_float_supported,
+        is_btf_func_global_supported, is_btf_func_supported, is_btf_supported,
+        is_btf_type_tag_supported, is_map_mmap_supported, is_perf_link_supported,
+        is_prog_name_supported, is_uprobe_multi_link_supported, retry_with_verifier_logs,
     },
     util::{bytes_of, possible_cpus, VerifierLog, POSSIBLE_CPUS},
 };
@@ -48,12 +50,20 @@ pub(crate) const PERF_EVENT_IOC_DISABLE: c_int = AYA_PERF_EVENT_IOC_DISABLE;
 pub(crate) const PERF_EVENT_IOC_SET_BPF: c_int = AYA_PERF_EVENT_IOC_SET_BPF;
 
 /// Marker trait for types that can safely be converted to and from byte slices.
-pub unsafe trait Pod: Copy + 'static {}
+pub unsafe trait Pod: Copy + 'static {
+    /// The kind of BTF type the type can be read from, checked by [`Bpf::global`].
+    #[doc(hidden)]
+    const GLOBAL_KIND: GlobalKind = GlobalKind::Struct;
+}
 
 macro_rules! unsafe_impl_pod {
     ($($struct_name:ident),+ $(,)?) => {
         $(
-            unsafe impl Pod for $struct_name { }
+            unsafe impl Pod for $struct_name {
+                const GLOBAL_KIND: GlobalKind = GlobalKind::Int {
+                    signed: $struct_name::MIN != 0,
+                };
+            }
         )+
     }
 }
@@ -61,7 +71,9 @@ macro_rules! unsafe_impl_pod {
 unsafe_impl_pod!(i8, u8, i16, u16, i32, u32, i64, u64, u128, i128);
 
 // It only makes sense that an array of POD types is itself POD
-unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {
+    const GLOBAL_KIND: GlobalKind = GlobalKind::Array;
+}
 
 pub use aya_obj::maps::{bpf_map_def, PinningType};
 
@@ -74,6 +86,8 @@ lazy_static! {
 pub(crate) struct Features {
     pub bpf_name: bool,
     pub bpf_perf_link: bool,
+    pub bpf_map_mmap: bool,
+    pub bpf_uprobe_multi_link: bool,
     pub btf: Option<BtfFeatures>,
 }
 
@@ -94,6 +108,8 @@ impl Features {
         let f = Features {
             bpf_name: is_prog_name_supported(),
             bpf_perf_link: is_perf_link_supported(),
+            bpf_map_mmap: is_map_mmap_supported(),
+            bpf_uprobe_multi_link: is_uprobe_multi_link_supported(),
             btf,
         };
         debug!("BPF Feature Detection: {:#?}", f);
@@ -126,10 +142,15 @@ impl Features {
 pub struct BpfLoader<'a> {
     btf: Option<Cow<'a, Btf>>,
     map_pin_path: Option<PathBuf>,
+    map_pinning: HashMap<&'a str, PinningType>,
+    map_pin_paths: HashMap<&'a str, PathBuf>,
     globals: HashMap<&'a str, &'a [u8]>,
     max_entries: HashMap<&'a str, u32>,
+    inner_maps: HashMap<&'a str, &'a str>,
+    maps: HashMap<&'a str, MapData>,
     extensions: HashSet<&'a str>,
     verifier_log_level: VerifierLogLevel,
+    pinned_map_policy: PinnedMapPolicy,
 }
 
 bitflags! {
@@ -154,16 +175,34 @@ impl Default for VerifierLogLevel {
     }
 }
 
+/// What [BpfLoader] does when a map pinned by name doesn't match the definition of the map
+/// in the object, see [BpfLoader::pinned_map_policy()].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PinnedMapPolicy {
+    /// Fails loading with [`MapError::IncompatibleMap`].
+    #[default]
+    Error,
+    /// Removes the pin and pins a new map in its place.
+    Replace,
+    /// Creates a new map without pinning it. The pinned map is left untouched.
+    Unpinned,
+}
+
 impl<'a> BpfLoader<'a> {
     /// Creates a new loader instance.
     pub fn new() -> BpfLoader<'a> {
         BpfLoader {
             btf: Btf::from_sys_fs().ok().map(Cow::Owned),
             map_pin_path: None,
+            map_pinning: HashMap::new(),
+            map_pin_paths: HashMap::new(),
             globals: HashMap::new(),
             max_entries: HashMap::new(),
+            inner_maps: HashMap::new(),
+            maps: HashMap::new(),
             extensions: HashSet::new(),
             verifier_log_level: VerifierLogLevel::default(),
+            pinned_map_policy: PinnedMapPolicy::default(),
         }
     }
 
@@ -210,6 +249,81 @@ impl<'a> BpfLoader<'a> {
         self
     }
 
+    /// Overrides the pinning of the specified map.
+    ///
+    /// By default maps are pinned according to the `pinning` of their definition in the
+    /// object. Use this method to pin a map by name in the [pin path](Self::map_pin_path) even
+    /// if its definition doesn't request it, or to stop a map from being pinned with
+    /// [PinningType::None].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use aya::{BpfLoader, PinningType};
+    ///
+    /// let bpf = BpfLoader::new()
+    ///     .map_pin_path("/sys/fs/bpf/my-program")
+    ///     .set_map_pinning("EVENTS", PinningType::ByName)
+    ///     .load_file("file.o")?;
+    /// # Ok::<(), aya::BpfError>(())
+    /// ```
+    ///
+    pub fn set_map_pinning(&mut self, name: &'a str, pinning: PinningType) -> &mut BpfLoader<'a> {
+        self.map_pinning.insert(name, pinning);
+        self
+    }
+
+    /// Pins the specified map at a custom path.
+    ///
+    /// The map is loaded from `path` if it's already pinned there, otherwise it's created and
+    /// pinned at `path`. This takes precedence over [set_map_pinning](Self::set_map_pinning)
+    /// and the [pin path](Self::map_pin_path), and applies whatever the `pinning` of the map
+    /// definition. The caller is responsible for ensuring the parent directory exists.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use aya::BpfLoader;
+    ///
+    /// let bpf = BpfLoader::new()
+    ///     .set_map_pin_path("CONFIG", "/sys/fs/bpf/shared/config")
+    ///     .load_file("file.o")?;
+    /// # Ok::<(), aya::BpfError>(())
+    /// ```
+    ///
+    pub fn set_map_pin_path<P: AsRef<Path>>(
+        &mut self,
+        name: &'a str,
+        path: P,
+    ) -> &mut BpfLoader<'a> {
+        self.map_pin_paths.insert(name, path.as_ref().to_owned());
+        self
+    }
+
+    /// Sets what to do when a pinned map doesn't match the object.
+    ///
+    /// Maps pinned by name are reused when they're found in the [pin path](Self::map_pin_path),
+    /// as long as their type, key size, value size, max entries and flags match the definition
+    /// of the map in the object. Otherwise loading fails by default, see [PinnedMapPolicy] for
+    /// the alternatives.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use aya::{BpfLoader, PinnedMapPolicy};
+    ///
+    /// let bpf = BpfLoader::new()
+    ///     .map_pin_path("/sys/fs/bpf/my-program")
+    ///     .pinned_map_policy(PinnedMapPolicy::Replace)
+    ///     .load_file("file.o")?;
+    /// # Ok::<(), aya::BpfError>(())
+    /// ```
+    ///
+    pub fn pinned_map_policy(&mut self, policy: PinnedMapPolicy) -> &mut BpfLoader<'a> {
+        self.pinned_map_policy = policy;
+        self
+    }
+
     /// Sets the value of a global variable
     ///
     /// From Rust eBPF, a global variable would be constructed as follows:
@@ -270,6 +384,54 @@ impl<'a> BpfLoader<'a> {
         self
     }
 
+    /// Uses an existing map instead of creating the specified map.
+    ///
+    /// The map isn't created when loading, and the programs of the object reference `map`
+    /// instead. This lets several objects share a map without pinning it. The type, key size,
+    /// value size, max entries and flags of `map` must match the definition of the map in the
+    /// object, otherwise loading fails with [`MapError::IncompatibleMap`]. The initial data of
+    /// global data maps isn't written to `map`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use aya::{maps::MapData, BpfLoader};
+    ///
+    /// let shared = MapData::from_id(42)?;
+    /// let bpf = BpfLoader::new()
+    ///     .set_map("SHARED", shared)
+    ///     .load_file("file.o")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    pub fn set_map(&mut self, name: &'a str, map: MapData) -> &mut BpfLoader<'a> {
+        self.maps.insert(name, map);
+        self
+    }
+
+    /// Sets the inner map template of a map-in-map.
+    ///
+    /// Array of maps and hash of maps need a template describing their inner maps when they're
+    /// created. The map named `inner` is used as the template of the map named `name`. Maps
+    /// declared in a `.maps` section with `__array(values, ...)` don't need a template, but the
+    /// one set here takes precedence over the declared one.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use aya::BpfLoader;
+    ///
+    /// let bpf = BpfLoader::new()
+    ///     .set_inner_map("POLICIES", "POLICY_TEMPLATE")
+    ///     .load_file("file.o")?;
+    /// # Ok::<(), aya::BpfError>(())
+    /// ```
+    ///
+    pub fn set_inner_map(&mut self, name: &'a str, inner: &'a str) -> &mut BpfLoader<'a> {
+        self.inner_maps.insert(name, inner);
+        self
+    }
+
     /// Treat the provided program as an [`Extension`]
     ///
     /// When attempting to load the program with the provided `name`
@@ -344,6 +506,7 @@ impl<'a> BpfLoader<'a> {
         let verifier_log_level = self.verifier_log_level.bits;
         let mut obj = Object::parse(data)?;
         obj.patch_map_data(self.globals.clone())?;
+        let globals = obj.globals();
 
         let btf_fd = if let Some(features) = &FEATURES.btf {
             if let Some(btf) = obj.fixup_and_sanitize_btf(features)? {
@@ -358,9 +521,25 @@ impl<'a> BpfLoader<'a> {
 
         if let Some(btf) = &self.btf {
             obj.relocate_btf(btf)?;
+            obj.relocate_struct_ops(btf)?;
+        } else if let Some(name) = obj
+            .maps
+            .iter()
+            .find(|(_, map)| map.struct_ops().is_some())
+            .map(|(name, _)| name)
+        {
+            // struct_ops maps are laid out after the kernel struct they implement
+            return Err(BpfError::KernelBtfRequired { name: name.clone() });
         }
-        let mut maps = HashMap::new();
-        for (name, mut obj) in obj.maps.drain() {
+        let mut maps: HashMap<String, MapData> = HashMap::new();
+        // maps-in-maps are created last, so that the maps they use as inner map templates and
+        // are initialised with exist by then
+        let mut map_objs = obj.maps.drain().collect::<Vec<_>>();
+        map_objs.sort_by_key(|(_, obj)| {
+            obj.map_type() == BPF_MAP_TYPE_ARRAY_OF_MAPS as u32
+                || obj.map_type() == BPF_MAP_TYPE_HASH_OF_MAPS as u32
+        });
+        for (name, mut obj) in map_objs {
             match self.max_entries.get(name.as_str()) {
                 Some(size) => obj.set_max_entries(*size),
                 None => {
@@ -378,37 +557,140 @@ impl<'a> BpfLoader<'a> {
                     }
                 }
             }
+            // lets user space access global variables without syscalls, see `Array::mmap`
+            if FEATURES.bpf_map_mmap && matches!(obj.kind(), MapKind::Bss | MapKind::Data) {
+                obj.set_map_flags(obj.map_flags() | BPF_F_MMAPABLE);
+            }
+            // the kernel refuses to create local storage maps without BPF_F_NO_PREALLOC
+            if is_local_storage(obj.map_type()) {
+                obj.set_map_flags(obj.map_flags() | BPF_F_NO_PREALLOC);
+            }
             let mut map = MapData {
                 obj,
                 fd: None,
                 pinned: false,
+                pin_path: None,
                 btf_fd,
             };
-            let fd = match map.obj.pinning() {
-                PinningType::ByName => {
-                    let path = match &self.map_pin_path {
-                        Some(p) => p,
+            if let Some(existing) = self.maps.get(name.as_str()) {
+                // the loader keeps its own fd, so that it can be used to load other objects
+                let mut existing = existing.clone();
+                map.fd = existing.fd.take();
+                map.pinned = existing.pinned;
+                map.pin_path = existing.pin_path.take();
+                map.check_compatible(&name)?;
+                maps.insert(name, map);
+                continue;
+            }
+            // the template created from a `__array(values, ...)` declaration is only needed until
+            // the map is created
+            let mut inner_template = None;
+            let inner_map_fd = match self.inner_maps.get(name.as_str()) {
+                Some(inner) => {
+                    let inner_map = maps.get(*inner).ok_or_else(|| BpfError::InnerMapNotFound {
+                        name: name.clone(),
+                        inner: inner.to_string(),
+                    })?;
+                    Some(inner_map.fd_or_err()?)
+                }
+                None => match map.obj.inner() {
+                    Some(obj) => {
+                        let mut inner = MapData {
+                            obj,
+                            fd: None,
+                            pinned: false,
+                            pin_path: None,
+                            btf_fd,
+                        };
+                        let fd = inner.create(&format!("{name}.inner"))?;
+                        inner_template = Some(inner);
+                        Some(fd)
+                    }
+                    None => None,
+                },
+            };
+            let pin_path = match self.map_pin_paths.get(name.as_str()) {
+                Some(path) => Some(path.clone()),
+                None => match self
+                    .map_pinning
+                    .get(name.as_str())
+                    .copied()
+                    .unwrap_or_else(|| map.obj.pinning())
+                {
+                    PinningType::ByName => match &self.map_pin_path {
+                        Some(p) => Some(p.join(&name)),
                         None => return Err(BpfError::NoPinPath),
-                    };
+                    },
+                    PinningType::None => None,
+                },
+            };
+            let mut reused = false;
+            let fd = match pin_path {
+                Some(path) => {
                     // try to open map in case it's already pinned
-                    match map.open_pinned(&name, path) {
-                        Ok(fd) => {
-                            map.pinned = true;
-                            fd as RawFd
+                    let mut pin = true;
+                    if let Ok(fd) = map.open_pinned(&name, &path) {
+                        match map.check_compatible(&name) {
+                            Ok(()) => reused = true,
+                            Err(e) => {
+                                if self.pinned_map_policy == PinnedMapPolicy::Error {
+                                    return Err(e.into());
+                                }
+                                warn!("{e}, policy is {:?}", self.pinned_map_policy);
+                                map.fd = None;
+                                map.pinned = false;
+                                map.pin_path = None;
+                                unsafe { libc::close(fd) };
+                                if self.pinned_map_policy == PinnedMapPolicy::Replace {
+                                    fs::remove_file(&path).map_err(|error| {
+                                        BpfError::FileError {
+                                            path: path.clone(),
+                                            error,
+                                        }
+                                    })?;
+                                } else {
+                                    pin = false;
+                                }
+                            }
                         }
-                        Err(_) => {
-                            let fd = map.create(&name)?;
-                            map.pin(&name, path).map_err(|error| MapError::PinError {
-                                name: Some(name.to_string()),
-                                error,
-                            })?;
+                    }
+                    match map.fd {
+                        Some(fd) => fd,
+                        None => {
+                            let fd = map.create_with_inner_map(&name, inner_map_fd)?;
+                            if pin {
+                                map.pin(&name, &path).map_err(|error| MapError::PinError {
+                                    name: Some(name.to_string()),
+                                    error,
+                                })?;
+                            }
                             fd
                         }
                     }
                 }
-                PinningType::None => map.create(&name)?,
+                None => map.create_with_inner_map(&name, inner_map_fd)?,
             };
-            if !map.obj.data().is_empty() && map.obj.kind() != MapKind::Bss {
+            drop(inner_template);
+            if !reused {
+                for (index, inner) in map.obj.initial_slots() {
+                    let inner_map = maps.get(inner).ok_or_else(|| BpfError::InnerMapNotFound {
+                        name: name.clone(),
+                        inner: inner.clone(),
+                    })?;
+                    let inner_fd = inner_map.fd_or_err()?;
+                    bpf_map_update_elem(fd, Some(index), &inner_fd, 0).map_err(
+                        |(_, io_error)| MapError::SyscallError {
+                            call: "bpf_map_update_elem".to_owned(),
+                            io_error,
+                        },
+                    )?;
+                }
+            }
+            // struct_ops values are written when the map is registered, once the programs they
+            // point to are loaded
+            if !map.obj.data().is_empty()
+                && !matches!(map.obj.kind(), MapKind::Bss | MapKind::StructOps)
+            {
                 bpf_map_update_elem_ptr(fd, &0 as *const _, map.obj.data_mut().as_mut_ptr(), 0)
                     .map_err(|(_, io_error)| MapError::SyscallError {
                         call: "bpf_map_update_elem".to_owned(),
@@ -430,6 +712,19 @@ impl<'a> BpfLoader<'a> {
         )?;
         obj.relocate_calls()?;
 
+        // struct_ops programs are loaded against the kernel struct member they're set as
+        let struct_ops_members = maps
+            .values()
+            .filter_map(|map| map.obj.struct_ops())
+            .filter_map(|struct_ops| struct_ops.layout.as_ref())
+            .flat_map(|layout| {
+                layout
+                    .programs
+                    .iter()
+                    .map(|p| (p.name.clone(), (layout.type_id, p.member_index)))
+            })
+            .collect::<HashMap<_, _>>();
+
         let programs = obj
             .programs
             .drain()
@@ -447,21 +742,25 @@ impl<'a> BpfLoader<'a> {
                     })
                 } else {
                     match &section {
-                        ProgramSection::KProbe { .. } => Program::KProbe(KProbe {
+                        ProgramSection::KProbe { multi, .. } => Program::KProbe(KProbe {
                             data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
                             kind: ProbeKind::KProbe,
+                            multi: *multi,
                         }),
-                        ProgramSection::KRetProbe { .. } => Program::KProbe(KProbe {
+                        ProgramSection::KRetProbe { multi, .. } => Program::KProbe(KProbe {
                             data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
                             kind: ProbeKind::KRetProbe,
+                            multi: *multi,
                         }),
-                        ProgramSection::UProbe { .. } => Program::UProbe(UProbe {
+                        ProgramSection::UProbe { multi, .. } => Program::UProbe(UProbe {
                             data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
                             kind: ProbeKind::UProbe,
+                            multi: *multi,
                         }),
-                        ProgramSection::URetProbe { .. } => Program::UProbe(UProbe {
+                        ProgramSection::URetProbe { multi, .. } => Program::UProbe(UProbe {
                             data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
                             kind: ProbeKind::URetProbe,
+                            multi: *multi,
                         }),
                         ProgramSection::TracePoint { .. } => Program::TracePoint(TracePoint {
                             data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
@@ -472,14 +771,19 @@ impl<'a> BpfLoader<'a> {
                             })
                         }
                         ProgramSection::Xdp {
-                            frags_supported, ..
+                            frags_supported,
+                            attach_type,
+                            ..
                         } => {
                             let mut data =
                                 ProgramData::new(prog_name, obj, btf_fd, verifier_log_level);
                             if *frags_supported {
                                 data.flags = BPF_F_XDP_HAS_FRAGS;
                             }
-                            Program::Xdp(Xdp { data })
+                            Program::Xdp(Xdp {
+                                data,
+                                attach_type: *attach_type,
+                            })
                         }
                         ProgramSection::SkMsg { .. } => Program::SkMsg(SkMsg {
                             data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
@@ -575,6 +879,22 @@ impl<'a> BpfLoader<'a> {
                                 data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
                             })
                         }
+                        ProgramSection::StructOps { sleepable, .. } => {
+                            let mut data =
+                                ProgramData::new(prog_name, obj, btf_fd, verifier_log_level);
+                            if *sleepable {
+                                data.flags = BPF_F_SLEEPABLE;
+                            }
+                            let member = struct_ops_members.get(&name);
+                            data.attach_btf_id = member.map(|(type_id, _)| *type_id);
+                            Program::StructOps(StructOps {
+                                data,
+                                member_index: member.map(|(_, index)| *index),
+                            })
+                        }
+                        ProgramSection::Usdt { .. } => Program::Usdt(Usdt {
+                            data: ProgramData::new(prog_name, obj, btf_fd, verifier_log_level),
+                        }),
                     }
                 };
                 (name, program)
@@ -585,10 +905,50 @@ impl<'a> BpfLoader<'a> {
         Ok(Bpf {
             maps: maps?,
             programs,
+            globals,
         })
     }
 }
 
+fn is_local_storage(map_type: u32) -> bool {
+    map_type == BPF_MAP_TYPE_SK_STORAGE as u32
+        || map_type == BPF_MAP_TYPE_TASK_STORAGE as u32
+        || map_type == BPF_MAP_TYPE_INODE_STORAGE as u32
+        || map_type == BPF_MAP_TYPE_CGRP_STORAGE as u32
+}
+
+fn global_not_found(name: &str) -> GlobalError {
+    GlobalError::NotFound {
+        name: name.to_owned(),
+    }
+}
+
+fn check_global<T: Pod>(name: &str, global: &GlobalVariable) -> Result<(), GlobalError> {
+    let size = mem::size_of::<T>();
+    for expected in Some(global.size as usize)
+        .into_iter()
+        .chain(global.btf_size)
+    {
+        if size != expected {
+            return Err(GlobalError::InvalidSize {
+                name: name.to_owned(),
+                size,
+                expected,
+            });
+        }
+    }
+    match global.btf_kind {
+        // pointers and floats have no Pod counterpart, only their size is checked
+        None | Some(GlobalKind::Other) => Ok(()),
+        Some(expected) if expected == T::GLOBAL_KIND => Ok(()),
+        Some(expected) => Err(GlobalError::InvalidKind {
+            name: name.to_owned(),
+            kind: T::GLOBAL_KIND,
+            expected,
+        }),
+    }
+}
+
 fn parse_map(data: (String, MapData)) -> Result<(String, Map), BpfError> {
     let name = data.0;
     let map = data.1;
@@ -600,10 +960,12 @@ fn parse_map(data: (String, MapData)) -> Result<(String, Map), BpfError> {
         BPF_MAP_TYPE_ARRAY => Ok(Map::Array(map)),
         BPF_MAP_TYPE_PERCPU_ARRAY => Ok(Map::PerCpuArray(map)),
         BPF_MAP_TYPE_PROG_ARRAY => Ok(Map::ProgramArray(map)),
+        BPF_MAP_TYPE_ARRAY_OF_MAPS => Ok(Map::ArrayOfMaps(map)),
         BPF_MAP_TYPE_HASH => Ok(Map::HashMap(map)),
         BPF_MAP_TYPE_LRU_HASH => Ok(Map::LruHashMap(map)),
         BPF_MAP_TYPE_PERCPU_HASH => Ok(Map::PerCpuHashMap(map)),
         BPF_MAP_TYPE_LRU_PERCPU_HASH => Ok(Map::PerCpuLruHashMap(map)),
+        BPF_MAP_TYPE_HASH_OF_MAPS => Ok(Map::HashOfMaps(map)),
         BPF_MAP_TYPE_PERF_EVENT_ARRAY => Ok(Map::PerfEventArray(map)),
         BPF_MAP_TYPE_SOCKHASH => Ok(Map::SockHash(map)),
         BPF_MAP_TYPE_SOCKMAP => Ok(Map::SockMap(map)),
@@ -612,6 +974,19 @@ fn parse_map(data: (String, MapData)) -> Result<(String, Map), BpfError> {
         BPF_MAP_TYPE_STACK => Ok(Map::Stack(map)),
         BPF_MAP_TYPE_STACK_TRACE => Ok(Map::StackTraceMap(map)),
         BPF_MAP_TYPE_QUEUE => Ok(Map::Queue(map)),
+        BPF_MAP_TYPE_RINGBUF => Ok(Map::RingBuf(map)),
+        BPF_MAP_TYPE_USER_RINGBUF => Ok(Map::UserRingBuf(map)),
+        BPF_MAP_TYPE_CPUMAP => Ok(Map::CpuMap(map)),
+        BPF_MAP_TYPE_DEVMAP => Ok(Map::DevMap(map)),
+        BPF_MAP_TYPE_DEVMAP_HASH => Ok(Map::DevMapHash(map)),
+        BPF_MAP_TYPE_XSKMAP => Ok(Map::XskMap(map)),
+        BPF_MAP_TYPE_SK_STORAGE => Ok(Map::SkStorage(map)),
+        BPF_MAP_TYPE_TASK_STORAGE => Ok(Map::TaskStorage(map)),
+        BPF_MAP_TYPE_INODE_STORAGE => Ok(Map::InodeStorage(map)),
+        BPF_MAP_TYPE_CGRP_STORAGE => Ok(Map::CgrpStorage(map)),
+        BPF_MAP_TYPE_CGROUP_STORAGE_DEPRECATED => Ok(Map::CgroupStorage(map)),
+        BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE => Ok(Map::PerCpuCgroupStorage(map)),
+        BPF_MAP_TYPE_STRUCT_OPS => Ok(Map::StructOps(map)),
         m => Err(BpfError::MapError(MapError::InvalidMapType {
             map_type: m as u32,
         })),
@@ -631,6 +1006,7 @@ impl<'a> Default for BpfLoader<'a> {
 pub struct Bpf {
     maps: HashMap<String, Map>,
     programs: HashMap<String, Program>,
+    globals: HashMap<String, GlobalVariable>,
 }
 
 impl Bpf {
@@ -736,6 +1112,80 @@ impl Bpf {
         self.maps.iter().map(|(name, map)| (name.as_str(), map))
     }
 
+    /// Returns a read-only handle to the global variable with the given name.
+    ///
+    /// The variable must be defined in the `.bss`, `.data` or `.rodata` section. Unlike
+    /// [`BpfLoader::set_global`], which patches `.rodata` before loading, the handle reads the
+    /// variable in the loaded map. Use [`global_mut`](Self::global_mut) to update it.
+    ///
+    /// The size of `T` is checked against the size of the variable. When the object has BTF for
+    /// the section, `T` must also match the kind of the BTF type of the variable: an integer of
+    /// the same signedness for integers and enums, an array for arrays and any other [`Pod`] for
+    /// structs and unions. `[u8; 8]` can't be used to read a `u64` variable for example.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GlobalError::NotFound`] if there's no such variable,
+    /// [`GlobalError::InvalidSize`] if the size of `T` doesn't match the size of the variable or
+    /// of its BTF type, [`GlobalError::InvalidKind`] if `T` doesn't match the kind of its BTF
+    /// type.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let bpf = aya::Bpf::load(&[])?;
+    /// let packets = bpf.global::<u64>("PACKETS")?;
+    /// println!("seen {} packets", packets.get()?);
+    /// # Ok::<(), aya::BpfError>(())
+    /// ```
+    pub fn global<T: Pod>(&self, name: &str) -> Result<Global<&MapData, T>, GlobalError> {
+        let global = self.global_var::<T>(name)?;
+        let map = match self.maps.get(&global.map_name) {
+            Some(Map::Array(map)) => map,
+            _ => return Err(global_not_found(name)),
+        };
+        Ok(Global::new(map, global.offset as usize)?)
+    }
+
+    /// Returns a handle to the global variable with the given name, to read and update it.
+    ///
+    /// The variable is checked as in [`global`](Self::global). Variables in `.rodata` can't be
+    /// updated, the map is frozen after loading.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`global`](Self::global).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// let mut verbose = bpf.global_mut::<u32>("VERBOSE")?;
+    /// verbose.set(1)?;
+    /// # Ok::<(), aya::BpfError>(())
+    /// ```
+    pub fn global_mut<T: Pod>(
+        &mut self,
+        name: &str,
+    ) -> Result<Global<&mut MapData, T>, GlobalError> {
+        let global = self.global_var::<T>(name)?;
+        let (map_name, offset) = (global.map_name.clone(), global.offset as usize);
+        let map = match self.maps.get_mut(&map_name) {
+            Some(Map::Array(map)) => map,
+            _ => return Err(global_not_found(name)),
+        };
+        Ok(Global::new(map, offset)?)
+    }
+
+    fn global_var<T: Pod>(&self, name: &str) -> Result<&GlobalVariable, GlobalError> {
+        let global = self
+            .globals
+            .get(name)
+            .ok_or_else(|| global_not_found(name))?;
+        check_global::<T>(name, global)?;
+        Ok(global)
+    }
+
     /// Returns a reference to the program with the given name.
     ///
     /// You can use this to inspect a program and its properties. To load and attach a program, use
@@ -865,10 +1315,30 @@ pub enum BpfError {
     #[error("error relocating section")]
     BtfRelocationError(#[from] BtfRelocationError),
 
+    /// Error relocating the struct_ops maps
+    #[error("error relocating struct_ops maps")]
+    StructOpsRelocationError(#[from] StructOpsRelocationError),
+
+    /// The object has a struct_ops map but the BTF of the running kernel isn't available
+    #[error("struct_ops map `{name}` requires kernel BTF, which is not available")]
+    KernelBtfRequired {
+        /// The map name
+        name: String,
+    },
+
     /// No BTF parsed for object
     #[error("no BTF parsed for object")]
     NoBTF,
 
+    /// The inner map of a map-in-map was not found
+    #[error("inner map `{inner}` of map `{name}` not found")]
+    InnerMapNotFound {
+        /// The name of the map-in-map
+        name: String,
+        /// The name of the inner map
+        inner: String,
+    },
+
     #[error("map error: {0}")]
     /// A map error
     MapError(#[from] MapError),
@@ -876,6 +1346,10 @@ pub enum BpfError {
     #[error("program error: {0}")]
     /// A program error
     ProgramError(#[from] ProgramError),
+
+    #[error("global error: {0}")]
+    /// A global variable error
+    GlobalError(#[from] GlobalError),
 }
 
 fn load_btf(raw_btf: Vec<u8>) -> Result<RawFd, BtfError> {
@@ -897,3 +1371,250 @@ fn load_btf(raw_btf: Vec<u8>) -> Result<RawFd, BtfError> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io, os::unix::io::IntoRawFd, slice};
+
+    use libc::EFAULT;
+    use object::elf::{EM_BPF, ET_REL, SHF_ALLOC, SHF_EXECINSTR, SHF_WRITE, SHT_PROGBITS, SHT_REL};
+
+    use crate::{
+        generated::{bpf_attr, bpf_cmd, bpf_map_info, BPF_PSEUDO_MAP_FD},
+        maps::MapData,
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, Syscall},
+        test_elf::{ElfBuilder, Section, Symbol},
+    };
+
+    use super::*;
+
+    const MAP_DEF: bpf_map_def = bpf_map_def {
+        map_type: BPF_MAP_TYPE_HASH as u32,
+        key_size: 4,
+        value_size: 4,
+        max_entries: 1024,
+        map_flags: 0,
+        id: 0,
+        pinning: PinningType::None,
+    };
+
+    // An object with a hash map `MAP` in the legacy `maps` section, and an XDP program `pass`
+    // loading its fd.
+    fn object() -> Vec<u8> {
+        let mut elf = ElfBuilder::new(ET_REL, EM_BPF);
+        let mut insns = Vec::new();
+        // r1 = MAP ll
+        insns.extend_from_slice(&[0x18, 0x01, 0, 0, 0, 0, 0, 0]);
+        insns.extend_from_slice(&[0; 8]);
+        // r0 = XDP_PASS
+        insns.extend_from_slice(&[0xb7, 0x00, 0, 0, 2, 0, 0, 0]);
+        // exit
+        insns.extend_from_slice(&[0x95, 0, 0, 0, 0, 0, 0, 0]);
+        let prog = elf.section(Section {
+            name: "xdp/pass".to_owned(),
+            sh_type: SHT_PROGBITS,
+            flags: (SHF_ALLOC | SHF_EXECINSTR) as u64,
+            data: insns,
+            ..Default::default()
+        });
+        let def = unsafe {
+            slice::from_raw_parts(
+                &MAP_DEF as *const bpf_map_def as *const u8,
+                mem::size_of::<bpf_map_def>(),
+            )
+        };
+        let maps = elf.section(Section {
+            name: "maps".to_owned(),
+            sh_type: SHT_PROGBITS,
+            flags: (SHF_ALLOC | SHF_WRITE) as u64,
+            data: def.to_vec(),
+            ..Default::default()
+        });
+        // STB_GLOBAL with STT_OBJECT and STT_FUNC
+        let symtab = elf.symtab(&[
+            Symbol {
+                name: "MAP",
+                info: 0x11,
+                section: maps as u16,
+                value: 0,
+                size: def.len() as u64,
+            },
+            Symbol {
+                name: "pass",
+                info: 0x12,
+                section: prog as u16,
+                value: 0,
+                size: 32,
+            },
+        ]);
+        // R_BPF_64_64 of the `r1 = MAP ll` instruction against the first symbol
+        let mut rel = Vec::new();
+        rel.extend_from_slice(&0u64.to_le_bytes());
+        rel.extend_from_slice(&((1u64 << 32) | 1).to_le_bytes());
+        elf.section(Section {
+            name: ".relxdp/pass".to_owned(),
+            sh_type: SHT_REL,
+            data: rel,
+            link: symtab as u32,
+            info: prog as u32,
+            entsize: 16,
+            ..Default::default()
+        });
+        elf.build()
+    }
+
+    // A map with a real fd that can be duplicated.
+    fn existing_map() -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: MAP_DEF,
+                section_index: 0,
+                symbol_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+            }),
+            fd: Some(File::open("/dev/null").unwrap().into_raw_fd()),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn map_info(max_entries: u32, attr: &bpf_attr) {
+        let info = unsafe { &mut *(attr.info.info as *mut bpf_map_info) };
+        info.type_ = BPF_MAP_TYPE_HASH as u32;
+        info.key_size = 4;
+        info.value_size = 4;
+        info.max_entries = max_entries;
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_set_map() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                map_info(1024, attr);
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let mut loader = BpfLoader::new();
+        loader.btf(None).set_map("MAP", existing_map());
+        let bpf = loader.load(&object()).unwrap();
+
+        // the loader keeps its fd, and the object uses a duplicate of it
+        let own_fd = loader.maps["MAP"].fd.unwrap();
+        let fd = match bpf.map("MAP") {
+            Some(Map::HashMap(map)) => map.fd.unwrap(),
+            map => panic!("unexpected map {map:?}"),
+        };
+        assert_ne!(fd, own_fd);
+
+        let insns = match bpf.program("pass") {
+            Some(Program::Xdp(prog)) => &prog.data.obj.as_ref().unwrap().function.instructions,
+            _ => panic!("program not found"),
+        };
+        assert_eq!(insns[0].src_reg(), BPF_PSEUDO_MAP_FD as u8);
+        assert_eq!(insns[0].imm, fd);
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_set_map_incompatible() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                map_info(512, attr);
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let mut loader = BpfLoader::new();
+        loader.btf(None).set_map("MAP", existing_map());
+        assert!(matches!(
+            loader.load(&object()),
+            Err(BpfError::MapError(MapError::IncompatibleMap {
+                name,
+                field: "max entries",
+                actual: 512,
+                expected: 1024,
+            })) if name == "MAP"
+        ));
+        // the loader can still be used with another object
+        assert!(loader.maps["MAP"].fd.is_some());
+    }
+
+    #[test]
+    fn test_check_global() {
+        let global = |btf_kind| GlobalVariable {
+            map_name: ".data".to_owned(),
+            offset: 0,
+            size: 8,
+            btf_size: Some(8),
+            btf_kind,
+        };
+
+        assert!(check_global::<u64>("X", &global(None)).is_ok());
+        assert!(check_global::<[u8; 8]>("X", &global(None)).is_ok());
+        assert!(matches!(
+            check_global::<u32>("X", &global(None)),
+            Err(GlobalError::InvalidSize {
+                size: 4,
+                expected: 8,
+                ..
+            })
+        ));
+
+        let int = global(Some(GlobalKind::Int { signed: false }));
+        assert!(check_global::<u64>("X", &int).is_ok());
+        assert!(matches!(
+            check_global::<[u8; 8]>("X", &int),
+            Err(GlobalError::InvalidKind {
+                kind: GlobalKind::Array,
+                expected: GlobalKind::Int { signed: false },
+                ..
+            })
+        ));
+        assert!(matches!(
+            check_global::<i64>("X", &int),
+            Err(GlobalError::InvalidKind {
+                kind: GlobalKind::Int { signed: true },
+                ..
+            })
+        ));
+
+        let array = global(Some(GlobalKind::Array));
+        assert!(check_global::<[u32; 2]>("X", &array).is_ok());
+        assert!(check_global::<u64>("X", &array).is_err());
+
+        #[derive(Clone, Copy)]
+        struct Pair {
+            _first: u32,
+            _second: u32,
+        }
+        unsafe impl Pod for Pair {}
+        let strukt = global(Some(GlobalKind::Struct));
+        assert!(check_global::<Pair>("X", &strukt).is_ok());
+        assert!(check_global::<u64>("X", &strukt).is_err());
+
+        // only the size of pointers is checked
+        assert!(check_global::<u64>("X", &global(Some(GlobalKind::Other))).is_ok());
+    }
+}
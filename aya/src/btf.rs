@@ -0,0 +1,223 @@
+//! BTF objects loaded in the kernel.
+//!
+//! Besides the vmlinux BTF, the kernel holds the BTF of the loaded modules and the BTF of the
+//! programs and maps loaded by user space. [`loaded_btfs`] lists them and [`BtfExt::from_id`]
+//! fetches them so that the types of foreign programs and maps can be inspected.
+use std::os::unix::io::RawFd;
+
+use object::Endianness;
+
+use crate::{
+    generated::bpf_btf_info,
+    obj::btf::{Btf, BtfError},
+    sys::{bpf_btf_get_fd_by_id, bpf_btf_get_info_by_fd, bpf_btf_get_next_id},
+};
+
+// MODULE_NAME_LEN
+const BTF_NAME_LEN: usize = 64;
+
+/// Loads [`Btf`] held by the kernel.
+pub trait BtfExt: Sized {
+    /// Fetches the BTF object with the given id from the kernel.
+    ///
+    /// The ids of the BTF objects can be listed with [`loaded_btfs`], or read from
+    /// [`ProgramInfo`](crate::programs::ProgramInfo) and [`MapInfo`](crate::maps::MapInfo).
+    /// The BTF of kernel modules is parsed on top of the vmlinux BTF, see
+    /// [`Btf::parse_split`].
+    fn from_id(id: u32) -> Result<Self, BtfError>;
+}
+
+impl BtfExt for Btf {
+    fn from_id(id: u32) -> Result<Btf, BtfError> {
+        with_btf_fd(id, |fd| {
+            let info = BtfInfo::new_from_fd(fd)?;
+            let mut data = vec![0u8; info.size() as usize];
+            bpf_btf_get_info_by_fd(fd, &mut data, &mut []).map_err(|io_error| {
+                BtfError::SyscallError {
+                    call: "bpf_btf_get_info_by_fd".to_owned(),
+                    io_error,
+                }
+            })?;
+
+            if info.is_kernel_btf() && info.name() != "vmlinux" {
+                Btf::parse_split(&data, &Btf::from_sys_fs()?, Endianness::default())
+            } else {
+                Btf::parse(&data, Endianness::default())
+            }
+        })
+    }
+}
+
+/// Provides information about a BTF object loaded in the kernel.
+#[derive(Debug)]
+pub struct BtfInfo {
+    info: bpf_btf_info,
+    name: String,
+}
+
+impl BtfInfo {
+    fn new_from_fd(fd: RawFd) -> Result<BtfInfo, BtfError> {
+        let mut name = [0u8; BTF_NAME_LEN];
+        let info = bpf_btf_get_info_by_fd(fd, &mut [], &mut name).map_err(|io_error| {
+            BtfError::SyscallError {
+                call: "bpf_btf_get_info_by_fd".to_owned(),
+                io_error,
+            }
+        })?;
+        let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+        let name = String::from_utf8_lossy(&name[..len]).into_owned();
+
+        Ok(BtfInfo { info, name })
+    }
+
+    /// Returns the information of the BTF object with the given id.
+    pub fn from_id(id: u32) -> Result<BtfInfo, BtfError> {
+        with_btf_fd(id, BtfInfo::new_from_fd)
+    }
+
+    /// The BTF id. Each BTF object has a unique id.
+    pub fn id(&self) -> u32 {
+        self.info.id
+    }
+
+    /// The name of the BTF object: `vmlinux`, the name of a kernel module, or an empty string
+    /// for the BTF loaded by user space.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if the BTF object belongs to the kernel or to a kernel module.
+    pub fn is_kernel_btf(&self) -> bool {
+        self.info.kernel_btf != 0
+    }
+
+    /// The size of the raw BTF data in bytes.
+    pub fn size(&self) -> u32 {
+        self.info.btf_size
+    }
+
+    /// Fetches the BTF object from the kernel.
+    pub fn btf(&self) -> Result<Btf, BtfError> {
+        Btf::from_id(self.info.id)
+    }
+}
+
+fn with_btf_fd<T>(id: u32, f: impl FnOnce(RawFd) -> Result<T, BtfError>) -> Result<T, BtfError> {
+    let fd = bpf_btf_get_fd_by_id(id).map_err(|io_error| BtfError::SyscallError {
+        call: "bpf_btf_get_fd_by_id".to_owned(),
+        io_error,
+    })?;
+    let ret = f(fd);
+    unsafe { libc::close(fd) };
+    ret
+}
+
+/// BtfsIter is an Iterator over the BTF objects loaded in the kernel.
+pub struct BtfsIter {
+    current: u32,
+    error: bool,
+}
+
+impl Iterator for BtfsIter {
+    type Item = Result<BtfInfo, BtfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error {
+            return None;
+        }
+
+        match bpf_btf_get_next_id(self.current) {
+            Ok(Some(next)) => {
+                self.current = next;
+                Some(BtfInfo::from_id(next))
+            }
+            Ok(None) => None,
+            Err((_, io_error)) => {
+                // If getting the next BTF object failed, we have to yield None in our next
+                // iteration to avoid an infinite loop.
+                self.error = true;
+                Some(Err(BtfError::SyscallError {
+                    call: "bpf_btf_get_next_id".to_owned(),
+                    io_error,
+                }))
+            }
+        }
+    }
+}
+
+/// Returns an iterator over all the BTF objects loaded in the kernel.
+///
+/// # Example
+/// ```
+/// # use aya::btf::loaded_btfs;
+///
+/// for b in loaded_btfs() {
+///     match b {
+///         Ok(btf) => println!("{} {}", btf.id(), btf.name()),
+///         Err(e) => println!("Error iterating BTF objects: {:?}", e),
+///     }
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`BtfError::SyscallError`] if any of the syscalls required to either get
+/// next BTF id, get the BTF fd, or the [`BtfInfo`] fail. In cases where
+/// iteration can't be performed, for example the caller does not have the necessary privileges,
+/// a single item will be yielded containing the error that occurred.
+pub fn loaded_btfs() -> BtfsIter {
+    BtfsIter {
+        current: 0,
+        error: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::EFAULT;
+
+    use crate::{
+        generated::bpf_cmd,
+        sys::{override_syscall, Syscall},
+    };
+
+    use super::*;
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_btf_info_name() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_BTF_GET_FD_BY_ID,
+                ..
+            } => Ok(42),
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                let info = unsafe { &mut *(attr.info.info as *mut bpf_btf_info) };
+                assert_eq!(info.name_len as usize, BTF_NAME_LEN);
+                let name = info.name as *mut u8;
+                for (i, c) in b"nf_tables\0".iter().enumerate() {
+                    unsafe { name.add(i).write(*c) };
+                }
+                info.id = 7;
+                info.kernel_btf = 1;
+                info.btf_size = 1024;
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let info = BtfInfo::from_id(7).unwrap();
+        assert_eq!(info.id(), 7);
+        assert_eq!(info.name(), "nf_tables");
+        assert!(info.is_kernel_btf());
+        assert_eq!(info.size(), 1024);
+    }
+}
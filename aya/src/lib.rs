@@ -45,14 +45,20 @@ extern crate lazy_static;
 #[macro_use]
 extern crate bitflags;
 
+pub mod af_xdp;
 mod bpf;
+pub mod btf;
 use aya_obj::generated;
+pub use btf::loaded_btfs;
 pub mod maps;
 use aya_obj as obj;
+pub use maps::loaded_maps;
 pub mod pin;
 pub mod programs;
 pub use programs::loaded_programs;
 mod sys;
+#[cfg(test)]
+mod test_elf;
 pub mod util;
 
 pub use bpf::*;
@@ -2,11 +2,16 @@ use std::{
     borrow::Borrow,
     convert::{AsMut, AsRef},
     marker::PhantomData,
+    mem, ptr,
 };
 
+use libc::{MAP_SHARED, PROT_READ, PROT_WRITE};
+
 use crate::{
-    maps::{check_bounds, check_kv_size, IterableMap, MapData, MapError},
+    generated::BPF_F_MMAPABLE,
+    maps::{batch, check_bounds, check_kv_size, IterableMap, MMap, MapData, MapError},
     sys::{bpf_map_lookup_elem, bpf_map_update_elem},
+    util::page_size,
     Pod,
 };
 
@@ -31,7 +36,7 @@ use crate::{
 /// ```
 #[doc(alias = "BPF_MAP_TYPE_ARRAY")]
 pub struct Array<T, V: Pod> {
-    inner: T,
+    pub(crate) inner: T,
     _v: PhantomData<V>,
 }
 
@@ -80,6 +85,65 @@ impl<T: AsRef<MapData>, V: Pod> Array<T, V> {
     pub fn iter(&self) -> impl Iterator<Item = Result<V, MapError>> + '_ {
         (0..self.len()).map(move |i| self.get(&i, 0))
     }
+
+    /// Returns all the elements of the array with their index.
+    ///
+    /// The elements are read `batch_size` at a time with `BPF_MAP_LOOKUP_BATCH`, which needs
+    /// kernel 5.6. On older kernels, or if the map doesn't support batch operations, the elements
+    /// are read one by one.
+    pub fn get_batch(&self, batch_size: u32, flags: u64) -> Result<Vec<(u32, V)>, MapError> {
+        batch::get_batch(self.inner.as_ref(), batch_size, flags)
+    }
+
+    /// Maps the elements of the array in memory, to access them without syscalls.
+    ///
+    /// The array must have been created with the `BPF_F_MMAPABLE` flag. The loader sets the flag
+    /// on the maps backing the `.bss` and `.data` sections when the kernel supports it, which
+    /// requires kernel 5.5.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::NotMmapable`] if the array wasn't created with `BPF_F_MMAPABLE`,
+    /// [`MapError::SyscallError`] if `mmap` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let bpf = aya::Bpf::load(&[])?;
+    /// use aya::maps::Array;
+    ///
+    /// let counters = Array::<_, u64>::try_from(bpf.map("COUNTERS").unwrap())?;
+    /// let counters = counters.mmap()?;
+    /// let packets = counters.get(0)?;
+    /// # Ok::<(), aya::BpfError>(())
+    /// ```
+    pub fn mmap(&self) -> Result<ArrayMmap<V>, MapError> {
+        let data = self.inner.as_ref();
+        if data.obj.map_flags() & BPF_F_MMAPABLE == 0 {
+            return Err(MapError::NotMmapable);
+        }
+        let fd = data.fd_or_err()?;
+
+        // the kernel aligns the elements to 8 bytes
+        let elem_size = (mem::size_of::<V>() + 7) & !7;
+        let len = self.len();
+        let page_size = page_size();
+        let size = (len as usize * elem_size + page_size - 1) & !(page_size - 1);
+        let mmap =
+            MMap::new(fd, size, PROT_READ | PROT_WRITE, MAP_SHARED, 0).map_err(|io_error| {
+                MapError::SyscallError {
+                    call: "mmap".to_owned(),
+                    io_error,
+                }
+            })?;
+
+        Ok(ArrayMmap {
+            mmap,
+            len,
+            elem_size,
+            _v: PhantomData,
+        })
+    }
 }
 
 impl<T: AsMut<MapData>, V: Pod> Array<T, V> {
@@ -101,6 +165,92 @@ impl<T: AsMut<MapData>, V: Pod> Array<T, V> {
         })?;
         Ok(())
     }
+
+    /// Sets the values of the elements at `indices[i]` to `values[i]`.
+    ///
+    /// The elements are set with a single `BPF_MAP_UPDATE_BATCH` syscall, which needs kernel 5.6,
+    /// or one by one otherwise. Array elements can't be deleted, so there are no `drain_batch` and
+    /// `remove_batch` methods.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if an index is out of bounds,
+    /// [`MapError::BatchSizeMismatch`] if `indices` and `values` have different lengths.
+    pub fn insert_batch(
+        &mut self,
+        indices: &[u32],
+        values: &[V],
+        flags: u64,
+    ) -> Result<(), MapError> {
+        let data = self.inner.as_mut();
+        for index in indices {
+            check_bounds(data, *index)?;
+        }
+        batch::insert_batch(data, indices, values, flags)
+    }
+}
+
+/// The elements of an [`Array`] mapped in memory.
+///
+/// Returned by [`Array::mmap`]. eBPF programs can update the elements at any time, so they're
+/// accessed with volatile reads and writes. The mapping stays valid after the array is dropped.
+#[derive(Debug)]
+pub struct ArrayMmap<V: Pod> {
+    mmap: MMap,
+    len: u32,
+    elem_size: usize,
+    _v: PhantomData<V>,
+}
+
+impl<V: Pod> ArrayMmap<V> {
+    /// Returns the number of elements in the array.
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    /// Returns `true` if the array has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the value stored at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds.
+    pub fn get(&self, index: u32) -> Result<V, MapError> {
+        let ptr = self.elem_ptr(index)?;
+        // Safety: the element is in the mapping and V is Pod
+        Ok(unsafe { ptr::read_volatile(ptr) })
+    }
+
+    /// Sets the value of the element at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds.
+    pub fn set(&mut self, index: u32, value: impl Borrow<V>) -> Result<(), MapError> {
+        let ptr = self.elem_ptr(index)?;
+        // Safety: the element is in the mapping and V is Pod
+        unsafe { ptr::write_volatile(ptr, *value.borrow()) };
+        Ok(())
+    }
+
+    /// An iterator over the elements of the array. The iterator item type is `V`.
+    pub fn iter(&self) -> impl Iterator<Item = V> + '_ {
+        (0..self.len).map(move |i| self.get(i).unwrap())
+    }
+
+    fn elem_ptr(&self, index: u32) -> Result<*mut V, MapError> {
+        if index >= self.len {
+            return Err(MapError::OutOfBounds {
+                index,
+                max_entries: self.len,
+            });
+        }
+        let offset = index as usize * self.elem_size;
+        Ok(unsafe { self.mmap.ptr().as_ptr().cast::<u8>().add(offset) }.cast())
+    }
 }
 
 impl<T: AsRef<MapData>, V: Pod> IterableMap<u32, V> for Array<T, V> {
@@ -112,3 +262,72 @@ impl<T: AsRef<MapData>, V: Pod> IterableMap<u32, V> for Array<T, V> {
         self.get(index, 0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bpf_map_def,
+        generated::bpf_map_type::BPF_MAP_TYPE_ARRAY,
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::TEST_MMAP_RET,
+    };
+
+    use super::*;
+
+    fn new_map(map_flags: u32) -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_ARRAY as u32,
+                    key_size: 4,
+                    value_size: 4,
+                    max_entries: 4,
+                    map_flags,
+                    ..Default::default()
+                },
+                section_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+                symbol_index: 0,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    #[test]
+    fn test_mmap_not_mmapable() {
+        let map = new_map(0);
+        let array = Array::<_, u32>::new(&map).unwrap();
+        assert!(matches!(array.mmap(), Err(MapError::NotMmapable)));
+    }
+
+    #[test]
+    fn test_mmap_get_set() {
+        let mut buf = vec![0u64; page_size() / 8];
+        TEST_MMAP_RET.with(|ret| *ret.borrow_mut() = buf.as_mut_ptr().cast());
+
+        let map = new_map(BPF_F_MMAPABLE);
+        let array = Array::<_, u32>::new(&map).unwrap();
+        let mut mmap = array.mmap().unwrap();
+        assert_eq!(mmap.len(), 4);
+
+        mmap.set(1, 42).unwrap();
+        assert_eq!(mmap.get(1).unwrap(), 42);
+        // the elements are 8 bytes aligned
+        assert_eq!(unsafe { *buf.as_ptr().add(1).cast::<u32>() }, 42);
+        assert!(matches!(
+            mmap.get(4),
+            Err(MapError::OutOfBounds {
+                index: 4,
+                max_entries: 4
+            })
+        ));
+        drop(mmap);
+    }
+}
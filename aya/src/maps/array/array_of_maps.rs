@@ -0,0 +1,260 @@
+//! An array of eBPF maps.
+
+use std::{
+    convert::{AsMut, AsRef},
+    os::unix::prelude::RawFd,
+};
+
+use crate::{
+    maps::{check_bounds, check_kv_size, MapData, MapError, MapKeys},
+    sys::{bpf_map_delete_elem, bpf_map_lookup_elem, bpf_map_update_elem},
+};
+
+/// An array of eBPF maps.
+///
+/// eBPF programs look up an index of the array to get one of the inner maps, which they then use
+/// like any other map. Replacing the map at an index from user space atomically switches all the
+/// programs over to the new map.
+///
+/// All the inner maps must have the same definition as the inner map template the array was
+/// created with. See [`BpfLoader::set_inner_map`](crate::BpfLoader::set_inner_map).
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.12.
+///
+/// # Examples
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::maps::{ArrayOfMaps, MapData};
+///
+/// let policy = MapData::from_pin("/sys/fs/bpf/policies/tenant_3")?;
+/// let mut policies = ArrayOfMaps::try_from(bpf.map_mut("POLICIES").unwrap())?;
+///
+/// // programs looking up index 3 now get the new policy
+/// policies.set(3, &policy, 0)?;
+/// # Ok::<(), aya::BpfError>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_ARRAY_OF_MAPS")]
+pub struct ArrayOfMaps<T> {
+    pub(crate) inner: T,
+}
+
+impl<T: AsRef<MapData>> ArrayOfMaps<T> {
+    pub(crate) fn new(map: T) -> Result<ArrayOfMaps<T>, MapError> {
+        let data = map.as_ref();
+        check_kv_size::<u32, RawFd>(data)?;
+
+        let _fd = data.fd_or_err()?;
+
+        Ok(ArrayOfMaps { inner: map })
+    }
+
+    /// Returns the number of elements in the array.
+    ///
+    /// This corresponds to the value of `bpf_map_def::max_entries` on the eBPF side.
+    pub fn len(&self) -> u32 {
+        self.inner.as_ref().obj.max_entries()
+    }
+
+    /// Returns the id of the map stored at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds, [`MapError::KeyNotFound`] if
+    /// no map is set at `index`, [`MapError::SyscallError`] if `bpf_map_lookup_elem` fails.
+    pub fn get(&self, index: u32, flags: u64) -> Result<u32, MapError> {
+        let data = self.inner.as_ref();
+        check_bounds(data, index)?;
+        let fd = data.fd_or_err()?;
+
+        let value = bpf_map_lookup_elem(fd, &index, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_lookup_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        value.ok_or(MapError::KeyNotFound)
+    }
+
+    /// An iterator over all the indices of the array, including those that don't point to a map.
+    /// The iterator item type is `Result<u32, MapError>`.
+    pub fn indices(&self) -> MapKeys<'_, u32> {
+        MapKeys::new(self.inner.as_ref())
+    }
+}
+
+impl<T: AsMut<MapData>> ArrayOfMaps<T> {
+    /// Sets the map stored at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds, [`MapError::SyscallError`]
+    /// if `bpf_map_update_elem` fails.
+    pub fn set(&mut self, index: u32, value: &MapData, flags: u64) -> Result<(), MapError> {
+        let data = self.inner.as_mut();
+        check_bounds(data, index)?;
+        let fd = data.fd_or_err()?;
+        let map_fd = value.fd_or_err()?;
+
+        bpf_map_update_elem(fd, Some(&index), &map_fd, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_update_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        Ok(())
+    }
+
+    /// Clears the map stored at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds, [`MapError::SyscallError`]
+    /// if `bpf_map_delete_elem` fails.
+    pub fn clear_index(&mut self, index: u32) -> Result<(), MapError> {
+        let data = self.inner.as_mut();
+        check_bounds(data, index)?;
+        let fd = data.fd_or_err()?;
+
+        bpf_map_delete_elem(fd, &index)
+            .map(|_| ())
+            .map_err(|(_, io_error)| MapError::SyscallError {
+                call: "bpf_map_delete_elem".to_owned(),
+                io_error,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::EFAULT;
+
+    use crate::{
+        bpf_map_def,
+        generated::{bpf_cmd, bpf_map_type::BPF_MAP_TYPE_ARRAY_OF_MAPS},
+        maps::{Map, MapData},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, SysResult, Syscall},
+    };
+
+    use super::*;
+
+    fn new_obj_map(value_size: u32) -> obj::Map {
+        obj::Map::Legacy(LegacyMap {
+            def: bpf_map_def {
+                map_type: BPF_MAP_TYPE_ARRAY_OF_MAPS as u32,
+                key_size: 4,
+                value_size,
+                max_entries: 8,
+                ..Default::default()
+            },
+            section_index: 0,
+            data: Vec::new(),
+            kind: MapKind::Other,
+            symbol_index: 0,
+        })
+    }
+
+    fn new_map(value_size: u32, fd: RawFd) -> MapData {
+        MapData {
+            obj: new_obj_map(value_size),
+            fd: Some(fd),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn sys_error(value: i32) -> SysResult {
+        Err((-1, io::Error::from_raw_os_error(value)))
+    }
+
+    #[test]
+    fn test_wrong_value_size() {
+        let map = new_map(8, 42);
+        assert!(matches!(
+            ArrayOfMaps::new(&map),
+            Err(MapError::InvalidValueSize {
+                size: 4,
+                expected: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_from_wrong_map() {
+        let map = Map::Array(new_map(4, 42));
+        assert!(matches!(
+            ArrayOfMaps::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_out_of_bounds() {
+        let mut map = new_map(4, 42);
+        let inner = new_map(4, 43);
+        let mut array = ArrayOfMaps::new(&mut map).unwrap();
+
+        assert!(matches!(
+            array.set(8, &inner, 0),
+            Err(MapError::OutOfBounds {
+                index: 8,
+                max_entries: 8
+            })
+        ));
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_set_inner_map_fd() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_ELEM,
+                attr,
+            } => {
+                let value =
+                    unsafe { *(attr.__bindgen_anon_2.__bindgen_anon_1.value as *const RawFd) };
+                assert_eq!(value, 43);
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let mut map = new_map(4, 42);
+        let inner = new_map(4, 43);
+        let mut array = ArrayOfMaps::new(&mut map).unwrap();
+
+        assert!(array.set(1, &inner, 0).is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_get_inner_map_id() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_ELEM,
+                attr,
+            } => {
+                let value = unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.value } as *mut u32;
+                unsafe { *value = 7 };
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let map = new_map(4, 42);
+        let array = ArrayOfMaps::new(&map).unwrap();
+
+        assert_eq!(array.get(1, 0).unwrap(), 7);
+    }
+}
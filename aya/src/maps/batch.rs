@@ -0,0 +1,550 @@
+//! Batch operations on maps.
+//!
+//! Batch operations look up, update or delete many elements with a single syscall. They're
+//! available since kernel 5.6 and not on all map types, so every operation falls back to
+//! processing the elements one by one when the kernel doesn't support it.
+use std::{cmp, io, mem, ptr, slice};
+
+use libc::{EINVAL, ENOENT, ENOSPC, EOPNOTSUPP};
+
+use crate::{
+    maps::{MapData, MapError, MapKeys, PerCpuKernelMem, PerCpuValues, ENOTSUPP},
+    sys::{
+        bpf_map_delete_batch, bpf_map_delete_elem, bpf_map_lookup_and_delete_batch,
+        bpf_map_lookup_batch, bpf_map_lookup_elem, bpf_map_lookup_elem_per_cpu,
+        bpf_map_update_batch, bpf_map_update_elem, bpf_map_update_elem_per_cpu,
+    },
+    util::nr_cpus,
+    Pod,
+};
+
+// Kernels before 5.6 don't know the batch commands and fail with EINVAL.
+fn is_unsupported(io_error: &io::Error) -> bool {
+    matches!(
+        io_error.raw_os_error(),
+        Some(EINVAL) | Some(EOPNOTSUPP) | Some(ENOTSUPP)
+    )
+}
+
+fn as_bytes<T: Pod>(items: &[T]) -> &[u8] {
+    // Safety: T is Pod
+    unsafe { slice::from_raw_parts(items.as_ptr() as *const u8, mem::size_of_val(items)) }
+}
+
+fn read<T: Pod>(bytes: &[u8]) -> T {
+    // Safety: T is Pod and the batch buffers hold `size_of::<T>()` bytes per element
+    unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+fn per_cpu_value_size<V: Pod>() -> Result<usize, MapError> {
+    let nr_cpus = nr_cpus().map_err(|io_error| MapError::SyscallError {
+        call: "nr_cpus".to_owned(),
+        io_error,
+    })?;
+    Ok(nr_cpus * ((mem::size_of::<V>() + 7) & !7))
+}
+
+fn read_per_cpu<V: Pod>(bytes: &[u8]) -> PerCpuValues<V> {
+    let mem = PerCpuKernelMem {
+        bytes: bytes.to_vec(),
+    };
+    // Safety: the batch buffers hold the values of all the CPUs for each element
+    unsafe { PerCpuValues::from_kernel_mem(mem) }
+}
+
+fn check_batch_len(keys: usize, values: usize) -> Result<(), MapError> {
+    if keys != values {
+        return Err(MapError::BatchSizeMismatch { keys, values });
+    }
+    Ok(())
+}
+
+// Looks up, and deletes if `delete` is set, all the elements of the map, `batch_size` elements per
+// syscall. Each element takes `value_size` bytes of the values buffer, which `value` decodes.
+// Returns `None` if the kernel doesn't support batch lookups on the map.
+fn lookup_batch<K: Pod, V>(
+    map: &MapData,
+    delete: bool,
+    batch_size: u32,
+    value_size: usize,
+    flags: u64,
+    value: impl Fn(&[u8]) -> V,
+) -> Result<Option<Vec<(K, V)>>, MapError> {
+    let fd = map.fd_or_err()?;
+    let mut batch_size = cmp::max(batch_size, 1);
+    let key_size = mem::size_of::<K>();
+    // hash maps use the index of the next bucket as batch token, the other maps the last key
+    let mut in_batch: Option<Vec<u8>> = None;
+    let mut out_batch = vec![0u8; cmp::max(key_size, mem::size_of::<u32>())];
+    let mut keys = vec![0u8; batch_size as usize * key_size];
+    let mut values = vec![0u8; batch_size as usize * value_size];
+    let mut entries = Vec::new();
+
+    loop {
+        let mut count = batch_size;
+        let (call, ret) = if delete {
+            (
+                "bpf_map_lookup_and_delete_batch",
+                bpf_map_lookup_and_delete_batch(
+                    fd,
+                    in_batch.as_deref(),
+                    &mut out_batch,
+                    &mut keys,
+                    &mut values,
+                    &mut count,
+                    flags,
+                ),
+            )
+        } else {
+            (
+                "bpf_map_lookup_batch",
+                bpf_map_lookup_batch(
+                    fd,
+                    in_batch.as_deref(),
+                    &mut out_batch,
+                    &mut keys,
+                    &mut values,
+                    &mut count,
+                    flags,
+                ),
+            )
+        };
+        // ENOENT signals the last batch
+        let done = match ret {
+            Ok(_) => false,
+            Err((_, io_error)) if io_error.raw_os_error() == Some(ENOENT) => true,
+            Err((_, io_error)) if in_batch.is_none() && is_unsupported(&io_error) => {
+                return Ok(None)
+            }
+            // hash maps return whole buckets, and fail if the next one doesn't fit in the batch
+            Err((_, io_error))
+                if io_error.raw_os_error() == Some(ENOSPC)
+                    && batch_size < map.obj.max_entries() =>
+            {
+                batch_size = batch_size.saturating_mul(2);
+                keys.resize(batch_size as usize * key_size, 0);
+                values.resize(batch_size as usize * value_size, 0);
+                continue;
+            }
+            Err((_, io_error)) => {
+                return Err(MapError::SyscallError {
+                    call: call.to_owned(),
+                    io_error,
+                })
+            }
+        };
+
+        let count = cmp::min(count, batch_size) as usize;
+        entries.extend(
+            keys.chunks_exact(key_size)
+                .zip(values.chunks_exact(value_size))
+                .take(count)
+                .map(|(k, v)| (read(k), value(v))),
+        );
+        if done {
+            return Ok(Some(entries));
+        }
+        in_batch = Some(out_batch.clone());
+    }
+}
+
+// Looks up the elements one by one, for kernels without batch lookups.
+fn lookup_each<K: Pod, V>(
+    map: &MapData,
+    delete: bool,
+    get: impl Fn(&K) -> Result<Option<V>, (libc::c_long, io::Error)>,
+) -> Result<Vec<(K, V)>, MapError> {
+    let fd = map.fd_or_err()?;
+    let keys = MapKeys::<K>::new(map).collect::<Result<Vec<_>, _>>()?;
+    let mut entries = Vec::with_capacity(keys.len());
+    for key in keys {
+        let value = get(&key).map_err(|(_, io_error)| MapError::SyscallError {
+            call: "bpf_map_lookup_elem".to_owned(),
+            io_error,
+        })?;
+        // skip the elements deleted since the keys were listed
+        let value = match value {
+            Some(value) => value,
+            None => continue,
+        };
+        if delete {
+            match bpf_map_delete_elem(fd, &key) {
+                Ok(_) => {}
+                Err((_, io_error)) if io_error.raw_os_error() == Some(ENOENT) => continue,
+                Err((_, io_error)) => {
+                    return Err(MapError::SyscallError {
+                        call: "bpf_map_delete_elem".to_owned(),
+                        io_error,
+                    })
+                }
+            }
+        }
+        entries.push((key, value));
+    }
+    Ok(entries)
+}
+
+// Updates the elements with a single syscall. Returns `false` if the kernel doesn't support batch
+// updates on the map.
+fn update_batch<K: Pod>(
+    map: &MapData,
+    keys: &[K],
+    values: &[u8],
+    flags: u64,
+) -> Result<bool, MapError> {
+    let fd = map.fd_or_err()?;
+    let mut count = keys.len() as u32;
+    match bpf_map_update_batch(fd, as_bytes(keys), values, &mut count, flags) {
+        Ok(_) => Ok(true),
+        Err((_, io_error)) if is_unsupported(&io_error) => Ok(false),
+        Err((_, io_error)) => Err(MapError::SyscallError {
+            call: "bpf_map_update_batch".to_owned(),
+            io_error,
+        }),
+    }
+}
+
+pub(crate) fn get_batch<K: Pod, V: Pod>(
+    map: &MapData,
+    batch_size: u32,
+    flags: u64,
+) -> Result<Vec<(K, V)>, MapError> {
+    let fd = map.fd_or_err()?;
+    match lookup_batch(map, false, batch_size, mem::size_of::<V>(), flags, read)? {
+        Some(entries) => Ok(entries),
+        None => lookup_each(map, false, |key| bpf_map_lookup_elem(fd, key, flags)),
+    }
+}
+
+pub(crate) fn drain_batch<K: Pod, V: Pod>(
+    map: &MapData,
+    batch_size: u32,
+    flags: u64,
+) -> Result<Vec<(K, V)>, MapError> {
+    let fd = map.fd_or_err()?;
+    match lookup_batch(map, true, batch_size, mem::size_of::<V>(), flags, read)? {
+        Some(entries) => Ok(entries),
+        None => lookup_each(map, true, |key| bpf_map_lookup_elem(fd, key, flags)),
+    }
+}
+
+pub(crate) fn get_batch_per_cpu<K: Pod, V: Pod>(
+    map: &MapData,
+    batch_size: u32,
+    flags: u64,
+) -> Result<Vec<(K, PerCpuValues<V>)>, MapError> {
+    let value_size = per_cpu_value_size::<V>()?;
+    let fd = map.fd_or_err()?;
+    match lookup_batch(map, false, batch_size, value_size, flags, read_per_cpu)? {
+        Some(entries) => Ok(entries),
+        None => lookup_each(map, false, |key| {
+            bpf_map_lookup_elem_per_cpu(fd, key, flags)
+        }),
+    }
+}
+
+pub(crate) fn drain_batch_per_cpu<K: Pod, V: Pod>(
+    map: &MapData,
+    batch_size: u32,
+    flags: u64,
+) -> Result<Vec<(K, PerCpuValues<V>)>, MapError> {
+    let value_size = per_cpu_value_size::<V>()?;
+    let fd = map.fd_or_err()?;
+    match lookup_batch(map, true, batch_size, value_size, flags, read_per_cpu)? {
+        Some(entries) => Ok(entries),
+        None => lookup_each(map, true, |key| bpf_map_lookup_elem_per_cpu(fd, key, flags)),
+    }
+}
+
+pub(crate) fn insert_batch<K: Pod, V: Pod>(
+    map: &MapData,
+    keys: &[K],
+    values: &[V],
+    flags: u64,
+) -> Result<(), MapError> {
+    check_batch_len(keys.len(), values.len())?;
+    if keys.is_empty() || update_batch(map, keys, as_bytes(values), flags)? {
+        return Ok(());
+    }
+    let fd = map.fd_or_err()?;
+    for (key, value) in keys.iter().zip(values) {
+        bpf_map_update_elem(fd, Some(key), value, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_update_elem".to_owned(),
+                io_error,
+            }
+        })?;
+    }
+    Ok(())
+}
+
+pub(crate) fn insert_batch_per_cpu<K: Pod, V: Pod>(
+    map: &MapData,
+    keys: &[K],
+    values: &[PerCpuValues<V>],
+    flags: u64,
+) -> Result<(), MapError> {
+    check_batch_len(keys.len(), values.len())?;
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let mut bytes = Vec::with_capacity(values.len() * per_cpu_value_size::<V>()?);
+    for value in values {
+        let mem = value
+            .build_kernel_mem()
+            .map_err(|io_error| MapError::SyscallError {
+                call: "nr_cpus".to_owned(),
+                io_error,
+            })?;
+        bytes.extend_from_slice(&mem.bytes);
+    }
+    if update_batch(map, keys, &bytes, flags)? {
+        return Ok(());
+    }
+    let fd = map.fd_or_err()?;
+    for (key, value) in keys.iter().zip(values) {
+        bpf_map_update_elem_per_cpu(fd, key, value, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_update_elem".to_owned(),
+                io_error,
+            }
+        })?;
+    }
+    Ok(())
+}
+
+pub(crate) fn remove_batch<K: Pod>(map: &MapData, keys: &[K]) -> Result<(), MapError> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let fd = map.fd_or_err()?;
+    let mut count = keys.len() as u32;
+    match bpf_map_delete_batch(fd, as_bytes(keys), &mut count, 0) {
+        Ok(_) => return Ok(()),
+        Err((_, io_error)) if is_unsupported(&io_error) => {}
+        Err((_, io_error)) => {
+            return Err(MapError::SyscallError {
+                call: "bpf_map_delete_batch".to_owned(),
+                io_error,
+            })
+        }
+    }
+    for key in keys {
+        bpf_map_delete_elem(fd, key).map_err(|(_, io_error)| MapError::SyscallError {
+            call: "bpf_map_delete_elem".to_owned(),
+            io_error,
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use libc::EFAULT;
+
+    use crate::{
+        bpf_map_def,
+        generated::{bpf_cmd, bpf_map_type::BPF_MAP_TYPE_HASH},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, SysResult, Syscall},
+    };
+
+    use super::*;
+
+    fn new_map() -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_HASH as u32,
+                    key_size: 4,
+                    value_size: 4,
+                    max_entries: 1024,
+                    ..Default::default()
+                },
+                section_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+                symbol_index: 0,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn sys_error(value: i32) -> SysResult {
+        Err((-1, io::Error::from_raw_os_error(value)))
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_get_batch() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_BATCH,
+                attr,
+            } => {
+                let batch = unsafe { &attr.batch };
+                assert_eq!(batch.count, 2);
+                let keys = batch.keys as *mut u32;
+                let values = batch.values as *mut u32;
+                unsafe {
+                    keys.write(10);
+                    keys.add(1).write(20);
+                    values.write(100);
+                    values.add(1).write(200);
+                }
+                // the last batch
+                sys_error(ENOENT)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let map = new_map();
+        let entries = get_batch::<u32, u32>(&map, 2, 0).unwrap();
+        assert_eq!(entries, vec![(10, 100), (20, 200)]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_get_batch_bucket_too_big() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_BATCH,
+                attr,
+            } => {
+                let batch = unsafe { &attr.batch };
+                // the first bucket holds two elements
+                if batch.count < 2 {
+                    return sys_error(ENOSPC);
+                }
+                assert_eq!(batch.in_batch, 0);
+                let keys = batch.keys as *mut u32;
+                let values = batch.values as *mut u32;
+                unsafe {
+                    keys.write(10);
+                    keys.add(1).write(20);
+                    values.write(100);
+                    values.add(1).write(200);
+                }
+                sys_error(ENOENT)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let map = new_map();
+        let entries = get_batch::<u32, u32>(&map, 1, 0).unwrap();
+        assert_eq!(entries, vec![(10, 100), (20, 200)]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_get_batch_fallback() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_BATCH,
+                ..
+            } => sys_error(EINVAL),
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_GET_NEXT_KEY,
+                attr,
+            } => {
+                let key = unsafe { attr.__bindgen_anon_2.key } as *const u32;
+                let next_key =
+                    unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.next_key } as *mut u32;
+                match key.is_null() {
+                    true => {
+                        unsafe { next_key.write(10) };
+                        Ok(1)
+                    }
+                    false => sys_error(ENOENT),
+                }
+            }
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_ELEM,
+                attr,
+            } => {
+                let value = unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.value } as *mut u32;
+                unsafe { value.write(100) };
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let map = new_map();
+        let entries = get_batch::<u32, u32>(&map, 16, 0).unwrap();
+        assert_eq!(entries, vec![(10, 100)]);
+    }
+
+    #[test]
+    fn test_get_batch_not_created() {
+        let mut map = new_map();
+        map.fd = None;
+        assert!(matches!(
+            get_batch::<u32, u32>(&map, 16, 0),
+            Err(MapError::NotCreated)
+        ));
+        assert!(matches!(
+            drain_batch_per_cpu::<u32, u32>(&map, 16, 0),
+            Err(MapError::NotCreated)
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_insert_batch() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_BATCH,
+                attr,
+            } => {
+                let batch = unsafe { &attr.batch };
+                assert_eq!(batch.count, 2);
+                let keys = unsafe { slice::from_raw_parts(batch.keys as *const u32, 2) };
+                let values = unsafe { slice::from_raw_parts(batch.values as *const u32, 2) };
+                assert_eq!(keys, [1, 2]);
+                assert_eq!(values, [10, 20]);
+                Ok(0)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let map = new_map();
+        assert!(insert_batch::<u32, u32>(&map, &[1, 2], &[10, 20], 0).is_ok());
+    }
+
+    #[test]
+    fn test_insert_batch_len_mismatch() {
+        let map = new_map();
+        assert!(matches!(
+            insert_batch::<u32, u32>(&map, &[1, 2], &[10], 0),
+            Err(MapError::BatchSizeMismatch { keys: 2, values: 1 })
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_remove_batch_fallback() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_DELETE_BATCH,
+                ..
+            } => sys_error(ENOTSUPP),
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_DELETE_ELEM,
+                ..
+            } => Ok(1),
+            _ => sys_error(EFAULT),
+        });
+
+        let map = new_map();
+        assert!(remove_batch::<u32>(&map, &[1, 2]).is_ok());
+    }
+}
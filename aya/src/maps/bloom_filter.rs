@@ -31,7 +31,7 @@ use crate::{
 
 #[doc(alias = "BPF_MAP_TYPE_BLOOM_FILTER")]
 pub struct BloomFilter<T, V: Pod> {
-    inner: T,
+    pub(crate) inner: T,
     _v: PhantomData<V>,
 }
 
@@ -119,6 +119,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         assert!(matches!(
@@ -148,6 +149,7 @@ mod tests {
             }),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -165,6 +167,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -180,6 +183,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -192,6 +196,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -207,6 +212,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let bloom_filter = BloomFilter::<_, u32>::new(&mut map).unwrap();
@@ -231,6 +237,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -245,6 +252,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let bloom_filter = BloomFilter::<_, u32>::new(&map).unwrap();
@@ -268,6 +276,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let bloom_filter = BloomFilter::<_, u32>::new(&map).unwrap();
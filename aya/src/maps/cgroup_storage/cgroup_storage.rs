@@ -0,0 +1,234 @@
+//! Cgroup storage.
+use std::{
+    borrow::Borrow,
+    convert::{AsMut, AsRef},
+    marker::PhantomData,
+};
+
+use crate::{
+    maps::{check_kv_size, CgroupStorageKey, IterableMap, MapData, MapError, MapIter, MapKeys},
+    sys::{bpf_map_lookup_elem, bpf_map_update_elem},
+    Pod,
+};
+
+/// A map holding a value for each cgroup a program is attached to.
+///
+/// eBPF programs get the value of the current cgroup with `bpf_get_local_storage`. The values
+/// are created when the program is attached and can't be added or removed from user space.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.19.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[derive(Debug, thiserror::Error)]
+/// # enum Error {
+/// #     #[error(transparent)]
+/// #     IO(#[from] std::io::Error),
+/// #     #[error(transparent)]
+/// #     Map(#[from] aya::maps::MapError),
+/// #     #[error(transparent)]
+/// #     Program(#[from] aya::programs::ProgramError),
+/// #     #[error(transparent)]
+/// #     Bpf(#[from] aya::BpfError)
+/// # }
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use std::{fs::File, os::unix::io::AsRawFd};
+/// use aya::maps::CgroupStorage;
+/// use aya::programs::{CgroupSkb, CgroupSkbAttachType};
+///
+/// let file = File::open("/sys/fs/cgroup/unified")?;
+/// let program: &mut CgroupSkb = bpf.program_mut("count_egress").unwrap().try_into()?;
+/// program.load()?;
+/// program.attach(file.as_raw_fd(), CgroupSkbAttachType::Egress)?;
+///
+/// let key = CgroupSkb::storage_key(file.as_raw_fd(), CgroupSkbAttachType::Egress)?;
+/// let storage = CgroupStorage::<_, u64>::try_from(bpf.map("EGRESS_BYTES").unwrap())?;
+/// let bytes = storage.get(&key, 0)?;
+/// # Ok::<(), Error>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_CGROUP_STORAGE")]
+#[derive(Debug)]
+pub struct CgroupStorage<T, V> {
+    pub(crate) inner: T,
+    _v: PhantomData<V>,
+}
+
+impl<T: AsRef<MapData>, V: Pod> CgroupStorage<T, V> {
+    pub(crate) fn new(map: T) -> Result<CgroupStorage<T, V>, MapError> {
+        let data = map.as_ref();
+        check_kv_size::<CgroupStorageKey, V>(data)?;
+        let _ = data.fd_or_err()?;
+
+        Ok(CgroupStorage {
+            inner: map,
+            _v: PhantomData,
+        })
+    }
+
+    /// Returns a copy of the value stored for the key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::KeyNotFound`] if the program isn't attached to the cgroup with the
+    /// attach type of the key, [`MapError::SyscallError`] if `bpf_map_lookup_elem` fails.
+    pub fn get(&self, key: &CgroupStorageKey, flags: u64) -> Result<V, MapError> {
+        let fd = self.inner.as_ref().fd_or_err()?;
+        let value = bpf_map_lookup_elem(fd, key, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_lookup_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        value.ok_or(MapError::KeyNotFound)
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The
+    /// iterator item type is `Result<(CgroupStorageKey, V), MapError>`.
+    pub fn iter(&self) -> MapIter<'_, CgroupStorageKey, V, Self> {
+        MapIter::new(self)
+    }
+
+    /// An iterator visiting all keys in arbitrary order. The iterator element
+    /// type is `Result<CgroupStorageKey, MapError>`.
+    pub fn keys(&self) -> MapKeys<'_, CgroupStorageKey> {
+        MapKeys::new(self.inner.as_ref())
+    }
+}
+
+impl<T: AsMut<MapData>, V: Pod> CgroupStorage<T, V> {
+    /// Updates the value stored for the key.
+    ///
+    /// The value must already exist, i.e. the program must be attached to the cgroup.
+    pub fn set(
+        &mut self,
+        key: &CgroupStorageKey,
+        value: impl Borrow<V>,
+        flags: u64,
+    ) -> Result<(), MapError> {
+        let fd = self.inner.as_mut().fd_or_err()?;
+        bpf_map_update_elem(fd, Some(key), value.borrow(), flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_update_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        Ok(())
+    }
+}
+
+impl<T: AsRef<MapData>, V: Pod> IterableMap<CgroupStorageKey, V> for CgroupStorage<T, V> {
+    fn map(&self) -> &MapData {
+        self.inner.as_ref()
+    }
+
+    fn get(&self, key: &CgroupStorageKey) -> Result<V, MapError> {
+        CgroupStorage::get(self, key, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::{EFAULT, ENOENT};
+
+    use crate::{
+        bpf_map_def,
+        generated::{bpf_cmd, bpf_map_type},
+        maps::{Map, MapData},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, SysResult, Syscall},
+    };
+
+    use super::*;
+
+    fn new_map() -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: bpf_map_type::BPF_MAP_TYPE_CGROUP_STORAGE as u32,
+                    key_size: 16,
+                    value_size: 8,
+                    max_entries: 0,
+                    ..Default::default()
+                },
+                section_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+                symbol_index: 0,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn sys_error(value: i32) -> SysResult {
+        Err((-1, io::Error::from_raw_os_error(value)))
+    }
+
+    #[test]
+    fn test_wrong_value_size() {
+        let map = new_map();
+        assert!(matches!(
+            CgroupStorage::<_, u32>::new(&map),
+            Err(MapError::InvalidValueSize {
+                size: 4,
+                expected: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_from_wrong_map() {
+        let map = Map::HashMap(new_map());
+        assert!(matches!(
+            CgroupStorage::<_, u64>::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_set_key() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_ELEM,
+                attr,
+            } => {
+                let key = unsafe { *(attr.__bindgen_anon_2.key as *const CgroupStorageKey) };
+                assert_eq!(key, CgroupStorageKey::new(1234, 1));
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let mut map = new_map();
+        let mut storage = CgroupStorage::<_, u64>::new(&mut map).unwrap();
+
+        assert!(storage.set(&CgroupStorageKey::new(1234, 1), 42, 0).is_ok());
+    }
+
+    #[test]
+    fn test_get_not_found() {
+        override_syscall(|_| sys_error(ENOENT));
+
+        let map = new_map();
+        let storage = CgroupStorage::<_, u64>::new(&map).unwrap();
+
+        assert!(matches!(
+            storage.get(&CgroupStorageKey::new(1234, 1), 0),
+            Err(MapError::KeyNotFound)
+        ));
+    }
+}
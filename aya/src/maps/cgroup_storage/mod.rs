@@ -0,0 +1,42 @@
+//! Cgroup storage maps.
+//!
+//! Cgroup storage maps hold a value for each cgroup a program is attached to. eBPF programs get
+//! the value of the cgroup they run for with `bpf_get_local_storage`. The kernel creates the
+//! values when the program is attached and frees them when it is detached, so user space can only
+//! read and update them.
+#[allow(clippy::module_inception)]
+mod cgroup_storage;
+mod per_cpu_cgroup_storage;
+
+pub use cgroup_storage::CgroupStorage;
+pub use per_cpu_cgroup_storage::PerCpuCgroupStorage;
+
+use crate::Pod;
+
+/// The key of a cgroup storage map.
+///
+/// Identifies the value created when a program was attached to a cgroup. See
+/// [`CgroupSkb::storage_key`](crate::programs::CgroupSkb::storage_key) and
+/// [`CgroupSockAddr::storage_key`](crate::programs::CgroupSockAddr::storage_key).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CgroupStorageKey {
+    /// The inode number of the cgroup directory.
+    pub cgroup_inode_id: u64,
+    /// The attach type of the program.
+    pub attach_type: u32,
+    _pad: u32,
+}
+
+impl CgroupStorageKey {
+    /// Creates a new key.
+    pub fn new(cgroup_inode_id: u64, attach_type: u32) -> CgroupStorageKey {
+        CgroupStorageKey {
+            cgroup_inode_id,
+            attach_type,
+            _pad: 0,
+        }
+    }
+}
+
+unsafe impl Pod for CgroupStorageKey {}
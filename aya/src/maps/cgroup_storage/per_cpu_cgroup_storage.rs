@@ -0,0 +1,195 @@
+//! Per-CPU cgroup storage.
+use std::{
+    convert::{AsMut, AsRef},
+    marker::PhantomData,
+};
+
+use crate::{
+    maps::{
+        check_kv_size, CgroupStorageKey, IterableMap, MapData, MapError, MapIter, MapKeys,
+        PerCpuValues,
+    },
+    sys::{bpf_map_lookup_elem_per_cpu, bpf_map_update_elem_per_cpu},
+    Pod,
+};
+
+/// Similar to [`CgroupStorage`](crate::maps::CgroupStorage) but each CPU holds a separate value
+/// for a given cgroup.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.20.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[derive(Debug, thiserror::Error)]
+/// # enum Error {
+/// #     #[error(transparent)]
+/// #     IO(#[from] std::io::Error),
+/// #     #[error(transparent)]
+/// #     Map(#[from] aya::maps::MapError),
+/// #     #[error(transparent)]
+/// #     Program(#[from] aya::programs::ProgramError),
+/// #     #[error(transparent)]
+/// #     Bpf(#[from] aya::BpfError)
+/// # }
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use std::{fs::File, os::unix::io::AsRawFd};
+/// use aya::maps::PerCpuCgroupStorage;
+/// use aya::programs::{CgroupSkb, CgroupSkbAttachType};
+///
+/// let file = File::open("/sys/fs/cgroup/unified")?;
+/// let key = CgroupSkb::storage_key(file.as_raw_fd(), CgroupSkbAttachType::Ingress)?;
+/// let storage = PerCpuCgroupStorage::<_, u64>::try_from(bpf.map("INGRESS_PACKETS").unwrap())?;
+/// let packets: u64 = storage.get(&key, 0)?.iter().sum();
+/// # Ok::<(), Error>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE")]
+#[derive(Debug)]
+pub struct PerCpuCgroupStorage<T, V: Pod> {
+    pub(crate) inner: T,
+    _v: PhantomData<V>,
+}
+
+impl<T: AsRef<MapData>, V: Pod> PerCpuCgroupStorage<T, V> {
+    pub(crate) fn new(map: T) -> Result<PerCpuCgroupStorage<T, V>, MapError> {
+        let data = map.as_ref();
+        check_kv_size::<CgroupStorageKey, V>(data)?;
+        let _ = data.fd_or_err()?;
+
+        Ok(PerCpuCgroupStorage {
+            inner: map,
+            _v: PhantomData,
+        })
+    }
+
+    /// Returns a slice of values - one for each CPU - stored for the key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::KeyNotFound`] if the program isn't attached to the cgroup with the
+    /// attach type of the key, [`MapError::SyscallError`] if `bpf_map_lookup_elem` fails.
+    pub fn get(&self, key: &CgroupStorageKey, flags: u64) -> Result<PerCpuValues<V>, MapError> {
+        let fd = self.inner.as_ref().fd_or_err()?;
+        let values = bpf_map_lookup_elem_per_cpu(fd, key, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_lookup_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        values.ok_or(MapError::KeyNotFound)
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order. The
+    /// iterator item type is `Result<(CgroupStorageKey, PerCpuValues<V>), MapError>`.
+    pub fn iter(&self) -> MapIter<'_, CgroupStorageKey, PerCpuValues<V>, Self> {
+        MapIter::new(self)
+    }
+
+    /// An iterator visiting all keys in arbitrary order. The iterator element
+    /// type is `Result<CgroupStorageKey, MapError>`.
+    pub fn keys(&self) -> MapKeys<'_, CgroupStorageKey> {
+        MapKeys::new(self.inner.as_ref())
+    }
+}
+
+impl<T: AsMut<MapData>, V: Pod> PerCpuCgroupStorage<T, V> {
+    /// Updates the values - one for each CPU - stored for the key.
+    ///
+    /// The values must already exist, i.e. the program must be attached to the cgroup.
+    pub fn set(
+        &mut self,
+        key: &CgroupStorageKey,
+        values: PerCpuValues<V>,
+        flags: u64,
+    ) -> Result<(), MapError> {
+        let fd = self.inner.as_mut().fd_or_err()?;
+        bpf_map_update_elem_per_cpu(fd, key, &values, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_update_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        Ok(())
+    }
+}
+
+impl<T: AsRef<MapData>, V: Pod> IterableMap<CgroupStorageKey, PerCpuValues<V>>
+    for PerCpuCgroupStorage<T, V>
+{
+    fn map(&self) -> &MapData {
+        self.inner.as_ref()
+    }
+
+    fn get(&self, key: &CgroupStorageKey) -> Result<PerCpuValues<V>, MapError> {
+        PerCpuCgroupStorage::get(self, key, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bpf_map_def,
+        generated::bpf_map_type::BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE,
+        maps::{Map, MapData},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+    };
+
+    use super::*;
+
+    fn new_map() -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE as u32,
+                    key_size: 16,
+                    value_size: 8,
+                    max_entries: 0,
+                    ..Default::default()
+                },
+                section_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+                symbol_index: 0,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    #[test]
+    fn test_wrong_key_size() {
+        let mut map = new_map();
+        if let obj::Map::Legacy(m) = &mut map.obj {
+            m.def.key_size = 8;
+        }
+        assert!(matches!(
+            PerCpuCgroupStorage::<_, u64>::new(&map),
+            Err(MapError::InvalidKeySize {
+                size: 16,
+                expected: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_from_wrong_map() {
+        let map = Map::CgroupStorage(new_map());
+        assert!(matches!(
+            PerCpuCgroupStorage::<_, u64>::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_try_from_ok() {
+        let map = Map::PerCpuCgroupStorage(new_map());
+        assert!(PerCpuCgroupStorage::<_, u64>::try_from(&map).is_ok());
+    }
+}
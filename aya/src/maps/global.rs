@@ -0,0 +1,291 @@
+//! Global variables of eBPF programs.
+use std::{borrow::Borrow, marker::PhantomData, mem, ptr};
+
+use libc::{MAP_SHARED, PROT_READ, PROT_WRITE};
+use thiserror::Error;
+
+use crate::{
+    generated::BPF_F_MMAPABLE,
+    maps::{MMap, MapData, MapError},
+    sys::{bpf_map_lookup_elem_ptr, bpf_map_update_elem_ptr},
+    util::page_size,
+    Pod,
+};
+
+pub use aya_obj::GlobalKind;
+
+/// Errors returned by [`Bpf::global`](crate::Bpf::global) and
+/// [`Bpf::global_mut`](crate::Bpf::global_mut).
+#[derive(Error, Debug)]
+pub enum GlobalError {
+    /// The global variable doesn't exist, or the map holding it was taken out of the
+    /// [`Bpf`](crate::Bpf) instance.
+    #[error("global `{name}` not found")]
+    NotFound {
+        /// The name of the global variable
+        name: String,
+    },
+
+    /// The size of the type doesn't match the size of the global variable.
+    #[error("invalid size {size} for global `{name}`, expected {expected}")]
+    InvalidSize {
+        /// The name of the global variable
+        name: String,
+        /// The size of the type
+        size: usize,
+        /// The size of the global variable
+        expected: usize,
+    },
+
+    /// The type doesn't match the kind of the BTF type of the global variable.
+    #[error("invalid type for global `{name}`: expected {expected:?}, got {kind:?}")]
+    InvalidKind {
+        /// The name of the global variable
+        name: String,
+        /// The kind of the type
+        kind: GlobalKind,
+        /// The kind of the BTF type of the global variable
+        expected: GlobalKind,
+    },
+
+    /// A map error.
+    #[error(transparent)]
+    MapError(#[from] MapError),
+}
+
+/// A handle to a global variable of an eBPF program.
+///
+/// Global variables live in the `.bss`, `.data` and `.rodata` maps. When the map is mmapable (see
+/// [`Array::mmap`](crate::maps::Array::mmap)) the variable is accessed in place without syscalls.
+/// Otherwise every access looks up the whole map value, so [`Global::set`] races with eBPF
+/// programs updating the other variables of the section.
+///
+/// Handles returned by [`Bpf::global`](crate::Bpf::global) can only read the variable, use
+/// [`Bpf::global_mut`](crate::Bpf::global_mut) to update it. `.rodata` is frozen after loading,
+/// so its variables can only be read.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// let mut verbose = bpf.global_mut::<u32>("VERBOSE")?;
+/// verbose.set(1)?;
+/// assert_eq!(verbose.get()?, 1);
+/// # Ok::<(), aya::BpfError>(())
+/// ```
+#[derive(Debug)]
+pub struct Global<T, V: Pod> {
+    inner: T,
+    offset: usize,
+    mmap: Option<MMap>,
+    _v: PhantomData<V>,
+}
+
+impl<T: AsRef<MapData>, V: Pod> Global<T, V> {
+    pub(crate) fn new(map: T, offset: usize) -> Result<Global<T, V>, MapError> {
+        let data = map.as_ref();
+        let fd = data.fd_or_err()?;
+        let mmap = if data.obj.map_flags() & BPF_F_MMAPABLE != 0 {
+            let page_size = page_size();
+            let size = (data.obj.value_size() as usize + page_size - 1) & !(page_size - 1);
+            let mmap =
+                MMap::new(fd, size, PROT_READ | PROT_WRITE, MAP_SHARED, 0).map_err(|io_error| {
+                    MapError::SyscallError {
+                        call: "mmap".to_owned(),
+                        io_error,
+                    }
+                })?;
+            Some(mmap)
+        } else {
+            None
+        };
+
+        Ok(Global {
+            inner: map,
+            offset,
+            mmap,
+            _v: PhantomData,
+        })
+    }
+
+    /// Returns the value of the variable.
+    pub fn get(&self) -> Result<V, MapError> {
+        match &self.mmap {
+            Some(mmap) => {
+                let ptr = mmap_ptr::<V>(mmap, self.offset);
+                // Safety: the variable is in the mapping and V is Pod
+                if ptr.align_offset(mem::align_of::<V>()) == 0 {
+                    Ok(unsafe { ptr::read_volatile(ptr) })
+                } else {
+                    Ok(unsafe { ptr::read_unaligned(ptr) })
+                }
+            }
+            None => {
+                let value = lookup(self.inner.as_ref())?;
+                // Safety: the variable is in the value and V is Pod
+                Ok(unsafe { ptr::read_unaligned(value[self.offset..].as_ptr().cast::<V>()) })
+            }
+        }
+    }
+}
+
+impl<T: AsMut<MapData>, V: Pod> Global<T, V> {
+    /// Sets the value of the variable.
+    pub fn set(&mut self, value: impl Borrow<V>) -> Result<(), MapError> {
+        match &self.mmap {
+            Some(mmap) => {
+                let ptr = mmap_ptr::<V>(mmap, self.offset);
+                // Safety: the variable is in the mapping and V is Pod
+                if ptr.align_offset(mem::align_of::<V>()) == 0 {
+                    unsafe { ptr::write_volatile(ptr, *value.borrow()) };
+                } else {
+                    unsafe { ptr::write_unaligned(ptr, *value.borrow()) };
+                }
+                Ok(())
+            }
+            None => {
+                let map = self.inner.as_mut();
+                let mut data = lookup(map)?;
+                // Safety: the variable is in the value and V is Pod
+                unsafe {
+                    ptr::write_unaligned(
+                        data[self.offset..].as_mut_ptr().cast::<V>(),
+                        *value.borrow(),
+                    )
+                };
+                let fd = map.fd_or_err()?;
+                bpf_map_update_elem_ptr(fd, &0u32, data.as_mut_ptr(), 0).map_err(
+                    |(_, io_error)| MapError::SyscallError {
+                        call: "bpf_map_update_elem".to_owned(),
+                        io_error,
+                    },
+                )?;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn mmap_ptr<V>(mmap: &MMap, offset: usize) -> *mut V {
+    unsafe { mmap.ptr().as_ptr().cast::<u8>().add(offset) }.cast()
+}
+
+fn lookup(map: &MapData) -> Result<Vec<u8>, MapError> {
+    let fd = map.fd_or_err()?;
+    let mut data = vec![0u8; map.obj.value_size() as usize];
+    bpf_map_lookup_elem_ptr(fd, Some(&0u32), data.as_mut_ptr(), 0)
+        .map_err(|(_, io_error)| MapError::SyscallError {
+            call: "bpf_map_lookup_elem".to_owned(),
+            io_error,
+        })?
+        .ok_or(MapError::KeyNotFound)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::EFAULT;
+
+    use crate::{
+        bpf_map_def,
+        generated::{bpf_cmd, bpf_map_type::BPF_MAP_TYPE_ARRAY},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, Syscall, TEST_MMAP_RET},
+    };
+
+    use super::*;
+
+    fn new_map(map_flags: u32) -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_ARRAY as u32,
+                    key_size: 4,
+                    value_size: 16,
+                    max_entries: 1,
+                    map_flags,
+                    ..Default::default()
+                },
+                section_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Data,
+                symbol_index: 0,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_get_syscall() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_ELEM,
+                attr,
+            } => {
+                let value = unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.value } as *mut u64;
+                unsafe { value.add(1).write(42) };
+                Ok(1)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let map = new_map(0);
+        let global = Global::<_, u64>::new(&map, 8).unwrap();
+        assert_eq!(global.get().unwrap(), 42);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_set_syscall() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_ELEM,
+                attr,
+            } => {
+                let value = unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.value } as *mut u64;
+                unsafe { value.write(7) };
+                Ok(1)
+            }
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_ELEM,
+                attr,
+            } => {
+                let value = unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.value } as *const u64;
+                // the other variables of the section are preserved
+                assert_eq!(unsafe { *value }, 7);
+                assert_eq!(unsafe { *value.add(1) }, 42);
+                Ok(1)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let mut map = new_map(0);
+        let mut global = Global::<_, u64>::new(&mut map, 8).unwrap();
+        assert!(global.set(42).is_ok());
+    }
+
+    #[test]
+    fn test_get_set_mmap() {
+        let mut buf = vec![0u64; page_size() / 8];
+        TEST_MMAP_RET.with(|ret| *ret.borrow_mut() = buf.as_mut_ptr().cast());
+
+        let mut map = new_map(BPF_F_MMAPABLE);
+        let mut global = Global::<_, u64>::new(&mut map, 8).unwrap();
+        global.set(42).unwrap();
+        assert_eq!(global.get().unwrap(), 42);
+        drop(global);
+        assert_eq!(buf[1], 42);
+    }
+}
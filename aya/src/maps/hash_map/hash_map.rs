@@ -5,7 +5,7 @@ use std::{
 };
 
 use crate::{
-    maps::{check_kv_size, hash_map, IterableMap, MapData, MapError, MapIter, MapKeys},
+    maps::{batch, check_kv_size, hash_map, IterableMap, MapData, MapError, MapIter, MapKeys},
     sys::bpf_map_lookup_elem,
     Pod,
 };
@@ -34,7 +34,7 @@ use crate::{
 #[doc(alias = "BPF_MAP_TYPE_LRU_HASH")]
 #[derive(Debug)]
 pub struct HashMap<T, K, V> {
-    inner: T,
+    pub(crate) inner: T,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
 }
@@ -75,6 +75,15 @@ impl<T: AsRef<MapData>, K: Pod, V: Pod> HashMap<T, K, V> {
     pub fn keys(&self) -> MapKeys<'_, K> {
         MapKeys::new(self.inner.as_ref())
     }
+
+    /// Returns all the key-value pairs of the map.
+    ///
+    /// The elements are read `batch_size` at a time with `BPF_MAP_LOOKUP_BATCH`, which needs
+    /// kernel 5.6. On older kernels, or if the map doesn't support batch operations, the elements
+    /// are read one by one.
+    pub fn get_batch(&self, batch_size: u32, flags: u64) -> Result<Vec<(K, V)>, MapError> {
+        batch::get_batch(self.inner.as_ref(), batch_size, flags)
+    }
 }
 
 impl<T: AsMut<MapData>, K: Pod, V: Pod> HashMap<T, K, V> {
@@ -92,6 +101,33 @@ impl<T: AsMut<MapData>, K: Pod, V: Pod> HashMap<T, K, V> {
     pub fn remove(&mut self, key: &K) -> Result<(), MapError> {
         hash_map::remove(self.inner.as_mut(), key)
     }
+
+    /// Removes all the key-value pairs of the map and returns them.
+    ///
+    /// Like [`HashMap::get_batch`], but with `BPF_MAP_LOOKUP_AND_DELETE_BATCH`.
+    pub fn drain_batch(&mut self, batch_size: u32, flags: u64) -> Result<Vec<(K, V)>, MapError> {
+        batch::drain_batch(self.inner.as_mut(), batch_size, flags)
+    }
+
+    /// Inserts the key-value pairs `keys[i]`, `values[i]` into the map.
+    ///
+    /// The pairs are inserted with a single `BPF_MAP_UPDATE_BATCH` syscall, which needs kernel
+    /// 5.6, or one by one otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::BatchSizeMismatch`] if `keys` and `values` have different lengths.
+    pub fn insert_batch(&mut self, keys: &[K], values: &[V], flags: u64) -> Result<(), MapError> {
+        batch::insert_batch(self.inner.as_mut(), keys, values, flags)
+    }
+
+    /// Removes the keys from the map.
+    ///
+    /// The keys are removed with a single `BPF_MAP_DELETE_BATCH` syscall, which needs kernel 5.6,
+    /// or one by one otherwise.
+    pub fn remove_batch(&mut self, keys: &[K]) -> Result<(), MapError> {
+        batch::remove_batch(self.inner.as_mut(), keys)
+    }
 }
 
 impl<T: AsRef<MapData>, K: Pod, V: Pod> IterableMap<K, V> for HashMap<T, K, V> {
@@ -152,6 +188,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         assert!(matches!(
@@ -169,6 +206,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         assert!(matches!(
@@ -186,6 +224,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -202,6 +241,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -221,6 +261,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -236,6 +277,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -248,6 +290,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -273,6 +316,7 @@ mod tests {
             }),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -289,6 +333,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut hm = HashMap::<_, u32, u32>::new(&mut map).unwrap();
@@ -313,6 +358,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut hm = HashMap::<_, u32, u32>::new(&mut map).unwrap();
@@ -334,6 +380,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut hm = HashMap::<_, u32, u32>::new(&mut map).unwrap();
@@ -349,6 +396,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut hm = HashMap::<_, u32, u32>::new(&mut map).unwrap();
@@ -373,6 +421,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut hm = HashMap::<_, u32, u32>::new(&mut map).unwrap();
@@ -387,6 +436,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -410,6 +460,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -447,6 +498,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -496,6 +548,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -529,6 +582,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -564,6 +618,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -602,6 +657,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -641,6 +697,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
@@ -686,6 +743,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let hm = HashMap::<_, u32, u32>::new(&map).unwrap();
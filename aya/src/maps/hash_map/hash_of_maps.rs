@@ -0,0 +1,220 @@
+//! A hash map of eBPF maps.
+use std::{
+    borrow::Borrow,
+    convert::{AsMut, AsRef},
+    marker::PhantomData,
+    os::unix::prelude::RawFd,
+};
+
+use crate::{
+    maps::{check_kv_size, hash_map, IterableMap, MapData, MapError, MapIter, MapKeys},
+    sys::bpf_map_lookup_elem,
+    Pod,
+};
+
+/// A hash map of eBPF maps.
+///
+/// eBPF programs look up a key of the hash map to get one of the inner maps, which they then use
+/// like any other map. Replacing the map stored at a key from user space atomically switches all
+/// the programs over to the new map.
+///
+/// All the inner maps must have the same definition as the inner map template the hash map was
+/// created with. See [`BpfLoader::set_inner_map`](crate::BpfLoader::set_inner_map).
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.12.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::maps::{HashOfMaps, MapData};
+///
+/// let policy = MapData::from_pin("/sys/fs/bpf/policies/tenant_42")?;
+/// let mut policies = HashOfMaps::try_from(bpf.map_mut("POLICIES").unwrap())?;
+///
+/// // programs looking up tenant 42 now get the new policy
+/// policies.insert(42u32, &policy, 0)?;
+/// # Ok::<(), aya::BpfError>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_HASH_OF_MAPS")]
+#[derive(Debug)]
+pub struct HashOfMaps<T, K> {
+    pub(crate) inner: T,
+    _k: PhantomData<K>,
+}
+
+impl<T: AsRef<MapData>, K: Pod> HashOfMaps<T, K> {
+    pub(crate) fn new(map: T) -> Result<HashOfMaps<T, K>, MapError> {
+        let data = map.as_ref();
+        check_kv_size::<K, RawFd>(data)?;
+        let _ = data.fd_or_err()?;
+
+        Ok(HashOfMaps {
+            inner: map,
+            _k: PhantomData,
+        })
+    }
+
+    /// Returns the id of the map associated with the key.
+    pub fn get(&self, key: &K, flags: u64) -> Result<u32, MapError> {
+        let fd = self.inner.as_ref().fd_or_err()?;
+        let value = bpf_map_lookup_elem(fd, key, flags).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_lookup_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        value.ok_or(MapError::KeyNotFound)
+    }
+
+    /// An iterator visiting all the keys and the ids of their maps in arbitrary order. The
+    /// iterator item type is `Result<(K, u32), MapError>`.
+    pub fn iter(&self) -> MapIter<'_, K, u32, Self> {
+        MapIter::new(self)
+    }
+
+    /// An iterator visiting all keys in arbitrary order. The iterator element
+    /// type is `Result<K, MapError>`.
+    pub fn keys(&self) -> MapKeys<'_, K> {
+        MapKeys::new(self.inner.as_ref())
+    }
+}
+
+impl<T: AsMut<MapData>, K: Pod> HashOfMaps<T, K> {
+    /// Inserts a map into the hash map.
+    pub fn insert(
+        &mut self,
+        key: impl Borrow<K>,
+        value: &MapData,
+        flags: u64,
+    ) -> Result<(), MapError> {
+        let map_fd = value.fd_or_err()?;
+        hash_map::insert(self.inner.as_mut(), key.borrow(), &map_fd, flags)
+    }
+
+    /// Removes a key from the map.
+    pub fn remove(&mut self, key: &K) -> Result<(), MapError> {
+        hash_map::remove(self.inner.as_mut(), key)
+    }
+}
+
+impl<T: AsRef<MapData>, K: Pod> IterableMap<K, u32> for HashOfMaps<T, K> {
+    fn map(&self) -> &MapData {
+        self.inner.as_ref()
+    }
+
+    fn get(&self, key: &K) -> Result<u32, MapError> {
+        HashOfMaps::get(self, key, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::{EFAULT, ENOENT};
+
+    use crate::{
+        bpf_map_def,
+        generated::{bpf_cmd, bpf_map_type::BPF_MAP_TYPE_HASH_OF_MAPS},
+        maps::{Map, MapData},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, SysResult, Syscall},
+    };
+
+    use super::*;
+
+    fn new_obj_map(key_size: u32) -> obj::Map {
+        obj::Map::Legacy(LegacyMap {
+            def: bpf_map_def {
+                map_type: BPF_MAP_TYPE_HASH_OF_MAPS as u32,
+                key_size,
+                value_size: 4,
+                max_entries: 1024,
+                ..Default::default()
+            },
+            section_index: 0,
+            data: Vec::new(),
+            kind: MapKind::Other,
+            symbol_index: 0,
+        })
+    }
+
+    fn new_map(key_size: u32, fd: RawFd) -> MapData {
+        MapData {
+            obj: new_obj_map(key_size),
+            fd: Some(fd),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn sys_error(value: i32) -> SysResult {
+        Err((-1, io::Error::from_raw_os_error(value)))
+    }
+
+    #[test]
+    fn test_wrong_key_size() {
+        let map = new_map(4, 42);
+        assert!(matches!(
+            HashOfMaps::<_, u64>::new(&map),
+            Err(MapError::InvalidKeySize {
+                size: 8,
+                expected: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_from_wrong_map() {
+        let map = Map::HashMap(new_map(4, 42));
+        assert!(matches!(
+            HashOfMaps::<_, u32>::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_insert_inner_map_fd() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_ELEM,
+                attr,
+            } => {
+                let key = unsafe { *(attr.__bindgen_anon_2.key as *const u32) };
+                let value =
+                    unsafe { *(attr.__bindgen_anon_2.__bindgen_anon_1.value as *const RawFd) };
+                assert_eq!(key, 1);
+                assert_eq!(value, 43);
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let mut map = new_map(4, 42);
+        let inner = new_map(4, 43);
+        let mut hm = HashOfMaps::<_, u32>::new(&mut map).unwrap();
+
+        assert!(hm.insert(1, &inner, 0).is_ok());
+    }
+
+    #[test]
+    fn test_get_not_found() {
+        override_syscall(|_| sys_error(ENOENT));
+
+        let map = new_map(4, 42);
+        let hm = HashOfMaps::<_, u32>::new(&map).unwrap();
+
+        assert!(matches!(hm.get(&1, 0), Err(MapError::KeyNotFound)));
+    }
+}
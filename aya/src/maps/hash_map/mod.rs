@@ -7,9 +7,11 @@ use crate::{
 
 #[allow(clippy::module_inception)]
 mod hash_map;
+mod hash_of_maps;
 mod per_cpu_hash_map;
 
 pub use hash_map::*;
+pub use hash_of_maps::HashOfMaps;
 pub use per_cpu_hash_map::*;
 
 use super::MapData;
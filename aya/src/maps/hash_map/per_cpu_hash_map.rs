@@ -7,7 +7,8 @@ use std::{
 
 use crate::{
     maps::{
-        check_kv_size, hash_map, IterableMap, MapData, MapError, MapIter, MapKeys, PerCpuValues,
+        batch, check_kv_size, hash_map, IterableMap, MapData, MapError, MapIter, MapKeys,
+        PerCpuValues,
     },
     sys::{bpf_map_lookup_elem_per_cpu, bpf_map_update_elem_per_cpu},
     Pod,
@@ -43,7 +44,7 @@ use crate::{
 #[doc(alias = "BPF_MAP_TYPE_LRU_PERCPU_HASH")]
 #[doc(alias = "BPF_MAP_TYPE_PERCPU_HASH")]
 pub struct PerCpuHashMap<T, K: Pod, V: Pod> {
-    inner: T,
+    pub(crate) inner: T,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
 }
@@ -85,6 +86,19 @@ impl<T: AsRef<MapData>, K: Pod, V: Pod> PerCpuHashMap<T, K, V> {
     pub fn keys(&self) -> MapKeys<'_, K> {
         MapKeys::new(self.inner.as_ref())
     }
+
+    /// Returns all the keys of the map and their per-CPU values.
+    ///
+    /// The elements are read `batch_size` at a time with `BPF_MAP_LOOKUP_BATCH`, which needs
+    /// kernel 5.6. On older kernels, or if the map doesn't support batch operations, the elements
+    /// are read one by one.
+    pub fn get_batch(
+        &self,
+        batch_size: u32,
+        flags: u64,
+    ) -> Result<Vec<(K, PerCpuValues<V>)>, MapError> {
+        batch::get_batch_per_cpu(self.inner.as_ref(), batch_size, flags)
+    }
 }
 
 impl<T: AsMut<MapData>, K: Pod, V: Pod> PerCpuHashMap<T, K, V> {
@@ -137,6 +151,42 @@ impl<T: AsMut<MapData>, K: Pod, V: Pod> PerCpuHashMap<T, K, V> {
     pub fn remove(&mut self, key: &K) -> Result<(), MapError> {
         hash_map::remove(self.inner.as_mut(), key)
     }
+
+    /// Removes all the keys of the map and returns them with their per-CPU values.
+    ///
+    /// Like [`PerCpuHashMap::get_batch`], but with `BPF_MAP_LOOKUP_AND_DELETE_BATCH`.
+    pub fn drain_batch(
+        &mut self,
+        batch_size: u32,
+        flags: u64,
+    ) -> Result<Vec<(K, PerCpuValues<V>)>, MapError> {
+        batch::drain_batch_per_cpu(self.inner.as_mut(), batch_size, flags)
+    }
+
+    /// Inserts the per-CPU values `values[i]` for the keys `keys[i]`.
+    ///
+    /// The values are inserted with a single `BPF_MAP_UPDATE_BATCH` syscall, which needs kernel
+    /// 5.6, or one by one otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::BatchSizeMismatch`] if `keys` and `values` have different lengths.
+    pub fn insert_batch(
+        &mut self,
+        keys: &[K],
+        values: &[PerCpuValues<V>],
+        flags: u64,
+    ) -> Result<(), MapError> {
+        batch::insert_batch_per_cpu(self.inner.as_mut(), keys, values, flags)
+    }
+
+    /// Removes the keys from the map.
+    ///
+    /// The keys are removed with a single `BPF_MAP_DELETE_BATCH` syscall, which needs kernel 5.6,
+    /// or one by one otherwise.
+    pub fn remove_batch(&mut self, keys: &[K]) -> Result<(), MapError> {
+        batch::remove_batch(self.inner.as_mut(), keys)
+    }
 }
 
 impl<T: AsRef<MapData>, K: Pod, V: Pod> IterableMap<K, PerCpuValues<V>> for PerCpuHashMap<T, K, V> {
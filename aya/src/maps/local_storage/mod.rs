@@ -0,0 +1,421 @@
+//! Local storage maps.
+//!
+//! Local storage maps attach a value to a kernel object - a socket, a task, an inode or a cgroup -
+//! and free it along with the object. User space refers to the object with a file descriptor.
+use std::{
+    borrow::Borrow,
+    convert::{AsMut, AsRef},
+    marker::PhantomData,
+    os::unix::io::{AsRawFd, RawFd},
+};
+
+use crate::{
+    maps::{check_kv_size, MapData, MapError},
+    sys::{bpf_map_delete_elem, bpf_map_lookup_elem, bpf_map_update_elem},
+    Pod,
+};
+
+// The local storage maps only differ in the kind of object the file descriptor used as key
+// refers to, so they're all defined by this macro.
+macro_rules! local_storage {
+    ($(#[$attr:meta])* $name:ident, $object:literal, $handle:ident) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<T, V> {
+            pub(crate) inner: T,
+            _v: PhantomData<V>,
+        }
+
+        impl<T: AsRef<MapData>, V: Pod> $name<T, V> {
+            pub(crate) fn new(map: T) -> Result<$name<T, V>, MapError> {
+                let data = map.as_ref();
+                check_kv_size::<RawFd, V>(data)?;
+                let _ = data.fd_or_err()?;
+
+                Ok($name {
+                    inner: map,
+                    _v: PhantomData,
+                })
+            }
+
+            #[doc = concat!("Returns a copy of the value stored for the ", $object, ".")]
+            ///
+            /// # Errors
+            ///
+            #[doc = concat!(
+                "Returns [`MapError::KeyNotFound`] if no value is stored for the ",
+                $object,
+                ","
+            )]
+            /// [`MapError::SyscallError`] if `bpf_map_lookup_elem` fails.
+            pub fn get(&self, $handle: impl AsRawFd, flags: u64) -> Result<V, MapError> {
+                get(self.inner.as_ref(), $handle.as_raw_fd(), flags)
+            }
+        }
+
+        impl<T: AsMut<MapData>, V: Pod> $name<T, V> {
+            #[doc = concat!("Stores a value for the ", $object, ".")]
+            pub fn insert(
+                &mut self,
+                $handle: impl AsRawFd,
+                value: impl Borrow<V>,
+                flags: u64,
+            ) -> Result<(), MapError> {
+                insert(self.inner.as_mut(), $handle.as_raw_fd(), value.borrow(), flags)
+            }
+
+            #[doc = concat!("Removes the value stored for the ", $object, ".")]
+            pub fn remove(&mut self, $handle: impl AsRawFd) -> Result<(), MapError> {
+                remove(self.inner.as_mut(), $handle.as_raw_fd())
+            }
+        }
+    };
+}
+
+local_storage!(
+    /// A map attaching a value to each socket.
+    ///
+    /// eBPF programs store and look up the value of a socket with `bpf_sk_storage_get`. The
+    /// value is freed when the socket is closed. User space refers to the socket with its file
+    /// descriptor.
+    ///
+    /// # Minimum kernel version
+    ///
+    /// The minimum kernel version required to use this feature is 5.2.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[derive(Debug, thiserror::Error)]
+    /// # enum Error {
+    /// #     #[error(transparent)]
+    /// #     IO(#[from] std::io::Error),
+    /// #     #[error(transparent)]
+    /// #     Map(#[from] aya::maps::MapError),
+    /// #     #[error(transparent)]
+    /// #     Bpf(#[from] aya::BpfError)
+    /// # }
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// use std::net::TcpStream;
+    /// use std::os::unix::io::AsRawFd;
+    /// use aya::maps::SkStorage;
+    ///
+    /// let mut storage = SkStorage::<_, u64>::try_from(bpf.map_mut("SOCKET_BYTES").unwrap())?;
+    /// let client = TcpStream::connect("127.0.0.1:1234")?;
+    ///
+    /// storage.insert(client.as_raw_fd(), 0, 0)?;
+    /// let bytes = storage.get(client.as_raw_fd(), 0)?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[doc(alias = "BPF_MAP_TYPE_SK_STORAGE")]
+    SkStorage, "socket", socket
+);
+
+local_storage!(
+    /// A map attaching a value to each task.
+    ///
+    /// eBPF programs store and look up the value of a task with `bpf_task_storage_get`. The value
+    /// is freed when the task exits. User space refers to the task with a pidfd, see
+    /// `pidfd_open(2)`.
+    ///
+    /// # Minimum kernel version
+    ///
+    /// The minimum kernel version required to use this feature is 5.11.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[derive(Debug, thiserror::Error)]
+    /// # enum Error {
+    /// #     #[error(transparent)]
+    /// #     IO(#[from] std::io::Error),
+    /// #     #[error(transparent)]
+    /// #     Map(#[from] aya::maps::MapError),
+    /// #     #[error(transparent)]
+    /// #     Bpf(#[from] aya::BpfError)
+    /// # }
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// use std::os::unix::io::RawFd;
+    /// use aya::maps::TaskStorage;
+    ///
+    /// let mut storage = TaskStorage::<_, u32>::try_from(bpf.map_mut("TASK_FLAGS").unwrap())?;
+    /// let pid = 1234;
+    /// let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) } as RawFd;
+    /// if pidfd < 0 {
+    ///     return Err(std::io::Error::last_os_error().into());
+    /// }
+    ///
+    /// storage.insert(pidfd, 1, 0)?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[doc(alias = "BPF_MAP_TYPE_TASK_STORAGE")]
+    TaskStorage, "task", pidfd
+);
+
+local_storage!(
+    /// A map attaching a value to each inode.
+    ///
+    /// eBPF LSM programs store and look up the value of an inode with `bpf_inode_storage_get`. The
+    /// value is freed with the inode. User space refers to the inode with the file descriptor of a
+    /// file open on it.
+    ///
+    /// # Minimum kernel version
+    ///
+    /// The minimum kernel version required to use this feature is 5.10.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[derive(Debug, thiserror::Error)]
+    /// # enum Error {
+    /// #     #[error(transparent)]
+    /// #     IO(#[from] std::io::Error),
+    /// #     #[error(transparent)]
+    /// #     Map(#[from] aya::maps::MapError),
+    /// #     #[error(transparent)]
+    /// #     Bpf(#[from] aya::BpfError)
+    /// # }
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// use std::fs::File;
+    /// use std::os::unix::io::AsRawFd;
+    /// use aya::maps::InodeStorage;
+    ///
+    /// let mut storage = InodeStorage::<_, u32>::try_from(bpf.map_mut("PROTECTED").unwrap())?;
+    /// let file = File::open("/etc/shadow")?;
+    ///
+    /// storage.insert(file.as_raw_fd(), 1, 0)?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[doc(alias = "BPF_MAP_TYPE_INODE_STORAGE")]
+    InodeStorage, "inode", file
+);
+
+local_storage!(
+    /// A map attaching a value to each cgroup.
+    ///
+    /// eBPF programs store and look up the value of a cgroup with `bpf_cgrp_storage_get`. The value
+    /// is freed with the cgroup. User space refers to the cgroup with the file descriptor of its
+    /// directory in the cgroup v2 hierarchy.
+    ///
+    /// This is the storage of `BPF_MAP_TYPE_CGRP_STORAGE`, which unlike the older
+    /// `BPF_MAP_TYPE_CGROUP_STORAGE` isn't tied to the programs attached to the cgroup.
+    ///
+    /// # Minimum kernel version
+    ///
+    /// The minimum kernel version required to use this feature is 6.2.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[derive(Debug, thiserror::Error)]
+    /// # enum Error {
+    /// #     #[error(transparent)]
+    /// #     IO(#[from] std::io::Error),
+    /// #     #[error(transparent)]
+    /// #     Map(#[from] aya::maps::MapError),
+    /// #     #[error(transparent)]
+    /// #     Bpf(#[from] aya::BpfError)
+    /// # }
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// use std::fs::File;
+    /// use std::os::unix::io::AsRawFd;
+    /// use aya::maps::CgrpStorage;
+    ///
+    /// let mut storage = CgrpStorage::<_, u64>::try_from(bpf.map_mut("CGROUP_QUOTA").unwrap())?;
+    /// let cgroup = File::open("/sys/fs/cgroup/system.slice")?;
+    ///
+    /// storage.insert(cgroup.as_raw_fd(), 1 << 30, 0)?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[doc(alias = "BPF_MAP_TYPE_CGRP_STORAGE")]
+    CgrpStorage, "cgroup", cgroup
+);
+
+fn get<V: Pod>(map: &MapData, fd: RawFd, flags: u64) -> Result<V, MapError> {
+    let map_fd = map.fd_or_err()?;
+    let value = bpf_map_lookup_elem(map_fd, &fd, flags).map_err(|(_, io_error)| {
+        MapError::SyscallError {
+            call: "bpf_map_lookup_elem".to_owned(),
+            io_error,
+        }
+    })?;
+    value.ok_or(MapError::KeyNotFound)
+}
+
+fn insert<V: Pod>(map: &mut MapData, fd: RawFd, value: &V, flags: u64) -> Result<(), MapError> {
+    let map_fd = map.fd_or_err()?;
+    bpf_map_update_elem(map_fd, Some(&fd), value, flags).map_err(|(_, io_error)| {
+        MapError::SyscallError {
+            call: "bpf_map_update_elem".to_owned(),
+            io_error,
+        }
+    })?;
+
+    Ok(())
+}
+
+fn remove(map: &mut MapData, fd: RawFd) -> Result<(), MapError> {
+    let map_fd = map.fd_or_err()?;
+    bpf_map_delete_elem(map_fd, &fd)
+        .map(|_| ())
+        .map_err(|(_, io_error)| MapError::SyscallError {
+            call: "bpf_map_delete_elem".to_owned(),
+            io_error,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::{EFAULT, ENOENT};
+
+    use crate::{
+        bpf_map_def,
+        generated::{
+            bpf_cmd,
+            bpf_map_type::{
+                self, BPF_MAP_TYPE_CGRP_STORAGE, BPF_MAP_TYPE_INODE_STORAGE,
+                BPF_MAP_TYPE_SK_STORAGE, BPF_MAP_TYPE_TASK_STORAGE,
+            },
+        },
+        maps::Map,
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, SysResult, Syscall},
+    };
+
+    use super::*;
+
+    fn new_map(map_type: bpf_map_type) -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: map_type as u32,
+                    key_size: 4,
+                    value_size: 8,
+                    max_entries: 0,
+                    ..Default::default()
+                },
+                section_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+                symbol_index: 0,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn sys_error(value: i32) -> SysResult {
+        Err((-1, io::Error::from_raw_os_error(value)))
+    }
+
+    #[test]
+    fn test_wrong_value_size() {
+        let map = new_map(BPF_MAP_TYPE_SK_STORAGE);
+        assert!(matches!(
+            SkStorage::<_, u32>::new(&map),
+            Err(MapError::InvalidValueSize {
+                size: 4,
+                expected: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_from() {
+        let map = Map::SkStorage(new_map(BPF_MAP_TYPE_SK_STORAGE));
+        assert!(SkStorage::<_, u64>::try_from(&map).is_ok());
+        let map = Map::TaskStorage(new_map(BPF_MAP_TYPE_TASK_STORAGE));
+        assert!(TaskStorage::<_, u64>::try_from(&map).is_ok());
+        let map = Map::InodeStorage(new_map(BPF_MAP_TYPE_INODE_STORAGE));
+        assert!(InodeStorage::<_, u64>::try_from(&map).is_ok());
+        let map = Map::CgrpStorage(new_map(BPF_MAP_TYPE_CGRP_STORAGE));
+        assert!(CgrpStorage::<_, u64>::try_from(&map).is_ok());
+    }
+
+    #[test]
+    fn test_try_from_wrong_map() {
+        let map = Map::SkStorage(new_map(BPF_MAP_TYPE_SK_STORAGE));
+        assert!(matches!(
+            TaskStorage::<_, u64>::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+        assert!(matches!(
+            InodeStorage::<_, u64>::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+        assert!(matches!(
+            CgrpStorage::<_, u64>::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+        let map = Map::TaskStorage(new_map(BPF_MAP_TYPE_TASK_STORAGE));
+        assert!(matches!(
+            SkStorage::<_, u64>::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_insert_keyed_by_fd() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_ELEM,
+                attr,
+            } => {
+                let key = unsafe { *(attr.__bindgen_anon_2.key as *const RawFd) };
+                assert_eq!(key, 7);
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let mut map = new_map(BPF_MAP_TYPE_TASK_STORAGE);
+        let mut storage = TaskStorage::<_, u64>::new(&mut map).unwrap();
+
+        assert!(storage.insert(7, 42, 0).is_ok());
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_remove_keyed_by_fd() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_DELETE_ELEM,
+                attr,
+            } => {
+                let key = unsafe { *(attr.__bindgen_anon_2.key as *const RawFd) };
+                assert_eq!(key, 7);
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let mut map = new_map(BPF_MAP_TYPE_INODE_STORAGE);
+        let mut storage = InodeStorage::<_, u64>::new(&mut map).unwrap();
+
+        assert!(storage.remove(7).is_ok());
+    }
+
+    #[test]
+    fn test_get_not_found() {
+        override_syscall(|_| sys_error(ENOENT));
+
+        let map = new_map(BPF_MAP_TYPE_CGRP_STORAGE);
+        let storage = CgrpStorage::<_, u64>::new(&map).unwrap();
+
+        assert!(matches!(storage.get(7, 0), Err(MapError::KeyNotFound)));
+    }
+}
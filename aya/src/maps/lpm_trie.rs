@@ -6,7 +6,7 @@ use std::{
 };
 
 use crate::{
-    maps::{check_kv_size, IterableMap, MapData, MapError, MapIter, MapKeys},
+    maps::{batch, check_kv_size, IterableMap, MapData, MapError, MapIter, MapKeys},
     sys::{bpf_map_delete_elem, bpf_map_get_next_key, bpf_map_lookup_elem, bpf_map_update_elem},
     Pod,
 };
@@ -47,7 +47,7 @@ use crate::{
 
 #[doc(alias = "BPF_MAP_TYPE_LPM_TRIE")]
 pub struct LpmTrie<T, K, V> {
-    inner: T,
+    pub(crate) inner: T,
     _k: PhantomData<K>,
     _v: PhantomData<V>,
 }
@@ -142,6 +142,15 @@ impl<T: AsRef<MapData>, K: Pod, V: Pod> LpmTrie<T, K, V> {
     pub fn iter_key(&self, key: Key<K>) -> LpmTrieKeys<'_, K> {
         LpmTrieKeys::new(self.inner.as_ref(), key)
     }
+
+    /// Returns all the key-value pairs of the map.
+    ///
+    /// The elements are read `batch_size` at a time with `BPF_MAP_LOOKUP_BATCH`, which needs
+    /// kernel 5.6. On older kernels, or if the map doesn't support batch operations, the elements
+    /// are read one by one.
+    pub fn get_batch(&self, batch_size: u32, flags: u64) -> Result<Vec<(Key<K>, V)>, MapError> {
+        batch::get_batch(self.inner.as_ref(), batch_size, flags)
+    }
 }
 
 impl<T: AsMut<MapData>, K: Pod, V: Pod> LpmTrie<T, K, V> {
@@ -175,6 +184,42 @@ impl<T: AsMut<MapData>, K: Pod, V: Pod> LpmTrie<T, K, V> {
                 io_error,
             })
     }
+
+    /// Removes all the key-value pairs of the map and returns them.
+    ///
+    /// Like [`LpmTrie::get_batch`], but with `BPF_MAP_LOOKUP_AND_DELETE_BATCH`.
+    pub fn drain_batch(
+        &mut self,
+        batch_size: u32,
+        flags: u64,
+    ) -> Result<Vec<(Key<K>, V)>, MapError> {
+        batch::drain_batch(self.inner.as_mut(), batch_size, flags)
+    }
+
+    /// Inserts the key-value pairs `keys[i]`, `values[i]` into the map.
+    ///
+    /// The pairs are inserted with a single `BPF_MAP_UPDATE_BATCH` syscall, which needs kernel
+    /// 5.6, or one by one otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::BatchSizeMismatch`] if `keys` and `values` have different lengths.
+    pub fn insert_batch(
+        &mut self,
+        keys: &[Key<K>],
+        values: &[V],
+        flags: u64,
+    ) -> Result<(), MapError> {
+        batch::insert_batch(self.inner.as_mut(), keys, values, flags)
+    }
+
+    /// Removes the keys from the map.
+    ///
+    /// Both the prefix and data of each key must match exactly. The keys are removed with a single
+    /// `BPF_MAP_DELETE_BATCH` syscall, which needs kernel 5.6, or one by one otherwise.
+    pub fn remove_batch(&mut self, keys: &[Key<K>]) -> Result<(), MapError> {
+        batch::remove_batch(self.inner.as_mut(), keys)
+    }
 }
 
 impl<T: AsRef<MapData>, K: Pod, V: Pod> IterableMap<Key<K>, V> for LpmTrie<T, K, V> {
@@ -282,6 +327,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         assert!(matches!(
@@ -299,6 +345,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         assert!(matches!(
@@ -329,6 +376,7 @@ mod tests {
             fd: None,
             btf_fd: None,
             pinned: false,
+            pin_path: None,
         };
 
         let map = Map::PerfEventArray(map_data);
@@ -345,6 +393,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -360,6 +409,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -372,6 +422,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -387,6 +438,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut trie = LpmTrie::<_, u32, u32>::new(&mut map).unwrap();
@@ -412,6 +464,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
 
@@ -429,6 +482,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut trie = LpmTrie::<_, u32, u32>::new(&mut map).unwrap();
@@ -454,6 +508,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let mut trie = LpmTrie::<_, u32, u32>::new(&mut map).unwrap();
@@ -469,6 +524,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let trie = LpmTrie::<_, u32, u32>::new(&map).unwrap();
@@ -494,6 +550,7 @@ mod tests {
             obj: new_obj_map(),
             fd: Some(42),
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         };
         let trie = LpmTrie::<_, u32, u32>::new(&map).unwrap();
@@ -39,52 +39,81 @@
 use std::{
     convert::{AsMut, AsRef},
     ffi::CString,
-    fmt, io,
+    fmt, fs, io,
     marker::PhantomData,
     mem,
     ops::Deref,
     os::unix::{io::RawFd, prelude::AsRawFd},
-    path::Path,
-    ptr,
+    path::{Path, PathBuf},
+    ptr::{self, NonNull},
+    slice,
 };
 
-use libc::{getrlimit, rlimit, RLIMIT_MEMLOCK, RLIM_INFINITY};
+use libc::{
+    c_int, c_void, getrlimit, off_t, rlimit, EOPNOTSUPP, MAP_FAILED, RLIMIT_MEMLOCK, RLIM_INFINITY,
+};
 use log::warn;
 use thiserror::Error;
 
 use crate::{
-    obj::{self, parse_map_info},
+    btf::BtfExt,
+    generated::{bpf_map_info, bpf_map_type, BPF_F_MMAPABLE},
+    obj::{
+        self,
+        btf::{Btf, BtfError, BtfValue},
+        maps::MapKind,
+        parse_map_info,
+    },
     pin::PinError,
     sys::{
-        bpf_create_map, bpf_get_object, bpf_map_get_info_by_fd, bpf_map_get_next_key,
-        bpf_pin_object, kernel_version,
+        bpf_create_map, bpf_get_object, bpf_map_get_fd_by_id, bpf_map_get_info_by_fd,
+        bpf_map_get_next_id, bpf_map_get_next_key, bpf_map_get_next_key_bytes,
+        bpf_map_lookup_elem_bytes, bpf_pin_object, kernel_version, mmap, munmap,
     },
     util::nr_cpus,
     PinningType, Pod,
 };
 
 pub mod array;
+mod batch;
 pub mod bloom_filter;
+pub mod cgroup_storage;
+pub mod global;
 pub mod hash_map;
+pub mod local_storage;
 pub mod lpm_trie;
 pub mod perf;
 pub mod queue;
+pub mod ring_buf;
 pub mod sock;
 pub mod stack;
 pub mod stack_trace;
+pub mod struct_ops;
+pub mod user_ring_buf;
+pub mod xdp;
 
-pub use array::{Array, PerCpuArray, ProgramArray};
+pub use array::{Array, ArrayOfMaps, PerCpuArray, ProgramArray};
 pub use bloom_filter::BloomFilter;
-pub use hash_map::{HashMap, PerCpuHashMap};
+pub use cgroup_storage::{CgroupStorage, CgroupStorageKey, PerCpuCgroupStorage};
+pub use global::{Global, GlobalError, GlobalKind};
+pub use hash_map::{HashMap, HashOfMaps, PerCpuHashMap};
+pub use local_storage::{CgrpStorage, InodeStorage, SkStorage, TaskStorage};
 pub use lpm_trie::LpmTrie;
 #[cfg(feature = "async")]
 #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
 pub use perf::AsyncPerfEventArray;
 pub use perf::PerfEventArray;
 pub use queue::Queue;
+pub use ring_buf::RingBuf;
 pub use sock::{SockHash, SockMap};
 pub use stack::Stack;
 pub use stack_trace::StackTraceMap;
+pub use struct_ops::StructOpsMap;
+pub use user_ring_buf::UserRingBuf;
+pub use xdp::{CpuMap, DevMap, DevMapHash, XskMap};
+
+// The kernel internal error returned for operations a map type doesn't implement.
+const ENOTSUPP: i32 = 524;
 
 #[derive(Error, Debug)]
 /// Errors occuring from working with Maps
@@ -153,6 +182,19 @@ pub enum MapError {
         max_entries: u32,
     },
 
+    /// The number of keys and values of a batch operation differ
+    #[error("batch has {keys} keys but {values} values")]
+    BatchSizeMismatch {
+        /// Number of keys
+        keys: usize,
+        /// Number of values
+        values: usize,
+    },
+
+    /// The map wasn't created with `BPF_F_MMAPABLE`
+    #[error("the map is not mmapable")]
+    NotMmapable,
+
     /// Key not found
     #[error("key not found")]
     KeyNotFound,
@@ -183,6 +225,28 @@ pub enum MapError {
         #[source]
         error: PinError,
     },
+
+    /// The `struct_ops` map has no value layout for the running kernel, eg because it wasn't
+    /// created from an object file
+    #[error("the struct_ops map has no layout for the running kernel")]
+    NoStructOpsLayout,
+
+    /// Reading the BTF of the map failed
+    #[error(transparent)]
+    Btf(#[from] BtfError),
+
+    /// An existing map, e.g. a pinned one, doesn't match the definition of the map in the object
+    #[error("map `{name}` is incompatible with its definition: {field} is {actual}, expected {expected}")]
+    IncompatibleMap {
+        /// The map name
+        name: String,
+        /// The mismatching field of the map definition
+        field: &'static str,
+        /// The value of the existing map
+        actual: u32,
+        /// The value in the object
+        expected: u32,
+    },
 }
 
 /// A map file descriptor.
@@ -238,6 +302,8 @@ pub enum Map {
     PerCpuArray(MapData),
     /// A [`ProgramArray`] map
     ProgramArray(MapData),
+    /// A [`ArrayOfMaps`] map
+    ArrayOfMaps(MapData),
     /// A [`HashMap`] map
     HashMap(MapData),
     /// A [`PerCpuHashMap`] map
@@ -246,6 +312,8 @@ pub enum Map {
     LruHashMap(MapData),
     /// A [`PerCpuHashMap`] map that uses a LRU eviction policy.
     PerCpuLruHashMap(MapData),
+    /// A [`HashOfMaps`] map
+    HashOfMaps(MapData),
     /// A [`PerfEventArray`] map
     PerfEventArray(MapData),
     /// A [`SockMap`] map
@@ -262,6 +330,32 @@ pub enum Map {
     StackTraceMap(MapData),
     /// A [`Queue`] map
     Queue(MapData),
+    /// A [`RingBuf`] map
+    RingBuf(MapData),
+    /// A [`UserRingBuf`] map
+    UserRingBuf(MapData),
+    /// A [`CpuMap`] map
+    CpuMap(MapData),
+    /// A [`DevMap`] map
+    DevMap(MapData),
+    /// A [`DevMapHash`] map
+    DevMapHash(MapData),
+    /// A [`XskMap`] map
+    XskMap(MapData),
+    /// A [`SkStorage`] map
+    SkStorage(MapData),
+    /// A [`TaskStorage`] map
+    TaskStorage(MapData),
+    /// A [`InodeStorage`] map
+    InodeStorage(MapData),
+    /// A [`CgrpStorage`] map
+    CgrpStorage(MapData),
+    /// A [`CgroupStorage`] map
+    CgroupStorage(MapData),
+    /// A [`PerCpuCgroupStorage`] map
+    PerCpuCgroupStorage(MapData),
+    /// A [`StructOpsMap`] map
+    StructOps(MapData),
 }
 
 impl Map {
@@ -271,10 +365,12 @@ impl Map {
             Map::Array(map) => map.obj.map_type(),
             Map::PerCpuArray(map) => map.obj.map_type(),
             Map::ProgramArray(map) => map.obj.map_type(),
+            Map::ArrayOfMaps(map) => map.obj.map_type(),
             Map::HashMap(map) => map.obj.map_type(),
             Map::LruHashMap(map) => map.obj.map_type(),
             Map::PerCpuHashMap(map) => map.obj.map_type(),
             Map::PerCpuLruHashMap(map) => map.obj.map_type(),
+            Map::HashOfMaps(map) => map.obj.map_type(),
             Map::PerfEventArray(map) => map.obj.map_type(),
             Map::SockHash(map) => map.obj.map_type(),
             Map::SockMap(map) => map.obj.map_type(),
@@ -283,6 +379,19 @@ impl Map {
             Map::Stack(map) => map.obj.map_type(),
             Map::StackTraceMap(map) => map.obj.map_type(),
             Map::Queue(map) => map.obj.map_type(),
+            Map::RingBuf(map) => map.obj.map_type(),
+            Map::UserRingBuf(map) => map.obj.map_type(),
+            Map::CpuMap(map) => map.obj.map_type(),
+            Map::DevMap(map) => map.obj.map_type(),
+            Map::DevMapHash(map) => map.obj.map_type(),
+            Map::XskMap(map) => map.obj.map_type(),
+            Map::SkStorage(map) => map.obj.map_type(),
+            Map::TaskStorage(map) => map.obj.map_type(),
+            Map::InodeStorage(map) => map.obj.map_type(),
+            Map::CgrpStorage(map) => map.obj.map_type(),
+            Map::CgroupStorage(map) => map.obj.map_type(),
+            Map::PerCpuCgroupStorage(map) => map.obj.map_type(),
+            Map::StructOps(map) => map.obj.map_type(),
         }
     }
 }
@@ -334,9 +443,17 @@ macro_rules! impl_try_from_map {
 
 impl_try_from_map!(
     ProgramArray from Map::ProgramArray,
+    ArrayOfMaps from Map::ArrayOfMaps,
     SockMap from Map::SockMap,
     PerfEventArray from Map::PerfEventArray,
     StackTraceMap from Map::StackTraceMap,
+    RingBuf from Map::RingBuf,
+    UserRingBuf from Map::UserRingBuf,
+    CpuMap from Map::CpuMap,
+    DevMap from Map::DevMap,
+    DevMapHash from Map::DevMapHash,
+    XskMap from Map::XskMap,
+    StructOpsMap from Map::StructOps,
 );
 
 #[cfg(feature = "async")]
@@ -390,7 +507,21 @@ macro_rules! impl_try_from_map_generic_key_or_value {
    }
 }
 
-impl_try_from_map_generic_key_or_value!(Array, PerCpuArray, SockHash, BloomFilter, Queue, Stack,);
+impl_try_from_map_generic_key_or_value!(
+    Array,
+    PerCpuArray,
+    SockHash,
+    BloomFilter,
+    Queue,
+    Stack,
+    HashOfMaps,
+    SkStorage,
+    TaskStorage,
+    InodeStorage,
+    CgrpStorage,
+    CgroupStorage,
+    PerCpuCgroupStorage,
+);
 
 macro_rules! impl_try_from_map_generic_key_and_value {
     ($($ty:ident),+ $(,)?) => {
@@ -437,6 +568,64 @@ macro_rules! impl_try_from_map_generic_key_and_value {
 
 impl_try_from_map_generic_key_and_value!(HashMap, PerCpuHashMap, LpmTrie);
 
+macro_rules! impl_map_pin {
+    ($ty_params:tt { $($ty:ident),+ $(,)? }) => {
+        $(impl_map_pin!(<$ty_params> $ty);)+
+    };
+    (<($($ty_param:ident),*)> $ty:ident) => {
+        impl<T: AsMut<MapData>, $($ty_param: Pod),*> $ty<T, $($ty_param),*> {
+            /// Pins the map to a BPF filesystem.
+            ///
+            /// When a map is pinned it will remain loaded after Aya has dropped it, until
+            /// the file on the BPF filesystem is removed, e.g. with `unpin`. Any
+            /// directories in the path provided should have been created by the caller.
+            pub fn pin<P: AsRef<Path>>(&mut self, path: P) -> Result<(), PinError> {
+                let path = path.as_ref();
+                self.inner.as_mut().pin(&path.to_string_lossy(), path)
+            }
+
+            /// Removes the pinned map from the filesystem.
+            ///
+            /// This does nothing if the map isn't pinned.
+            pub fn unpin(&mut self) -> Result<(), io::Error> {
+                self.inner.as_mut().unpin()
+            }
+        }
+    };
+}
+
+impl_map_pin!(() {
+    ProgramArray,
+    ArrayOfMaps,
+    SockMap,
+    StackTraceMap,
+    CpuMap,
+    DevMap,
+    DevMapHash,
+    XskMap,
+    StructOpsMap,
+    RingBuf,
+    UserRingBuf,
+});
+
+impl_map_pin!((V) {
+    Array,
+    PerCpuArray,
+    BloomFilter,
+    Queue,
+    Stack,
+    SkStorage,
+    TaskStorage,
+    InodeStorage,
+    CgrpStorage,
+    CgroupStorage,
+    PerCpuCgroupStorage,
+});
+
+impl_map_pin!((K) { SockHash, HashOfMaps });
+
+impl_map_pin!((K, V) { HashMap, PerCpuHashMap, LpmTrie });
+
 pub(crate) fn check_bounds(map: &MapData, index: u32) -> Result<(), MapError> {
     let max_entries = map.obj.max_entries();
     if index >= max_entries {
@@ -479,6 +668,7 @@ pub struct MapData {
     pub(crate) btf_fd: Option<RawFd>,
     /// Indicates if this map has been pinned to bpffs
     pub pinned: bool,
+    pub(crate) pin_path: Option<PathBuf>,
 }
 
 impl AsRef<MapData> for MapData {
@@ -496,24 +686,36 @@ impl AsMut<MapData> for MapData {
 impl MapData {
     /// Creates a new map with the provided `name`
     pub fn create(&mut self, name: &str) -> Result<RawFd, MapError> {
+        self.create_with_inner_map(name, None)
+    }
+
+    /// Creates a new map with the provided `name`, using the map `inner_map_fd` as the template
+    /// of the inner maps of a map-in-map.
+    pub(crate) fn create_with_inner_map(
+        &mut self,
+        name: &str,
+        inner_map_fd: Option<RawFd>,
+    ) -> Result<RawFd, MapError> {
         if self.fd.is_some() {
             return Err(MapError::AlreadyCreated { name: name.into() });
         }
 
         let c_name = CString::new(name).map_err(|_| MapError::InvalidName { name: name.into() })?;
 
-        let fd = bpf_create_map(&c_name, &self.obj, self.btf_fd).map_err(|(code, io_error)| {
-            let k_ver = kernel_version().unwrap();
-            if k_ver < (5, 11, 0) {
-                maybe_warn_rlimit();
-            }
+        let fd = bpf_create_map(&c_name, &self.obj, self.btf_fd, inner_map_fd).map_err(
+            |(code, io_error)| {
+                let k_ver = kernel_version().unwrap();
+                if k_ver < (5, 11, 0) {
+                    maybe_warn_rlimit();
+                }
 
-            MapError::CreateError {
-                name: name.into(),
-                code,
-                io_error,
-            }
-        })? as RawFd;
+                MapError::CreateError {
+                    name: name.into(),
+                    code,
+                    io_error,
+                }
+            },
+        )? as RawFd;
 
         self.fd = Some(fd);
 
@@ -528,18 +730,68 @@ impl MapData {
         if self.fd.is_some() {
             return Err(MapError::AlreadyCreated { name: name.into() });
         }
-        let map_path = path.as_ref().join(name);
-        let path_string = CString::new(map_path.to_str().unwrap()).unwrap();
+        let path_string =
+            CString::new(path.as_ref().to_string_lossy().into_owned()).map_err(|e| {
+                MapError::PinError {
+                    name: Some(name.into()),
+                    error: PinError::InvalidPinPath {
+                        error: e.to_string(),
+                    },
+                }
+            })?;
         let fd = bpf_get_object(&path_string).map_err(|(_, io_error)| MapError::SyscallError {
             call: "BPF_OBJ_GET".to_string(),
             io_error,
         })? as RawFd;
 
         self.fd = Some(fd);
+        self.pinned = true;
+        self.pin_path = Some(path.as_ref().to_path_buf());
 
         Ok(fd)
     }
 
+    /// Checks that the type, key size, value size, max entries and flags of the map the fd
+    /// refers to, e.g. one opened with [`MapData::open_pinned`] or passed to
+    /// [`BpfLoader::set_map`](crate::BpfLoader::set_map), match its definition.
+    ///
+    /// `BPF_F_MMAPABLE` is ignored on `.bss` and `.data` maps: the loader only adds it when the
+    /// kernel supports it, so maps pinned by older versions or on older kernels don't have it.
+    /// The definition is updated with the flags of the map.
+    pub(crate) fn check_compatible(&mut self, name: &str) -> Result<(), MapError> {
+        let fd = self.fd_or_err()?;
+        let info = bpf_map_get_info_by_fd(fd).map_err(|io_error| MapError::SyscallError {
+            call: "bpf_map_get_info_by_fd".to_owned(),
+            io_error,
+        })?;
+        let loader_flags = match self.obj.kind() {
+            MapKind::Bss | MapKind::Data => BPF_F_MMAPABLE,
+            _ => 0,
+        };
+        for (field, actual, expected) in [
+            ("map type", info.type_, self.obj.map_type()),
+            ("key size", info.key_size, self.obj.key_size()),
+            ("value size", info.value_size, self.obj.value_size()),
+            ("max entries", info.max_entries, self.obj.max_entries()),
+            (
+                "map flags",
+                info.map_flags & !loader_flags,
+                self.obj.map_flags() & !loader_flags,
+            ),
+        ] {
+            if actual != expected {
+                return Err(MapError::IncompatibleMap {
+                    name: name.to_owned(),
+                    field,
+                    actual,
+                    expected,
+                });
+            }
+        }
+        self.obj.set_map_flags(info.map_flags);
+        Ok(())
+    }
+
     /// Loads a map from a pinned path in bpffs.
     pub fn from_pin<P: AsRef<Path>>(path: P) -> Result<MapData, MapError> {
         let path_string =
@@ -567,6 +819,7 @@ impl MapData {
             fd: Some(fd),
             btf_fd: None,
             pinned: true,
+            pin_path: Some(path.as_ref().to_path_buf()),
         })
     }
 
@@ -586,31 +839,180 @@ impl MapData {
             fd: Some(fd),
             btf_fd: None,
             pinned: false,
+            pin_path: None,
         })
     }
 
+    /// Loads a map from its id.
+    ///
+    /// The ids of the maps loaded in the kernel can be listed with [`loaded_maps`].
+    pub fn from_id(id: u32) -> Result<MapData, MapError> {
+        let fd = bpf_map_get_fd_by_id(id).map_err(|io_error| MapError::SyscallError {
+            call: "bpf_map_get_fd_by_id".to_owned(),
+            io_error,
+        })?;
+
+        let map = MapData::from_fd(fd);
+        if map.is_err() {
+            unsafe { libc::close(fd) };
+        }
+        map
+    }
+
     pub(crate) fn fd_or_err(&self) -> Result<RawFd, MapError> {
         self.fd.ok_or(MapError::NotCreated)
     }
 
+    /// Dumps the keys and values of the map, decoded with the BTF of the map.
+    ///
+    /// The returned value is an array with one `{"key": ..., "value": ...}` struct per
+    /// entry, or `{"key": ..., "values": [{"cpu": ..., "value": ...}]}` for per-CPU maps,
+    /// in the same layout as `bpftool map dump`. If the map has no BTF, keys and values
+    /// are dumped as raw bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use aya::maps::MapData;
+    ///
+    /// let map = MapData::from_id(42)?;
+    /// println!("{}", map.dump_btf()?.to_json());
+    /// # Ok::<(), aya::maps::MapError>(())
+    /// ```
+    pub fn dump_btf(&self) -> Result<BtfValue, MapError> {
+        let fd = self.fd_or_err()?;
+        let info = bpf_map_get_info_by_fd(fd).map_err(|io_error| MapError::SyscallError {
+            call: "bpf_map_get_info_by_fd".to_owned(),
+            io_error,
+        })?;
+        let btf = match info.btf_id {
+            0 => None,
+            id => Some(Btf::from_id(id)?),
+        };
+        let decode = |type_id: u32, data: &[u8]| -> Result<BtfValue, MapError> {
+            match &btf {
+                Some(btf) if type_id != 0 => Ok(btf.decode(type_id, data)?),
+                _ => Ok(BtfValue::Bytes(data.to_vec())),
+            }
+        };
+
+        // the values of these maps are file descriptors that can't be looked up from user space
+        let lookup_unsupported = matches!(
+            info.type_,
+            x if x == bpf_map_type::BPF_MAP_TYPE_PROG_ARRAY as u32
+                || x == bpf_map_type::BPF_MAP_TYPE_PERF_EVENT_ARRAY as u32
+                || x == bpf_map_type::BPF_MAP_TYPE_CGROUP_ARRAY as u32
+        );
+        if lookup_unsupported {
+            return Ok(BtfValue::Array(Vec::new()));
+        }
+
+        let per_cpu = matches!(
+            info.type_,
+            x if x == bpf_map_type::BPF_MAP_TYPE_PERCPU_HASH as u32
+                || x == bpf_map_type::BPF_MAP_TYPE_LRU_PERCPU_HASH as u32
+                || x == bpf_map_type::BPF_MAP_TYPE_PERCPU_ARRAY as u32
+                || x == bpf_map_type::BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE as u32
+        );
+        let value_size = info.value_size as usize;
+        let (nr_cpus, stride) = if per_cpu {
+            let nr_cpus = nr_cpus().map_err(|io_error| MapError::SyscallError {
+                call: "nr_cpus".to_owned(),
+                io_error,
+            })?;
+            (nr_cpus, (value_size + 7) & !7)
+        } else {
+            (1, value_size)
+        };
+
+        let mut entries = Vec::new();
+        let mut key: Option<Vec<u8>> = None;
+        let mut next_key = vec![0u8; info.key_size as usize];
+        let mut value = vec![0u8; nr_cpus * stride];
+        loop {
+            let found = bpf_map_get_next_key_bytes(fd, key.as_deref(), &mut next_key).map_err(
+                |(_, io_error)| MapError::SyscallError {
+                    call: "bpf_map_get_next_key".to_owned(),
+                    io_error,
+                },
+            )?;
+            if found.is_none() {
+                break;
+            }
+
+            // The entry may have been deleted since we got its key. The next key is then looked
+            // up from the last key that was read, as the kernel restarts from the first key when
+            // given one that doesn't exist.
+            match bpf_map_lookup_elem_bytes(fd, &next_key, &mut value, 0) {
+                Ok(Some(())) => {}
+                Ok(None) => continue,
+                Err((_, io_error))
+                    if entries.is_empty()
+                        && matches!(io_error.raw_os_error(), Some(EOPNOTSUPP) | Some(ENOTSUPP)) =>
+                {
+                    return Ok(BtfValue::Array(entries));
+                }
+                Err((_, io_error)) => {
+                    return Err(MapError::SyscallError {
+                        call: "bpf_map_lookup_elem".to_owned(),
+                        io_error,
+                    })
+                }
+            }
+            let current = key.insert(next_key.clone());
+
+            let mut entry = vec![("key".to_owned(), decode(info.btf_key_type_id, current)?)];
+            if per_cpu {
+                let values = value
+                    .chunks(stride)
+                    .enumerate()
+                    .map(|(cpu, value)| {
+                        Ok(BtfValue::Struct(vec![
+                            ("cpu".to_owned(), BtfValue::Unsigned(cpu as u128)),
+                            (
+                                "value".to_owned(),
+                                decode(info.btf_value_type_id, &value[..value_size])?,
+                            ),
+                        ]))
+                    })
+                    .collect::<Result<_, MapError>>()?;
+                entry.push(("values".to_owned(), BtfValue::Array(values)));
+            } else {
+                entry.push(("value".to_owned(), decode(info.btf_value_type_id, &value)?));
+            }
+            entries.push(BtfValue::Struct(entry));
+        }
+
+        Ok(BtfValue::Array(entries))
+    }
+
     pub(crate) fn pin<P: AsRef<Path>>(&mut self, name: &str, path: P) -> Result<(), PinError> {
         if self.pinned {
             return Err(PinError::AlreadyPinned { name: name.into() });
         }
-        let map_path = path.as_ref().join(name);
         let fd = self.fd.ok_or(PinError::NoFd {
             name: name.to_string(),
         })?;
-        let path_string = CString::new(map_path.to_string_lossy().into_owned()).map_err(|e| {
-            PinError::InvalidPinPath {
-                error: e.to_string(),
-            }
-        })?;
+        let path_string =
+            CString::new(path.as_ref().to_string_lossy().into_owned()).map_err(|e| {
+                PinError::InvalidPinPath {
+                    error: e.to_string(),
+                }
+            })?;
         bpf_pin_object(fd, &path_string).map_err(|(_, io_error)| PinError::SyscallError {
             name: "BPF_OBJ_PIN".to_string(),
             io_error,
         })?;
         self.pinned = true;
+        self.pin_path = Some(path.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    pub(crate) fn unpin(&mut self) -> Result<(), io::Error> {
+        if let Some(path) = self.pin_path.take() {
+            fs::remove_file(path)?;
+        }
+        self.pinned = false;
         Ok(())
     }
 
@@ -638,7 +1040,262 @@ impl Clone for MapData {
             fd: self.fd.map(|fd| unsafe { libc::dup(fd) }),
             btf_fd: self.btf_fd,
             pinned: self.pinned,
+            pin_path: self.pin_path.clone(),
+        }
+    }
+}
+
+/// Provides information about a loaded map, like name, id and size.
+#[derive(Debug)]
+pub struct MapInfo {
+    info: bpf_map_info,
+    memlock: Option<u64>,
+}
+
+impl MapInfo {
+    fn new_from_fd(fd: RawFd) -> Result<MapInfo, MapError> {
+        let info = bpf_map_get_info_by_fd(fd).map_err(|io_error| MapError::SyscallError {
+            call: "bpf_map_get_info_by_fd".to_owned(),
+            io_error,
+        })?;
+        let memlock = fs::read_to_string(format!("/proc/self/fdinfo/{fd}"))
+            .ok()
+            .and_then(|fdinfo| parse_memlock(&fdinfo));
+
+        Ok(MapInfo { info, memlock })
+    }
+
+    /// Returns the information of the map with the given id.
+    pub fn from_id(id: u32) -> Result<MapInfo, MapError> {
+        let fd = bpf_map_get_fd_by_id(id).map_err(|io_error| MapError::SyscallError {
+            call: "bpf_map_get_fd_by_id".to_owned(),
+            io_error,
+        })?;
+        let info = MapInfo::new_from_fd(fd);
+        unsafe { libc::close(fd) };
+        info
+    }
+
+    /// Returns the information of a map pinned in bpffs.
+    pub fn from_pin<P: AsRef<Path>>(path: P) -> Result<MapInfo, MapError> {
+        let path_string =
+            CString::new(path.as_ref().to_string_lossy().into_owned()).map_err(|e| {
+                MapError::PinError {
+                    name: None,
+                    error: PinError::InvalidPinPath {
+                        error: e.to_string(),
+                    },
+                }
+            })?;
+        let fd = bpf_get_object(&path_string).map_err(|(_, io_error)| MapError::SyscallError {
+            call: "BPF_OBJ_GET".to_owned(),
+            io_error,
+        })? as RawFd;
+        let info = MapInfo::new_from_fd(fd);
+        unsafe { libc::close(fd) };
+        info
+    }
+
+    /// The name of the map as was provided when it was created. This is limited to 16 bytes.
+    pub fn name(&self) -> &[u8] {
+        let length = self
+            .info
+            .name
+            .iter()
+            .rposition(|ch| *ch != 0)
+            .map(|pos| pos + 1)
+            .unwrap_or(0);
+
+        // The name field is defined as [std::os::raw::c_char; 16]. c_char may be signed or
+        // unsigned depending on the platform; that's why we're using from_raw_parts here
+        unsafe { slice::from_raw_parts(self.info.name.as_ptr() as *const _, length) }
+    }
+
+    /// The name of the map as a &str. If the name was not valid unicode, None is returned.
+    pub fn name_as_str(&self) -> Option<&str> {
+        std::str::from_utf8(self.name()).ok()
+    }
+
+    /// The map id. Each map has a unique id.
+    pub fn id(&self) -> u32 {
+        self.info.id
+    }
+
+    /// The low level map type, one of the `BPF_MAP_TYPE_*` constants.
+    pub fn map_type(&self) -> u32 {
+        self.info.type_
+    }
+
+    /// The size of the keys in bytes.
+    pub fn key_size(&self) -> u32 {
+        self.info.key_size
+    }
+
+    /// The size of the values in bytes.
+    pub fn value_size(&self) -> u32 {
+        self.info.value_size
+    }
+
+    /// The maximum number of entries of the map.
+    pub fn max_entries(&self) -> u32 {
+        self.info.max_entries
+    }
+
+    /// The flags the map was created with.
+    pub fn map_flags(&self) -> u32 {
+        self.info.map_flags
+    }
+
+    /// The id of the BTF object describing the map, or `None` if the map has no BTF.
+    pub fn btf_id(&self) -> Option<u32> {
+        (self.info.btf_id != 0).then_some(self.info.btf_id)
+    }
+
+    /// Fetches the BTF describing the map, or returns `None` if the map has no BTF.
+    pub fn btf(&self) -> Result<Option<Btf>, MapError> {
+        self.btf_id()
+            .map(|id| Btf::from_id(id).map_err(MapError::from))
+            .transpose()
+    }
+
+    /// The amount of locked memory used by the map in bytes, as reported in
+    /// `/proc/self/fdinfo`. Returns `None` if it couldn't be read.
+    pub fn memlock(&self) -> Option<u64> {
+        self.memlock
+    }
+
+    /// Returns the fd associated with the map.
+    ///
+    /// The returned fd must be closed when no longer needed.
+    pub fn fd(&self) -> Result<RawFd, MapError> {
+        bpf_map_get_fd_by_id(self.info.id).map_err(|io_error| MapError::SyscallError {
+            call: "bpf_map_get_fd_by_id".to_owned(),
+            io_error,
+        })
+    }
+}
+
+fn parse_memlock(fdinfo: &str) -> Option<u64> {
+    fdinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("memlock:"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// MapsIter is an Iterator over loaded eBPF maps.
+pub struct MapsIter {
+    current: u32,
+    error: bool,
+}
+
+impl Iterator for MapsIter {
+    type Item = Result<MapInfo, MapError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error {
+            return None;
+        }
+
+        match bpf_map_get_next_id(self.current) {
+            Ok(Some(next)) => {
+                self.current = next;
+                Some(MapInfo::from_id(next))
+            }
+            Ok(None) => None,
+            Err((_, io_error)) => {
+                // If getting the next map failed, we have to yield None in our next
+                // iteration to avoid an infinite loop.
+                self.error = true;
+                Some(Err(MapError::SyscallError {
+                    call: "bpf_map_get_next_id".to_owned(),
+                    io_error,
+                }))
+            }
+        }
+    }
+}
+
+/// Returns an iterator over all loaded bpf maps.
+///
+/// This differs from [`crate::Bpf::maps`] since it will return all maps
+/// listed on the host system and not only maps of a specific [`crate::Bpf`] instance.
+///
+/// # Example
+/// ```
+/// # use aya::maps::loaded_maps;
+///
+/// for m in loaded_maps() {
+///     match m {
+///         Ok(map) => println!("{} {}", map.id(), String::from_utf8_lossy(map.name())),
+///         Err(e) => println!("Error iterating maps: {:?}", e),
+///     }
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`MapError::SyscallError`] if any of the syscalls required to either get
+/// next map id, get the map fd, or the [`MapInfo`] fail. In cases where
+/// iteration can't be performed, for example the caller does not have the necessary privileges,
+/// a single item will be yielded containing the error that occurred.
+pub fn loaded_maps() -> MapsIter {
+    MapsIter {
+        current: 0,
+        error: false,
+    }
+}
+
+/// A memory mapped region backed by a map file descriptor.
+///
+/// The region is unmapped on drop.
+#[derive(Debug)]
+pub(crate) struct MMap {
+    ptr: NonNull<c_void>,
+    len: usize,
+}
+
+// Safety: the mapping is owned by `MMap` and access to the memory is synchronized by the map
+// types that use it.
+unsafe impl Send for MMap {}
+unsafe impl Sync for MMap {}
+
+impl MMap {
+    pub(crate) fn new(
+        fd: RawFd,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        offset: off_t,
+    ) -> Result<MMap, io::Error> {
+        let ptr = unsafe { mmap(ptr::null_mut(), len, prot, flags, fd, offset) };
+        if ptr == MAP_FAILED {
+            return Err(io::Error::last_os_error());
         }
+        let ptr = NonNull::new(ptr)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "mmap returned a null pointer"))?;
+        Ok(MMap { ptr, len })
+    }
+
+    pub(crate) fn ptr(&self) -> NonNull<c_void> {
+        self.ptr
+    }
+}
+
+impl AsRef<[u8]> for MMap {
+    fn as_ref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr() as *const u8, self.len) }
+    }
+}
+
+impl AsMut<[u8]> for MMap {
+    fn as_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr() as *mut u8, self.len) }
+    }
+}
+
+impl Drop for MMap {
+    fn drop(&mut self) {
+        unsafe { munmap(self.ptr.as_ptr(), self.len) };
     }
 }
 
@@ -839,11 +1496,16 @@ impl<T: Pod> Deref for PerCpuValues<T> {
 
 #[cfg(test)]
 mod tests {
-    use libc::EFAULT;
+    use std::cell::Cell;
+
+    use libc::{EFAULT, ENOENT};
 
     use crate::{
         bpf_map_def,
-        generated::{bpf_cmd, bpf_map_type::BPF_MAP_TYPE_HASH},
+        generated::{
+            bpf_cmd,
+            bpf_map_type::{BPF_MAP_TYPE_ARRAY, BPF_MAP_TYPE_HASH, BPF_MAP_TYPE_PROG_ARRAY},
+        },
         maps::MapData,
         obj::maps::{LegacyMap, MapKind},
         sys::{override_syscall, Syscall},
@@ -851,6 +1513,13 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_parse_memlock() {
+        let fdinfo = "pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\nmap_type:\t1\nkey_size:\t4\nvalue_size:\t8\nmax_entries:\t1024\nmap_flags:\t0x0\nmap_extra:\t0x0\nmemlock:\t86144\nmap_id:\t42\nfrozen:\t0\n";
+        assert_eq!(parse_memlock(fdinfo), Some(86144));
+        assert_eq!(parse_memlock("pos:\t0\n"), None);
+    }
+
     fn new_obj_map() -> obj::Map {
         obj::Map::Legacy(LegacyMap {
             def: bpf_map_def {
@@ -872,6 +1541,7 @@ mod tests {
             obj: new_obj_map(),
             fd: None,
             pinned: false,
+            pin_path: None,
             btf_fd: None,
         }
     }
@@ -895,6 +1565,302 @@ mod tests {
         ));
     }
 
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_from_id() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_GET_FD_BY_ID,
+                attr,
+            } => {
+                assert_eq!(unsafe { attr.__bindgen_anon_6.__bindgen_anon_1.map_id }, 7);
+                Ok(42)
+            }
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                let info = unsafe { &mut *(attr.info.info as *mut bpf_map_info) };
+                info.type_ = BPF_MAP_TYPE_HASH as u32;
+                info.id = 7;
+                info.key_size = 4;
+                info.value_size = 8;
+                info.max_entries = 1024;
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let map = MapData::from_id(7).unwrap();
+        assert_eq!(map.fd, Some(42));
+        assert_eq!(map.obj.map_type(), BPF_MAP_TYPE_HASH as u32);
+        assert_eq!(map.obj.key_size(), 4);
+        assert_eq!(map.obj.value_size(), 8);
+        assert_eq!(map.obj.max_entries(), 1024);
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_dump_btf_without_btf() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                let info = unsafe { &mut *(attr.info.info as *mut bpf_map_info) };
+                info.type_ = BPF_MAP_TYPE_HASH as u32;
+                info.key_size = 4;
+                info.value_size = 2;
+                Ok(0)
+            }
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_GET_NEXT_KEY,
+                attr,
+            } => {
+                let u = unsafe { &attr.__bindgen_anon_2 };
+                if u.key != 0 {
+                    return Err((-1, io::Error::from_raw_os_error(ENOENT)));
+                }
+                let next_key = unsafe { u.__bindgen_anon_1.next_key } as *mut [u8; 4];
+                unsafe { *next_key = [1, 2, 3, 4] };
+                Ok(0)
+            }
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_ELEM,
+                attr,
+            } => {
+                let value = unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.value } as *mut [u8; 2];
+                unsafe { *value = [0xab, 0xcd] };
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let mut map = new_map();
+        map.fd = Some(42);
+        let dump = map.dump_btf().unwrap();
+        assert_eq!(
+            dump.to_json(),
+            r#"[{"key":["0x01","0x02","0x03","0x04"],"value":["0xab","0xcd"]}]"#
+        );
+    }
+
+    thread_local! {
+        static DELETED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_dump_btf_deleted_entry() {
+        // keys 1, 2 and 3, with 2 being deleted between getting its key and looking it up
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                let info = unsafe { &mut *(attr.info.info as *mut bpf_map_info) };
+                info.type_ = BPF_MAP_TYPE_HASH as u32;
+                info.key_size = 4;
+                info.value_size = 4;
+                Ok(0)
+            }
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_GET_NEXT_KEY,
+                attr,
+            } => {
+                let u = unsafe { &attr.__bindgen_anon_2 };
+                let next = match unsafe { (u.key as *const u32).as_ref() } {
+                    None => 1,
+                    Some(1) if DELETED.with(|deleted| deleted.get()) => 3,
+                    Some(1) => 2,
+                    // a key that doesn't exist restarts the iteration
+                    Some(2) => 1,
+                    Some(3) => return Err((-1, io::Error::from_raw_os_error(ENOENT))),
+                    Some(_) => return Err((-1, io::Error::from_raw_os_error(EFAULT))),
+                };
+                unsafe { *(u.__bindgen_anon_1.next_key as *mut u32) = next };
+                Ok(0)
+            }
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_ELEM,
+                attr,
+            } => {
+                let u = unsafe { &attr.__bindgen_anon_2 };
+                let key = unsafe { *(u.key as *const u32) };
+                if key == 2 {
+                    DELETED.with(|deleted| deleted.set(true));
+                    return Err((-1, io::Error::from_raw_os_error(ENOENT)));
+                }
+                unsafe { *(u.__bindgen_anon_1.value as *mut u32) = key * 10 };
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let mut map = new_map();
+        map.fd = Some(42);
+        let dump = map.dump_btf().unwrap();
+        assert_eq!(
+            dump.to_json(),
+            concat!(
+                r#"[{"key":["0x01","0x00","0x00","0x00"],"value":["0x0a","0x00","0x00","0x00"]},"#,
+                r#"{"key":["0x03","0x00","0x00","0x00"],"value":["0x1e","0x00","0x00","0x00"]}]"#
+            )
+        );
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_dump_btf_lookup_unsupported() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                let info = unsafe { &mut *(attr.info.info as *mut bpf_map_info) };
+                info.type_ = BPF_MAP_TYPE_PROG_ARRAY as u32;
+                info.key_size = 4;
+                info.value_size = 4;
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let mut map = new_map();
+        map.fd = Some(42);
+        assert_eq!(map.dump_btf().unwrap().to_json(), "[]");
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_check_compatible() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                let info = unsafe { &mut *(attr.info.info as *mut bpf_map_info) };
+                info.type_ = BPF_MAP_TYPE_HASH as u32;
+                info.key_size = 4;
+                info.value_size = 4;
+                info.max_entries = 512;
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let mut map = new_map();
+        map.fd = Some(42);
+        assert!(matches!(
+            map.check_compatible("foo"),
+            Err(MapError::IncompatibleMap {
+                name,
+                field: "max entries",
+                actual: 512,
+                expected: 1024,
+            }) if name == "foo"
+        ));
+
+        map.obj.set_max_entries(512);
+        assert!(map.check_compatible("foo").is_ok());
+    }
+
+    #[test]
+    fn test_check_compatible_not_mmapable() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_OBJ_GET_INFO_BY_FD,
+                attr,
+            } => {
+                // a .data map pinned before the loader made it mmapable
+                let info = unsafe { &mut *(attr.info.info as *mut bpf_map_info) };
+                info.type_ = BPF_MAP_TYPE_ARRAY as u32;
+                info.key_size = 4;
+                info.value_size = 16;
+                info.max_entries = 1;
+                Ok(0)
+            }
+            _ => Err((-1, io::Error::from_raw_os_error(EFAULT))),
+        });
+
+        let new_data_map = |kind| MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_ARRAY as u32,
+                    key_size: 4,
+                    value_size: 16,
+                    max_entries: 1,
+                    map_flags: BPF_F_MMAPABLE,
+                    ..Default::default()
+                },
+                section_index: 0,
+                symbol_index: 0,
+                data: Vec::new(),
+                kind,
+            }),
+            fd: Some(42),
+            pinned: true,
+            pin_path: None,
+            btf_fd: None,
+        };
+
+        let mut map = new_data_map(MapKind::Data);
+        assert!(map.check_compatible(".data").is_ok());
+        // the map isn't mmaped
+        assert_eq!(map.obj.map_flags(), 0);
+
+        // the flag is only added by the loader to .bss and .data
+        let mut map = new_data_map(MapKind::Other);
+        assert!(matches!(
+            map.check_compatible("foo"),
+            Err(MapError::IncompatibleMap {
+                field: "map flags",
+                actual: 0,
+                expected: BPF_F_MMAPABLE,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_pin_unpin() {
+        let dir = std::env::temp_dir();
+        let pin_path = dir.join("aya-map-pin");
+
+        // override syscall to allow for pin to happen in our tmpdir
+        override_syscall(|_| Ok(0));
+        // create the file that would have happened as a side-effect of a real pin operation
+        fs::File::create(&pin_path).expect("unable to create file in tmpdir");
+
+        let mut map = new_map();
+        map.fd = Some(42);
+        let mut hm = HashMap::<_, u32, u32>::new(&mut map).unwrap();
+        hm.pin(&pin_path).expect("pin failed");
+        assert!(matches!(
+            hm.pin(&pin_path),
+            Err(PinError::AlreadyPinned { .. })
+        ));
+        hm.unpin().expect("unpin failed");
+        assert!(!pin_path.exists());
+        assert!(!map.pinned);
+    }
+
     #[test]
     fn test_create_failed() {
         override_syscall(|_| Err((-42, io::Error::from_raw_os_error(EFAULT))));
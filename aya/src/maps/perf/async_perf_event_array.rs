@@ -1,7 +1,9 @@
 use bytes::BytesMut;
 use std::{
     convert::AsMut,
+    io,
     os::unix::prelude::{AsRawFd, RawFd},
+    path::Path,
 };
 
 #[cfg(all(not(feature = "async_tokio"), feature = "async_std"))]
@@ -10,9 +12,12 @@ use async_io::Async;
 #[cfg(feature = "async_tokio")]
 use tokio::io::unix::AsyncFd;
 
-use crate::maps::{
-    perf::{Events, PerfBufferError, PerfEventArray, PerfEventArrayBuffer},
-    MapData, MapError,
+use crate::{
+    maps::{
+        perf::{Events, PerfBufferError, PerfEventArray, PerfEventArrayBuffer},
+        MapData, MapError,
+    },
+    pin::PinError,
 };
 
 /// A `Future` based map that can be used to receive events from eBPF programs using the linux
@@ -110,6 +115,20 @@ impl<T: AsMut<MapData> + AsRef<MapData>> AsyncPerfEventArray<T> {
             async_fd: Async::new(fd)?,
         })
     }
+
+    /// Pins the map to a BPF filesystem.
+    ///
+    /// See [`PerfEventArray::pin`].
+    pub fn pin<P: AsRef<Path>>(&mut self, path: P) -> Result<(), PinError> {
+        self.perf_map.pin(path)
+    }
+
+    /// Removes the pinned map from the filesystem.
+    ///
+    /// See [`PerfEventArray::unpin`].
+    pub fn unpin(&mut self) -> Result<(), io::Error> {
+        self.perf_map.unpin()
+    }
 }
 
 impl<T: AsRef<MapData>> AsyncPerfEventArray<T> {
@@ -3,18 +3,22 @@
 //! [`perf`]: https://perf.wiki.kernel.org/index.php/Main_Page.
 use std::{
     convert::AsMut,
+    io,
     ops::Deref,
     os::unix::io::{AsRawFd, RawFd},
+    path::Path,
     sync::Arc,
 };
 
 use bytes::BytesMut;
+use libc::EBUSY;
 
 use crate::{
     maps::{
         perf::{Events, PerfBuffer, PerfBufferError},
         MapData, MapError,
     },
+    pin::PinError,
     sys::bpf_map_update_elem,
     util::page_size,
 };
@@ -194,4 +198,102 @@ impl<T: AsMut<MapData> + AsRef<MapData>> PerfEventArray<T> {
             _map: self.map.clone(),
         })
     }
+
+    /// Pins the map to a BPF filesystem.
+    ///
+    /// When a map is pinned it will remain loaded after Aya has dropped it, until
+    /// the file on the BPF filesystem is removed, e.g. with `unpin`. Any
+    /// directories in the path provided should have been created by the caller.
+    ///
+    /// The map must be pinned before any buffer is opened, otherwise [`PinError::InUse`] is
+    /// returned.
+    pub fn pin<P: AsRef<Path>>(&mut self, path: P) -> Result<(), PinError> {
+        let path = path.as_ref();
+        let name = path.to_string_lossy();
+        match Arc::get_mut(&mut self.map) {
+            Some(map) => map.as_mut().pin(&name, path),
+            None => Err(PinError::InUse {
+                name: name.into_owned(),
+            }),
+        }
+    }
+
+    /// Removes the pinned map from the filesystem.
+    ///
+    /// This does nothing if the map isn't pinned. The buffers opened with [`open`](Self::open)
+    /// must have been dropped, otherwise `EBUSY` is returned.
+    pub fn unpin(&mut self) -> Result<(), io::Error> {
+        match Arc::get_mut(&mut self.map) {
+            Some(map) => map.as_mut().unpin(),
+            None => Err(io::Error::from_raw_os_error(EBUSY)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        bpf_map_def,
+        generated::bpf_map_type::BPF_MAP_TYPE_PERF_EVENT_ARRAY,
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::override_syscall,
+    };
+
+    use super::*;
+
+    fn new_map() -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_PERF_EVENT_ARRAY as u32,
+                    key_size: 4,
+                    value_size: 4,
+                    max_entries: 1,
+                    ..Default::default()
+                },
+                section_index: 0,
+                symbol_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_pin_unpin() {
+        let pin_path = std::env::temp_dir().join("aya-perf-event-array-pin");
+
+        // override syscall to allow for pin to happen in our tmpdir
+        override_syscall(|_| Ok(0));
+        // create the file that would have happened as a side-effect of a real pin operation
+        fs::File::create(&pin_path).expect("unable to create file in tmpdir");
+
+        let mut map = new_map();
+        let mut perf = PerfEventArray::new(&mut map).unwrap();
+        // the open buffers share the map
+        let buffer = perf.map.clone();
+        assert!(matches!(perf.pin(&pin_path), Err(PinError::InUse { .. })));
+        drop(buffer);
+
+        perf.pin(&pin_path).expect("pin failed");
+        let buffer = perf.map.clone();
+        assert!(perf.unpin().is_err());
+        assert!(pin_path.exists());
+        drop(buffer);
+
+        perf.unpin().expect("unpin failed");
+        assert!(!pin_path.exists());
+        drop(perf);
+        assert!(!map.pinned);
+    }
 }
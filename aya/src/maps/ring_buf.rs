@@ -0,0 +1,503 @@
+//! A [ring buffer map][ringbuf] that may be used to receive events from eBPF programs.
+//! As of Linux 5.8, this is the preferred way to transfer per-event data from eBPF
+//! programs to userspace.
+//!
+//! [ringbuf]: https://www.kernel.org/doc/html/latest/bpf/ringbuf.html
+
+use std::{
+    convert::AsRef,
+    fmt::{self, Debug, Formatter},
+    mem,
+    ops::Deref,
+    os::unix::io::{AsRawFd, RawFd},
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use libc::{MAP_SHARED, PROT_READ, PROT_WRITE};
+
+use crate::{
+    generated::{BPF_RINGBUF_BUSY_BIT, BPF_RINGBUF_DISCARD_BIT, BPF_RINGBUF_HDR_SZ},
+    maps::{MMap, MapData, MapError},
+    util::page_size,
+};
+
+/// A map that can be used to receive events from eBPF programs.
+///
+/// This is similar to [`crate::maps::PerfEventArray`], but different in a few ways:
+/// * It's shared across all CPUs, which allows a strong ordering between events.
+/// * Data notifications are delivered precisely instead of being sampled for every N events; the
+///   eBPF program can also control notification delivery if sampling is desired for performance
+///   reasons. By default, a notification will be sent if the consumer is caught up at the time of
+///   committing. The eBPF program can use the `BPF_RB_NO_WAKEUP` or `BPF_RB_FORCE_WAKEUP` flags to
+///   control this behavior.
+/// * On the eBPF side, it supports the reserve-commit pattern where the event can be directly
+///   written into the ring without copying from a temporary location.
+/// * Dropped sample notifications go to the eBPF program as the return value of `reserve`/`output`,
+///   and not the userspace reader. This might require extra code to handle, but allows for more
+///   flexible schemes to handle dropped samples.
+///
+/// To receive events you need to:
+/// * Construct [`RingBuf`] using [`RingBuf::try_from`].
+/// * Call [`RingBuf::next`] to poll events from the [`RingBuf`].
+///
+/// To receive async notifications of data availability, you may construct an
+/// [`tokio::io::unix::AsyncFd`] from the [`RingBuf`]'s file descriptor and poll it for readiness.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.8.
+///
+/// # Examples
+///
+/// ```no_run
+/// # struct PollFd<T>(T);
+/// # fn poll_fd<T>(t: T) -> PollFd<T> { PollFd(t) }
+/// # impl<T> PollFd<T> {
+/// #     fn readable(&mut self) -> Guard<'_, T> { Guard(self) }
+/// # }
+/// # struct Guard<'a, T>(&'a mut PollFd<T>);
+/// # impl<T> Guard<'_, T> {
+/// #     fn inner_mut(&mut self) -> &mut T {
+/// #         let Guard(PollFd(t)) = self;
+/// #         t
+/// #     }
+/// #     fn clear_ready(&mut self) {}
+/// # }
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::maps::RingBuf;
+///
+/// let ring_buf = RingBuf::try_from(bpf.map_mut("EVENTS").unwrap())?;
+/// let mut poll = poll_fd(ring_buf);
+/// loop {
+///     let mut guard = poll.readable();
+///     let ring_buf = guard.inner_mut();
+///     while let Some(item) = ring_buf.next() {
+///         println!("Received: {:?}", item);
+///     }
+///     guard.clear_ready();
+/// }
+/// # Ok::<(), aya::BpfError>(())
+/// ```
+///
+/// # Polling
+///
+/// In the example above the implementations of poll(), poll.readable(), guard.inner_mut(), and
+/// guard.clear_ready() are not given. RingBuf implements the [`AsRawFd`] trait, so you can
+/// implement polling using any crate that can poll file descriptors, like [epoll], [mio] etc.
+/// The above example API is motivated by that of [`tokio::io::unix::AsyncFd`].
+///
+/// [epoll]: https://docs.rs/epoll
+/// [mio]: https://docs.rs/mio
+/// [`tokio::io::unix::AsyncFd`]: https://docs.rs/tokio/latest/tokio/io/unix/struct.AsyncFd.html
+#[doc(alias = "BPF_MAP_TYPE_RINGBUF")]
+pub struct RingBuf<T> {
+    pub(crate) inner: T,
+    consumer: ConsumerPos,
+    producer: ProducerData,
+}
+
+impl<T: AsRef<MapData>> RingBuf<T> {
+    pub(crate) fn new(map: T) -> Result<RingBuf<T>, MapError> {
+        let data = map.as_ref();
+        let fd = data.fd_or_err()?;
+        let page_size = page_size();
+        let byte_size = data.obj.max_entries();
+        let consumer_metadata = ConsumerMetadata::new(fd, page_size)?;
+        let consumer = ConsumerPos::new(consumer_metadata);
+        let producer = ProducerData::new(fd, page_size, byte_size, consumer.pos)?;
+        Ok(RingBuf {
+            inner: map,
+            consumer,
+            producer,
+        })
+    }
+}
+
+impl<T> RingBuf<T> {
+    /// Try to take a new entry from the ringbuf.
+    ///
+    /// Returns `Some(item)` if the ringbuf is not empty. Returns `None` if the ringbuf is empty,
+    /// in which case the caller may register for availability notifications through `epoll` or
+    /// other APIs. Only one [`RingBufItem`] may be outstanding at a time.
+    ///
+    /// The returned item is committed, and the space it occupies released back to the
+    /// producer, when it is dropped.
+    //
+    // This is not an implementation of `Iterator` because we need to be able to refer to the
+    // lifetime of the iterator in the returned `RingBufItem`. If the Iterator::Item leveraged GATs,
+    // one could imagine an implementation of `Iterator` that would work. GATs are stabilized in
+    // Rust 1.65, but there's not yet a trait that the community seems to have standardized around.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<RingBufItem<'_>> {
+        let RingBuf {
+            consumer, producer, ..
+        } = self;
+        producer.next(consumer)
+    }
+}
+
+/// Access to the RawFd can be used to construct an AsyncFd for use with epoll.
+impl<T: AsRef<MapData>> AsRawFd for RingBuf<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        // this cannot fail as new() checks that the fd is open
+        self.inner.as_ref().fd_or_err().unwrap()
+    }
+}
+
+/// The current outstanding item read from the ringbuf.
+///
+/// The data is only valid while the item is alive. Dropping the item releases its space in the
+/// ring buffer back to the producer.
+pub struct RingBufItem<'a> {
+    data: &'a [u8],
+    consumer: &'a mut ConsumerPos,
+}
+
+impl Deref for RingBufItem<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl Drop for RingBufItem<'_> {
+    fn drop(&mut self) {
+        self.consumer.consume(self.data.len())
+    }
+}
+
+impl Debug for RingBufItem<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RingBufItem")
+            .field("pos", &self.consumer.pos)
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+// The consumer metadata page is mapped read-write. Its first word is the consumer position, which
+// userspace advances once it's done with a record.
+struct ConsumerMetadata {
+    mmap: MMap,
+}
+
+impl ConsumerMetadata {
+    fn new(fd: RawFd, page_size: usize) -> Result<ConsumerMetadata, MapError> {
+        let mmap = MMap::new(fd, page_size, PROT_READ | PROT_WRITE, MAP_SHARED, 0).map_err(
+            |io_error| MapError::SyscallError {
+                call: "mmap".to_owned(),
+                io_error,
+            },
+        )?;
+        Ok(ConsumerMetadata { mmap })
+    }
+}
+
+impl AsRef<AtomicUsize> for ConsumerMetadata {
+    fn as_ref(&self) -> &AtomicUsize {
+        unsafe { self.mmap.ptr().cast::<AtomicUsize>().as_ref() }
+    }
+}
+
+struct ConsumerPos {
+    pos: usize,
+    metadata: ConsumerMetadata,
+}
+
+impl ConsumerPos {
+    fn new(metadata: ConsumerMetadata) -> ConsumerPos {
+        // Load the initial value of the consumer position. SeqCst is used to be safe given we don't
+        // have any claims about memory synchronization performed by some previous writer.
+        let pos = metadata.as_ref().load(Ordering::SeqCst);
+        ConsumerPos { pos, metadata }
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.pos += next_record_pos(len);
+        // This value is read by the kernel using Acquire, so it must be stored with Release to make
+        // sure the kernel sees that the record has been fully processed.
+        self.metadata.as_ref().store(self.pos, Ordering::Release);
+    }
+}
+
+// Records are prefixed by an 8 byte header and padded to 8 byte alignment.
+fn next_record_pos(len: usize) -> usize {
+    let hdr_sz = BPF_RINGBUF_HDR_SZ as usize;
+    (len + hdr_sz + hdr_sz - 1) & !(hdr_sz - 1)
+}
+
+// The producer pages start with one page of metadata (the producer position) followed by the data
+// pages, all mapped read-only.
+struct ProducerData {
+    mmap: MMap,
+    // Offset in the mmap where the data starts.
+    data_offset: usize,
+    // A cache of the value of the producer position. It is used to avoid re-reading the producer
+    // position when we know there is more data to consume.
+    pos_cache: usize,
+    // A bitmask which truncates u32 values to the domain of valid offsets in the ringbuf.
+    mask: u32,
+}
+
+enum Item<'a> {
+    Busy,
+    Discard { len: usize },
+    Data(&'a [u8]),
+}
+
+impl ProducerData {
+    fn new(
+        fd: RawFd,
+        page_size: usize,
+        byte_size: u32,
+        consumer_pos: usize,
+    ) -> Result<ProducerData, MapError> {
+        // The kernel maps the data pages twice, one after the other, so that records that wrap
+        // around the end of the ring can be read as a contiguous slice. The length of the mapping
+        // must therefore include the data pages twice.
+        let len = page_size + 2 * byte_size as usize;
+        let mmap = MMap::new(fd, len, PROT_READ, MAP_SHARED, page_size as libc::off_t).map_err(
+            |io_error| MapError::SyscallError {
+                call: "mmap".to_owned(),
+                io_error,
+            },
+        )?;
+
+        // byte_size is required to be a power of two multiple of page_size (which implicitly is a
+        // power of 2), so subtracting one will create a bitmask for values less than byte_size.
+        debug_assert!(byte_size.is_power_of_two());
+        let mask = byte_size - 1;
+        Ok(ProducerData {
+            mmap,
+            data_offset: page_size,
+            // The ring may have been consumed before, eg by another process. Starting from the
+            // consumer position makes the first call to `next` load the producer position.
+            pos_cache: consumer_pos,
+            mask,
+        })
+    }
+
+    fn next<'a>(&'a mut self, consumer: &'a mut ConsumerPos) -> Option<RingBufItem<'a>> {
+        let ProducerData {
+            ref mmap,
+            data_offset,
+            pos_cache,
+            mask,
+        } = self;
+        let pos = unsafe { mmap.ptr().cast::<AtomicUsize>().as_ref() };
+        let data: &[u8] = &mmap.as_ref()[*data_offset..];
+
+        while data_available(pos, pos_cache, consumer) {
+            match read_item(data, *mask, consumer) {
+                Item::Busy => return None,
+                Item::Discard { len } => consumer.consume(len),
+                Item::Data(data) => return Some(RingBufItem { data, consumer }),
+            }
+        }
+        return None;
+
+        fn data_available(
+            producer: &AtomicUsize,
+            cache: &mut usize,
+            consumer: &ConsumerPos,
+        ) -> bool {
+            if consumer.pos == *cache {
+                // This value is written using Release by the kernel, and should be read with
+                // Acquire to ensure that the prior writes to the entry header are visible.
+                *cache = producer.load(Ordering::Acquire);
+            }
+
+            // Note that we don't compare the order of the values because the producer position may
+            // overflow and wrap around to 0. Instead we just compare equality and assume that the
+            // consumer position is always logically less than the producer position.
+            //
+            // Note also that the kernel, at the time of writing, doesn't seem to handle this
+            // overflow correctly at all, and it's not clear that one can produce events after the
+            // producer position has wrapped around.
+            consumer.pos != *cache
+        }
+
+        fn read_item<'data>(data: &'data [u8], mask: u32, consumer: &ConsumerPos) -> Item<'data> {
+            let offset = consumer.pos & mask as usize;
+            let header = &data[offset..offset + mem::size_of::<AtomicU32>()];
+            // The header is always 8 byte aligned within the mapping.
+            let header = unsafe { &*(header.as_ptr() as *const AtomicU32) };
+            // The kernel writes the header with Release, so Acquire ensures the record data is
+            // visible once the busy bit is clear.
+            let header = header.load(Ordering::Acquire);
+            if header & BPF_RINGBUF_BUSY_BIT != 0 {
+                Item::Busy
+            } else {
+                let len = (header & !(BPF_RINGBUF_BUSY_BIT | BPF_RINGBUF_DISCARD_BIT)) as usize;
+                if header & BPF_RINGBUF_DISCARD_BIT != 0 {
+                    Item::Discard { len }
+                } else {
+                    let data_offset = offset + BPF_RINGBUF_HDR_SZ as usize;
+                    Item::Data(&data[data_offset..data_offset + len])
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use crate::{
+        bpf_map_def,
+        generated::bpf_map_type::BPF_MAP_TYPE_RINGBUF,
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::TEST_MMAP_RET,
+    };
+
+    use super::*;
+
+    // The fake mmap maps the buffer as the whole file: the consumer page, the producer page and the
+    // data pages, which the kernel maps twice in a row.
+    struct FakeRing {
+        _buf: Vec<u64>,
+        base: *mut u8,
+        page_size: usize,
+    }
+
+    impl FakeRing {
+        fn new() -> FakeRing {
+            let page_size = page_size();
+            let mut buf = vec![0u64; page_size * 4 / mem::size_of::<u64>()];
+            let base = buf.as_mut_ptr().cast::<u8>();
+            TEST_MMAP_RET.with(|ret| *ret.borrow_mut() = base.cast());
+            FakeRing {
+                _buf: buf,
+                base,
+                page_size,
+            }
+        }
+
+        fn consumer_pos(&self) -> usize {
+            unsafe { ptr::read_volatile(self.base.cast::<usize>()) }
+        }
+
+        fn set_consumer_pos(&self, pos: usize) {
+            unsafe { ptr::write_volatile(self.base.cast::<usize>(), pos) }
+        }
+
+        fn set_producer_pos(&self, pos: usize) {
+            unsafe { ptr::write_volatile(self.base.add(self.page_size).cast::<usize>(), pos) }
+        }
+
+        fn write_record(&self, pos: usize, header: u32, data: &[u8]) {
+            let offset = pos & (self.page_size - 1);
+            self.write(offset, &header.to_ne_bytes());
+            self.write(offset + BPF_RINGBUF_HDR_SZ as usize, data);
+        }
+
+        // Writes to both mappings of the data pages.
+        fn write(&self, offset: usize, bytes: &[u8]) {
+            let data = unsafe { self.base.add(self.page_size * 2) };
+            for (i, b) in bytes.iter().enumerate() {
+                let offset = (offset + i) % self.page_size;
+                unsafe {
+                    ptr::write_volatile(data.add(offset), *b);
+                    ptr::write_volatile(data.add(self.page_size + offset), *b);
+                }
+            }
+        }
+    }
+
+    fn new_map() -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_RINGBUF as u32,
+                    key_size: 0,
+                    value_size: 0,
+                    max_entries: page_size() as u32,
+                    ..Default::default()
+                },
+                section_index: 0,
+                symbol_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    #[test]
+    fn test_next_record_pos() {
+        assert_eq!(next_record_pos(0), 8);
+        assert_eq!(next_record_pos(1), 16);
+        assert_eq!(next_record_pos(8), 16);
+        assert_eq!(next_record_pos(9), 24);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_next_busy() {
+        let fake = FakeRing::new();
+        let map = new_map();
+        let mut ring = RingBuf::new(&map).unwrap();
+        assert!(ring.next().is_none());
+
+        // the record is still being written
+        fake.write_record(0, 4 | BPF_RINGBUF_BUSY_BIT, &[1, 2, 3, 4]);
+        fake.set_producer_pos(16);
+        assert!(ring.next().is_none());
+        assert_eq!(fake.consumer_pos(), 0);
+
+        fake.write_record(0, 4, &[1, 2, 3, 4]);
+        let item = ring.next().unwrap();
+        assert_eq!(&*item, &[1, 2, 3, 4]);
+        // the record is committed when the item is dropped
+        assert_eq!(fake.consumer_pos(), 0);
+        drop(item);
+        assert_eq!(fake.consumer_pos(), 16);
+        assert!(ring.next().is_none());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_next_discard() {
+        let fake = FakeRing::new();
+        let map = new_map();
+        let mut ring = RingBuf::new(&map).unwrap();
+
+        fake.write_record(0, 4 | BPF_RINGBUF_DISCARD_BIT, &[1, 2, 3, 4]);
+        fake.write_record(16, 3, &[5, 6, 7]);
+        fake.set_producer_pos(32);
+        let item = ring.next().unwrap();
+        assert_eq!(&*item, &[5, 6, 7]);
+        // the discarded record is skipped and committed right away
+        assert_eq!(fake.consumer_pos(), 16);
+        drop(item);
+        assert_eq!(fake.consumer_pos(), 32);
+        assert!(ring.next().is_none());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_next_wraparound() {
+        let fake = FakeRing::new();
+        let start = fake.page_size - 8;
+        fake.set_consumer_pos(start);
+        let map = new_map();
+        let mut ring = RingBuf::new(&map).unwrap();
+
+        // the header is at the end of the ring and the data wraps around to its start
+        let data = (0..12).collect::<Vec<u8>>();
+        fake.write_record(start, data.len() as u32, &data);
+        fake.set_producer_pos(start + next_record_pos(data.len()));
+        let item = ring.next().unwrap();
+        assert_eq!(&*item, data.as_slice());
+        drop(item);
+        assert_eq!(fake.consumer_pos(), fake.page_size + 16);
+        assert!(ring.next().is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! A map implementing a kernel struct of function pointers.
+
+use std::{convert::AsRef, mem};
+
+use crate::{
+    maps::{MapData, MapError},
+    obj::struct_ops::StructOpsLayout,
+    programs::{links::FdLink, Program},
+    sys::{bpf_map_update_elem_ptr, bpf_struct_ops_link_create},
+};
+
+/// A map implementing a kernel struct of function pointers, eg `tcp_congestion_ops`.
+///
+/// The struct is declared in a `.struct_ops` section, and its function pointer members are set
+/// to `struct_ops` programs. Once the programs are loaded, registering the map hands the
+/// implementation over to the kernel, until the returned link is dropped.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 6.4.
+///
+/// # Examples
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::{maps::StructOpsMap, programs::StructOps};
+///
+/// for name in ["cubic_init", "cubic_cong_avoid"] {
+///     let program: &mut StructOps = bpf.program_mut(name).unwrap().try_into()?;
+///     program.load()?;
+/// }
+/// let ops = StructOpsMap::try_from(bpf.map("cubic").unwrap())?;
+/// let link = ops.register(bpf.programs())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_STRUCT_OPS")]
+pub struct StructOpsMap<T> {
+    pub(crate) inner: T,
+}
+
+impl<T: AsRef<MapData>> StructOpsMap<T> {
+    pub(crate) fn new(map: T) -> Result<StructOpsMap<T>, MapError> {
+        let data = map.as_ref();
+        let size = mem::size_of::<u32>();
+        let expected = data.obj.key_size() as usize;
+        if size != expected {
+            return Err(MapError::InvalidKeySize { size, expected });
+        }
+
+        let _fd = data.fd_or_err()?;
+
+        Ok(StructOpsMap { inner: map })
+    }
+
+    /// Returns the name of the implemented kernel struct, or `None` if the map wasn't created
+    /// from an object file.
+    pub fn type_name(&self) -> Option<&str> {
+        self.inner
+            .as_ref()
+            .obj
+            .struct_ops()
+            .map(|struct_ops| struct_ops.type_name.as_str())
+    }
+
+    /// Returns the names of the programs set as function pointer members of the struct.
+    pub fn programs(&self) -> impl Iterator<Item = &str> {
+        self.inner
+            .as_ref()
+            .obj
+            .struct_ops()
+            .into_iter()
+            .flat_map(|struct_ops| struct_ops.programs.iter())
+            .map(|(_, program)| program.as_str())
+    }
+
+    /// Registers the struct with the kernel.
+    ///
+    /// `programs` must contain the loaded programs set as function pointer members of the
+    /// struct, eg the ones returned by [`Bpf::programs`](crate::Bpf::programs). The struct stays
+    /// registered until the returned link is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::ProgramNotLoaded`] if one of the programs isn't loaded,
+    /// [`MapError::SyscallError`] if `bpf_map_update_elem` or `bpf_link_create` fail.
+    pub fn register<'a>(
+        &self,
+        programs: impl IntoIterator<Item = (&'a str, &'a Program)>,
+    ) -> Result<FdLink, MapError> {
+        let data = self.inner.as_ref();
+        let fd = data.fd_or_err()?;
+        let layout = self.layout()?;
+
+        let programs = programs.into_iter().collect::<Vec<_>>();
+        let mut value = data.obj.data().to_vec();
+        for member in &layout.programs {
+            let prog_fd = programs
+                .iter()
+                .find(|(name, _)| *name == member.name)
+                .and_then(|(_, program)| program.fd())
+                .ok_or(MapError::ProgramNotLoaded)?;
+            let prog_fd = prog_fd.0 as u64;
+            value[member.offset..member.offset + mem::size_of::<u64>()]
+                .copy_from_slice(&prog_fd.to_ne_bytes());
+        }
+
+        bpf_map_update_elem_ptr(fd, &0u32, value.as_mut_ptr(), 0).map_err(|(_, io_error)| {
+            MapError::SyscallError {
+                call: "bpf_map_update_elem".to_owned(),
+                io_error,
+            }
+        })?;
+        let link_fd =
+            bpf_struct_ops_link_create(fd).map_err(|(_, io_error)| MapError::SyscallError {
+                call: "bpf_link_create".to_owned(),
+                io_error,
+            })?;
+
+        Ok(FdLink::new(link_fd as _))
+    }
+
+    fn layout(&self) -> Result<&StructOpsLayout, MapError> {
+        self.inner
+            .as_ref()
+            .obj
+            .struct_ops()
+            .and_then(|struct_ops| struct_ops.layout.as_ref())
+            .ok_or(MapError::NoStructOpsLayout)
+    }
+}
@@ -0,0 +1,421 @@
+//! A [user ring buffer map][ringbuf] that may be used to send messages from user space to eBPF
+//! programs.
+//!
+//! [ringbuf]: https://www.kernel.org/doc/html/latest/bpf/ringbuf.html
+
+use std::{
+    convert::AsRef,
+    fmt::{self, Debug, Formatter},
+    ops::{Deref, DerefMut},
+    os::unix::io::{AsRawFd, RawFd},
+    slice,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use libc::{MAP_SHARED, PROT_READ, PROT_WRITE};
+use thiserror::Error;
+
+use crate::{
+    generated::{BPF_RINGBUF_BUSY_BIT, BPF_RINGBUF_DISCARD_BIT, BPF_RINGBUF_HDR_SZ},
+    maps::{MMap, MapData, MapError},
+    util::page_size,
+};
+
+/// User ring buffer error.
+#[derive(Error, Debug)]
+pub enum UserRingBufError {
+    /// The requested sample can never fit in the ring buffer.
+    #[error("a sample of {size} bytes does not fit in a ring buffer of {capacity} bytes")]
+    TooLarge {
+        /// the requested sample size
+        size: usize,
+        /// the size of the ring buffer data area
+        capacity: usize,
+    },
+
+    /// There isn't enough free space in the ring buffer for the requested sample.
+    ///
+    /// Space is freed when eBPF programs drain the ring buffer with `bpf_user_ringbuf_drain()`.
+    #[error("not enough space in the ring buffer for a sample of {size} bytes")]
+    Full {
+        /// the requested sample size
+        size: usize,
+    },
+}
+
+/// A ring buffer that can be used to send messages from user space to eBPF programs.
+///
+/// This is the opposite of [`RingBuf`](crate::maps::RingBuf): user space is the producer and eBPF
+/// programs consume the samples with `bpf_user_ringbuf_drain()`. Space for a sample is reserved
+/// with [`UserRingBuf::reserve`], filled in place and then made visible to the kernel with
+/// [`UserRingBufEntry::submit`].
+///
+/// Reserving is a single producer operation, which is enforced by [`UserRingBuf::reserve`] taking
+/// `&mut self`. Samples are consumed in the order in which they were reserved.
+///
+/// The file descriptor of the map becomes writable when eBPF programs drain samples, so you can
+/// poll it using any crate that can poll file descriptors, like [epoll] or [mio], to wait for free
+/// space.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 6.1.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[derive(thiserror::Error, Debug)]
+/// # enum Error {
+/// #    #[error(transparent)]
+/// #    Map(#[from] aya::maps::MapError),
+/// #    #[error(transparent)]
+/// #    Bpf(#[from] aya::BpfError),
+/// #    #[error(transparent)]
+/// #    UserRingBuf(#[from] aya::maps::user_ring_buf::UserRingBufError),
+/// # }
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::maps::UserRingBuf;
+///
+/// let mut commands = UserRingBuf::try_from(bpf.map_mut("COMMANDS").unwrap())?;
+///
+/// let command = 42u64.to_ne_bytes();
+/// let mut entry = commands.reserve(command.len())?;
+/// entry.copy_from_slice(&command);
+/// entry.submit();
+/// # Ok::<(), Error>(())
+/// ```
+///
+/// [epoll]: https://docs.rs/epoll
+/// [mio]: https://docs.rs/mio
+#[doc(alias = "BPF_MAP_TYPE_USER_RINGBUF")]
+pub struct UserRingBuf<T> {
+    pub(crate) inner: T,
+    // The consumer page, mapped read-only. It contains the consumer position, which is advanced
+    // by the kernel as samples are drained.
+    consumer: MMap,
+    // The producer page, mapped read-write, followed by the data pages mapped twice so that
+    // samples that wrap around the end of the ring are contiguous.
+    producer: MMap,
+    data_offset: usize,
+    mask: usize,
+}
+
+impl<T: AsRef<MapData>> UserRingBuf<T> {
+    pub(crate) fn new(map: T) -> Result<UserRingBuf<T>, MapError> {
+        let data = map.as_ref();
+        let fd = data.fd_or_err()?;
+        let page_size = page_size();
+        let byte_size = data.obj.max_entries() as usize;
+
+        let consumer = MMap::new(fd, page_size, PROT_READ, MAP_SHARED, 0).map_err(|io_error| {
+            MapError::SyscallError {
+                call: "mmap".to_owned(),
+                io_error,
+            }
+        })?;
+        let producer = MMap::new(
+            fd,
+            page_size + 2 * byte_size,
+            PROT_READ | PROT_WRITE,
+            MAP_SHARED,
+            page_size as libc::off_t,
+        )
+        .map_err(|io_error| MapError::SyscallError {
+            call: "mmap".to_owned(),
+            io_error,
+        })?;
+
+        // byte_size is required to be a power of two multiple of page_size, so subtracting one
+        // will create a bitmask for values less than byte_size.
+        debug_assert!(byte_size.is_power_of_two());
+        Ok(UserRingBuf {
+            inner: map,
+            consumer,
+            producer,
+            data_offset: page_size,
+            mask: byte_size - 1,
+        })
+    }
+}
+
+impl<T> UserRingBuf<T> {
+    /// Reserves space for a sample of `size` bytes.
+    ///
+    /// The returned entry dereferences to a zeroed `size` bytes long slice. It must be committed
+    /// with [`UserRingBufEntry::submit`] to be seen by eBPF programs. Entries that are dropped
+    /// without being submitted are discarded.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UserRingBufError::TooLarge`] if the sample can never fit in the ring buffer and
+    /// [`UserRingBufError::Full`] if there isn't currently enough free space for it.
+    pub fn reserve(&mut self, size: usize) -> Result<UserRingBufEntry<'_>, UserRingBufError> {
+        let hdr_sz = BPF_RINGBUF_HDR_SZ as usize;
+        let capacity = self.mask + 1;
+        let max_size = (BPF_RINGBUF_DISCARD_BIT - 1) as usize;
+        let total_size = (size + hdr_sz + hdr_sz - 1) & !(hdr_sz - 1);
+        if size > max_size || total_size > capacity {
+            return Err(UserRingBufError::TooLarge { size, capacity });
+        }
+
+        // The consumer position is written by the kernel using Release, so it must be read with
+        // Acquire to make sure the kernel is done with the space it released.
+        let consumer_pos = self.consumer_pos().load(Ordering::Acquire);
+        // We're the only producer, so nobody else changes this value under us.
+        let producer_pos = self.producer_pos().load(Ordering::Relaxed);
+        if capacity - producer_pos.wrapping_sub(consumer_pos) < total_size {
+            return Err(UserRingBufError::Full { size });
+        }
+
+        let offset = self.data_offset + (producer_pos & self.mask);
+        let base = self.producer.ptr().as_ptr() as *mut u8;
+        let (header, data) = unsafe {
+            let header = &*(base.add(offset) as *const AtomicU32);
+            let data = slice::from_raw_parts_mut(base.add(offset + hdr_sz), size);
+            (header, data)
+        };
+        // Mark the sample as busy so that the kernel stops draining at it until it's committed.
+        header.store(size as u32 | BPF_RINGBUF_BUSY_BIT, Ordering::Relaxed);
+        data.fill(0);
+
+        // The kernel reads the producer position using Acquire, so it must be stored with Release
+        // to make sure the busy header is visible before the new position.
+        self.producer_pos()
+            .store(producer_pos + total_size, Ordering::Release);
+
+        Ok(UserRingBufEntry {
+            header,
+            data,
+            done: false,
+        })
+    }
+
+    fn consumer_pos(&self) -> &AtomicUsize {
+        unsafe { self.consumer.ptr().cast::<AtomicUsize>().as_ref() }
+    }
+
+    fn producer_pos(&self) -> &AtomicUsize {
+        unsafe { self.producer.ptr().cast::<AtomicUsize>().as_ref() }
+    }
+}
+
+impl<T: AsRef<MapData>> AsRawFd for UserRingBuf<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        // this cannot fail as new() checks that the fd is open
+        self.inner.as_ref().fd_or_err().unwrap()
+    }
+}
+
+/// A sample reserved with [`UserRingBuf::reserve`].
+///
+/// Dereferences to the sample data. The sample is handed to the kernel by
+/// [`submit`](UserRingBufEntry::submit). Samples that are dropped without being submitted are
+/// discarded.
+pub struct UserRingBufEntry<'a> {
+    header: &'a AtomicU32,
+    data: &'a mut [u8],
+    done: bool,
+}
+
+impl UserRingBufEntry<'_> {
+    /// Commits the sample, making it visible to eBPF programs.
+    pub fn submit(mut self) {
+        self.commit(false)
+    }
+
+    /// Discards the sample. The kernel skips it when draining the ring buffer.
+    pub fn discard(mut self) {
+        self.commit(true)
+    }
+
+    fn commit(&mut self, discard: bool) {
+        let mut len = self.data.len() as u32;
+        if discard {
+            len |= BPF_RINGBUF_DISCARD_BIT;
+        }
+        // Clearing the busy bit with Release publishes the sample data to the kernel.
+        self.header.swap(len, Ordering::AcqRel);
+        self.done = true;
+    }
+}
+
+impl Deref for UserRingBufEntry<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl DerefMut for UserRingBufEntry<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
+}
+
+impl Drop for UserRingBufEntry<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.commit(true)
+        }
+    }
+}
+
+impl Debug for UserRingBufEntry<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserRingBufEntry")
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem, ptr};
+
+    use crate::{
+        bpf_map_def,
+        generated::bpf_map_type::BPF_MAP_TYPE_USER_RINGBUF,
+        maps::Map,
+        obj::{self, maps::LegacyMap, maps::MapKind},
+        sys::TEST_MMAP_RET,
+    };
+
+    use super::*;
+
+    // The fake mmap maps the buffer as the whole file: the consumer page, the producer page and
+    // the data pages, which the kernel maps twice in a row.
+    struct FakeRing {
+        _buf: Vec<u64>,
+        base: *mut u8,
+        page_size: usize,
+    }
+
+    impl FakeRing {
+        fn new() -> FakeRing {
+            let page_size = page_size();
+            let mut buf = vec![0u64; page_size * 4 / mem::size_of::<u64>()];
+            let base = buf.as_mut_ptr().cast::<u8>();
+            TEST_MMAP_RET.with(|ret| *ret.borrow_mut() = base.cast());
+            FakeRing {
+                _buf: buf,
+                base,
+                page_size,
+            }
+        }
+
+        // Advances the consumer position, as the kernel does when it drains samples.
+        fn set_consumer_pos(&self, pos: usize) {
+            unsafe { ptr::write_volatile(self.base.cast::<usize>(), pos) }
+        }
+
+        fn producer_pos(&self) -> usize {
+            unsafe { ptr::read_volatile(self.base.add(self.page_size).cast::<usize>()) }
+        }
+
+        fn data(&self, offset: usize, len: usize) -> Vec<u8> {
+            let data = unsafe { self.base.add(self.page_size * 2 + offset) };
+            (0..len)
+                .map(|i| unsafe { ptr::read_volatile(data.add(i)) })
+                .collect()
+        }
+    }
+
+    fn new_map(fd: Option<RawFd>) -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_USER_RINGBUF as u32,
+                    key_size: 0,
+                    value_size: 0,
+                    max_entries: page_size() as u32,
+                    ..Default::default()
+                },
+                section_index: 0,
+                symbol_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+            }),
+            fd,
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    #[test]
+    fn test_new_not_created() {
+        let map = new_map(None);
+        assert!(matches!(UserRingBuf::new(&map), Err(MapError::NotCreated)));
+    }
+
+    #[test]
+    fn test_try_from_wrong_map() {
+        let map = Map::RingBuf(new_map(Some(42)));
+        assert!(matches!(
+            UserRingBuf::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_reserve_too_large() {
+        let fake = FakeRing::new();
+        let map = new_map(Some(42));
+        let mut ring = UserRingBuf::new(&map).unwrap();
+        assert!(matches!(
+            ring.reserve(fake.page_size),
+            Err(UserRingBufError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_reserve_submit() {
+        let fake = FakeRing::new();
+        let map = new_map(Some(42));
+        let mut ring = UserRingBuf::new(&map).unwrap();
+
+        let mut entry = ring.reserve(4).unwrap();
+        entry.copy_from_slice(&[1, 2, 3, 4]);
+        entry.submit();
+        // the discard path runs on drop
+        drop(ring.reserve(1).unwrap());
+
+        assert_eq!(fake.producer_pos(), 32);
+        assert_eq!(fake.data(0, 4), 4u32.to_ne_bytes());
+        assert_eq!(fake.data(8, 4), [1, 2, 3, 4]);
+        assert_eq!(
+            fake.data(16, 4),
+            (1 | BPF_RINGBUF_DISCARD_BIT).to_ne_bytes()
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_reserve_full() {
+        let fake = FakeRing::new();
+        let map = new_map(Some(42));
+        let mut ring = UserRingBuf::new(&map).unwrap();
+
+        // two samples fill the ring
+        let size = fake.page_size / 2 - BPF_RINGBUF_HDR_SZ as usize;
+        ring.reserve(size).unwrap().submit();
+        ring.reserve(size).unwrap().submit();
+        assert_eq!(fake.producer_pos(), fake.page_size);
+        assert!(matches!(
+            ring.reserve(1),
+            Err(UserRingBufError::Full { size: 1 })
+        ));
+
+        // the kernel drains the first sample, its space can be reused
+        fake.set_consumer_pos(fake.page_size / 2);
+        ring.reserve(size).unwrap().submit();
+        assert_eq!(fake.producer_pos(), fake.page_size * 3 / 2);
+        assert!(matches!(
+            ring.reserve(1),
+            Err(UserRingBufError::Full { .. })
+        ));
+    }
+}
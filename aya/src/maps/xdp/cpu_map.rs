@@ -0,0 +1,205 @@
+//! An array of available CPUs.
+
+use std::convert::{AsMut, AsRef};
+
+use crate::{
+    generated::bpf_cpumap_val,
+    maps::{
+        check_bounds,
+        xdp::{check_kv_size, lookup, update, XdpMapError},
+        MapData, MapError,
+    },
+    programs::ProgramFd,
+};
+
+/// An array of available CPUs.
+///
+/// XDP programs can use this map to redirect packets to a target CPU for processing, by calling
+/// `bpf_redirect_map(cpu_map, cpu, flags)`. Each entry holds the size of the queue used to hand
+/// packets over to the target CPU, and optionally a second-level XDP program that is run on that
+/// CPU. Programs used this way must be declared with `#[xdp(map = "cpumap")]`.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.15, or 5.9 to chain programs.
+///
+/// # Examples
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::maps::xdp::CpuMap;
+///
+/// let ncpus = aya::util::nr_cpus().unwrap() as u32;
+/// let mut cpumap = CpuMap::try_from(bpf.map_mut("CPUS").unwrap())?;
+/// let flags = 0;
+/// let queue_size = 2048;
+/// for i in 0..ncpus {
+///     cpumap.set(i, queue_size, None, flags)?;
+/// }
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_CPUMAP")]
+pub struct CpuMap<T> {
+    pub(crate) inner: T,
+}
+
+/// The value of a [`CpuMap`] entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CpuMapValue {
+    /// Size of the queue used to hand packets over to the CPU.
+    pub queue_size: u32,
+    /// Id of the chained XDP program, if any.
+    pub prog_id: Option<u32>,
+}
+
+impl<T: AsRef<MapData>> CpuMap<T> {
+    pub(crate) fn new(map: T) -> Result<CpuMap<T>, MapError> {
+        let data = map.as_ref();
+        check_kv_size::<bpf_cpumap_val>(data)?;
+
+        let _fd = data.fd_or_err()?;
+
+        Ok(CpuMap { inner: map })
+    }
+
+    /// Returns the number of elements in the array.
+    ///
+    /// This corresponds to the value of `bpf_map_def::max_entries` on the eBPF side.
+    pub fn len(&self) -> u32 {
+        self.inner.as_ref().obj.max_entries()
+    }
+
+    /// Returns the queue size and chained program id for the given CPU index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `cpu_index` is out of bounds,
+    /// [`MapError::SyscallError`] if `bpf_map_lookup_elem` fails.
+    pub fn get(&self, cpu_index: u32, flags: u64) -> Result<CpuMapValue, MapError> {
+        let data = self.inner.as_ref();
+        check_bounds(data, cpu_index)?;
+        lookup::<_, bpf_cpumap_val>(data, &cpu_index, flags).map(|(queue_size, prog_id)| {
+            CpuMapValue {
+                queue_size,
+                prog_id,
+            }
+        })
+    }
+
+    /// An iterator over the elements of the map. The iterator item type is
+    /// `Result<CpuMapValue, MapError>`.
+    pub fn iter(&self) -> impl Iterator<Item = Result<CpuMapValue, MapError>> + '_ {
+        (0..self.len()).map(move |i| self.get(i, 0))
+    }
+}
+
+impl<T: AsMut<MapData>> CpuMap<T> {
+    /// Sets the queue size at the given CPU index, and optionally a chained program.
+    ///
+    /// When sending the packet to the CPU at the given index, the kernel will queue up to
+    /// `queue_size` packets before dropping them. If `program` is set, it is run on the packets
+    /// on the target CPU.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `cpu_index` is out of bounds,
+    /// [`MapError::SyscallError`] if `bpf_map_update_elem` fails,
+    /// [`XdpMapError::ChainedProgramNotSupported`] if a program is passed but the map values are
+    /// plain queue sizes.
+    pub fn set(
+        &mut self,
+        cpu_index: u32,
+        queue_size: u32,
+        program: Option<ProgramFd>,
+        flags: u64,
+    ) -> Result<(), XdpMapError> {
+        let data = self.inner.as_mut();
+        check_bounds(data, cpu_index)?;
+        update::<_, bpf_cpumap_val>(data, &cpu_index, queue_size, program, flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::EFAULT;
+
+    use crate::{
+        bpf_map_def,
+        generated::{bpf_cmd, bpf_map_type::BPF_MAP_TYPE_CPUMAP},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        sys::{override_syscall, SysResult, Syscall},
+    };
+
+    use super::*;
+
+    fn new_map(value_size: u32) -> MapData {
+        MapData {
+            obj: obj::Map::Legacy(LegacyMap {
+                def: bpf_map_def {
+                    map_type: BPF_MAP_TYPE_CPUMAP as u32,
+                    key_size: 4,
+                    value_size,
+                    max_entries: 4,
+                    ..Default::default()
+                },
+                section_index: 0,
+                data: Vec::new(),
+                kind: MapKind::Other,
+                symbol_index: 0,
+            }),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn sys_error(value: i32) -> SysResult {
+        Err((-1, io::Error::from_raw_os_error(value)))
+    }
+
+    #[test]
+    fn test_set_program_not_supported() {
+        override_syscall(|_| sys_error(EFAULT));
+
+        let mut map = new_map(4);
+        let mut cpumap = CpuMap::new(&mut map).unwrap();
+
+        assert!(matches!(
+            cpumap.set(0, 2048, Some(ProgramFd(7)), 0),
+            Err(XdpMapError::ChainedProgramNotSupported)
+        ));
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_set_cpumap_val() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_ELEM,
+                attr,
+            } => {
+                let value = unsafe {
+                    *(attr.__bindgen_anon_2.__bindgen_anon_1.value as *const bpf_cpumap_val)
+                };
+                assert_eq!(value.qsize, 2048);
+                assert_eq!(unsafe { value.bpf_prog.fd }, 7);
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let mut map = new_map(8);
+        let mut cpumap = CpuMap::new(&mut map).unwrap();
+
+        assert!(cpumap.set(1, 2048, Some(ProgramFd(7)), 0).is_ok());
+    }
+}
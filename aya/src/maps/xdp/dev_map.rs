@@ -0,0 +1,262 @@
+//! An array of network devices.
+
+use std::convert::{AsMut, AsRef};
+
+use crate::{
+    maps::{
+        check_bounds,
+        xdp::{check_devmap_kv_size, devmap_lookup, devmap_update, DevMapValue, XdpMapError},
+        MapData, MapError,
+    },
+    programs::ProgramFd,
+};
+
+/// An array of network devices.
+///
+/// XDP programs can use this map to redirect packets to other network devices by calling
+/// `bpf_redirect_map(dev_map, index, flags)`.
+///
+/// The map values can either be plain interface indices, or `bpf_devmap_val` structs which also
+/// carry a second-level XDP program that is run on the redirected packets before they are
+/// transmitted. Programs used this way must be declared with `#[xdp(map = "devmap")]`.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.14, or 5.8 to chain programs.
+///
+/// # Examples
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::maps::xdp::DevMap;
+///
+/// let mut devmap = DevMap::try_from(bpf.map_mut("IFACES").unwrap())?;
+/// let source = 32u32;
+/// let dest = 42u32;
+/// devmap.set(source, dest, None, 0)?;
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_DEVMAP")]
+pub struct DevMap<T> {
+    pub(crate) inner: T,
+}
+
+impl<T: AsRef<MapData>> DevMap<T> {
+    pub(crate) fn new(map: T) -> Result<DevMap<T>, MapError> {
+        let data = map.as_ref();
+        check_devmap_kv_size(data)?;
+
+        let _fd = data.fd_or_err()?;
+
+        Ok(DevMap { inner: map })
+    }
+
+    /// Returns the number of elements in the array.
+    ///
+    /// This corresponds to the value of `bpf_map_def::max_entries` on the eBPF side.
+    pub fn len(&self) -> u32 {
+        self.inner.as_ref().obj.max_entries()
+    }
+
+    /// Returns the target interface index and chained program id at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds, [`MapError::SyscallError`]
+    /// if `bpf_map_lookup_elem` fails.
+    pub fn get(&self, index: u32, flags: u64) -> Result<DevMapValue, MapError> {
+        let data = self.inner.as_ref();
+        check_bounds(data, index)?;
+        devmap_lookup(data, &index, flags)
+    }
+
+    /// An iterator over the elements of the array. The iterator item type is
+    /// `Result<DevMapValue, MapError>`.
+    pub fn iter(&self) -> impl Iterator<Item = Result<DevMapValue, MapError>> + '_ {
+        (0..self.len()).map(move |i| self.get(i, 0))
+    }
+}
+
+impl<T: AsMut<MapData>> DevMap<T> {
+    /// Sets the target interface index at the given index, and optionally a chained program.
+    ///
+    /// When redirecting using `index`, packets will be transmitted by the interface with
+    /// `target_if_index`. If `program` is set, it is run on the packets before they are
+    /// transmitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds, [`MapError::SyscallError`]
+    /// if `bpf_map_update_elem` fails, [`XdpMapError::ChainedProgramNotSupported`] if a program
+    /// is passed but the map values are plain interface indices.
+    pub fn set(
+        &mut self,
+        index: u32,
+        target_if_index: u32,
+        program: Option<ProgramFd>,
+        flags: u64,
+    ) -> Result<(), XdpMapError> {
+        let data = self.inner.as_mut();
+        check_bounds(data, index)?;
+        devmap_update(data, &index, target_if_index, program, flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use libc::EFAULT;
+
+    use crate::{
+        bpf_map_def,
+        generated::{bpf_attr, bpf_cmd, bpf_devmap_val, bpf_map_type::BPF_MAP_TYPE_DEVMAP},
+        maps::{Map, MapData},
+        obj::{
+            self,
+            maps::{LegacyMap, MapKind},
+        },
+        programs::ProgramFd,
+        sys::{override_syscall, SysResult, Syscall},
+    };
+
+    use super::*;
+
+    fn new_obj_map(value_size: u32) -> obj::Map {
+        obj::Map::Legacy(LegacyMap {
+            def: bpf_map_def {
+                map_type: BPF_MAP_TYPE_DEVMAP as u32,
+                key_size: 4,
+                value_size,
+                max_entries: 16,
+                ..Default::default()
+            },
+            section_index: 0,
+            data: Vec::new(),
+            kind: MapKind::Other,
+            symbol_index: 0,
+        })
+    }
+
+    fn new_map(value_size: u32) -> MapData {
+        MapData {
+            obj: new_obj_map(value_size),
+            fd: Some(42),
+            pinned: false,
+            pin_path: None,
+            btf_fd: None,
+        }
+    }
+
+    fn sys_error(value: i32) -> SysResult {
+        Err((-1, io::Error::from_raw_os_error(value)))
+    }
+
+    fn devmap_value(attr: &bpf_attr) -> bpf_devmap_val {
+        unsafe { *(attr.__bindgen_anon_2.__bindgen_anon_1.value as *const bpf_devmap_val) }
+    }
+
+    #[test]
+    fn test_wrong_value_size() {
+        let map = new_map(2);
+        assert!(matches!(
+            DevMap::new(&map),
+            Err(MapError::InvalidValueSize {
+                size: 2,
+                expected: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn test_try_from_wrong_map() {
+        let map = Map::Array(new_map(4));
+        assert!(matches!(
+            DevMap::try_from(&map),
+            Err(MapError::InvalidMapType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_set_out_of_bounds() {
+        let mut map = new_map(4);
+        let mut devmap = DevMap::new(&mut map).unwrap();
+
+        assert!(matches!(
+            devmap.set(16, 1, None, 0),
+            Err(XdpMapError::MapError(MapError::OutOfBounds {
+                index: 16,
+                max_entries: 16
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_set_program_not_supported() {
+        override_syscall(|_| sys_error(EFAULT));
+
+        let mut map = new_map(4);
+        let mut devmap = DevMap::new(&mut map).unwrap();
+
+        assert!(matches!(
+            devmap.set(0, 1, Some(ProgramFd(7)), 0),
+            Err(XdpMapError::ChainedProgramNotSupported)
+        ));
+    }
+
+    #[test]
+    // Syscall overrides are performing integer-to-pointer conversions, which
+    // should be done with `ptr::from_exposed_addr` in Rust nightly, but we have
+    // to support stable as well.
+    #[cfg_attr(miri, ignore)]
+    fn test_set_devmap_val() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_UPDATE_ELEM,
+                attr,
+            } => {
+                let value = devmap_value(attr);
+                assert_eq!(value.ifindex, 1);
+                assert_eq!(unsafe { value.bpf_prog.fd }, -1);
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let mut map = new_map(8);
+        let mut devmap = DevMap::new(&mut map).unwrap();
+
+        assert!(devmap.set(0, 1, None, 0).is_ok());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_get_devmap_val() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_MAP_LOOKUP_ELEM,
+                attr,
+            } => {
+                let value =
+                    unsafe { attr.__bindgen_anon_2.__bindgen_anon_1.value } as *mut bpf_devmap_val;
+                unsafe {
+                    (*value).ifindex = 3;
+                    (*value).bpf_prog.id = 9;
+                }
+                Ok(1)
+            }
+            _ => sys_error(EFAULT),
+        });
+
+        let map = new_map(8);
+        let devmap = DevMap::new(&map).unwrap();
+
+        assert_eq!(
+            devmap.get(0, 0).unwrap(),
+            DevMapValue {
+                if_index: 3,
+                prog_id: Some(9),
+            }
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! An hashmap of network devices.
+
+use std::convert::{AsMut, AsRef};
+
+use crate::{
+    maps::{
+        xdp::{check_devmap_kv_size, devmap_lookup, devmap_update, DevMapValue, XdpMapError},
+        MapData, MapError, MapKeys,
+    },
+    programs::ProgramFd,
+    sys::bpf_map_delete_elem,
+};
+
+/// An hashmap of network devices.
+///
+/// XDP programs can use this map to redirect packets to other network devices by calling
+/// `bpf_redirect_map(dev_map_hash, key, flags)`. Unlike [`DevMap`](super::DevMap), the keys
+/// don't need to be contiguous, which makes it convenient to key the map by interface index.
+///
+/// The map values can either be plain interface indices, or `bpf_devmap_val` structs which also
+/// carry a second-level XDP program that is run on the redirected packets before they are
+/// transmitted. Programs used this way must be declared with `#[xdp(map = "devmap")]`.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.4, or 5.8 to chain programs.
+///
+/// # Examples
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::maps::xdp::DevMapHash;
+///
+/// let mut devmap = DevMapHash::try_from(bpf.map_mut("IFACES").unwrap())?;
+/// let flags = 0;
+/// let ifindex = 32u32;
+/// devmap.insert(ifindex, ifindex, None, flags)?;
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_DEVMAP_HASH")]
+pub struct DevMapHash<T> {
+    pub(crate) inner: T,
+}
+
+impl<T: AsRef<MapData>> DevMapHash<T> {
+    pub(crate) fn new(map: T) -> Result<DevMapHash<T>, MapError> {
+        let data = map.as_ref();
+        check_devmap_kv_size(data)?;
+
+        let _fd = data.fd_or_err()?;
+
+        Ok(DevMapHash { inner: map })
+    }
+
+    /// Returns the target interface index and chained program id for a given key.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::KeyNotFound`] if the key does not exist, [`MapError::SyscallError`]
+    /// if `bpf_map_lookup_elem` fails.
+    pub fn get(&self, key: u32, flags: u64) -> Result<DevMapValue, MapError> {
+        devmap_lookup(self.inner.as_ref(), &key, flags)
+    }
+
+    /// An iterator over the elements of the devmap in arbitrary order. The iterator item type is
+    /// `Result<(u32, DevMapValue), MapError>`.
+    pub fn iter(&self) -> impl Iterator<Item = Result<(u32, DevMapValue), MapError>> + '_ {
+        self.keys().filter_map(move |key| {
+            let key = match key {
+                Ok(key) => key,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.get(key, 0) {
+                Ok(value) => Some(Ok((key, value))),
+                // the entry was removed while iterating
+                Err(MapError::KeyNotFound) => None,
+                Err(e) => Some(Err(e)),
+            }
+        })
+    }
+
+    /// An iterator visiting all keys in arbitrary order. The iterator element type is
+    /// `Result<u32, MapError>`.
+    pub fn keys(&self) -> MapKeys<'_, u32> {
+        MapKeys::new(self.inner.as_ref())
+    }
+}
+
+impl<T: AsMut<MapData>> DevMapHash<T> {
+    /// Inserts an interface index and optionally a chained program in the map.
+    ///
+    /// When redirecting using `key`, packets will be transmitted by the interface with
+    /// `target_if_index`. If `program` is set, it is run on the packets before they are
+    /// transmitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::SyscallError`] if `bpf_map_update_elem` fails,
+    /// [`XdpMapError::ChainedProgramNotSupported`] if a program is passed but the map values are
+    /// plain interface indices.
+    pub fn insert(
+        &mut self,
+        key: u32,
+        target_if_index: u32,
+        program: Option<ProgramFd>,
+        flags: u64,
+    ) -> Result<(), XdpMapError> {
+        devmap_update(self.inner.as_mut(), &key, target_if_index, program, flags)
+    }
+
+    /// Removes a value from the map.
+    pub fn remove(&mut self, key: u32) -> Result<(), MapError> {
+        let fd = self.inner.as_mut().fd_or_err()?;
+        bpf_map_delete_elem(fd, &key)
+            .map(|_| ())
+            .map_err(|(_, io_error)| MapError::SyscallError {
+                call: "bpf_map_delete_elem".to_owned(),
+                io_error,
+            })
+    }
+}
@@ -0,0 +1,185 @@
+//! XDP maps.
+mod cpu_map;
+mod dev_map;
+mod dev_map_hash;
+mod xsk_map;
+
+pub use cpu_map::{CpuMap, CpuMapValue};
+pub use dev_map::DevMap;
+pub use dev_map_hash::DevMapHash;
+pub use xsk_map::XskMap;
+
+use std::{
+    mem,
+    os::unix::io::{AsRawFd, RawFd},
+};
+
+use thiserror::Error;
+
+use crate::{
+    generated::{
+        bpf_cpumap_val, bpf_cpumap_val__bindgen_ty_1, bpf_devmap_val, bpf_devmap_val__bindgen_ty_1,
+    },
+    maps::{MapData, MapError},
+    programs::ProgramFd,
+    sys::{bpf_map_lookup_elem, bpf_map_update_elem},
+    Pod,
+};
+
+unsafe impl Pod for bpf_devmap_val {}
+unsafe impl Pod for bpf_cpumap_val {}
+
+// Device and CPU maps can either store a bare `u32` (an interface index or a queue size), or a
+// struct that also carries the second-level XDP program to run on redirected packets.
+trait ChainedValue: Pod {
+    fn new(value: u32, prog_fd: RawFd) -> Self;
+
+    fn value(&self) -> u32;
+
+    // the kernel returns the id of the chained program, 0 meaning no program
+    fn prog_id(&self) -> u32;
+}
+
+impl ChainedValue for bpf_devmap_val {
+    fn new(value: u32, prog_fd: RawFd) -> Self {
+        bpf_devmap_val {
+            ifindex: value,
+            bpf_prog: bpf_devmap_val__bindgen_ty_1 { fd: prog_fd },
+        }
+    }
+
+    fn value(&self) -> u32 {
+        self.ifindex
+    }
+
+    fn prog_id(&self) -> u32 {
+        unsafe { self.bpf_prog.id }
+    }
+}
+
+impl ChainedValue for bpf_cpumap_val {
+    fn new(value: u32, prog_fd: RawFd) -> Self {
+        bpf_cpumap_val {
+            qsize: value,
+            bpf_prog: bpf_cpumap_val__bindgen_ty_1 { fd: prog_fd },
+        }
+    }
+
+    fn value(&self) -> u32 {
+        self.qsize
+    }
+
+    fn prog_id(&self) -> u32 {
+        unsafe { self.bpf_prog.id }
+    }
+}
+
+/// Errors occuring from working with XDP maps.
+#[derive(Error, Debug)]
+pub enum XdpMapError {
+    /// Chained programs are not supported.
+    #[error("the map values do not support chained programs")]
+    ChainedProgramNotSupported,
+
+    /// Map operation failed.
+    #[error(transparent)]
+    MapError(#[from] MapError),
+}
+
+/// The value of a [`DevMap`] or [`DevMapHash`] entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DevMapValue {
+    /// Target interface index to redirect to.
+    pub if_index: u32,
+    /// Id of the chained XDP program, if any.
+    pub prog_id: Option<u32>,
+}
+
+fn check_kv_size<V: ChainedValue>(map: &MapData) -> Result<(), MapError> {
+    let size = mem::size_of::<u32>();
+    let expected = map.obj.key_size() as usize;
+    if size != expected {
+        return Err(MapError::InvalidKeySize { size, expected });
+    }
+    let size = map.obj.value_size() as usize;
+    if size != mem::size_of::<u32>() && size != mem::size_of::<V>() {
+        return Err(MapError::InvalidValueSize {
+            size,
+            expected: mem::size_of::<V>(),
+        });
+    }
+    Ok(())
+}
+
+fn supports_chained_program<V: ChainedValue>(map: &MapData) -> bool {
+    map.obj.value_size() as usize == mem::size_of::<V>()
+}
+
+// Returns the `u32` value stored at `key`, and the id of the chained program if any.
+fn lookup<K: Pod, V: ChainedValue>(
+    map: &MapData,
+    key: &K,
+    flags: u64,
+) -> Result<(u32, Option<u32>), MapError> {
+    let fd = map.fd_or_err()?;
+    let value = if supports_chained_program::<V>(map) {
+        bpf_map_lookup_elem::<_, V>(fd, key, flags).map(|value| {
+            value.map(|value| match value.prog_id() {
+                0 => (value.value(), None),
+                id => (value.value(), Some(id)),
+            })
+        })
+    } else {
+        bpf_map_lookup_elem::<_, u32>(fd, key, flags).map(|value| value.map(|value| (value, None)))
+    };
+    value
+        .map_err(|(_, io_error)| MapError::SyscallError {
+            call: "bpf_map_lookup_elem".to_owned(),
+            io_error,
+        })?
+        .ok_or(MapError::KeyNotFound)
+}
+
+fn update<K: Pod, V: ChainedValue>(
+    map: &MapData,
+    key: &K,
+    value: u32,
+    program: Option<ProgramFd>,
+    flags: u64,
+) -> Result<(), XdpMapError> {
+    let fd = map.fd_or_err()?;
+    let ret = if supports_chained_program::<V>(map) {
+        // -1 tells the kernel not to chain a program
+        let value = V::new(value, program.map(|fd| fd.as_raw_fd()).unwrap_or(-1));
+        bpf_map_update_elem(fd, Some(key), &value, flags)
+    } else {
+        if program.is_some() {
+            return Err(XdpMapError::ChainedProgramNotSupported);
+        }
+        bpf_map_update_elem(fd, Some(key), &value, flags)
+    };
+    ret.map_err(|(_, io_error)| MapError::SyscallError {
+        call: "bpf_map_update_elem".to_owned(),
+        io_error,
+    })?;
+    Ok(())
+}
+
+fn check_devmap_kv_size(map: &MapData) -> Result<(), MapError> {
+    check_kv_size::<bpf_devmap_val>(map)
+}
+
+fn devmap_lookup<K: Pod>(map: &MapData, key: &K, flags: u64) -> Result<DevMapValue, MapError> {
+    lookup::<_, bpf_devmap_val>(map, key, flags)
+        .map(|(if_index, prog_id)| DevMapValue { if_index, prog_id })
+}
+
+fn devmap_update<K: Pod>(
+    map: &MapData,
+    key: &K,
+    if_index: u32,
+    program: Option<ProgramFd>,
+    flags: u64,
+) -> Result<(), XdpMapError> {
+    update::<_, bpf_devmap_val>(map, key, if_index, program, flags)
+}
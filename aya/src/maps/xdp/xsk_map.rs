@@ -0,0 +1,98 @@
+//! An array of AF_XDP sockets.
+
+use std::{
+    convert::{AsMut, AsRef},
+    os::unix::io::{AsRawFd, RawFd},
+};
+
+use crate::{
+    maps::{check_bounds, check_kv_size, MapData, MapError},
+    sys::{bpf_map_delete_elem, bpf_map_update_elem},
+};
+
+/// An array of AF_XDP sockets.
+///
+/// XDP programs can use this map to redirect packets to a target AF_XDP socket using the
+/// `bpf_redirect_map(xsk_map, queue_id, flags)` helper. Sockets are usually stored at the index of
+/// the receive queue they are bound to, see [`XskSocket`](crate::af_xdp::XskSocket).
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.18.
+///
+/// # Examples
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// # let socket_fd = 1;
+/// use aya::maps::XskMap;
+///
+/// let mut xskmap = XskMap::try_from(bpf.map_mut("SOCKETS").unwrap())?;
+/// // socket_fd is the RawFd of an AF_XDP socket
+/// xskmap.set(0, socket_fd, 0)?;
+/// # Ok::<(), aya::BpfError>(())
+/// ```
+#[doc(alias = "BPF_MAP_TYPE_XSKMAP")]
+pub struct XskMap<T> {
+    pub(crate) inner: T,
+}
+
+impl<T: AsRef<MapData>> XskMap<T> {
+    pub(crate) fn new(map: T) -> Result<XskMap<T>, MapError> {
+        let data = map.as_ref();
+        check_kv_size::<u32, RawFd>(data)?;
+
+        let _fd = data.fd_or_err()?;
+
+        Ok(XskMap { inner: map })
+    }
+
+    /// Returns the number of elements in the array.
+    ///
+    /// This corresponds to the value of `bpf_map_def::max_entries` on the eBPF side.
+    pub fn len(&self) -> u32 {
+        self.inner.as_ref().obj.max_entries()
+    }
+}
+
+impl<T: AsMut<MapData>> XskMap<T> {
+    /// Sets the AF_XDP socket at the given index.
+    ///
+    /// When redirecting a packet using `index`, the packet will be received by `socket`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds, [`MapError::SyscallError`]
+    /// if `bpf_map_update_elem` fails.
+    pub fn set<S: AsRawFd>(&mut self, index: u32, socket: S, flags: u64) -> Result<(), MapError> {
+        let data = self.inner.as_mut();
+        check_bounds(data, index)?;
+        let fd = data.fd_or_err()?;
+        bpf_map_update_elem(fd, Some(&index), &socket.as_raw_fd(), flags).map_err(
+            |(_, io_error)| MapError::SyscallError {
+                call: "bpf_map_update_elem".to_owned(),
+                io_error,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Un-sets the AF_XDP socket at the given index.
+    ///
+    /// Packets redirected using `index` will be dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MapError::OutOfBounds`] if `index` is out of bounds, [`MapError::SyscallError`]
+    /// if `bpf_map_delete_elem` fails.
+    pub fn unset(&mut self, index: u32) -> Result<(), MapError> {
+        let data = self.inner.as_mut();
+        check_bounds(data, index)?;
+        let fd = data.fd_or_err()?;
+        bpf_map_delete_elem(fd, &index)
+            .map(|_| ())
+            .map_err(|(_, io_error)| MapError::SyscallError {
+                call: "bpf_map_delete_elem".to_owned(),
+                io_error,
+            })
+    }
+}
@@ -17,6 +17,13 @@ pub enum PinError {
         /// Object name.
         name: String,
     },
+    /// The object is shared, e.g. with the open buffers of a perf event array, and can't be
+    /// pinned.
+    #[error("the BPF object `{name}` is in use")]
+    InUse {
+        /// Object name.
+        name: String,
+    },
     /// The path for the BPF object is not valid.
     #[error("invalid pin path `{error}`")]
     InvalidPinPath {
@@ -10,8 +10,10 @@ use crate::{
         bpf_attach_type::{BPF_CGROUP_INET_EGRESS, BPF_CGROUP_INET_INGRESS},
         bpf_prog_type::BPF_PROG_TYPE_CGROUP_SKB,
     },
+    maps::CgroupStorageKey,
     programs::{
-        define_link_wrapper, load_program, FdLink, Link, ProgAttachLink, ProgramData, ProgramError,
+        define_link_wrapper, load_program, utils::cgroup_storage_key, FdLink, Link, ProgAttachLink,
+        ProgramData, ProgramError,
     },
     sys::{bpf_link_create, bpf_prog_attach, kernel_version},
 };
@@ -125,6 +127,22 @@ impl CgroupSkb {
         }
     }
 
+    /// Returns the key of the cgroup storage of a program attached to the given cgroup.
+    ///
+    /// The key can be used with [`CgroupStorage`](crate::maps::CgroupStorage) and
+    /// [`PerCpuCgroupStorage`](crate::maps::PerCpuCgroupStorage) to access the value created
+    /// by [CgroupSkb::attach].
+    pub fn storage_key<T: AsRawFd>(
+        cgroup: T,
+        attach_type: CgroupSkbAttachType,
+    ) -> Result<CgroupStorageKey, ProgramError> {
+        let attach_type = match attach_type {
+            CgroupSkbAttachType::Ingress => BPF_CGROUP_INET_INGRESS,
+            CgroupSkbAttachType::Egress => BPF_CGROUP_INET_EGRESS,
+        };
+        cgroup_storage_key(cgroup.as_raw_fd(), attach_type)
+    }
+
     /// Takes ownership of the link referenced by the provided link_id.
     ///
     /// The link will be detached on `Drop` and the caller is now responsible
@@ -9,8 +9,10 @@ use std::{
 
 use crate::{
     generated::bpf_prog_type::BPF_PROG_TYPE_CGROUP_SOCK_ADDR,
+    maps::CgroupStorageKey,
     programs::{
-        define_link_wrapper, load_program, FdLink, Link, ProgAttachLink, ProgramData, ProgramError,
+        define_link_wrapper, load_program, utils::cgroup_storage_key, FdLink, Link, ProgAttachLink,
+        ProgramData, ProgramError,
     },
     sys::{bpf_link_create, bpf_prog_attach, kernel_version},
 };
@@ -103,6 +105,18 @@ impl CgroupSockAddr {
         }
     }
 
+    /// Returns the key of the cgroup storage of a program attached to the given cgroup.
+    ///
+    /// The key can be used with [`CgroupStorage`](crate::maps::CgroupStorage) and
+    /// [`PerCpuCgroupStorage`](crate::maps::PerCpuCgroupStorage) to access the value created
+    /// by [CgroupSockAddr::attach].
+    pub fn storage_key<T: AsRawFd>(
+        cgroup: T,
+        attach_type: CgroupSockAddrAttachType,
+    ) -> Result<CgroupStorageKey, ProgramError> {
+        cgroup_storage_key(cgroup.as_raw_fd(), attach_type.into())
+    }
+
     /// Takes ownership of the link referenced by the provided link_id.
     ///
     /// The link will be detached on `Drop` and the caller is now responsible
@@ -1,15 +1,29 @@
 //! Kernel space probes.
-use std::{io, path::Path};
+use std::{
+    collections::BTreeSet,
+    ffi::CString,
+    fs,
+    io::{self, BufRead, BufReader},
+    os::unix::io::RawFd,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
 use crate::{
-    generated::bpf_prog_type::BPF_PROG_TYPE_KPROBE,
+    generated::{
+        bpf_attach_type::BPF_TRACE_KPROBE_MULTI, bpf_prog_type::BPF_PROG_TYPE_KPROBE,
+        BPF_F_KPROBE_MULTI_RETURN,
+    },
     programs::{
-        define_link_wrapper, load_program,
+        define_link_wrapper,
+        links::{FdLink, LinkError},
+        load_program,
         perf_attach::{PerfLinkIdInner, PerfLinkInner},
         probe::{attach, ProbeKind},
+        utils::find_tracefs_path,
         ProgramData, ProgramError,
     },
+    sys::bpf_link_create_kprobe_multi,
 };
 
 /// A kernel probe.
@@ -20,9 +34,14 @@ use crate::{
 /// - `kprobe`: get attached to the *start* of the target functions
 /// - `kretprobe`: get attached to the *return address* of the target functions
 ///
+/// Programs declared in `kprobe.multi` or `kretprobe.multi` sections can be attached to many
+/// functions at once with [`KProbe::attach_multi`] or [`KProbe::attach_multi_glob`], which
+/// create a single link instead of one perf event per function.
+///
 /// # Minimum kernel version
 ///
-/// The minimum kernel version required to use this feature is 4.1.
+/// The minimum kernel version required to use this feature is 4.1, or 5.18 for the
+/// `kprobe.multi` programs.
 ///
 /// # Examples
 ///
@@ -40,11 +59,15 @@ use crate::{
 pub struct KProbe {
     pub(crate) data: ProgramData<KProbeLink>,
     pub(crate) kind: ProbeKind,
+    pub(crate) multi: bool,
 }
 
 impl KProbe {
     /// Loads the program inside the kernel.
     pub fn load(&mut self) -> Result<(), ProgramError> {
+        if self.multi {
+            self.data.expected_attach_type = Some(BPF_TRACE_KPROBE_MULTI);
+        }
         load_program(BPF_PROG_TYPE_KPROBE, &mut self.data)
     }
 
@@ -65,8 +88,102 @@ impl KProbe {
     /// target function.
     ///
     /// The returned value can be used to detach from the given function, see [KProbe::detach].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KProbeError::MultiProgram`] if the program is a `kprobe.multi` program, see
+    /// [`KProbe::attach_multi`].
     pub fn attach(&mut self, fn_name: &str, offset: u64) -> Result<KProbeLinkId, ProgramError> {
-        attach(&mut self.data, self.kind, fn_name, offset, None)
+        if self.multi {
+            return Err(KProbeError::MultiProgram.into());
+        }
+        attach(&mut self.data, self.kind, fn_name, offset, None, None)
+    }
+
+    /// Attaches the program to multiple kernel functions at once.
+    ///
+    /// The program must be declared in a `kprobe.multi` or `kretprobe.multi` section. All the
+    /// functions are attached to through a single `BPF_TRACE_KPROBE_MULTI` link, which the kernel
+    /// resolves against `/proc/kallsyms`.
+    ///
+    /// If `cookies` is set, it must contain one cookie per function: the program can then read
+    /// the cookie of the function being probed with `bpf_get_attach_cookie`.
+    ///
+    /// The returned value can be used to detach, see [KProbe::detach].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// use aya::programs::KProbe;
+    ///
+    /// let program: &mut KProbe = bpf.program_mut("trace_tcp").unwrap().try_into()?;
+    /// program.load()?;
+    /// program.attach_multi(&["tcp_v4_connect", "tcp_v6_connect"], Some(&[4, 6]))?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn attach_multi<S: AsRef<str>>(
+        &mut self,
+        symbols: &[S],
+        cookies: Option<&[u64]>,
+    ) -> Result<KProbeLinkId, ProgramError> {
+        if !self.multi {
+            return Err(KProbeError::NotMultiProgram.into());
+        }
+        if let Some(cookies) = cookies {
+            if cookies.len() != symbols.len() {
+                return Err(KProbeError::CookieCountMismatch {
+                    symbols: symbols.len(),
+                    cookies: cookies.len(),
+                }
+                .into());
+            }
+        }
+        let prog_fd = self.data.fd_or_err()?;
+
+        let symbols = symbols
+            .iter()
+            .map(|symbol| {
+                let symbol = symbol.as_ref();
+                CString::new(symbol).map_err(|_| KProbeError::InvalidSymbol {
+                    symbol: symbol.to_owned(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let flags = match self.kind {
+            ProbeKind::KRetProbe => BPF_F_KPROBE_MULTI_RETURN,
+            _ => 0,
+        };
+
+        let link_fd = bpf_link_create_kprobe_multi(prog_fd, &symbols, cookies, flags).map_err(
+            |(_, io_error)| ProgramError::SyscallError {
+                call: "bpf_link_create".to_owned(),
+                io_error,
+            },
+        )? as RawFd;
+
+        self.data
+            .links
+            .insert(KProbeLink::new(PerfLinkInner::FdLink(FdLink::new(link_fd))))
+    }
+
+    /// Attaches the program to all the kernel functions matching `pattern`.
+    ///
+    /// `pattern` can contain `*` and `?` wildcards, eg `tcp_*`. The matching functions are
+    /// resolved with [`matching_functions`], then attached to with [`KProbe::attach_multi`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`KProbeError::NoMatchingFunction`] if no function matches `pattern`.
+    pub fn attach_multi_glob(&mut self, pattern: &str) -> Result<KProbeLinkId, ProgramError> {
+        let symbols = matching_functions(pattern)?;
+        if symbols.is_empty() {
+            return Err(KProbeError::NoMatchingFunction {
+                pattern: pattern.to_owned(),
+            }
+            .into());
+        }
+        self.attach_multi(&symbols, None)
     }
 
     /// Detaches the program.
@@ -92,7 +209,11 @@ impl KProbe {
     /// the program being unloaded from the kernel if it is still pinned.
     pub fn from_pin<P: AsRef<Path>>(path: P, kind: ProbeKind) -> Result<Self, ProgramError> {
         let data = ProgramData::from_pinned_path(path)?;
-        Ok(Self { data, kind })
+        Ok(Self {
+            data,
+            kind,
+            multi: false,
+        })
     }
 }
 
@@ -105,6 +226,18 @@ define_link_wrapper!(
     PerfLinkIdInner
 );
 
+impl TryFrom<KProbeLink> for FdLink {
+    type Error = LinkError;
+
+    fn try_from(value: KProbeLink) -> Result<Self, Self::Error> {
+        if let PerfLinkInner::FdLink(fd) = value.into_inner() {
+            Ok(fd)
+        } else {
+            Err(LinkError::InvalidLink)
+        }
+    }
+}
+
 /// The type returned when attaching a [`KProbe`] fails.
 #[derive(Debug, Error)]
 pub enum KProbeError {
@@ -117,4 +250,165 @@ pub enum KProbeError {
         #[source]
         io_error: io::Error,
     },
+
+    /// The program is a `kprobe.multi` program, which must be attached with
+    /// [`KProbe::attach_multi`].
+    #[error("kprobe.multi programs must be attached with `attach_multi`")]
+    MultiProgram,
+
+    /// The program isn't a `kprobe.multi` program, and can't be attached to multiple functions.
+    #[error("the program is not a kprobe.multi program")]
+    NotMultiProgram,
+
+    /// The number of cookies doesn't match the number of functions.
+    #[error("{cookies} cookies given for {symbols} functions")]
+    CookieCountMismatch {
+        /// The number of functions
+        symbols: usize,
+        /// The number of cookies
+        cookies: usize,
+    },
+
+    /// The function name contains a nul byte.
+    #[error("invalid function name `{symbol}`")]
+    InvalidSymbol {
+        /// The function name
+        symbol: String,
+    },
+
+    /// No kernel function matches the pattern.
+    #[error("no kernel function matches `{pattern}`")]
+    NoMatchingFunction {
+        /// The pattern
+        pattern: String,
+    },
+}
+
+/// Returns the kernel functions matching `pattern` that can be probed.
+///
+/// `pattern` can contain `*` and `?` wildcards. The functions are read from the
+/// `available_filter_functions` file of tracefs, or from `/proc/kallsyms` if tracefs isn't
+/// available. The returned names are sorted and deduplicated, and can be passed to
+/// [`KProbe::attach_multi`] along with cookies.
+pub fn matching_functions(pattern: &str) -> Result<Vec<String>, KProbeError> {
+    let (path, function_name): (PathBuf, fn(&str) -> Option<&str>) = match find_tracefs_path() {
+        Ok(tracefs) => (
+            tracefs.join("available_filter_functions"),
+            filter_function_name,
+        ),
+        Err(_) => (PathBuf::from("/proc/kallsyms"), kallsyms_function_name),
+    };
+    let names = fs::File::open(&path)
+        .and_then(|file| read_function_names(BufReader::new(file), function_name))
+        .map_err(|io_error| KProbeError::FileError {
+            filename: path.display().to_string(),
+            io_error,
+        })?;
+
+    Ok(names
+        .into_iter()
+        .filter(|name| glob_match(pattern, name))
+        .collect())
+}
+
+// available_filter_functions lines are `name` or `name [module]`
+fn filter_function_name(line: &str) -> Option<&str> {
+    line.split_whitespace().next()
+}
+
+// kallsyms lines are `address type name` or `address type name [module]`
+fn kallsyms_function_name(line: &str) -> Option<&str> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(_), Some("t" | "T"), name) => name,
+        _ => None,
+    }
+}
+
+fn read_function_names(
+    reader: impl BufRead,
+    function_name: fn(&str) -> Option<&str>,
+) -> Result<BTreeSet<String>, io::Error> {
+    let mut names = BTreeSet::new();
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(name) = function_name(&line) {
+            // ftrace reports functions whose address it can't resolve under this name
+            if !name.starts_with("__ftrace_invalid_address__") {
+                names.insert(name.to_owned());
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+fn glob_match(pattern: &str, s: &str) -> bool {
+    let (pattern, s) = (pattern.as_bytes(), s.as_bytes());
+    let (mut p, mut i) = (0, 0);
+    // position of the last `*` in the pattern, and of the input it was matched at
+    let mut backtrack = None;
+
+    while i < s.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    i = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("tcp_*", "tcp_v4_connect"));
+        assert!(glob_match("tcp_*", "tcp_"));
+        assert!(glob_match("*_connect", "tcp_v4_connect"));
+        assert!(glob_match("tcp_v?_*nect", "tcp_v6_connect"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("tcp_*", "udp_sendmsg"));
+        assert!(!glob_match("tcp_v?_connect", "tcp_v_connect"));
+        assert!(!glob_match("tcp", "tcp_v4_connect"));
+    }
+
+    #[test]
+    fn test_read_function_names() {
+        let data = b"tcp_v4_connect\ntcp_close\n__ftrace_invalid_address___64\nnf_hook [nf_tables]\ntcp_close\n";
+        let names = read_function_names(&data[..], filter_function_name).unwrap();
+        assert_eq!(
+            names.into_iter().collect::<Vec<_>>(),
+            vec!["nf_hook", "tcp_close", "tcp_v4_connect"]
+        );
+    }
+
+    #[test]
+    fn test_kallsyms_function_name() {
+        assert_eq!(
+            kallsyms_function_name("ffffffff81000000 T tcp_v4_connect"),
+            Some("tcp_v4_connect")
+        );
+        assert_eq!(
+            kallsyms_function_name("ffffffffc0a01000 t nf_hook\t[nf_tables]"),
+            Some("nf_hook")
+        );
+        assert_eq!(kallsyms_function_name("ffffffff82000000 D jiffies"), None);
+    }
 }
@@ -5,16 +5,20 @@ use thiserror::Error;
 use std::{
     collections::{hash_map::Entry, HashMap},
     ffi::CString,
-    io,
+    fmt, io,
     os::unix::prelude::RawFd,
     path::{Path, PathBuf},
+    ptr,
 };
 
 use crate::{
-    generated::bpf_attach_type,
+    generated::{bpf_attach_type, bpf_link_info, bpf_link_type},
     pin::PinError,
     programs::ProgramError,
-    sys::{bpf_get_object, bpf_pin_object, bpf_prog_detach},
+    sys::{
+        bpf_get_object, bpf_link_detach, bpf_link_get_fd_by_id, bpf_link_get_info_by_fd,
+        bpf_link_get_next_id, bpf_pin_object, bpf_prog_detach,
+    },
 };
 
 /// A Link.
@@ -134,6 +138,38 @@ impl FdLink {
         })?;
         Ok(PinnedLink::new(PathBuf::from(path.as_ref()), self))
     }
+
+    /// Opens the link with the given id.
+    ///
+    /// The ids of the links in the system can be listed with [`loaded_links`]. Dropping the
+    /// returned link only closes its file descriptor, use [`FdLink::force_detach`] to detach it.
+    pub fn from_id(id: u32) -> Result<FdLink, LinkError> {
+        let fd = bpf_link_get_fd_by_id(id).map_err(|(code, io_error)| LinkError::SyscallError {
+            call: "bpf_link_get_fd_by_id".to_string(),
+            code,
+            io_error,
+        })? as RawFd;
+        Ok(FdLink::new(fd))
+    }
+
+    /// Returns information about the link.
+    pub fn info(&self) -> Result<LinkInfo, LinkError> {
+        LinkInfo::new_from_fd(self.fd)
+    }
+
+    /// Detaches the link, even if other file descriptors or pins keep it alive.
+    ///
+    /// # Minimum kernel version
+    ///
+    /// The minimum kernel version required to use this feature is 5.9.
+    pub fn force_detach(self) -> Result<(), LinkError> {
+        bpf_link_detach(self.fd).map_err(|(code, io_error)| LinkError::SyscallError {
+            call: "bpf_link_detach".to_string(),
+            code,
+            io_error,
+        })?;
+        Ok(())
+    }
 }
 
 impl Link for FdLink {
@@ -308,6 +344,265 @@ macro_rules! define_link_wrapper {
 
 pub(crate) use define_link_wrapper;
 
+// Members of the `bpf_link_info` union that are missing from the generated bindings.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct KProbeMultiLinkInfo {
+    addrs: u64,
+    count: u32,
+    flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IfindexLinkInfo {
+    ifindex: u32,
+    attach_type: u32,
+}
+
+const BPF_LINK_TYPE_TCX: u32 = 11;
+const BPF_LINK_TYPE_NETKIT: u32 = 13;
+
+/// The type specific information of a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// A raw tracepoint link.
+    RawTracePoint,
+    /// A tracing link, used by fentry, fexit, lsm and extension programs.
+    Tracing {
+        /// The attach type of the program.
+        attach_type: u32,
+        /// The id of the BTF object or program the target belongs to.
+        target_obj_id: u32,
+        /// The BTF id of the target.
+        target_btf_id: u32,
+    },
+    /// A cgroup link.
+    Cgroup {
+        /// The id of the cgroup.
+        cgroup_id: u64,
+        /// The attach type of the program.
+        attach_type: u32,
+    },
+    /// An iterator link.
+    Iter,
+    /// A network namespace link.
+    NetNs {
+        /// The inode number of the network namespace.
+        netns_ino: u32,
+        /// The attach type of the program.
+        attach_type: u32,
+    },
+    /// An XDP link.
+    Xdp {
+        /// The index of the network interface.
+        ifindex: u32,
+    },
+    /// A perf event link, used by kprobes, uprobes and tracepoints.
+    PerfEvent,
+    /// A multi kprobe link.
+    KProbeMulti {
+        /// The number of attached functions.
+        count: u32,
+        /// The flags of the link, `BPF_F_KPROBE_MULTI_RETURN` for kretprobes.
+        flags: u32,
+    },
+    /// A struct_ops link.
+    StructOps {
+        /// The id of the struct_ops map.
+        map_id: u32,
+    },
+    /// A TCX link.
+    Tcx {
+        /// The index of the network interface.
+        ifindex: u32,
+        /// The attach type of the program, ingress or egress.
+        attach_type: u32,
+    },
+    /// A netkit link.
+    Netkit {
+        /// The index of the network interface.
+        ifindex: u32,
+        /// The attach type of the program, primary or peer.
+        attach_type: u32,
+    },
+    /// A link of a type that aya can't decode.
+    Other {
+        /// The low level link type.
+        link_type: u32,
+    },
+}
+
+/// Provides information about a link, like its id, type and target.
+pub struct LinkInfo(bpf_link_info);
+
+impl LinkInfo {
+    fn new_from_fd(fd: RawFd) -> Result<LinkInfo, LinkError> {
+        let info = bpf_link_get_info_by_fd(fd).map_err(|io_error| LinkError::SyscallError {
+            call: "bpf_link_get_info_by_fd".to_string(),
+            code: 0,
+            io_error,
+        })?;
+        Ok(LinkInfo(info))
+    }
+
+    /// Returns the information of the link with the given id.
+    pub fn from_id(id: u32) -> Result<LinkInfo, LinkError> {
+        FdLink::from_id(id)?.info()
+    }
+
+    /// The link id. Each link has a unique id.
+    pub fn id(&self) -> u32 {
+        self.0.id
+    }
+
+    /// The id of the program attached by the link.
+    pub fn program_id(&self) -> u32 {
+        self.0.prog_id
+    }
+
+    /// The low level link type, one of the `BPF_LINK_TYPE_*` constants.
+    pub fn link_type(&self) -> u32 {
+        self.0.type_
+    }
+
+    /// Decodes the type specific information of the link.
+    pub fn kind(&self) -> LinkKind {
+        use bpf_link_type::*;
+
+        let link_type = self.0.type_;
+        // Safety: the kernel fills the member of the union matching the link type
+        unsafe {
+            let info = &self.0.__bindgen_anon_1;
+            match link_type {
+                x if x == BPF_LINK_TYPE_RAW_TRACEPOINT as u32 => LinkKind::RawTracePoint,
+                x if x == BPF_LINK_TYPE_TRACING as u32 => LinkKind::Tracing {
+                    attach_type: info.tracing.attach_type,
+                    target_obj_id: info.tracing.target_obj_id,
+                    target_btf_id: info.tracing.target_btf_id,
+                },
+                x if x == BPF_LINK_TYPE_CGROUP as u32 => LinkKind::Cgroup {
+                    cgroup_id: info.cgroup.cgroup_id,
+                    attach_type: info.cgroup.attach_type,
+                },
+                x if x == BPF_LINK_TYPE_ITER as u32 => LinkKind::Iter,
+                x if x == BPF_LINK_TYPE_NETNS as u32 => LinkKind::NetNs {
+                    netns_ino: info.netns.netns_ino,
+                    attach_type: info.netns.attach_type,
+                },
+                x if x == BPF_LINK_TYPE_XDP as u32 => LinkKind::Xdp {
+                    ifindex: info.xdp.ifindex,
+                },
+                x if x == BPF_LINK_TYPE_PERF_EVENT as u32 => LinkKind::PerfEvent,
+                x if x == BPF_LINK_TYPE_KPROBE_MULTI as u32 => {
+                    let kprobe_multi = self.read_union::<KProbeMultiLinkInfo>();
+                    LinkKind::KProbeMulti {
+                        count: kprobe_multi.count,
+                        flags: kprobe_multi.flags,
+                    }
+                }
+                x if x == BPF_LINK_TYPE_STRUCT_OPS as u32 => LinkKind::StructOps {
+                    map_id: self.read_union::<u32>(),
+                },
+                BPF_LINK_TYPE_TCX => {
+                    let tcx = self.read_union::<IfindexLinkInfo>();
+                    LinkKind::Tcx {
+                        ifindex: tcx.ifindex,
+                        attach_type: tcx.attach_type,
+                    }
+                }
+                BPF_LINK_TYPE_NETKIT => {
+                    let netkit = self.read_union::<IfindexLinkInfo>();
+                    LinkKind::Netkit {
+                        ifindex: netkit.ifindex,
+                        attach_type: netkit.attach_type,
+                    }
+                }
+                link_type => LinkKind::Other { link_type },
+            }
+        }
+    }
+
+    // Safety: T must fit in the union and be valid for any bit pattern
+    unsafe fn read_union<T: Copy>(&self) -> T {
+        ptr::read_unaligned(&self.0.__bindgen_anon_1 as *const _ as *const T)
+    }
+}
+
+impl fmt::Debug for LinkInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinkInfo")
+            .field("id", &self.id())
+            .field("program_id", &self.program_id())
+            .field("kind", &self.kind())
+            .finish()
+    }
+}
+
+/// LinksIter is an Iterator over the links in the system.
+pub struct LinksIter {
+    current: u32,
+    error: bool,
+}
+
+impl Iterator for LinksIter {
+    type Item = Result<LinkInfo, LinkError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error {
+            return None;
+        }
+
+        match bpf_link_get_next_id(self.current) {
+            Ok(Some(next)) => {
+                self.current = next;
+                Some(LinkInfo::from_id(next))
+            }
+            Ok(None) => None,
+            Err((code, io_error)) => {
+                // If getting the next link failed, we have to yield None in our next
+                // iteration to avoid an infinite loop.
+                self.error = true;
+                Some(Err(LinkError::SyscallError {
+                    call: "bpf_link_get_next_id".to_string(),
+                    code,
+                    io_error,
+                }))
+            }
+        }
+    }
+}
+
+/// Returns an iterator over all the links in the system.
+///
+/// Unlike the links managed by [`crate::programs`], this includes links created by other
+/// processes and links kept alive by pins.
+///
+/// # Example
+/// ```
+/// # use aya::programs::links::loaded_links;
+///
+/// for l in loaded_links() {
+///     match l {
+///         Ok(link) => println!("{} {:?}", link.id(), link.kind()),
+///         Err(e) => println!("Error iterating links: {:?}", e),
+///     }
+/// }
+/// ```
+///
+/// # Errors
+///
+/// Returns [`LinkError::SyscallError`] if any of the syscalls required to either get
+/// next link id, get the link fd, or the [`LinkInfo`] fail. In cases where
+/// iteration can't be performed, for example the caller does not have the necessary privileges,
+/// a single item will be yielded containing the error that occurred.
+pub fn loaded_links() -> LinksIter {
+    LinksIter {
+        current: 0,
+        error: false,
+    }
+}
+
 #[derive(Error, Debug)]
 /// Errors from operations on links.
 pub enum LinkError {
@@ -331,9 +626,13 @@ pub enum LinkError {
 mod tests {
     use std::{cell::RefCell, env, fs::File, mem, os::unix::io::AsRawFd, rc::Rc};
 
-    use crate::{programs::ProgramError, sys::override_syscall};
+    use crate::{
+        generated::{bpf_link_info, bpf_link_type},
+        programs::ProgramError,
+        sys::override_syscall,
+    };
 
-    use super::{FdLink, Link, LinkMap};
+    use super::{FdLink, Link, LinkInfo, LinkKind, LinkMap, BPF_LINK_TYPE_TCX};
 
     #[derive(Debug, Hash, Eq, PartialEq)]
     struct TestLinkId(u8, u8);
@@ -484,4 +783,58 @@ mod tests {
         pinned_link.unpin().expect("unpin failed");
         assert!(!dir.join("f1-pin").exists());
     }
+
+    fn new_link_info(link_type: u32, data: &[u32]) -> LinkInfo {
+        let mut info = unsafe { mem::zeroed::<bpf_link_info>() };
+        info.type_ = link_type;
+        info.id = 1;
+        info.prog_id = 2;
+        let union = &mut info.__bindgen_anon_1 as *mut _ as *mut u32;
+        for (i, v) in data.iter().enumerate() {
+            unsafe { union.add(i).write(*v) };
+        }
+        LinkInfo(info)
+    }
+
+    #[test]
+    fn test_link_info_kind() {
+        let info = new_link_info(bpf_link_type::BPF_LINK_TYPE_XDP as u32, &[3]);
+        assert_eq!(info.id(), 1);
+        assert_eq!(info.program_id(), 2);
+        assert_eq!(info.kind(), LinkKind::Xdp { ifindex: 3 });
+
+        let info = new_link_info(bpf_link_type::BPF_LINK_TYPE_CGROUP as u32, &[42, 0, 1]);
+        assert_eq!(
+            info.kind(),
+            LinkKind::Cgroup {
+                cgroup_id: 42,
+                attach_type: 1
+            }
+        );
+
+        // addrs pointer, count, flags
+        let info = new_link_info(
+            bpf_link_type::BPF_LINK_TYPE_KPROBE_MULTI as u32,
+            &[0, 0, 12, 1],
+        );
+        assert_eq!(
+            info.kind(),
+            LinkKind::KProbeMulti {
+                count: 12,
+                flags: 1
+            }
+        );
+
+        let info = new_link_info(BPF_LINK_TYPE_TCX, &[3, 46]);
+        assert_eq!(
+            info.kind(),
+            LinkKind::Tcx {
+                ifindex: 3,
+                attach_type: 46
+            }
+        );
+
+        let info = new_link_info(100, &[]);
+        assert_eq!(info.kind(), LinkKind::Other { link_type: 100 });
+    }
 }
@@ -57,10 +57,13 @@ mod sk_msg;
 mod sk_skb;
 mod sock_ops;
 mod socket_filter;
+pub mod struct_ops;
+mod symbols;
 pub mod tc;
 pub mod tp_btf;
 pub mod trace_point;
 pub mod uprobe;
+pub mod usdt;
 mod utils;
 pub mod xdp;
 
@@ -96,16 +99,23 @@ pub use sk_msg::SkMsg;
 pub use sk_skb::{SkSkb, SkSkbKind};
 pub use sock_ops::SockOps;
 pub use socket_filter::{SocketFilter, SocketFilterError};
+pub use struct_ops::{StructOps, StructOpsError};
 pub use tc::{SchedClassifier, TcAttachType, TcError};
 pub use tp_btf::BtfTracePoint;
 pub use trace_point::{TracePoint, TracePointError};
-pub use uprobe::{UProbe, UProbeError};
-pub use xdp::{Xdp, XdpError, XdpFlags};
+pub use uprobe::{UProbe, UProbeAttachPoint, UProbeError};
+pub use usdt::{Usdt, UsdtError};
+pub use xdp::{Xdp, XdpAttachType, XdpError, XdpFlags};
 
 use crate::{
+    btf::BtfExt,
     generated::{bpf_attach_type, bpf_prog_info, bpf_prog_type},
     maps::MapError,
-    obj::{self, btf::BtfError, Function, KernelVersion},
+    obj::{
+        self,
+        btf::{Btf, BtfError},
+        Function, KernelVersion,
+    },
     pin::PinError,
     sys::{
         bpf_btf_get_fd_by_id, bpf_get_object, bpf_load_program, bpf_pin_object,
@@ -177,6 +187,10 @@ pub enum ProgramError {
     #[error(transparent)]
     UProbeError(#[from] UProbeError),
 
+    /// An error occurred while working with an [`Usdt`] program.
+    #[error(transparent)]
+    UsdtError(#[from] UsdtError),
+
     /// An error occurred while working with a [`TracePoint`].
     #[error(transparent)]
     TracePointError(#[from] TracePointError),
@@ -197,6 +211,10 @@ pub enum ProgramError {
     #[error(transparent)]
     ExtensionError(#[from] ExtensionError),
 
+    /// An error occurred while working with a [`StructOps`] program.
+    #[error(transparent)]
+    StructOpsError(#[from] StructOpsError),
+
     /// An error occurred while working with BTF.
     #[error(transparent)]
     Btf(#[from] BtfError),
@@ -215,7 +233,7 @@ pub enum ProgramError {
 
 /// A [`Program`] file descriptor.
 #[derive(Copy, Clone)]
-pub struct ProgramFd(RawFd);
+pub struct ProgramFd(pub(crate) RawFd);
 
 impl AsRawFd for ProgramFd {
     fn as_raw_fd(&self) -> RawFd {
@@ -274,6 +292,10 @@ pub enum Program {
     CgroupSock(CgroupSock),
     /// A [`CgroupDevice`] program
     CgroupDevice(CgroupDevice),
+    /// A [`StructOps`] program
+    StructOps(StructOps),
+    /// A [`Usdt`] program
+    Usdt(Usdt),
 }
 
 impl Program {
@@ -305,6 +327,8 @@ impl Program {
             Program::SkLookup(_) => BPF_PROG_TYPE_SK_LOOKUP,
             Program::CgroupSock(_) => BPF_PROG_TYPE_CGROUP_SOCK,
             Program::CgroupDevice(_) => BPF_PROG_TYPE_CGROUP_DEVICE,
+            Program::StructOps(_) => BPF_PROG_TYPE_STRUCT_OPS,
+            Program::Usdt(_) => BPF_PROG_TYPE_KPROBE,
         }
     }
 
@@ -335,6 +359,8 @@ impl Program {
             Program::SkLookup(p) => p.pin(path),
             Program::CgroupSock(p) => p.pin(path),
             Program::CgroupDevice(p) => p.pin(path),
+            Program::StructOps(p) => p.pin(path),
+            Program::Usdt(p) => p.pin(path),
         }
     }
 
@@ -365,6 +391,8 @@ impl Program {
             Program::SkLookup(mut p) => p.unload(),
             Program::CgroupSock(mut p) => p.unload(),
             Program::CgroupDevice(mut p) => p.unload(),
+            Program::StructOps(mut p) => p.unload(),
+            Program::Usdt(mut p) => p.unload(),
         }
     }
 
@@ -398,6 +426,8 @@ impl Program {
             Program::SkLookup(p) => p.fd(),
             Program::CgroupSock(p) => p.fd(),
             Program::CgroupDevice(p) => p.fd(),
+            Program::StructOps(p) => p.fd(),
+            Program::Usdt(p) => p.fd(),
         }
     }
 }
@@ -547,6 +577,26 @@ fn pin_program<T: Link, P: AsRef<Path>>(
 fn load_program<T: Link>(
     prog_type: bpf_prog_type,
     data: &mut ProgramData<T>,
+) -> Result<(), ProgramError> {
+    let expected_attach_type = data.expected_attach_type.map(|t| t as u32);
+    load_program_inner(prog_type, data, expected_attach_type)
+}
+
+// Some program types use the expected attach type for other purposes than attach types, eg
+// struct_ops programs pass the index of the struct member they implement, or need attach types
+// missing from the generated bindings.
+fn load_program_with_attach_type<T: Link>(
+    prog_type: bpf_prog_type,
+    data: &mut ProgramData<T>,
+    expected_attach_type: u32,
+) -> Result<(), ProgramError> {
+    load_program_inner(prog_type, data, Some(expected_attach_type))
+}
+
+fn load_program_inner<T: Link>(
+    prog_type: bpf_prog_type,
+    data: &mut ProgramData<T>,
+    expected_attach_type: Option<u32>,
 ) -> Result<(), ProgramError> {
     let ProgramData { obj, fd, .. } = data;
     if fd.is_some() {
@@ -600,7 +650,7 @@ fn load_program<T: Link>(
         insns: instructions,
         license,
         kernel_version: target_kernel_version,
-        expected_attach_type: data.expected_attach_type,
+        expected_attach_type,
         prog_btf_fd: data.btf_fd,
         attach_btf_obj_fd: data.attach_btf_obj_fd,
         attach_btf_id: data.attach_btf_id,
@@ -721,6 +771,8 @@ impl_program_unload!(
     SockOps,
     CgroupSock,
     CgroupDevice,
+    StructOps,
+    Usdt,
 );
 
 macro_rules! impl_fd {
@@ -761,6 +813,8 @@ impl_fd!(
     SockOps,
     CgroupSock,
     CgroupDevice,
+    StructOps,
+    Usdt,
 );
 
 macro_rules! impl_program_pin{
@@ -815,6 +869,8 @@ impl_program_pin!(
     SockOps,
     CgroupSock,
     CgroupDevice,
+    StructOps,
+    Usdt,
 );
 
 macro_rules! impl_from_pin {
@@ -840,7 +896,6 @@ macro_rules! impl_from_pin {
 impl_from_pin!(
     TracePoint,
     SocketFilter,
-    Xdp,
     SkMsg,
     CgroupSysctl,
     LircMode2,
@@ -909,6 +964,8 @@ impl_try_from_program!(
     SkLookup,
     CgroupSock,
     CgroupDevice,
+    StructOps,
+    Usdt,
 );
 
 /// Provides information about a loaded program, like name, id and statistics
@@ -941,6 +998,21 @@ impl ProgramInfo {
         self.0.id
     }
 
+    /// The id of the BTF object of the program, or `None` if it was loaded without BTF.
+    pub fn btf_id(&self) -> Option<u32> {
+        (self.0.btf_id != 0).then_some(self.0.btf_id)
+    }
+
+    /// Fetches the BTF of the program, or returns `None` if it was loaded without BTF.
+    ///
+    /// The BTF describes the functions of the program, e.g. to attach
+    /// [`FEntry`] programs to them.
+    pub fn btf(&self) -> Result<Option<Btf>, ProgramError> {
+        self.btf_id()
+            .map(|id| Btf::from_id(id).map_err(ProgramError::Btf))
+            .transpose()
+    }
+
     /// Returns the fd associated with the program.
     ///
     /// The returned fd must be closed when no longer needed.
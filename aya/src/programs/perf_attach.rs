@@ -5,7 +5,7 @@ use std::os::unix::io::RawFd;
 use crate::{
     generated::bpf_attach_type::BPF_PERF_EVENT,
     programs::{probe::detach_debug_fs, FdLink, Link, ProbeKind, ProgramError},
-    sys::{bpf_link_create, perf_event_ioctl},
+    sys::{bpf_link_create, bpf_link_create_perf_event, perf_event_ioctl},
     FEATURES, PERF_EVENT_IOC_DISABLE, PERF_EVENT_IOC_ENABLE, PERF_EVENT_IOC_SET_BPF,
 };
 
@@ -87,6 +87,25 @@ pub(crate) fn perf_attach(prog_fd: RawFd, fd: RawFd) -> Result<PerfLinkInner, Pr
     }
 }
 
+/// Attaches the program to the perf event through a perf link carrying `cookie`, which the
+/// program can read with `bpf_get_attach_cookie`.
+///
+/// Perf links need kernel 5.15, callers must check `FEATURES.bpf_perf_link` first.
+pub(crate) fn perf_attach_with_cookie(
+    prog_fd: RawFd,
+    fd: RawFd,
+    cookie: u64,
+) -> Result<PerfLinkInner, ProgramError> {
+    let link_fd = bpf_link_create_perf_event(prog_fd, fd, cookie);
+    // the link holds a reference to the perf event
+    unsafe { close(fd) };
+    let link_fd = link_fd.map_err(|(_, io_error)| ProgramError::SyscallError {
+        call: "bpf_link_create".to_owned(),
+        io_error,
+    })? as RawFd;
+    Ok(PerfLinkInner::FdLink(FdLink::new(link_fd)))
+}
+
 pub(crate) fn perf_attach_debugfs(
     prog_fd: RawFd,
     fd: RawFd,
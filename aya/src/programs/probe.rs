@@ -42,25 +42,33 @@ pub(crate) fn attach<T: Link + From<PerfLinkInner>>(
     kind: ProbeKind,
     fn_name: &str,
     offset: u64,
+    ref_ctr_offset: Option<u64>,
     pid: Option<pid_t>,
 ) -> Result<T::Id, ProgramError> {
+    let link = attach_perf_link(program_data, kind, fn_name, offset, ref_ctr_offset, pid)?;
+    program_data.links.insert(T::from(link))
+}
+
+/// Creates a perf event for the probe and attaches the program to it, without inserting the
+/// returned link in the program links.
+pub(crate) fn attach_perf_link<T: Link>(
+    program_data: &ProgramData<T>,
+    kind: ProbeKind,
+    fn_name: &str,
+    offset: u64,
+    ref_ctr_offset: Option<u64>,
+    pid: Option<pid_t>,
+) -> Result<PerfLinkInner, ProgramError> {
     // https://github.com/torvalds/linux/commit/e12f03d7031a977356e3d7b75a68c2185ff8d155
     // Use debugfs to create probe
     let k_ver = kernel_version().unwrap();
     if k_ver < (4, 17, 0) {
-        let (fd, event_alias) = create_as_trace_point(kind, fn_name, offset, pid)?;
-        let link = T::from(perf_attach_debugfs(
-            program_data.fd_or_err()?,
-            fd,
-            kind,
-            event_alias,
-        )?);
-        return program_data.links.insert(link);
+        let (fd, event_alias) = create_as_trace_point(kind, fn_name, offset, ref_ctr_offset, pid)?;
+        return perf_attach_debugfs(program_data.fd_or_err()?, fd, kind, event_alias);
     };
 
-    let fd = create_as_probe(kind, fn_name, offset, pid)?;
-    let link = T::from(perf_attach(program_data.fd_or_err()?, fd)?);
-    program_data.links.insert(link)
+    let fd = create_as_probe(kind, fn_name, offset, ref_ctr_offset, pid)?;
+    perf_attach(program_data.fd_or_err()?, fd)
 }
 
 pub(crate) fn detach_debug_fs(kind: ProbeKind, event_alias: &str) -> Result<(), ProgramError> {
@@ -78,10 +86,11 @@ pub(crate) fn detach_debug_fs(kind: ProbeKind, event_alias: &str) -> Result<(),
     Ok(())
 }
 
-fn create_as_probe(
+pub(crate) fn create_as_probe(
     kind: ProbeKind,
     fn_name: &str,
     offset: u64,
+    ref_ctr_offset: Option<u64>,
     pid: Option<pid_t>,
 ) -> Result<i32, ProgramError> {
     use ProbeKind::*;
@@ -105,7 +114,7 @@ fn create_as_probe(
         _ => None,
     };
 
-    let fd = perf_event_open_probe(perf_ty, ret_bit, fn_name, offset, pid).map_err(
+    let fd = perf_event_open_probe(perf_ty, ret_bit, fn_name, offset, ref_ctr_offset, pid).map_err(
         |(_code, io_error)| ProgramError::SyscallError {
             call: "perf_event_open".to_owned(),
             io_error,
@@ -119,6 +128,7 @@ fn create_as_trace_point(
     kind: ProbeKind,
     name: &str,
     offset: u64,
+    ref_ctr_offset: Option<u64>,
     pid: Option<pid_t>,
 ) -> Result<(i32, String), ProgramError> {
     use ProbeKind::*;
@@ -126,9 +136,9 @@ fn create_as_trace_point(
     let tracefs = find_tracefs_path()?;
 
     let event_alias = match kind {
-        KProbe | KRetProbe => create_probe_event(tracefs, kind, name, offset)
+        KProbe | KRetProbe => create_probe_event(tracefs, kind, name, offset, ref_ctr_offset)
             .map_err(|(filename, io_error)| KProbeError::FileError { filename, io_error })?,
-        UProbe | URetProbe => create_probe_event(tracefs, kind, name, offset)
+        UProbe | URetProbe => create_probe_event(tracefs, kind, name, offset, ref_ctr_offset)
             .map_err(|(filename, io_error)| UProbeError::FileError { filename, io_error })?,
     };
 
@@ -149,6 +159,7 @@ fn create_probe_event(
     kind: ProbeKind,
     fn_name: &str,
     offset: u64,
+    ref_ctr_offset: Option<u64>,
 ) -> Result<String, (String, io::Error)> {
     use ProbeKind::*;
 
@@ -169,13 +180,18 @@ fn create_probe_event(
         UProbe => format!(":{offset:#x}"),
         _ => "".to_string(),
     };
+    let ref_ctr_suffix = match (kind, ref_ctr_offset) {
+        (UProbe, Some(ref_ctr_offset)) => format!("({ref_ctr_offset:#x})"),
+        _ => "".to_string(),
+    };
     let probe = format!(
-        "{}:{}s/{} {}{}\n",
+        "{}:{}s/{} {}{}{}\n",
         probe_type_prefix,
         kind.pmu(),
         event_alias,
         fn_name,
-        offset_suffix
+        offset_suffix,
+        ref_ctr_suffix
     );
 
     let mut events_file = OpenOptions::new()
@@ -0,0 +1,53 @@
+//! Struct ops programs.
+use thiserror::Error;
+
+use crate::{
+    generated::bpf_prog_type::BPF_PROG_TYPE_STRUCT_OPS,
+    programs::{load_program_with_attach_type, FdLink, ProgramData, ProgramError},
+};
+
+/// The type returned when loading a [`StructOps`] program fails.
+#[derive(Debug, Error)]
+pub enum StructOpsError {
+    /// The program isn't set as a member of any `struct_ops` map.
+    #[error("the program is not a member of a struct_ops map")]
+    NotAMember,
+}
+
+/// A program implementing a function pointer member of a kernel struct.
+///
+/// [`StructOps`] programs are set as members of a struct declared in a `.struct_ops`
+/// section, eg a `tcp_congestion_ops`. The programs don't get attached themselves: the struct is
+/// registered with the kernel through its [`StructOpsMap`](crate::maps::StructOpsMap) once all
+/// its programs are loaded.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 6.4.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::programs::StructOps;
+///
+/// let program: &mut StructOps = bpf.program_mut("cubic_init").unwrap().try_into()?;
+/// program.load()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+#[doc(alias = "BPF_PROG_TYPE_STRUCT_OPS")]
+pub struct StructOps {
+    pub(crate) data: ProgramData<FdLink>,
+    pub(crate) member_index: Option<u32>,
+}
+
+impl StructOps {
+    /// Loads the program inside the kernel.
+    ///
+    /// The program is checked against the kernel definition of the struct member it's set as.
+    pub fn load(&mut self) -> Result<(), ProgramError> {
+        let member_index = self.member_index.ok_or(StructOpsError::NotAMember)?;
+        load_program_with_attach_type(BPF_PROG_TYPE_STRUCT_OPS, &mut self.data, member_index)
+    }
+}
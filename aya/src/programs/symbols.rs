@@ -0,0 +1,705 @@
+//! Resolution of the symbols of user space binaries.
+use log::debug;
+use object::{
+    elf::{FileHeader32, FileHeader64, SHT_DYNSYM},
+    read::elf::FileHeader,
+    Endianness, Object, ObjectKind, ObjectSection, ObjectSegment, ObjectSymbol,
+};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs, io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+const DEBUG_DIR: &str = "/usr/lib/debug";
+
+#[derive(Error, Debug)]
+pub(crate) enum ResolveSymbolError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("error parsing ELF")]
+    Object(#[from] object::Error),
+
+    #[cfg(feature = "minidebuginfo")]
+    #[error("error decompressing `.gnu_debugdata`")]
+    DebugData(#[source] lzma_rs::error::Error),
+
+    #[error("unknown symbol `{0}`")]
+    Unknown(String),
+
+    #[error("symbol `{0}` at address {1:#x} is not in a loaded segment")]
+    NotInSegment(String, u64),
+}
+
+// The name of a symbol version, and whether it's the default version of the symbol.
+type SymbolVersion = (String, bool);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Symbol {
+    name: String,
+    version: Option<String>,
+    // false for hidden versions (`name@VERSION`), which are only matched when no default version
+    // exists or when asked for explicitly
+    default_version: bool,
+    address: u64,
+}
+
+impl Symbol {
+    fn new(name: &str, version: Option<SymbolVersion>, address: u64) -> Symbol {
+        // static symbol tables can contain versioned names, eg created with `.symver`
+        let (name, version) = match (version, name.split_once('@')) {
+            (Some(version), _) => (name, Some(version)),
+            (None, Some((name, version))) => match version.strip_prefix('@') {
+                Some(version) => (name, Some((version.to_owned(), true))),
+                None => (name, Some((version.to_owned(), false))),
+            },
+            (None, None) => (name, None),
+        };
+        Symbol {
+            name: name.to_owned(),
+            default_version: !matches!(version, Some((_, false))),
+            version: version.map(|(version, _)| version),
+            address,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    address: u64,
+    size: u64,
+    offset: u64,
+}
+
+/// Resolves the symbols of a user space binary to file offsets, which is what the kernel needs to
+/// attach uprobes.
+///
+/// Symbols are looked up in the dynamic and static symbol tables of the binary. Stripped binaries
+/// are also searched through their separate debug file, found through their build ID or
+/// `.gnu_debuglink` section, and with the `minidebuginfo` feature through their MiniDebugInfo (the
+/// LZMA compressed `.gnu_debugdata` section).
+///
+/// Symbol names can be versioned, eg `malloc@@GLIBC_2.2.5` for the default version of `malloc` or
+/// `memcpy@GLIBC_2.2.5` for any version, and with the `demangle` feature Rust and C++ symbols can
+/// be given demangled, eg `std::io::stdio::_print` or `ns::Class::method`.
+#[derive(Debug)]
+pub(crate) struct SymbolResolver {
+    symbols: Vec<Symbol>,
+    // the index in `symbols` of the first symbol with each demangled name
+    demangled: HashMap<String, usize>,
+    // `None` for relocatable files, whose symbol addresses are already offsets
+    segments: Option<Vec<Segment>>,
+}
+
+impl SymbolResolver {
+    pub(crate) fn new<P: AsRef<Path>>(path: P) -> Result<SymbolResolver, ResolveSymbolError> {
+        let path = path.as_ref();
+        let data = fs::read(path)?;
+        Self::parse(path, &data, Path::new(DEBUG_DIR))
+    }
+
+    fn parse(
+        path: &Path,
+        data: &[u8],
+        debug_dir: &Path,
+    ) -> Result<SymbolResolver, ResolveSymbolError> {
+        let obj = object::read::File::parse(data)?;
+
+        let segments = match obj.kind() {
+            ObjectKind::Dynamic | ObjectKind::Executable => Some(
+                obj.segments()
+                    .map(|segment| Segment {
+                        address: segment.address(),
+                        size: segment.size(),
+                        offset: segment.file_range().0,
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let mut symbols = Vec::new();
+        read_symbols(&obj, data, &mut symbols)?;
+
+        if obj.symbols().next().is_none() {
+            // the addresses of the debug symbols are the ones of the stripped binary, so they are
+            // still translated with its segments
+            if let Some(section) = obj.section_by_name(".gnu_debugdata") {
+                read_debug_data(section.data()?, &mut symbols)?;
+            }
+            if let Some(debug_path) = find_debug_file(path, &obj, debug_dir)? {
+                debug!(
+                    "reading symbols of {} from {}",
+                    path.display(),
+                    debug_path.display()
+                );
+                let debug_data = fs::read(debug_path)?;
+                let debug_obj = object::read::File::parse(&*debug_data)?;
+                read_symbols(&debug_obj, &debug_data, &mut symbols)?;
+            }
+        }
+
+        Ok(SymbolResolver::from_symbols(symbols, segments))
+    }
+
+    fn from_symbols(symbols: Vec<Symbol>, segments: Option<Vec<Segment>>) -> SymbolResolver {
+        let mut demangled = HashMap::new();
+        for (index, sym) in symbols.iter().enumerate() {
+            for name in demangle(&sym.name) {
+                demangled.entry(name).or_insert(index);
+            }
+        }
+        SymbolResolver {
+            symbols,
+            demangled,
+            segments,
+        }
+    }
+
+    /// Returns the file offset of `symbol`.
+    pub(crate) fn resolve(&self, symbol: &str) -> Result<u64, ResolveSymbolError> {
+        let sym = self
+            .find(symbol)
+            .ok_or_else(|| ResolveSymbolError::Unknown(symbol.to_owned()))?;
+
+        let segments = match &self.segments {
+            Some(segments) => segments,
+            None => return Ok(sym.address),
+        };
+        segments
+            .iter()
+            .find(|segment| {
+                (segment.address..segment.address + segment.size).contains(&sym.address)
+            })
+            .map(|segment| sym.address - segment.address + segment.offset)
+            .ok_or_else(|| ResolveSymbolError::NotInSegment(symbol.to_owned(), sym.address))
+    }
+
+    fn find(&self, symbol: &str) -> Option<&Symbol> {
+        let (name, version) = match symbol.split_once('@') {
+            Some((name, version)) => match version.strip_prefix('@') {
+                Some(version) => (name, Some((version, true))),
+                None => (name, Some((version, false))),
+            },
+            None => (symbol, None),
+        };
+        let mut symbols = self.symbols.iter().filter(|sym| sym.name == name);
+
+        match version {
+            Some((version, default)) => symbols.find(|sym| {
+                sym.version.as_deref() == Some(version) && (sym.default_version || !default)
+            }),
+            None => symbols
+                .clone()
+                .find(|sym| sym.default_version)
+                .or_else(|| symbols.next()),
+        }
+        .or_else(|| {
+            self.demangled
+                .get(symbol)
+                .map(|&index| &self.symbols[index])
+        })
+    }
+}
+
+fn read_symbols(
+    obj: &object::read::File<'_>,
+    data: &[u8],
+    symbols: &mut Vec<Symbol>,
+) -> Result<(), object::Error> {
+    let versions = if obj.is_64() {
+        dynamic_symbol_versions::<FileHeader64<Endianness>>(data)?
+    } else {
+        dynamic_symbol_versions::<FileHeader32<Endianness>>(data)?
+    };
+
+    for sym in obj.dynamic_symbols().filter(|sym| sym.is_definition()) {
+        let version = versions.get(sym.index().0).cloned().flatten();
+        symbols.push(Symbol::new(sym.name()?, version, sym.address()));
+    }
+    for sym in obj.symbols().filter(|sym| sym.is_definition()) {
+        symbols.push(Symbol::new(sym.name()?, None, sym.address()));
+    }
+
+    Ok(())
+}
+
+// Returns the version of each dynamic symbol.
+fn dynamic_symbol_versions<Elf: FileHeader<Endian = Endianness>>(
+    data: &[u8],
+) -> Result<Vec<Option<SymbolVersion>>, object::Error> {
+    let header = Elf::parse(data)?;
+    let endian = header.endian()?;
+    let sections = header.sections(endian, data)?;
+    let versions = match sections.versions(endian, data)? {
+        Some(versions) => versions,
+        None => return Ok(Vec::new()),
+    };
+
+    (0..sections.symbols(endian, data, SHT_DYNSYM)?.len())
+        .map(|index| {
+            let version_index = versions.version_index(endian, index);
+            Ok(versions.version(version_index)?.map(|version| {
+                (
+                    String::from_utf8_lossy(version.name()).into_owned(),
+                    !version_index.is_hidden(),
+                )
+            }))
+        })
+        .collect()
+}
+
+// Returns the path of the separate debug file of `path`, looking it up by build ID then by
+// `.gnu_debuglink` as gdb does.
+fn find_debug_file(
+    path: &Path,
+    obj: &object::read::File<'_>,
+    debug_dir: &Path,
+) -> Result<Option<PathBuf>, object::Error> {
+    if let Some(build_id) = obj.build_id()? {
+        if let Some((first, rest)) = build_id.split_first() {
+            let rest = rest.iter().map(|b| format!("{b:02x}")).collect::<String>();
+            let debug_path = debug_dir
+                .join(".build-id")
+                .join(format!("{first:02x}"))
+                .join(format!("{rest}.debug"));
+            if debug_path.is_file() {
+                return Ok(Some(debug_path));
+            }
+        }
+    }
+
+    let (debug_link, crc) = match obj.gnu_debuglink()? {
+        Some((debug_link, crc)) => (debug_link, crc),
+        None => return Ok(None),
+    };
+    let debug_link = Path::new(OsStr::from_bytes(debug_link));
+    let dir = fs::canonicalize(path)
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    let candidates = [
+        dir.join(debug_link),
+        dir.join(".debug").join(debug_link),
+        debug_dir
+            .join(dir.strip_prefix("/").unwrap_or(&dir))
+            .join(debug_link),
+    ];
+
+    Ok(candidates.into_iter().find(|candidate| {
+        // the debug link can name the binary itself, which doesn't match its checksum
+        fs::read(candidate)
+            .map(|data| debuglink_crc(&data) == crc)
+            .unwrap_or(false)
+    }))
+}
+
+// Reads the symbols of the MiniDebugInfo of a binary, see
+// https://sourceware.org/gdb/onlinedocs/gdb/MiniDebugInfo.html.
+#[cfg(feature = "minidebuginfo")]
+fn read_debug_data(data: &[u8], symbols: &mut Vec<Symbol>) -> Result<(), ResolveSymbolError> {
+    let mut debug_data = Vec::new();
+    lzma_rs::xz_decompress(&mut &*data, &mut debug_data).map_err(ResolveSymbolError::DebugData)?;
+    let debug_obj = object::read::File::parse(&*debug_data)?;
+    read_symbols(&debug_obj, &debug_data, symbols)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "minidebuginfo"))]
+fn read_debug_data(_data: &[u8], _symbols: &mut Vec<Symbol>) -> Result<(), ResolveSymbolError> {
+    debug!("ignoring `.gnu_debugdata`, reading it requires the `minidebuginfo` feature");
+    Ok(())
+}
+
+// The CRC-32 of the separate debug file stored in `.gnu_debuglink`.
+fn debuglink_crc(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        *entry = (0..8).fold(n as u32, |crc, _| {
+            if crc & 1 != 0 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            }
+        });
+    }
+    !data.iter().fold(!0u32, |crc, &byte| {
+        table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+// Returns the demangled names `name` can be looked up with.
+#[cfg(feature = "demangle")]
+fn demangle(name: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        // without the hash suffix
+        names.push(format!("{demangled:#}"));
+    }
+    if let Ok(symbol) = cpp_demangle::Symbol::new(name) {
+        let options = cpp_demangle::DemangleOptions::new();
+        if let Ok(demangled) = symbol.demangle(&options) {
+            names.push(demangled);
+        }
+        if let Ok(demangled) = symbol.demangle(&options.no_params()) {
+            names.push(demangled);
+        }
+    }
+    names
+}
+
+#[cfg(not(feature = "demangle"))]
+fn demangle(_name: &str) -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use object::elf::{
+        EM_X86_64, ET_DYN, ET_EXEC, NT_GNU_BUILD_ID, SHF_ALLOC, SHF_EXECINSTR, SHT_GNU_VERDEF,
+        SHT_GNU_VERSYM, SHT_NOTE, SHT_PROGBITS, STB_GLOBAL, STT_FUNC, VER_FLG_BASE,
+    };
+
+    use crate::test_elf::{self, ElfBuilder, Section};
+
+    use super::*;
+
+    const TEXT_ADDRESS: u64 = 0x401000;
+
+    // versions are given in the names, eg `memcpy@@GLIBC_2.14`
+    fn resolver(symbols: &[(&str, u64)]) -> SymbolResolver {
+        SymbolResolver::from_symbols(
+            symbols
+                .iter()
+                .map(|(name, address)| Symbol::new(name, None, *address))
+                .collect(),
+            Some(vec![
+                Segment {
+                    address: 0,
+                    size: 0x1000,
+                    offset: 0,
+                },
+                Segment {
+                    address: 0x401000,
+                    size: 0x2000,
+                    offset: 0x1000,
+                },
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_symbol_version_from_name() {
+        assert_eq!(
+            Symbol::new("foo@@VERS_2", None, 0),
+            Symbol {
+                name: "foo".to_owned(),
+                version: Some("VERS_2".to_owned()),
+                default_version: true,
+                address: 0,
+            }
+        );
+        assert_eq!(
+            Symbol::new("foo@VERS_1", None, 0),
+            Symbol {
+                name: "foo".to_owned(),
+                version: Some("VERS_1".to_owned()),
+                default_version: false,
+                address: 0,
+            }
+        );
+        assert_eq!(Symbol::new("foo", None, 0).version, None);
+    }
+
+    #[test]
+    fn test_resolve_segment_offset() {
+        let resolver = resolver(&[("main", 0x401234), ("foo", 0x800000)]);
+
+        assert_eq!(resolver.resolve("main").unwrap(), 0x1234);
+        assert!(matches!(
+            resolver.resolve("foo"),
+            Err(ResolveSymbolError::NotInSegment(_, 0x800000))
+        ));
+        assert!(matches!(
+            resolver.resolve("bar"),
+            Err(ResolveSymbolError::Unknown(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_versioned() {
+        let resolver = resolver(&[
+            ("memcpy@GLIBC_2.2.5", 0x10),
+            ("memcpy@@GLIBC_2.14", 0x20),
+            ("old@GLIBC_2.2.5", 0x30),
+        ]);
+
+        assert_eq!(resolver.resolve("memcpy").unwrap(), 0x20);
+        assert_eq!(resolver.resolve("memcpy@@GLIBC_2.14").unwrap(), 0x20);
+        assert_eq!(resolver.resolve("memcpy@GLIBC_2.14").unwrap(), 0x20);
+        assert_eq!(resolver.resolve("memcpy@GLIBC_2.2.5").unwrap(), 0x10);
+        assert!(resolver.resolve("memcpy@@GLIBC_2.2.5").is_err());
+        assert_eq!(resolver.resolve("old").unwrap(), 0x30);
+    }
+
+    #[test]
+    #[cfg(feature = "demangle")]
+    fn test_resolve_demangled() {
+        let resolver = resolver(&[
+            ("_ZN3foo3bar17h0123456789abcdefE", 0x10),
+            ("_ZN2ns5Class6methodEi", 0x20),
+        ]);
+
+        assert_eq!(resolver.resolve("foo::bar").unwrap(), 0x10);
+        assert_eq!(resolver.resolve("ns::Class::method").unwrap(), 0x20);
+        assert_eq!(resolver.resolve("ns::Class::method(int)").unwrap(), 0x20);
+        assert_eq!(resolver.resolve("_ZN2ns5Class6methodEi").unwrap(), 0x20);
+    }
+
+    #[test]
+    fn test_relocatable_addresses() {
+        let mut resolver = resolver(&[("foo", 0x800000)]);
+        resolver.segments = None;
+
+        assert_eq!(resolver.resolve("foo").unwrap(), 0x800000);
+    }
+
+    fn func(name: &str, section: usize, address: u64) -> test_elf::Symbol<'_> {
+        test_elf::Symbol {
+            name,
+            info: (STB_GLOBAL << 4) | STT_FUNC,
+            section: section as u16,
+            value: address,
+            size: 16,
+        }
+    }
+
+    // An executable with its `.text` mapped at TEXT_ADDRESS, and the index of `.text`.
+    fn executable() -> (ElfBuilder, usize) {
+        let mut elf = ElfBuilder::new(ET_EXEC, EM_X86_64);
+        let text = elf.section(Section {
+            name: ".text".to_owned(),
+            sh_type: SHT_PROGBITS,
+            flags: (SHF_ALLOC | SHF_EXECINSTR) as u64,
+            addr: TEXT_ADDRESS,
+            data: vec![0; 0x1000],
+            ..Default::default()
+        });
+        elf.segment(text);
+        (elf, text)
+    }
+
+    // The debug file of `executable()` with a `main` symbol at `address`.
+    fn debug_file(address: u64) -> Vec<u8> {
+        let (mut elf, text) = executable();
+        elf.symtab(&[func("main", text, address)]);
+        elf.build()
+    }
+
+    // The file offset of `address` in the `.text` of `executable()`.
+    fn text_offset(resolver: &SymbolResolver, address: u64) -> u64 {
+        address - TEXT_ADDRESS + resolver.segments.as_ref().unwrap()[0].offset
+    }
+
+    // An empty directory for the files of a test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aya-symbols-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_debuglink_crc() {
+        assert_eq!(debuglink_crc(b""), 0);
+        assert_eq!(debuglink_crc(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn test_dynamic_symbol_versions() {
+        let mut elf = ElfBuilder::new(ET_DYN, EM_X86_64);
+        let text = elf.section(Section {
+            name: ".text".to_owned(),
+            sh_type: SHT_PROGBITS,
+            flags: (SHF_ALLOC | SHF_EXECINSTR) as u64,
+            addr: 0x1000,
+            data: vec![0; 0x100],
+            ..Default::default()
+        });
+        elf.segment(text);
+        let dynsym = elf.symbols(
+            ".dynsym",
+            ".dynstr",
+            SHT_DYNSYM,
+            &[
+                func("memcpy", text, 0x1010),
+                func("memcpy", text, 0x1020),
+                func("old", text, 0x1030),
+            ],
+        );
+
+        // the versions of the null symbol and each of the above, 0x8000 marking hidden versions
+        let versym = [0u16, 0x8002, 3, 0x8002]
+            .iter()
+            .flat_map(|version| version.to_le_bytes())
+            .collect();
+        elf.section(Section {
+            name: ".gnu.version".to_owned(),
+            sh_type: SHT_GNU_VERSYM,
+            data: versym,
+            link: dynsym as u32,
+            entsize: 2,
+            ..Default::default()
+        });
+        let mut verdef = Vec::new();
+        let versions = [
+            (VER_FLG_BASE, 1, "libfoo.so"),
+            (0, 2, "GLIBC_2.2.5"),
+            (0, 3, "GLIBC_2.14"),
+        ];
+        for (i, (flags, index, name)) in versions.iter().enumerate() {
+            let name = elf.string(dynsym, name);
+            let next: u32 = if i == versions.len() - 1 { 0 } else { 28 };
+            // Elf64_Verdef followed by its Elf64_Verdaux
+            verdef.extend_from_slice(&1u16.to_le_bytes());
+            verdef.extend_from_slice(&flags.to_le_bytes());
+            verdef.extend_from_slice(&(*index as u16).to_le_bytes());
+            verdef.extend_from_slice(&1u16.to_le_bytes());
+            verdef.extend_from_slice(&0u32.to_le_bytes());
+            verdef.extend_from_slice(&20u32.to_le_bytes());
+            verdef.extend_from_slice(&next.to_le_bytes());
+            verdef.extend_from_slice(&name.to_le_bytes());
+            verdef.extend_from_slice(&0u32.to_le_bytes());
+        }
+        elf.section(Section {
+            name: ".gnu.version_d".to_owned(),
+            sh_type: SHT_GNU_VERDEF,
+            data: verdef,
+            // the string table added along with `.dynsym`
+            link: dynsym as u32 - 1,
+            info: versions.len() as u32,
+            ..Default::default()
+        });
+        let data = elf.build();
+
+        let hidden = |name: &str| Some((name.to_owned(), false));
+        assert_eq!(
+            dynamic_symbol_versions::<FileHeader64<Endianness>>(&data).unwrap(),
+            vec![
+                None,
+                hidden("GLIBC_2.2.5"),
+                Some(("GLIBC_2.14".to_owned(), true)),
+                hidden("GLIBC_2.2.5"),
+            ]
+        );
+
+        let resolver =
+            SymbolResolver::parse(Path::new("libfoo.so"), &data, Path::new(DEBUG_DIR)).unwrap();
+        let offset =
+            |address: u64| address - 0x1000 + resolver.segments.as_ref().unwrap()[0].offset;
+        assert_eq!(resolver.resolve("memcpy").unwrap(), offset(0x1020));
+        assert_eq!(
+            resolver.resolve("memcpy@GLIBC_2.2.5").unwrap(),
+            offset(0x1010)
+        );
+        assert_eq!(resolver.resolve("old").unwrap(), offset(0x1030));
+    }
+
+    #[test]
+    #[cfg(feature = "minidebuginfo")]
+    fn test_resolve_debug_data() {
+        let mut debug_data = Vec::new();
+        lzma_rs::xz_compress(&mut &*debug_file(0x401234), &mut debug_data).unwrap();
+        let (mut elf, _) = executable();
+        elf.section(Section {
+            name: ".gnu_debugdata".to_owned(),
+            sh_type: SHT_PROGBITS,
+            data: debug_data,
+            ..Default::default()
+        });
+
+        let resolver =
+            SymbolResolver::parse(Path::new("bin"), &elf.build(), Path::new(DEBUG_DIR)).unwrap();
+        assert_eq!(
+            resolver.resolve("main").unwrap(),
+            text_offset(&resolver, 0x401234)
+        );
+    }
+
+    #[test]
+    fn test_resolve_build_id() {
+        let dir = test_dir("build-id");
+        let debug_dir = dir.join("debug");
+        let build_id_dir = debug_dir.join(".build-id").join("ab");
+        fs::create_dir_all(&build_id_dir).unwrap();
+        fs::write(build_id_dir.join("cdef01.debug"), debug_file(0x401234)).unwrap();
+
+        let (mut elf, _) = executable();
+        let mut note = Vec::new();
+        note.extend_from_slice(&4u32.to_le_bytes());
+        note.extend_from_slice(&4u32.to_le_bytes());
+        note.extend_from_slice(&NT_GNU_BUILD_ID.to_le_bytes());
+        note.extend_from_slice(b"GNU\0");
+        note.extend_from_slice(&[0xab, 0xcd, 0xef, 0x01]);
+        elf.section(Section {
+            name: ".note.gnu.build-id".to_owned(),
+            sh_type: SHT_NOTE,
+            flags: SHF_ALLOC as u64,
+            data: note,
+            ..Default::default()
+        });
+
+        let resolver = SymbolResolver::parse(&dir.join("bin"), &elf.build(), &debug_dir).unwrap();
+        assert_eq!(
+            resolver.resolve("main").unwrap(),
+            text_offset(&resolver, 0x401234)
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_debuglink() {
+        let dir = test_dir("debuglink");
+        let debug_data = debug_file(0x401234);
+        fs::create_dir(dir.join(".debug")).unwrap();
+        fs::write(dir.join(".debug").join("bin.debug"), &debug_data).unwrap();
+
+        let binary = |crc: u32| {
+            let (mut elf, _) = executable();
+            let mut debuglink = b"bin.debug\0\0\0".to_vec();
+            debuglink.extend_from_slice(&crc.to_le_bytes());
+            elf.section(Section {
+                name: ".gnu_debuglink".to_owned(),
+                sh_type: SHT_PROGBITS,
+                data: debuglink,
+                ..Default::default()
+            });
+            let data = elf.build();
+            fs::write(dir.join("bin"), &data).unwrap();
+            data
+        };
+        let debug_dir = dir.join("debug");
+
+        let data = binary(debuglink_crc(&debug_data));
+        let resolver = SymbolResolver::parse(&dir.join("bin"), &data, &debug_dir).unwrap();
+        assert_eq!(
+            resolver.resolve("main").unwrap(),
+            text_offset(&resolver, 0x401234)
+        );
+
+        // a debug file that doesn't match the checksum is ignored
+        let data = binary(debuglink_crc(&debug_data) ^ 1);
+        let resolver = SymbolResolver::parse(&dir.join("bin"), &data, &debug_dir).unwrap();
+        assert!(matches!(
+            resolver.resolve("main"),
+            Err(ResolveSymbolError::Unknown(_))
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
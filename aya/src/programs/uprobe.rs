@@ -1,13 +1,15 @@
 //! User space probes.
 use libc::pid_t;
-use object::{Object, ObjectSection, ObjectSymbol};
 use std::{
     error::Error,
-    ffi::CStr,
+    ffi::{CStr, CString},
     fs,
     io::{self, BufRead, Cursor, Read},
     mem,
-    os::raw::c_char,
+    os::{
+        raw::c_char,
+        unix::{fs::MetadataExt, io::RawFd},
+    },
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -16,11 +18,16 @@ use thiserror::Error;
 use crate::{
     generated::bpf_prog_type::BPF_PROG_TYPE_KPROBE,
     programs::{
-        define_link_wrapper, load_program,
+        define_link_wrapper,
+        links::{FdLink, Link, LinkError},
+        load_program, load_program_with_attach_type,
         perf_attach::{PerfLinkIdInner, PerfLinkInner},
-        probe::{attach, ProbeKind},
+        probe::{attach, attach_perf_link, ProbeKind},
+        symbols::{ResolveSymbolError, SymbolResolver},
         ProgramData, ProgramError,
     },
+    sys::{bpf_link_create_uprobe_multi, BPF_F_UPROBE_MULTI_RETURN, BPF_TRACE_UPROBE_MULTI},
+    FEATURES,
 };
 
 const LD_SO_CACHE_FILE: &str = "/etc/ld.so.cache";
@@ -38,17 +45,43 @@ const LD_SO_CACHE_HEADER: &str = "glibc-ld.so.cache1.1";
 ///
 /// - `uprobe`: get attached to the *start* of the target functions
 /// - `uretprobe`: get attached to the *return address* of the target functions
+///
+/// Programs declared in `uprobe.multi` or `uretprobe.multi` sections can be attached to many
+/// locations of a binary at once with [`UProbe::attach_multi`], which creates a single link.
 #[derive(Debug)]
 #[doc(alias = "BPF_PROG_TYPE_KPROBE")]
 pub struct UProbe {
     pub(crate) data: ProgramData<UProbeLink>,
     pub(crate) kind: ProbeKind,
+    pub(crate) multi: bool,
+}
+
+/// A location of a binary to attach a [`UProbe`] to, see [`UProbe::attach_multi`].
+#[derive(Debug, Clone, Default)]
+pub struct UProbeAttachPoint<'a> {
+    /// The function to attach to, or `None` to attach to `offset` in the binary.
+    pub fn_name: Option<&'a str>,
+    /// The offset added to the address of the function.
+    pub offset: u64,
+    /// The offset in the binary of a reference counter, eg the semaphore of a USDT probe, that
+    /// the kernel increments while the probe is attached.
+    pub ref_ctr_offset: Option<u64>,
+    /// The cookie the program can read with `bpf_get_attach_cookie`.
+    pub cookie: u64,
 }
 
 impl UProbe {
     /// Loads the program inside the kernel.
     pub fn load(&mut self) -> Result<(), ProgramError> {
-        load_program(BPF_PROG_TYPE_KPROBE, &mut self.data)
+        if self.uses_multi_link() {
+            load_program_with_attach_type(
+                BPF_PROG_TYPE_KPROBE,
+                &mut self.data,
+                BPF_TRACE_UPROBE_MULTI,
+            )
+        } else {
+            load_program(BPF_PROG_TYPE_KPROBE, &mut self.data)
+        }
     }
 
     /// Returns `UProbe` if the program is a `uprobe`, or `URetProbe` if the
@@ -67,11 +100,30 @@ impl UProbe {
     /// The `target` argument can be an absolute path to a binary or library, or
     /// a library name (eg: `"libc"`).
     ///
+    /// When `pid` is given, `target` is interpreted as seen by that process, so
+    /// it can be a path or library name inside a container. Libraries are first
+    /// looked up in the mappings of the process, then in its own
+    /// `/etc/ld.so.cache`, and paths are opened through `/proc/<pid>/root`. A
+    /// mapped library that was deleted or replaced on disk is attached through
+    /// `/proc/<pid>/map_files`, which requires `CAP_SYS_ADMIN`.
+    ///
+    /// `fn_name` can be versioned (eg: `"malloc@@GLIBC_2.2.5"`) or, for Rust and
+    /// C++ functions and with the `demangle` feature, demangled (eg:
+    /// `"std::io::stdio::_print"`). Stripped binaries are searched through their
+    /// separate debug file, found by build ID or `.gnu_debuglink` under
+    /// `/usr/lib/debug`, and with the `minidebuginfo` feature through their
+    /// MiniDebugInfo.
+    ///
     /// If the program is an `uprobe`, it is attached to the *start* address of the target
     /// function.  Instead if the program is a `uretprobe`, it is attached to the return address of
     /// the target function.
     ///
     /// The returned value can be used to detach, see [UProbe::detach].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`UProbeError::MultiProgram`] if the program is a `uprobe.multi` program, see
+    /// [`UProbe::attach_multi`].
     pub fn attach<T: AsRef<Path>>(
         &mut self,
         fn_name: Option<&str>,
@@ -79,47 +131,151 @@ impl UProbe {
         target: T,
         pid: Option<pid_t>,
     ) -> Result<UProbeLinkId, ProgramError> {
-        let target = target.as_ref();
-        let target_str = &*target.as_os_str().to_string_lossy();
-
-        let mut path = if let Some(pid) = pid {
-            find_lib_in_proc_maps(pid, target_str).map_err(|io_error| UProbeError::FileError {
-                filename: format!("/proc/{pid}/maps"),
-                io_error,
-            })?
+        if self.multi {
+            return Err(UProbeError::MultiProgram.into());
+        }
+        let path = resolve_attach_path(target.as_ref(), pid)?;
+
+        let sym_offset = if let Some(fn_name) = fn_name {
+            SymbolResolver::new(&path)
+                .and_then(|resolver| resolver.resolve(fn_name))
+                .map_err(|error| UProbeError::SymbolError {
+                    symbol: fn_name.to_string(),
+                    error: Box::new(error),
+                })?
         } else {
-            None
+            0
         };
 
-        if path.is_none() {
-            path = if target.is_absolute() {
-                Some(target_str)
-            } else {
-                let cache =
-                    LD_SO_CACHE
-                        .as_ref()
-                        .map_err(|error| UProbeError::InvalidLdSoCache {
-                            io_error: error.clone(),
-                        })?;
-                cache.resolve(target_str)
+        attach(
+            &mut self.data,
+            self.kind,
+            &path,
+            sym_offset + offset,
+            None,
+            pid,
+        )
+    }
+
+    /// Attaches the program to multiple locations of the same binary at once.
+    ///
+    /// The program must be declared in a `uprobe.multi` or `uretprobe.multi` section. `target`
+    /// and `pid` are interpreted as in [`UProbe::attach`], and each of the `points` is resolved
+    /// to an offset in `target`.
+    ///
+    /// All the locations are attached to through a single `BPF_TRACE_UPROBE_MULTI` link. On
+    /// kernels that don't support it, one perf event is created per location instead, and the
+    /// returned link detaches all of them. Cookies are only supported by the
+    /// `BPF_TRACE_UPROBE_MULTI` links.
+    ///
+    /// The returned value can be used to detach, see [UProbe::detach].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// use aya::programs::{UProbe, UProbeAttachPoint};
+    ///
+    /// let program: &mut UProbe = bpf.program_mut("alloc").unwrap().try_into()?;
+    /// program.load()?;
+    /// let points = ["malloc", "calloc", "realloc"]
+    ///     .iter()
+    ///     .enumerate()
+    ///     .map(|(i, fn_name)| UProbeAttachPoint {
+    ///         fn_name: Some(fn_name),
+    ///         cookie: i as u64,
+    ///         ..Default::default()
+    ///     })
+    ///     .collect::<Vec<_>>();
+    /// program.attach_multi(&points, "libc", None)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn attach_multi<T: AsRef<Path>>(
+        &mut self,
+        points: &[UProbeAttachPoint<'_>],
+        target: T,
+        pid: Option<pid_t>,
+    ) -> Result<UProbeLinkId, ProgramError> {
+        if !self.multi {
+            return Err(UProbeError::NotMultiProgram.into());
+        }
+        if points.is_empty() {
+            return Err(UProbeError::NoAttachPoints.into());
+        }
+        let path = resolve_attach_path(target.as_ref(), pid)?;
+        let offsets = resolve_attach_points(&path, points)?;
+
+        if !self.uses_multi_link() {
+            if points.iter().any(|point| point.cookie != 0) {
+                return Err(UProbeError::CookiesUnsupported.into());
             }
-            .map(String::from)
-        };
 
-        let path = path.ok_or(UProbeError::InvalidTarget {
-            path: target.to_owned(),
-        })?;
+            let mut links = Vec::with_capacity(offsets.len());
+            for (point, offset) in points.iter().zip(offsets) {
+                match attach_perf_link(
+                    &self.data,
+                    self.kind,
+                    &path,
+                    offset,
+                    point.ref_ctr_offset,
+                    pid,
+                ) {
+                    Ok(link) => links.push(link),
+                    Err(e) => {
+                        for link in links {
+                            let _ = link.detach();
+                        }
+                        return Err(e);
+                    }
+                }
+            }
+            return self
+                .data
+                .links
+                .insert(UProbeLink::new(UProbeLinkInner::PerfLinks(links)));
+        }
 
-        let sym_offset = if let Some(fn_name) = fn_name {
-            resolve_symbol(&path, fn_name).map_err(|error| UProbeError::SymbolError {
-                symbol: fn_name.to_string(),
-                error: Box::new(error),
-            })?
-        } else {
-            0
+        let prog_fd = self.data.fd_or_err()?;
+        let ref_ctr_offsets = points
+            .iter()
+            .any(|point| point.ref_ctr_offset.is_some())
+            .then(|| {
+                points
+                    .iter()
+                    .map(|point| point.ref_ctr_offset.unwrap_or(0))
+                    .collect::<Vec<_>>()
+            });
+        let cookies = points
+            .iter()
+            .any(|point| point.cookie != 0)
+            .then(|| points.iter().map(|point| point.cookie).collect::<Vec<_>>());
+        let flags = match self.kind {
+            ProbeKind::URetProbe => BPF_F_UPROBE_MULTI_RETURN,
+            _ => 0,
         };
+        let c_path = CString::new(path.as_str()).map_err(|_| UProbeError::InvalidTarget {
+            path: PathBuf::from(&path),
+        })?;
 
-        attach(&mut self.data, self.kind, &path, sym_offset + offset, pid)
+        let link_fd = bpf_link_create_uprobe_multi(
+            prog_fd,
+            &c_path,
+            &offsets,
+            ref_ctr_offsets.as_deref(),
+            cookies.as_deref(),
+            pid,
+            flags,
+        )
+        .map_err(|(_, io_error)| ProgramError::SyscallError {
+            call: "bpf_link_create".to_owned(),
+            io_error,
+        })? as RawFd;
+
+        self.data
+            .links
+            .insert(UProbeLink::new(UProbeLinkInner::PerfLink(
+                PerfLinkInner::FdLink(FdLink::new(link_fd)),
+            )))
     }
 
     /// Detaches the program.
@@ -145,7 +301,50 @@ impl UProbe {
     /// the program being unloaded from the kernel if it is still pinned.
     pub fn from_pin<P: AsRef<Path>>(path: P, kind: ProbeKind) -> Result<Self, ProgramError> {
         let data = ProgramData::from_pinned_path(path)?;
-        Ok(Self { data, kind })
+        Ok(Self {
+            data,
+            kind,
+            multi: false,
+        })
+    }
+
+    fn uses_multi_link(&self) -> bool {
+        self.multi && FEATURES.bpf_uprobe_multi_link
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum UProbeLinkInner {
+    PerfLink(PerfLinkInner),
+    // the perf events created by `attach_multi` on kernels without uprobe_multi links
+    PerfLinks(Vec<PerfLinkInner>),
+}
+
+impl Link for UProbeLinkInner {
+    type Id = PerfLinkIdInner;
+
+    fn id(&self) -> Self::Id {
+        match self {
+            UProbeLinkInner::PerfLink(link) => link.id(),
+            // `attach_multi` never creates an empty list of links
+            UProbeLinkInner::PerfLinks(links) => links[0].id(),
+        }
+    }
+
+    fn detach(self) -> Result<(), ProgramError> {
+        match self {
+            UProbeLinkInner::PerfLink(link) => link.detach(),
+            UProbeLinkInner::PerfLinks(links) => {
+                let mut result = Ok(());
+                for link in links {
+                    let res = link.detach();
+                    if result.is_ok() {
+                        result = res;
+                    }
+                }
+                result
+            }
+        }
     }
 }
 
@@ -154,10 +353,28 @@ define_link_wrapper!(
     UProbeLink,
     /// The type returned by [UProbe::attach]. Can be passed to [UProbe::detach].
     UProbeLinkId,
-    PerfLinkInner,
+    UProbeLinkInner,
     PerfLinkIdInner
 );
 
+impl From<PerfLinkInner> for UProbeLink {
+    fn from(link: PerfLinkInner) -> UProbeLink {
+        UProbeLink::new(UProbeLinkInner::PerfLink(link))
+    }
+}
+
+impl TryFrom<UProbeLink> for FdLink {
+    type Error = LinkError;
+
+    fn try_from(value: UProbeLink) -> Result<Self, Self::Error> {
+        if let UProbeLinkInner::PerfLink(PerfLinkInner::FdLink(fd)) = value.into_inner() {
+            Ok(fd)
+        } else {
+            Err(LinkError::InvalidLink)
+        }
+    }
+}
+
 /// The type returned when attaching an [`UProbe`] fails.
 #[derive(Debug, Error)]
 pub enum UProbeError {
@@ -195,41 +412,228 @@ pub enum UProbeError {
         #[source]
         io_error: io::Error,
     },
+
+    /// The program is a `uprobe.multi` program, which must be attached with
+    /// [`UProbe::attach_multi`].
+    #[error("uprobe.multi programs must be attached with `attach_multi`")]
+    MultiProgram,
+
+    /// The program isn't a `uprobe.multi` program, and can't be attached to multiple locations.
+    #[error("the program is not a uprobe.multi program")]
+    NotMultiProgram,
+
+    /// No location to attach to was given.
+    #[error("no attach point given")]
+    NoAttachPoints,
+
+    /// Cookies were given but the kernel doesn't support `BPF_TRACE_UPROBE_MULTI` links.
+    #[error("attach cookies require uprobe_multi links, which the kernel doesn't support")]
+    CookiesUnsupported,
 }
 
-fn proc_maps_libs(pid: pid_t) -> Result<Vec<(String, String)>, io::Error> {
-    let maps_file = format!("/proc/{pid}/maps");
-    let data = fs::read_to_string(maps_file)?;
+pub(crate) fn resolve_attach_path(
+    target: &Path,
+    pid: Option<pid_t>,
+) -> Result<String, UProbeError> {
+    let target_str = &*target.as_os_str().to_string_lossy();
+
+    let path = match pid {
+        Some(pid) => resolve_proc_attach_path(pid, target, target_str)?,
+        None => if target.is_absolute() {
+            Some(target_str)
+        } else {
+            let cache = LD_SO_CACHE
+                .as_ref()
+                .map_err(|error| UProbeError::InvalidLdSoCache {
+                    io_error: error.clone(),
+                })?;
+            cache.resolve(target_str)
+        }
+        .map(String::from),
+    };
 
-    Ok(data
-        .lines()
+    path.ok_or(UProbeError::InvalidTarget {
+        path: target.to_owned(),
+    })
+}
+
+// Resolves `target` as seen by the process `pid`, which can run in another mount namespace (eg
+// in a container), to a path that can be opened from ours.
+fn resolve_proc_attach_path(
+    pid: pid_t,
+    target: &Path,
+    target_str: &str,
+) -> Result<Option<String>, UProbeError> {
+    let root = proc_root(pid)?;
+    let host_path = |path: &str| match &root {
+        Some(root) => format!("{root}{path}"),
+        None => path.to_owned(),
+    };
+
+    let entry =
+        find_lib_in_proc_maps(pid, target_str).map_err(|io_error| UProbeError::FileError {
+            filename: format!("/proc/{pid}/maps"),
+            io_error,
+        })?;
+    if let Some(entry) = entry {
+        let path = host_path(&entry.path);
+        // the file was deleted or replaced since it was mapped, eg by a package upgrade, but the
+        // mapped inode can still be reached through map_files
+        let same_file = fs::metadata(&path)
+            .map(|metadata| {
+                dev_numbers(metadata.dev()) == entry.dev && metadata.ino() == entry.inode
+            })
+            .unwrap_or(false);
+        if entry.deleted || !same_file {
+            return Ok(Some(format!("/proc/{pid}/map_files/{}", entry.range)));
+        }
+        return Ok(Some(path));
+    }
+
+    if target.is_absolute() {
+        return Ok(Some(host_path(target_str)));
+    }
+    match &root {
+        // the libraries of the container are found with its own cache
+        Some(root) => {
+            let cache = LdSoCache::load(format!("{root}{LD_SO_CACHE_FILE}")).map_err(|error| {
+                UProbeError::InvalidLdSoCache {
+                    io_error: Arc::new(error),
+                }
+            })?;
+            Ok(cache.resolve(target_str).map(host_path))
+        }
+        None => {
+            let cache = LD_SO_CACHE
+                .as_ref()
+                .map_err(|error| UProbeError::InvalidLdSoCache {
+                    io_error: error.clone(),
+                })?;
+            Ok(cache.resolve(target_str).map(String::from))
+        }
+    }
+}
+
+// Returns the root directory of `pid` if it doesn't see the same file system as us, because it
+// runs in another mount namespace or is chrooted.
+fn proc_root(pid: pid_t) -> Result<Option<String>, UProbeError> {
+    let read_link = |filename: String| {
+        fs::read_link(&filename).map_err(|io_error| UProbeError::FileError { filename, io_error })
+    };
+    let own_ns = read_link("/proc/self/ns/mnt".to_owned())?;
+    let ns = read_link(format!("/proc/{pid}/ns/mnt"))?;
+    let root = format!("/proc/{pid}/root");
+    if own_ns == ns && read_link(root.clone())? == Path::new("/") {
+        Ok(None)
+    } else {
+        Ok(Some(root))
+    }
+}
+
+// Splits a device number into its major and minor numbers, as glibc's `major` and `minor` do.
+fn dev_numbers(dev: u64) -> (u32, u32) {
+    let major = ((dev & 0x0000_0000_000f_ff00) >> 8) | ((dev & 0xffff_f000_0000_0000) >> 32);
+    let minor = (dev & 0x0000_0000_0000_00ff) | ((dev & 0x0000_0fff_fff0_0000) >> 12);
+    (major as u32, minor as u32)
+}
+
+// Returns the offsets in `path` of the given attach points, parsing the binary only once.
+fn resolve_attach_points(
+    path: &str,
+    points: &[UProbeAttachPoint<'_>],
+) -> Result<Vec<u64>, UProbeError> {
+    let first_symbol = match points.iter().find_map(|point| point.fn_name) {
+        Some(fn_name) => fn_name,
+        None => return Ok(points.iter().map(|point| point.offset).collect()),
+    };
+
+    let symbol_error = |symbol: &str, error: ResolveSymbolError| UProbeError::SymbolError {
+        symbol: symbol.to_string(),
+        error: Box::new(error),
+    };
+    let resolver = SymbolResolver::new(path).map_err(|error| symbol_error(first_symbol, error))?;
+
+    points
+        .iter()
+        .map(|point| match point.fn_name {
+            Some(fn_name) => resolver
+                .resolve(fn_name)
+                .map(|sym_offset| sym_offset + point.offset)
+                .map_err(|error| symbol_error(fn_name, error)),
+            None => Ok(point.offset),
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ProcMapsEntry {
+    range: String,
+    // the major and minor numbers of the device holding the file
+    dev: (u32, u32),
+    inode: u64,
+    path: String,
+    deleted: bool,
+}
+
+fn parse_proc_maps(data: &str) -> Vec<ProcMapsEntry> {
+    data.lines()
         .filter_map(|line| {
-            let line = line.split_whitespace().last()?;
-            if line.starts_with('/') {
-                let path = PathBuf::from(line);
-                let key = path.file_name().unwrap().to_string_lossy().into_owned();
-                Some((key, path.to_string_lossy().to_string()))
-            } else {
-                None
+            // address perms offset dev inode path, where path can contain spaces
+            let mut fields = line.splitn(6, ' ');
+            let range = fields.next()?;
+            let (major, minor) = fields.nth(2)?.split_once(':')?;
+            let dev = (
+                u32::from_str_radix(major, 16).ok()?,
+                u32::from_str_radix(minor, 16).ok()?,
+            );
+            let inode = fields.next()?.parse().ok()?;
+            let path = fields.next()?.trim_start();
+            if !path.starts_with('/') {
+                return None;
             }
+            let (path, deleted) = match path.strip_suffix(" (deleted)") {
+                Some(path) => (path, true),
+                None => (path, false),
+            };
+            Some(ProcMapsEntry {
+                range: range.to_owned(),
+                dev,
+                inode,
+                path: path.to_owned(),
+                deleted,
+            })
         })
-        .collect())
+        .collect()
 }
 
-fn find_lib_in_proc_maps(pid: pid_t, lib: &str) -> Result<Option<String>, io::Error> {
-    let libs = proc_maps_libs(pid)?;
+fn find_lib_in_proc_maps(pid: pid_t, lib: &str) -> Result<Option<ProcMapsEntry>, io::Error> {
+    let maps_file = format!("/proc/{pid}/maps");
+    let data = fs::read_to_string(maps_file)?;
+
+    Ok(find_lib_in_maps(parse_proc_maps(&data), lib))
+}
 
-    let ret = if lib.contains(".so") {
-        libs.iter().find(|(k, _)| k.as_str().starts_with(lib))
-    } else {
-        let lib = lib.to_string();
-        let lib1 = lib.clone() + ".so";
-        let lib2 = lib + "-";
-        libs.iter()
-            .find(|(k, _)| k.starts_with(&lib1) || k.starts_with(&lib2))
+fn find_lib_in_maps(entries: Vec<ProcMapsEntry>, lib: &str) -> Option<ProcMapsEntry> {
+    let file_name = |entry: &ProcMapsEntry| {
+        Path::new(&entry.path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
     };
 
-    Ok(ret.map(|(_, v)| v.clone()))
+    let mut entries = entries.into_iter();
+    if lib.starts_with('/') {
+        entries.find(|entry| entry.path == lib)
+    } else if lib.contains(".so") {
+        entries.find(|entry| file_name(entry).starts_with(lib))
+    } else {
+        let lib1 = lib.to_string() + ".so";
+        let lib2 = lib.to_string() + "-";
+        entries.find(|entry| {
+            let name = file_name(entry);
+            name.starts_with(&lib1) || name.starts_with(&lib2)
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -320,51 +724,79 @@ impl LdSoCache {
     }
 }
 
-#[derive(Error, Debug)]
-enum ResolveSymbolError {
-    #[error(transparent)]
-    Io(#[from] io::Error),
-
-    #[error("error parsing ELF")]
-    Object(#[from] object::Error),
-
-    #[error("unknown symbol `{0}`")]
-    Unknown(String),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPS: &str = "\
+55d0f9a00000-55d0f9a02000 r--p 00000000 00:2f 1577060                    /usr/bin/my app
+7f1c2f600000-7f1c2f628000 r--p 00000000 00:2f 1575236                    /usr/lib/x86_64-linux-gnu/libc.so.6
+7f1c2f900000-7f1c2f902000 r-xp 00002000 00:2f 1575240                    /usr/lib/libssl.so.3 (deleted)
+7f1c2fa00000-7f1c2fa21000 rw-p 00000000 00:00 0                          [heap]
+7ffd5e9f0000-7ffd5ea11000 rw-p 00000000 00:00 0
+";
+
+    #[test]
+    fn test_parse_proc_maps() {
+        assert_eq!(
+            parse_proc_maps(MAPS),
+            vec![
+                ProcMapsEntry {
+                    range: "55d0f9a00000-55d0f9a02000".to_owned(),
+                    dev: (0, 0x2f),
+                    inode: 1577060,
+                    path: "/usr/bin/my app".to_owned(),
+                    deleted: false,
+                },
+                ProcMapsEntry {
+                    range: "7f1c2f600000-7f1c2f628000".to_owned(),
+                    dev: (0, 0x2f),
+                    inode: 1575236,
+                    path: "/usr/lib/x86_64-linux-gnu/libc.so.6".to_owned(),
+                    deleted: false,
+                },
+                ProcMapsEntry {
+                    range: "7f1c2f900000-7f1c2f902000".to_owned(),
+                    dev: (0, 0x2f),
+                    inode: 1575240,
+                    path: "/usr/lib/libssl.so.3".to_owned(),
+                    deleted: true,
+                },
+            ]
+        );
+    }
 
-    #[error("symbol `{0}` does not appear in section")]
-    NotInSection(String),
+    #[test]
+    fn test_dev_numbers() {
+        assert_eq!(dev_numbers(0x2f), (0, 0x2f));
+        assert_eq!(dev_numbers(0x0803), (8, 3));
+        assert_eq!(dev_numbers(0x0000_1234_5678_9abc), (0x189a, 0x234567bc));
+    }
 
-    #[error("symbol `{0}` in section `{1:?}` which has no offset")]
-    SectionFileRangeNone(String, Result<String, object::Error>),
-}
+    #[test]
+    fn test_proc_root_error() {
+        // pid_max is at most 2^22, so this process doesn't exist
+        assert!(matches!(
+            proc_root(pid_t::MAX),
+            Err(UProbeError::FileError { filename, .. }) if filename == format!("/proc/{}/ns/mnt", pid_t::MAX)
+        ));
+    }
 
-fn resolve_symbol(path: &str, symbol: &str) -> Result<u64, ResolveSymbolError> {
-    let data = fs::read(path)?;
-    let obj = object::read::File::parse(&*data)?;
-
-    let sym = obj
-        .dynamic_symbols()
-        .chain(obj.symbols())
-        .find(|sym| sym.name().map(|name| name == symbol).unwrap_or(false))
-        .ok_or_else(|| ResolveSymbolError::Unknown(symbol.to_string()))?;
-
-    let needs_addr_translation = matches!(
-        obj.kind(),
-        object::ObjectKind::Dynamic | object::ObjectKind::Executable
-    );
-    if !needs_addr_translation {
-        Ok(sym.address())
-    } else {
-        let index = sym
-            .section_index()
-            .ok_or_else(|| ResolveSymbolError::NotInSection(symbol.to_string()))?;
-        let section = obj.section_by_index(index)?;
-        let (offset, _size) = section.file_range().ok_or_else(|| {
-            ResolveSymbolError::SectionFileRangeNone(
-                symbol.to_string(),
-                section.name().map(str::to_owned),
-            )
-        })?;
-        Ok(sym.address() - section.address() + offset)
+    #[test]
+    fn test_find_lib_in_maps() {
+        let find = |lib| find_lib_in_maps(parse_proc_maps(MAPS), lib).map(|entry| entry.path);
+
+        assert_eq!(
+            find("libc").as_deref(),
+            Some("/usr/lib/x86_64-linux-gnu/libc.so.6")
+        );
+        assert_eq!(
+            find("libc.so.6").as_deref(),
+            Some("/usr/lib/x86_64-linux-gnu/libc.so.6")
+        );
+        assert_eq!(find("libssl").as_deref(), Some("/usr/lib/libssl.so.3"));
+        assert_eq!(find("/usr/bin/my app").as_deref(), Some("/usr/bin/my app"));
+        assert_eq!(find("/usr/bin/other"), None);
+        assert_eq!(find("libcrypto"), None);
     }
 }
@@ -0,0 +1,756 @@
+//! User statically-defined tracing probes.
+use libc::pid_t;
+use object::{Architecture, Object, ObjectSection, ObjectSegment};
+use std::{
+    fs, io, mem,
+    os::unix::io::RawFd,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use thiserror::Error;
+
+use crate::{
+    generated::bpf_prog_type::BPF_PROG_TYPE_KPROBE,
+    maps::{HashMap, MapData},
+    programs::{
+        define_link_wrapper,
+        links::Link,
+        load_program,
+        perf_attach::{perf_attach_with_cookie, PerfLinkInner},
+        probe::{create_as_probe, ProbeKind},
+        uprobe::resolve_attach_path,
+        ProgramData, ProgramError,
+    },
+    sys::bpf_map_delete_elem,
+    Pod, FEATURES,
+};
+
+/// The name of the map holding the argument specs of the attached USDT probes.
+///
+/// The map is declared by `UsdtContext` in aya-bpf, and must be passed to [`Usdt::attach`].
+pub const USDT_SPEC_MAP: &str = "AYA_USDT_SPECS";
+
+/// The maximum number of arguments of a USDT probe.
+pub const USDT_MAX_ARGS: usize = 12;
+
+const STAPSDT_NOTE_SECTION: &str = ".note.stapsdt";
+const STAPSDT_BASE_SECTION: &str = ".stapsdt.base";
+const STAPSDT_NOTE_OWNER: &[u8] = b"stapsdt\0";
+const STAPSDT_NOTE_TYPE: u32 = 3;
+
+// The spec ids are used as the cookies of the perf links, so they must be unique among the
+// probes attached through the same spec map.
+static NEXT_SPEC_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A USDT probe.
+///
+/// USDT (user statically-defined tracing) probes are tracing points compiled into user space
+/// binaries, eg with the `DTRACE_PROBE` macros of `sys/sdt.h`. Each probe is described by an ELF
+/// note in the `.note.stapsdt` section of the binary, which gives its location and how to read
+/// its arguments. [`Usdt`] programs read the arguments with `UsdtContext::arg` in aya-bpf.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.15.
+///
+/// # Examples
+///
+/// ```no_run
+/// # let mut bpf = aya::Bpf::load(&[])?;
+/// use aya::{
+///     maps::HashMap,
+///     programs::{usdt::USDT_SPEC_MAP, Usdt},
+/// };
+///
+/// let mut specs = HashMap::try_from(bpf.take_map(USDT_SPEC_MAP).unwrap())?;
+/// let program: &mut Usdt = bpf.program_mut("query_start").unwrap().try_into()?;
+/// program.load()?;
+/// program.attach(&mut specs, "postgresql", "query__start", "/usr/bin/postgres", None, 0)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+#[doc(alias = "BPF_PROG_TYPE_KPROBE")]
+pub struct Usdt {
+    pub(crate) data: ProgramData<UsdtLink>,
+}
+
+impl Usdt {
+    /// Loads the program inside the kernel.
+    pub fn load(&mut self) -> Result<(), ProgramError> {
+        load_program(BPF_PROG_TYPE_KPROBE, &mut self.data)
+    }
+
+    /// Attaches the program to the USDT probe `provider:name` of `target`.
+    ///
+    /// `target` and `pid` are interpreted as in [`UProbe::attach`](crate::programs::UProbe::attach).
+    /// The program is attached to all the locations of the probe, incrementing its semaphore if
+    /// it has one. The argument specs of the locations are written to `specs`, which must be the
+    /// [`USDT_SPEC_MAP`] map of the object, and removed when the program is detached.
+    ///
+    /// `cookie` can be read by the program with `UsdtContext::cookie`.
+    ///
+    /// The returned value can be used to detach, see [Usdt::detach].
+    pub fn attach<T: AsMut<MapData>, P: AsRef<Path>>(
+        &mut self,
+        specs: &mut HashMap<T, u64, UsdtSpec>,
+        provider: &str,
+        name: &str,
+        target: P,
+        pid: Option<pid_t>,
+        cookie: u64,
+    ) -> Result<UsdtLinkId, ProgramError> {
+        if !FEATURES.bpf_perf_link {
+            return Err(UsdtError::CookiesUnsupported.into());
+        }
+        let prog_fd = self.data.fd_or_err()?;
+        let path = resolve_attach_path(target.as_ref(), pid)?;
+
+        let data = fs::read(&path).map_err(|io_error| UsdtError::FileError {
+            filename: path.clone(),
+            io_error,
+        })?;
+        let obj = object::read::File::parse(&*data).map_err(|error| UsdtError::ElfError {
+            filename: path.clone(),
+            error,
+        })?;
+        let probes = parse_usdt_probes(&obj)?
+            .into_iter()
+            .filter(|probe| probe.provider == provider && probe.name == name)
+            .map(|probe| {
+                let spec = UsdtSpec::new(obj.architecture(), &probe.args, cookie)?;
+                Ok((probe, spec))
+            })
+            .collect::<Result<Vec<_>, UsdtError>>()?;
+        if probes.is_empty() {
+            return Err(UsdtError::ProbeNotFound {
+                provider: provider.to_owned(),
+                name: name.to_owned(),
+            }
+            .into());
+        }
+
+        // keep our own reference to the spec map, to remove the specs on detach
+        let map_fd = specs.inner.as_mut().fd_or_err()?;
+        let spec_map_fd = unsafe { libc::fcntl(map_fd, libc::F_DUPFD_CLOEXEC, 0) };
+        if spec_map_fd < 0 {
+            return Err(ProgramError::SyscallError {
+                call: "fcntl".to_owned(),
+                io_error: io::Error::last_os_error(),
+            });
+        }
+        let mut link = UsdtLinkInner {
+            perf_links: Vec::with_capacity(probes.len()),
+            spec_map_fd,
+            spec_ids: Vec::with_capacity(probes.len()),
+        };
+
+        for (probe, spec) in probes {
+            let spec_id = NEXT_SPEC_ID.fetch_add(1, Ordering::Relaxed);
+            let perf_link = specs
+                .insert(spec_id, spec, 0)
+                .map_err(ProgramError::from)
+                .and_then(|()| {
+                    link.spec_ids.push(spec_id);
+                    let fd = create_as_probe(
+                        ProbeKind::UProbe,
+                        &path,
+                        probe.offset,
+                        probe.semaphore_offset,
+                        pid,
+                    )?;
+                    perf_attach_with_cookie(prog_fd, fd, spec_id)
+                });
+            match perf_link {
+                Ok(perf_link) => link.perf_links.push(perf_link),
+                Err(e) => {
+                    let _ = link.detach();
+                    return Err(e);
+                }
+            }
+        }
+
+        self.data.links.insert(UsdtLink::new(link))
+    }
+
+    /// Detaches the program.
+    ///
+    /// See [Usdt::attach].
+    pub fn detach(&mut self, link_id: UsdtLinkId) -> Result<(), ProgramError> {
+        self.data.links.remove(link_id)
+    }
+
+    /// Takes ownership of the link referenced by the provided link_id.
+    ///
+    /// The link will be detached on `Drop` and the caller is now responsible
+    /// for managing its lifetime.
+    pub fn take_link(&mut self, link_id: UsdtLinkId) -> Result<UsdtLink, ProgramError> {
+        self.data.take_link(link_id)
+    }
+}
+
+#[derive(Debug, Hash, Eq, PartialEq)]
+pub(crate) struct UsdtLinkIdInner(u64);
+
+#[derive(Debug)]
+pub(crate) struct UsdtLinkInner {
+    perf_links: Vec<PerfLinkInner>,
+    spec_map_fd: RawFd,
+    spec_ids: Vec<u64>,
+}
+
+impl Link for UsdtLinkInner {
+    type Id = UsdtLinkIdInner;
+
+    fn id(&self) -> Self::Id {
+        // spec ids are unique, and `attach` never creates a link without specs
+        UsdtLinkIdInner(self.spec_ids[0])
+    }
+
+    fn detach(self) -> Result<(), ProgramError> {
+        let mut result = Ok(());
+        for link in self.perf_links {
+            let res = link.detach();
+            if result.is_ok() {
+                result = res;
+            }
+        }
+        for spec_id in &self.spec_ids {
+            let _ = bpf_map_delete_elem(self.spec_map_fd, spec_id);
+        }
+        unsafe { libc::close(self.spec_map_fd) };
+        result
+    }
+}
+
+define_link_wrapper!(
+    /// The link used by [Usdt] programs.
+    UsdtLink,
+    /// The type returned by [Usdt::attach]. Can be passed to [Usdt::detach].
+    UsdtLinkId,
+    UsdtLinkInner,
+    UsdtLinkIdInner
+);
+
+/// The type returned when attaching a [`Usdt`] fails.
+#[derive(Debug, Error)]
+pub enum UsdtError {
+    /// There was an error accessing `filename`.
+    #[error("`{filename}`")]
+    FileError {
+        /// The file name
+        filename: String,
+        /// The [`io::Error`] returned from the file operation
+        #[source]
+        io_error: io::Error,
+    },
+
+    /// There was an error parsing the ELF binary `filename`.
+    #[error("error parsing ELF `{filename}`")]
+    ElfError {
+        /// The file name
+        filename: String,
+        /// The original error
+        #[source]
+        error: object::Error,
+    },
+
+    /// The binary doesn't contain the probe.
+    #[error("USDT probe `{provider}:{name}` not found")]
+    ProbeNotFound {
+        /// The provider of the probe
+        provider: String,
+        /// The name of the probe
+        name: String,
+    },
+
+    /// A `.note.stapsdt` note is malformed.
+    #[error("invalid USDT note: {error}")]
+    InvalidNote {
+        /// A description of the error
+        error: String,
+    },
+
+    /// The argument spec of a probe couldn't be parsed.
+    #[error("invalid USDT argument spec `{spec}`")]
+    InvalidArgSpec {
+        /// The argument spec
+        spec: String,
+    },
+
+    /// The probe has more than [`USDT_MAX_ARGS`] arguments.
+    #[error("USDT probe with {count} arguments, the maximum is {USDT_MAX_ARGS}")]
+    TooManyArgs {
+        /// The number of arguments
+        count: usize,
+    },
+
+    /// The argument specs of the architecture of the binary aren't supported.
+    #[error("USDT arguments are not supported on {architecture}")]
+    UnsupportedArchitecture {
+        /// The architecture of the binary
+        architecture: String,
+    },
+
+    /// The kernel doesn't support attaching programs with cookies, which are used to find the
+    /// argument specs of the probes.
+    #[error("USDT probes need perf links with cookies, which the kernel doesn't support")]
+    CookiesUnsupported,
+}
+
+/// A USDT probe found in the `.note.stapsdt` section of a binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsdtProbe {
+    /// The provider of the probe, eg `postgresql`
+    pub provider: String,
+    /// The name of the probe, eg `query__start`
+    pub name: String,
+    /// The offset of the probe location in the binary file
+    pub offset: u64,
+    /// The offset of the probe semaphore in the binary file, if the probe has one
+    pub semaphore_offset: Option<u64>,
+    /// The argument specs of the probe, eg `-4@%edi 8@-8(%rbp)`
+    pub args: String,
+}
+
+/// Returns the USDT probes of the binary at `path`.
+///
+/// A probe inlined in several places of the binary is returned once per location.
+pub fn usdt_probes<P: AsRef<Path>>(path: P) -> Result<Vec<UsdtProbe>, UsdtError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|io_error| UsdtError::FileError {
+        filename: path.display().to_string(),
+        io_error,
+    })?;
+    let obj = object::read::File::parse(&*data).map_err(|error| UsdtError::ElfError {
+        filename: path.display().to_string(),
+        error,
+    })?;
+    parse_usdt_probes(&obj)
+}
+
+fn parse_usdt_probes(obj: &object::read::File<'_>) -> Result<Vec<UsdtProbe>, UsdtError> {
+    let section = match obj.section_by_name(STAPSDT_NOTE_SECTION) {
+        Some(section) => section,
+        None => return Ok(Vec::new()),
+    };
+    let data = section.data().map_err(|error| UsdtError::InvalidNote {
+        error: error.to_string(),
+    })?;
+    let base = obj
+        .section_by_name(STAPSDT_BASE_SECTION)
+        .map(|section| section.address());
+    // the probe addresses are virtual addresses, translated to file offsets for the kernel
+    let segments = obj
+        .segments()
+        .map(|segment| {
+            let (offset, _) = segment.file_range();
+            (segment.address(), segment.size(), offset)
+        })
+        .collect::<Vec<_>>();
+    let file_offset = |address: u64| {
+        segments
+            .iter()
+            .find(|(start, size, _)| (*start..start + size).contains(&address))
+            .map(|(start, _, offset)| address - start + offset)
+            .ok_or_else(|| UsdtError::InvalidNote {
+                error: format!("address {address:#x} is not in a loaded segment"),
+            })
+    };
+
+    parse_notes(data, obj.is_little_endian(), obj.is_64())?
+        .into_iter()
+        .map(|note| {
+            let mut address = note.address;
+            // the binary was prelinked at a different address than the one of the note
+            if let (Some(base), true) = (base, note.base != 0) {
+                address = address.wrapping_add(base).wrapping_sub(note.base);
+            }
+            let semaphore_offset = match note.semaphore {
+                0 => None,
+                semaphore => Some(file_offset(semaphore)?),
+            };
+            Ok(UsdtProbe {
+                provider: note.provider,
+                name: note.name,
+                offset: file_offset(address)?,
+                semaphore_offset,
+                args: note.args,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct StapsdtNote {
+    address: u64,
+    base: u64,
+    semaphore: u64,
+    provider: String,
+    name: String,
+    args: String,
+}
+
+fn parse_notes(
+    data: &[u8],
+    little_endian: bool,
+    is_64: bool,
+) -> Result<Vec<StapsdtNote>, UsdtError> {
+    let invalid = |error: &str| UsdtError::InvalidNote {
+        error: error.to_owned(),
+    };
+    let read_u32 = |data: &[u8], offset: usize| {
+        let bytes = data
+            .get(offset..offset + 4)
+            .ok_or_else(|| invalid("truncated note"))?;
+        let bytes = bytes.try_into().unwrap();
+        Ok::<_, UsdtError>(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    let read_addr = |data: &[u8], offset: usize| {
+        if !is_64 {
+            return read_u32(data, offset).map(u64::from);
+        }
+        let bytes = data
+            .get(offset..offset + 8)
+            .ok_or_else(|| invalid("truncated note"))?;
+        let bytes = bytes.try_into().unwrap();
+        Ok(if little_endian {
+            u64::from_le_bytes(bytes)
+        } else {
+            u64::from_be_bytes(bytes)
+        })
+    };
+    let align = |size: usize| (size + 3) & !3;
+
+    let mut notes = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let name_size = read_u32(data, offset)? as usize;
+        let desc_size = read_u32(data, offset + 4)? as usize;
+        let note_type = read_u32(data, offset + 8)?;
+        let name_offset = offset + 12;
+        let desc_offset = name_offset + align(name_size);
+        offset = desc_offset + align(desc_size);
+
+        let name = data
+            .get(name_offset..name_offset + name_size)
+            .ok_or_else(|| invalid("truncated note"))?;
+        if name != STAPSDT_NOTE_OWNER || note_type != STAPSDT_NOTE_TYPE {
+            continue;
+        }
+        let desc = data
+            .get(desc_offset..desc_offset + desc_size)
+            .ok_or_else(|| invalid("truncated note"))?;
+
+        let addr_size = if is_64 { 8 } else { 4 };
+        let address = read_addr(desc, 0)?;
+        let base = read_addr(desc, addr_size)?;
+        let semaphore = read_addr(desc, 2 * addr_size)?;
+        let mut strings = desc
+            .get(3 * addr_size..)
+            .ok_or_else(|| invalid("truncated note"))?
+            .split(|b| *b == 0)
+            .map(|s| String::from_utf8_lossy(s).into_owned());
+        let (provider, name) = match (strings.next(), strings.next()) {
+            (Some(provider), Some(name)) => (provider, name),
+            _ => return Err(invalid("missing probe name")),
+        };
+        notes.push(StapsdtNote {
+            address,
+            base,
+            semaphore,
+            provider,
+            name,
+            args: strings.next().unwrap_or_default(),
+        });
+    }
+
+    Ok(notes)
+}
+
+// The layout of the argument specs must be kept in sync with `UsdtContext` in aya-bpf.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UsdtArgType {
+    Const = 0,
+    Reg = 1,
+    RegDeref = 2,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct UsdtArgSpec {
+    // the constant value, or the offset added to the register before dereferencing it
+    val_off: u64,
+    arg_type: u32,
+    // the offset of the register in `pt_regs`
+    reg_off: i16,
+    arg_signed: u8,
+    // the shifts applied to the 64 bits value to keep only the size of the argument
+    arg_bitshift: u8,
+}
+
+/// How to read the arguments of a USDT probe location, stored in the [`USDT_SPEC_MAP`] map.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsdtSpec {
+    args: [UsdtArgSpec; USDT_MAX_ARGS],
+    cookie: u64,
+    arg_count: u64,
+}
+
+unsafe impl Pod for UsdtSpec {}
+
+impl UsdtSpec {
+    fn new(architecture: Architecture, args: &str, cookie: u64) -> Result<UsdtSpec, UsdtError> {
+        let reg_offset = match architecture {
+            Architecture::X86_64 => x86_64_reg_offset,
+            Architecture::Aarch64 => aarch64_reg_offset,
+            architecture => {
+                return Err(UsdtError::UnsupportedArchitecture {
+                    architecture: format!("{architecture:?}"),
+                })
+            }
+        };
+
+        let specs = args.split_whitespace().collect::<Vec<_>>();
+        if specs.len() > USDT_MAX_ARGS {
+            return Err(UsdtError::TooManyArgs { count: specs.len() });
+        }
+        let mut spec = UsdtSpec {
+            cookie,
+            arg_count: specs.len() as u64,
+            ..Default::default()
+        };
+        for (arg, s) in spec.args.iter_mut().zip(specs) {
+            *arg = parse_arg_spec(architecture, reg_offset, s)
+                .ok_or_else(|| UsdtError::InvalidArgSpec { spec: s.to_owned() })?;
+        }
+
+        Ok(spec)
+    }
+}
+
+// Parses an argument spec, eg `-4@%edi`, `8@-8(%rbp)` or `4@$5` on x86_64, or `-4@x0`,
+// `8@[sp, 16]` or `4@5` on aarch64.
+fn parse_arg_spec(
+    architecture: Architecture,
+    reg_offset: fn(&str) -> Option<i16>,
+    spec: &str,
+) -> Option<UsdtArgSpec> {
+    let (size, operand) = spec.split_once('@')?;
+    let size = size.parse::<i32>().ok()?;
+    let bits = match size.unsigned_abs() {
+        n @ (1 | 2 | 4 | 8) => n * 8,
+        _ => return None,
+    };
+
+    let (arg_type, reg, val_off) = if architecture == Architecture::X86_64 {
+        if let Some(value) = operand.strip_prefix('$') {
+            (UsdtArgType::Const, None, parse_int(value)?)
+        } else if let Some(reg) = operand.strip_prefix('%') {
+            (UsdtArgType::Reg, Some(reg), 0)
+        } else {
+            let (offset, reg) = operand.strip_suffix(')')?.split_once('(')?;
+            let offset = if offset.is_empty() {
+                0
+            } else {
+                parse_int(offset)?
+            };
+            (UsdtArgType::RegDeref, Some(reg.strip_prefix('%')?), offset)
+        }
+    } else if let Some(operand) = operand.strip_prefix('[') {
+        let operand = operand.strip_suffix(']')?;
+        match operand.split_once(',') {
+            Some((reg, offset)) => (
+                UsdtArgType::RegDeref,
+                Some(reg.trim()),
+                parse_int(offset.trim())?,
+            ),
+            None => (UsdtArgType::RegDeref, Some(operand.trim()), 0),
+        }
+    } else if operand.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        (UsdtArgType::Const, None, parse_int(operand)?)
+    } else {
+        (UsdtArgType::Reg, Some(operand), 0)
+    };
+
+    let reg_off = match reg {
+        Some(reg) => reg_offset(reg)?,
+        None => 0,
+    };
+    Some(UsdtArgSpec {
+        val_off: val_off as u64,
+        arg_type: arg_type as u32,
+        reg_off,
+        arg_signed: u8::from(size < 0),
+        arg_bitshift: (64 - bits) as u8,
+    })
+}
+
+fn parse_int(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let value = match s.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => s.parse::<i64>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+// Offsets of the registers in the x86_64 `struct pt_regs`.
+fn x86_64_reg_offset(reg: &str) -> Option<i16> {
+    let index = match reg {
+        "r15" | "r15d" | "r15w" | "r15b" => 0,
+        "r14" | "r14d" | "r14w" | "r14b" => 1,
+        "r13" | "r13d" | "r13w" | "r13b" => 2,
+        "r12" | "r12d" | "r12w" | "r12b" => 3,
+        "rbp" | "ebp" | "bp" | "bpl" => 4,
+        "rbx" | "ebx" | "bx" | "bl" => 5,
+        "r11" | "r11d" | "r11w" | "r11b" => 6,
+        "r10" | "r10d" | "r10w" | "r10b" => 7,
+        "r9" | "r9d" | "r9w" | "r9b" => 8,
+        "r8" | "r8d" | "r8w" | "r8b" => 9,
+        "rax" | "eax" | "ax" | "al" => 10,
+        "rcx" | "ecx" | "cx" | "cl" => 11,
+        "rdx" | "edx" | "dx" | "dl" => 12,
+        "rsi" | "esi" | "si" | "sil" => 13,
+        "rdi" | "edi" | "di" | "dil" => 14,
+        "rip" | "eip" => 16,
+        "rsp" | "esp" | "sp" | "spl" => 19,
+        _ => return None,
+    };
+    Some(index * mem::size_of::<u64>() as i16)
+}
+
+// Offsets of the registers in the aarch64 `struct user_pt_regs`.
+fn aarch64_reg_offset(reg: &str) -> Option<i16> {
+    let index = match reg {
+        "sp" => 31,
+        reg => {
+            let index = reg
+                .strip_prefix('x')
+                .or_else(|| reg.strip_prefix('w'))?
+                .parse::<i16>()
+                .ok()?;
+            if !(0..=30).contains(&index) {
+                return None;
+            }
+            index
+        }
+    };
+    Some(index * mem::size_of::<u64>() as i16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arg(arg_type: UsdtArgType, reg_off: i16, val_off: i64, size: i32) -> UsdtArgSpec {
+        UsdtArgSpec {
+            val_off: val_off as u64,
+            arg_type: arg_type as u32,
+            reg_off,
+            arg_signed: u8::from(size < 0),
+            arg_bitshift: (64 - size.unsigned_abs() * 8) as u8,
+        }
+    }
+
+    #[test]
+    fn test_parse_arg_spec_x86_64() {
+        let parse = |spec| parse_arg_spec(Architecture::X86_64, x86_64_reg_offset, spec);
+
+        assert_eq!(parse("-4@%edi"), Some(arg(UsdtArgType::Reg, 112, 0, -4)));
+        assert_eq!(
+            parse("8@-8(%rbp)"),
+            Some(arg(UsdtArgType::RegDeref, 32, -8, 8))
+        );
+        assert_eq!(
+            parse("2@(%rsp)"),
+            Some(arg(UsdtArgType::RegDeref, 152, 0, 2))
+        );
+        assert_eq!(parse("1@$0x10"), Some(arg(UsdtArgType::Const, 0, 16, 1)));
+        assert_eq!(parse("-8@$-5"), Some(arg(UsdtArgType::Const, 0, -5, -8)));
+        assert_eq!(parse("4@%r15d"), Some(arg(UsdtArgType::Reg, 0, 0, 4)));
+        assert_eq!(parse("3@%eax"), None);
+        assert_eq!(parse("4@%xmm0"), None);
+        assert_eq!(parse("8@8(%rax,%rbx,4)"), None);
+    }
+
+    #[test]
+    fn test_parse_arg_spec_aarch64() {
+        let parse = |spec| parse_arg_spec(Architecture::Aarch64, aarch64_reg_offset, spec);
+
+        assert_eq!(parse("-4@x1"), Some(arg(UsdtArgType::Reg, 8, 0, -4)));
+        assert_eq!(
+            parse("8@[sp, 16]"),
+            Some(arg(UsdtArgType::RegDeref, 248, 16, 8))
+        );
+        assert_eq!(
+            parse("8@[x29]"),
+            Some(arg(UsdtArgType::RegDeref, 232, 0, 8))
+        );
+        assert_eq!(parse("4@-3"), Some(arg(UsdtArgType::Const, 0, -3, 4)));
+        assert_eq!(parse("8@x31"), None);
+    }
+
+    #[test]
+    fn test_usdt_spec() {
+        let spec = UsdtSpec::new(Architecture::X86_64, "-4@%edi 8@%rsi", 42).unwrap();
+        assert_eq!(spec.arg_count, 2);
+        assert_eq!(spec.cookie, 42);
+        assert_eq!(spec.args[1], arg(UsdtArgType::Reg, 104, 0, 8));
+
+        let args = vec!["8@%rax"; USDT_MAX_ARGS + 1].join(" ");
+        assert!(matches!(
+            UsdtSpec::new(Architecture::X86_64, &args, 0),
+            Err(UsdtError::TooManyArgs { count: 13 })
+        ));
+        assert!(matches!(
+            UsdtSpec::new(Architecture::Riscv64, "8@a0", 0),
+            Err(UsdtError::UnsupportedArchitecture { .. })
+        ));
+        assert_eq!(mem::size_of::<UsdtSpec>(), 16 * USDT_MAX_ARGS + 16);
+    }
+
+    #[test]
+    fn test_parse_notes() {
+        let mut data = Vec::new();
+        let mut push_note = |owner: &[u8], note_type: u32, desc: &[u8]| {
+            data.extend_from_slice(&(owner.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+            data.extend_from_slice(&note_type.to_le_bytes());
+            data.extend_from_slice(owner);
+            data.resize((data.len() + 3) & !3, 0);
+            data.extend_from_slice(desc);
+            data.resize((data.len() + 3) & !3, 0);
+        };
+        let mut desc = Vec::new();
+        for addr in [0x1234u64, 0x5000, 0x6010] {
+            desc.extend_from_slice(&addr.to_le_bytes());
+        }
+        desc.extend_from_slice(b"postgresql\0query__start\0-4@%edi 8@-8(%rbp)\0");
+        push_note(b"GNU\0", 3, &[0; 16]);
+        push_note(STAPSDT_NOTE_OWNER, STAPSDT_NOTE_TYPE, &desc);
+
+        assert_eq!(
+            parse_notes(&data, true, true).unwrap(),
+            vec![StapsdtNote {
+                address: 0x1234,
+                base: 0x5000,
+                semaphore: 0x6010,
+                provider: "postgresql".to_owned(),
+                name: "query__start".to_owned(),
+                args: "-4@%edi 8@-8(%rbp)".to_owned(),
+            }]
+        );
+        assert!(matches!(
+            parse_notes(&data[..data.len() - 8], true, true),
+            Err(UsdtError::InvalidNote { .. })
+        ));
+    }
+}
@@ -1,7 +1,9 @@
 //! Common functions shared between multiple eBPF program types.
-use std::{ffi::CStr, io, os::unix::io::RawFd, path::Path};
+use std::{ffi::CStr, io, mem, os::unix::io::RawFd, path::Path};
 
 use crate::{
+    generated::bpf_attach_type,
+    maps::CgroupStorageKey,
     programs::{FdLink, Link, ProgramData, ProgramError},
     sys::bpf_raw_tracepoint_open,
 };
@@ -45,3 +47,19 @@ pub(crate) fn find_tracefs_path() -> Result<&'static Path, ProgramError> {
         .as_deref()
         .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "tracefs not found").into())
 }
+
+/// Returns the key of the cgroup storage created when a program is attached to `cgroup_fd`.
+pub(crate) fn cgroup_storage_key(
+    cgroup_fd: RawFd,
+    attach_type: bpf_attach_type,
+) -> Result<CgroupStorageKey, ProgramError> {
+    let mut stat = unsafe { mem::zeroed::<libc::stat>() };
+    if unsafe { libc::fstat(cgroup_fd, &mut stat) } < 0 {
+        return Err(ProgramError::SyscallError {
+            call: "fstat".to_owned(),
+            io_error: io::Error::last_os_error(),
+        });
+    }
+
+    Ok(CgroupStorageKey::new(stat.st_ino, attach_type as u32))
+}
@@ -1,17 +1,18 @@
 //! eXpress Data Path (XDP) programs.
+pub use aya_obj::programs::XdpAttachType;
 use bitflags;
 use libc::if_nametoindex;
-use std::{convert::TryFrom, ffi::CString, hash::Hash, io, mem, os::unix::io::RawFd};
+use std::{convert::TryFrom, ffi::CString, hash::Hash, io, mem, os::unix::io::RawFd, path::Path};
 use thiserror::Error;
 
 use crate::{
+    af_xdp::XskSocket,
     generated::{
-        bpf_attach_type::{self, BPF_XDP},
-        bpf_link_type,
-        bpf_prog_type::BPF_PROG_TYPE_XDP,
+        bpf_attach_type::BPF_XDP, bpf_link_type, bpf_prog_type::BPF_PROG_TYPE_XDP,
         XDP_FLAGS_DRV_MODE, XDP_FLAGS_HW_MODE, XDP_FLAGS_REPLACE, XDP_FLAGS_SKB_MODE,
         XDP_FLAGS_UPDATE_IF_NOEXIST,
     },
+    maps::{MapData, XskMap},
     programs::{
         define_link_wrapper, load_program, FdLink, Link, LinkError, ProgramData, ProgramError,
     },
@@ -75,12 +76,13 @@ bitflags! {
 #[doc(alias = "BPF_PROG_TYPE_XDP")]
 pub struct Xdp {
     pub(crate) data: ProgramData<XdpLink>,
+    pub(crate) attach_type: XdpAttachType,
 }
 
 impl Xdp {
     /// Loads the program inside the kernel.
     pub fn load(&mut self) -> Result<(), ProgramError> {
-        self.data.expected_attach_type = Some(bpf_attach_type::BPF_XDP);
+        self.data.expected_attach_type = Some(self.attach_type.into());
         load_program(BPF_PROG_TYPE_XDP, &mut self.data)
     }
 
@@ -98,7 +100,6 @@ impl Xdp {
     /// [`XdpError::NetlinkError`] is returned for older
     /// kernels.
     pub fn attach(&mut self, interface: &str, flags: XdpFlags) -> Result<XdpLinkId, ProgramError> {
-        let prog_fd = self.data.fd_or_err()?;
         let c_interface = CString::new(interface).unwrap();
         let if_index = unsafe { if_nametoindex(c_interface.as_ptr()) } as RawFd;
         if if_index == 0 {
@@ -107,6 +108,65 @@ impl Xdp {
             });
         }
 
+        self.attach_to_if_index(if_index, flags)
+    }
+
+    /// Attaches the program to the interface of an AF_XDP `socket`, and stores the socket in
+    /// `sockets` at the index of the queue it is bound to.
+    ///
+    /// The program can then deliver the packets to the socket of their receive queue with
+    /// `bpf_redirect_map(sockets, ctx.rx_queue_index(), 0)`. The sockets bound to the other
+    /// queues of the interface can be added with [`XskMap::set`].
+    ///
+    /// The returned value can be used to detach, see [Xdp::detach]. Detaching doesn't remove the
+    /// socket from `sockets`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ProgramError::MapError`] if the socket can't be stored in `sockets`, and the
+    /// errors of [`Xdp::attach`] if attaching fails, in which case the socket is removed from
+    /// `sockets`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # let mut bpf = aya::Bpf::load(&[])?;
+    /// use aya::{
+    ///     af_xdp::{Umem, UmemConfig, XskSocket, XskSocketConfig},
+    ///     maps::XskMap,
+    ///     programs::{Xdp, XdpFlags},
+    /// };
+    ///
+    /// let mut sockets = XskMap::try_from(bpf.take_map("SOCKETS").unwrap())?;
+    /// let umem = Umem::new(UmemConfig::default())?;
+    /// let socket = XskSocket::new(umem, "eth0", 0, XskSocketConfig::default())?;
+    ///
+    /// let program: &mut Xdp = bpf.program_mut("redirect_sock").unwrap().try_into()?;
+    /// program.load()?;
+    /// program.attach_to_socket(&socket, &mut sockets, XdpFlags::default())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn attach_to_socket<T: AsMut<MapData>>(
+        &mut self,
+        socket: &XskSocket,
+        sockets: &mut XskMap<T>,
+        flags: XdpFlags,
+    ) -> Result<XdpLinkId, ProgramError> {
+        let queue_id = socket.queue_id();
+        sockets.set(queue_id, socket, 0)?;
+        let link_id = self.attach_to_if_index(socket.if_index() as RawFd, flags);
+        if link_id.is_err() {
+            let _ = sockets.unset(queue_id);
+        }
+        link_id
+    }
+
+    fn attach_to_if_index(
+        &mut self,
+        if_index: RawFd,
+        flags: XdpFlags,
+    ) -> Result<XdpLinkId, ProgramError> {
+        let prog_fd = self.data.fd_or_err()?;
         let k_ver = kernel_version().unwrap();
         if k_ver >= (5, 9, 0) {
             let link_fd = bpf_link_create(prog_fd, if_index, BPF_XDP, None, flags.bits).map_err(
@@ -188,6 +248,32 @@ impl Xdp {
             }
         }
     }
+
+    /// Creates a program from a pinned entry on a bpffs.
+    ///
+    /// Existing links will not be populated. To work with existing links you should use [`crate::programs::links::PinnedLink`].
+    ///
+    /// On drop, any managed links are detached and the program is unloaded. This will not result in
+    /// the program being unloaded from the kernel if it is still pinned.
+    ///
+    /// The program is assumed to attach to interfaces, use
+    /// [`from_pin_with_attach_type`](Self::from_pin_with_attach_type) for programs stored in a
+    /// [`DevMap`](crate::maps::DevMap), [`DevMapHash`](crate::maps::DevMapHash) or
+    /// [`CpuMap`](crate::maps::CpuMap).
+    pub fn from_pin<P: AsRef<Path>>(path: P) -> Result<Self, ProgramError> {
+        Self::from_pin_with_attach_type(path, XdpAttachType::Interface)
+    }
+
+    /// Creates a program with the given attach type from a pinned entry on a bpffs.
+    ///
+    /// See [`from_pin`](Self::from_pin).
+    pub fn from_pin_with_attach_type<P: AsRef<Path>>(
+        path: P,
+        attach_type: XdpAttachType,
+    ) -> Result<Self, ProgramError> {
+        let data = ProgramData::from_pinned_path(path)?;
+        Ok(Self { data, attach_type })
+    }
 }
 
 #[derive(Debug)]
@@ -7,19 +7,19 @@ use std::{
     slice,
 };
 
-use libc::{c_char, c_long, close, ENOENT, ENOSPC};
+use libc::{c_char, c_long, close, pid_t, ENOENT, ENOSPC};
 
 use crate::{
     generated::{
         bpf_attach_type, bpf_attr, bpf_btf_info, bpf_cmd, bpf_insn, bpf_link_info, bpf_map_info,
-        bpf_map_type, bpf_prog_info, bpf_prog_type, BPF_F_REPLACE,
+        bpf_map_type, bpf_prog_info, bpf_prog_type, BPF_F_MMAPABLE, BPF_F_REPLACE,
     },
     maps::PerCpuValues,
     obj::{
         self,
         btf::{
-            BtfParam, BtfType, DataSec, DataSecEntry, DeclTag, Float, Func, FuncLinkage, FuncProto,
-            FuncSecInfo, Int, IntEncoding, LineSecInfo, Ptr, TypeTag, Var, VarLinkage,
+            Array, BtfParam, BtfType, DataSec, DataSecEntry, DeclTag, Float, Func, FuncLinkage,
+            FuncProto, FuncSecInfo, Int, IntEncoding, LineSecInfo, Ptr, TypeTag, Var, VarLinkage,
         },
         copy_instructions,
     },
@@ -28,7 +28,12 @@ use crate::{
     Btf, Pod, BPF_OBJ_NAME_LEN,
 };
 
-pub(crate) fn bpf_create_map(name: &CStr, def: &obj::Map, btf_fd: Option<RawFd>) -> SysResult {
+pub(crate) fn bpf_create_map(
+    name: &CStr,
+    def: &obj::Map,
+    btf_fd: Option<RawFd>,
+    inner_map_fd: Option<RawFd>,
+) -> SysResult {
     let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
 
     let u = unsafe { &mut attr.__bindgen_anon_1 };
@@ -37,6 +42,29 @@ pub(crate) fn bpf_create_map(name: &CStr, def: &obj::Map, btf_fd: Option<RawFd>)
     u.value_size = def.value_size();
     u.max_entries = def.max_entries();
     u.map_flags = def.map_flags();
+    if let Some(fd) = inner_map_fd {
+        u.inner_map_fd = fd as u32;
+    }
+
+    // the BTF describing the key and value of a local storage map declared without BTF
+    let mut storage_btf_fd = None;
+    if let obj::Map::Legacy(_) = def {
+        use bpf_map_type::*;
+
+        if let Ok(
+            BPF_MAP_TYPE_SK_STORAGE
+            | BPF_MAP_TYPE_INODE_STORAGE
+            | BPF_MAP_TYPE_TASK_STORAGE
+            | BPF_MAP_TYPE_CGRP_STORAGE,
+        ) = u.map_type.try_into()
+        {
+            let fd = load_local_storage_btf(def.value_size())?;
+            u.btf_fd = fd as u32;
+            u.btf_key_type_id = LOCAL_STORAGE_KEY_TYPE_ID;
+            u.btf_value_type_id = LOCAL_STORAGE_VALUE_TYPE_ID;
+            storage_btf_fd = Some(fd);
+        }
+    }
 
     if let obj::Map::Btf(m) = def {
         use bpf_map_type::*;
@@ -58,11 +86,24 @@ pub(crate) fn bpf_create_map(name: &CStr, def: &obj::Map, btf_fd: Option<RawFd>)
             | Ok(BPF_MAP_TYPE_SOCKHASH)
             | Ok(BPF_MAP_TYPE_QUEUE)
             | Ok(BPF_MAP_TYPE_STACK)
-            | Ok(BPF_MAP_TYPE_RINGBUF) => {
+            | Ok(BPF_MAP_TYPE_RINGBUF)
+            | Ok(BPF_MAP_TYPE_USER_RINGBUF) => {
                 u.btf_key_type_id = 0;
                 u.btf_value_type_id = 0;
                 u.btf_fd = 0;
             }
+            // the value of a struct_ops map is described by the kernel BTF, but the kernel
+            // still requires the object BTF
+            Ok(BPF_MAP_TYPE_STRUCT_OPS) => {
+                u.btf_key_type_id = 0;
+                u.btf_value_type_id = 0;
+                u.btf_vmlinux_value_type_id = def
+                    .struct_ops()
+                    .and_then(|struct_ops| struct_ops.layout.as_ref())
+                    .map(|layout| layout.value_type_id)
+                    .unwrap_or_default();
+                u.btf_fd = btf_fd.unwrap() as u32;
+            }
             _ => {
                 u.btf_key_type_id = m.def.btf_key_type_id;
                 u.btf_value_type_id = m.def.btf_value_type_id;
@@ -82,7 +123,41 @@ pub(crate) fn bpf_create_map(name: &CStr, def: &obj::Map, btf_fd: Option<RawFd>)
             .copy_from_slice(unsafe { slice::from_raw_parts(name.as_ptr(), name_len) });
     }
 
-    sys_bpf(bpf_cmd::BPF_MAP_CREATE, &attr)
+    let ret = sys_bpf(bpf_cmd::BPF_MAP_CREATE, &attr);
+    // the map holds a reference to the BTF
+    if let Some(fd) = storage_btf_fd {
+        unsafe { close(fd) };
+    }
+    ret
+}
+
+const LOCAL_STORAGE_KEY_TYPE_ID: u32 = 1;
+const LOCAL_STORAGE_VALUE_TYPE_ID: u32 = 3;
+
+// Local storage maps can't be created without BTF for their key and value, which must be an int
+// and a type of the size of the value. Legacy map definitions don't have any, so describe the
+// value as an array of bytes.
+fn load_local_storage_btf(value_size: u32) -> Result<RawFd, (c_long, io::Error)> {
+    let mut btf = Btf::new();
+    let name_offset = btf.add_string("int".to_string());
+    let int_type = btf.add_type(BtfType::Int(Int::new(
+        name_offset,
+        4,
+        IntEncoding::Signed,
+        0,
+    )));
+    let name_offset = btf.add_string("u8".to_string());
+    let byte_type = btf.add_type(BtfType::Int(Int::new(name_offset, 1, IntEncoding::None, 0)));
+    let value_type = btf.add_type(BtfType::Array(Array::new(
+        0, byte_type, int_type, value_size,
+    )));
+    debug_assert_eq!(
+        (int_type, value_type),
+        (LOCAL_STORAGE_KEY_TYPE_ID, LOCAL_STORAGE_VALUE_TYPE_ID)
+    );
+
+    let mut logger = VerifierLog::new();
+    bpf_load_btf(&btf.to_bytes(), &mut logger).map(|fd| fd as RawFd)
 }
 
 pub(crate) fn bpf_pin_object(fd: RawFd, path: &CStr) -> SysResult {
@@ -106,7 +181,7 @@ pub(crate) struct BpfLoadProgramAttrs<'a> {
     pub(crate) insns: &'a [bpf_insn],
     pub(crate) license: &'a CStr,
     pub(crate) kernel_version: u32,
-    pub(crate) expected_attach_type: Option<bpf_attach_type>,
+    pub(crate) expected_attach_type: Option<u32>,
     pub(crate) prog_btf_fd: Option<RawFd>,
     pub(crate) attach_btf_obj_fd: Option<u32>,
     pub(crate) attach_btf_id: Option<u32>,
@@ -140,7 +215,7 @@ pub(crate) fn bpf_load_program(
     u.prog_flags = aya_attr.flags;
     u.prog_type = aya_attr.ty as u32;
     if let Some(v) = aya_attr.expected_attach_type {
-        u.expected_attach_type = v as u32;
+        u.expected_attach_type = v;
     }
     u.insns = aya_attr.insns.as_ptr() as u64;
     u.insn_cnt = aya_attr.insns.len() as u32;
@@ -348,6 +423,163 @@ pub(crate) fn bpf_map_get_next_key<K: Pod>(
     }
 }
 
+pub(crate) fn bpf_map_get_next_key_bytes(
+    fd: RawFd,
+    key: Option<&[u8]>,
+    next_key: &mut [u8],
+) -> Result<Option<()>, (c_long, io::Error)> {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    let u = unsafe { &mut attr.__bindgen_anon_2 };
+    u.map_fd = fd as u32;
+    if let Some(key) = key {
+        u.key = key.as_ptr() as u64;
+    }
+    u.__bindgen_anon_1.next_key = next_key.as_mut_ptr() as u64;
+
+    match sys_bpf(bpf_cmd::BPF_MAP_GET_NEXT_KEY, &attr) {
+        Ok(_) => Ok(Some(())),
+        Err((_, io_error)) if io_error.raw_os_error() == Some(ENOENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn bpf_map_lookup_elem_bytes(
+    fd: RawFd,
+    key: &[u8],
+    value: &mut [u8],
+    flags: u64,
+) -> Result<Option<()>, (c_long, io::Error)> {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    let u = unsafe { &mut attr.__bindgen_anon_2 };
+    u.map_fd = fd as u32;
+    u.key = key.as_ptr() as u64;
+    u.__bindgen_anon_1.value = value.as_mut_ptr() as u64;
+    u.flags = flags;
+
+    match sys_bpf(bpf_cmd::BPF_MAP_LOOKUP_ELEM, &attr) {
+        Ok(_) => Ok(Some(())),
+        Err((_, io_error)) if io_error.raw_os_error() == Some(ENOENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn map_batch(
+    cmd: bpf_cmd,
+    fd: RawFd,
+    in_batch: Option<&[u8]>,
+    out_batch: Option<&mut [u8]>,
+    keys: *const u8,
+    values: *const u8,
+    count: &mut u32,
+    elem_flags: u64,
+) -> SysResult {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    let u = unsafe { &mut attr.batch };
+    u.map_fd = fd as u32;
+    if let Some(in_batch) = in_batch {
+        u.in_batch = in_batch.as_ptr() as u64;
+    }
+    if let Some(out_batch) = out_batch {
+        u.out_batch = out_batch.as_mut_ptr() as u64;
+    }
+    u.keys = keys as u64;
+    u.values = values as u64;
+    u.count = *count;
+    u.elem_flags = elem_flags;
+
+    let ret = sys_bpf(cmd, &attr);
+    // the kernel sets count to the number of elements processed, also on failure
+    *count = unsafe { attr.batch.count };
+    ret
+}
+
+// since kernel 5.6
+pub(crate) fn bpf_map_lookup_batch(
+    fd: RawFd,
+    in_batch: Option<&[u8]>,
+    out_batch: &mut [u8],
+    keys: &mut [u8],
+    values: &mut [u8],
+    count: &mut u32,
+    elem_flags: u64,
+) -> SysResult {
+    map_batch(
+        bpf_cmd::BPF_MAP_LOOKUP_BATCH,
+        fd,
+        in_batch,
+        Some(out_batch),
+        keys.as_mut_ptr(),
+        values.as_mut_ptr(),
+        count,
+        elem_flags,
+    )
+}
+
+// since kernel 5.6
+pub(crate) fn bpf_map_lookup_and_delete_batch(
+    fd: RawFd,
+    in_batch: Option<&[u8]>,
+    out_batch: &mut [u8],
+    keys: &mut [u8],
+    values: &mut [u8],
+    count: &mut u32,
+    elem_flags: u64,
+) -> SysResult {
+    map_batch(
+        bpf_cmd::BPF_MAP_LOOKUP_AND_DELETE_BATCH,
+        fd,
+        in_batch,
+        Some(out_batch),
+        keys.as_mut_ptr(),
+        values.as_mut_ptr(),
+        count,
+        elem_flags,
+    )
+}
+
+// since kernel 5.6
+pub(crate) fn bpf_map_update_batch(
+    fd: RawFd,
+    keys: &[u8],
+    values: &[u8],
+    count: &mut u32,
+    elem_flags: u64,
+) -> SysResult {
+    map_batch(
+        bpf_cmd::BPF_MAP_UPDATE_BATCH,
+        fd,
+        None,
+        None,
+        keys.as_ptr(),
+        values.as_ptr(),
+        count,
+        elem_flags,
+    )
+}
+
+// since kernel 5.6
+pub(crate) fn bpf_map_delete_batch(
+    fd: RawFd,
+    keys: &[u8],
+    count: &mut u32,
+    elem_flags: u64,
+) -> SysResult {
+    map_batch(
+        bpf_cmd::BPF_MAP_DELETE_BATCH,
+        fd,
+        None,
+        None,
+        keys.as_ptr(),
+        std::ptr::null(),
+        count,
+        elem_flags,
+    )
+}
+
 // since kernel 5.2
 pub(crate) fn bpf_map_freeze(fd: RawFd) -> SysResult {
     let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
@@ -377,6 +609,119 @@ pub(crate) fn bpf_link_create(
     sys_bpf(bpf_cmd::BPF_LINK_CREATE, &attr)
 }
 
+// since kernel 5.15
+pub(crate) fn bpf_link_create_perf_event(prog_fd: RawFd, perf_fd: RawFd, cookie: u64) -> SysResult {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    attr.link_create.prog_fd = prog_fd as u32;
+    attr.link_create.__bindgen_anon_1.target_fd = perf_fd as u32;
+    attr.link_create.attach_type = bpf_attach_type::BPF_PERF_EVENT as u32;
+    attr.link_create.__bindgen_anon_2.perf_event.bpf_cookie = cookie;
+
+    sys_bpf(bpf_cmd::BPF_LINK_CREATE, &attr)
+}
+
+// since kernel 5.18
+pub(crate) fn bpf_link_create_kprobe_multi(
+    prog_fd: RawFd,
+    symbols: &[CString],
+    cookies: Option<&[u64]>,
+    flags: u32,
+) -> SysResult {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    let syms = symbols.iter().map(|s| s.as_ptr()).collect::<Vec<_>>();
+
+    attr.link_create.prog_fd = prog_fd as u32;
+    attr.link_create.attach_type = bpf_attach_type::BPF_TRACE_KPROBE_MULTI as u32;
+    let kprobe_multi = unsafe { &mut attr.link_create.__bindgen_anon_2.kprobe_multi };
+    kprobe_multi.flags = flags;
+    kprobe_multi.cnt = syms.len() as u32;
+    kprobe_multi.syms = syms.as_ptr() as u64;
+    if let Some(cookies) = cookies {
+        kprobe_multi.cookies = cookies.as_ptr() as u64;
+    }
+
+    sys_bpf(bpf_cmd::BPF_LINK_CREATE, &attr)
+}
+
+// The `BPF_TRACE_UPROBE_MULTI` attach type and the `uprobe_multi` member of
+// `bpf_attr.link_create`, missing from the generated bindings.
+pub(crate) const BPF_TRACE_UPROBE_MULTI: u32 = 48;
+pub(crate) const BPF_F_UPROBE_MULTI_RETURN: u32 = 1;
+
+#[repr(C)]
+struct bpf_link_create_uprobe_multi {
+    prog_fd: u32,
+    target_fd: u32,
+    attach_type: u32,
+    flags: u32,
+    path: u64,
+    offsets: u64,
+    ref_ctr_offsets: u64,
+    cookies: u64,
+    cnt: u32,
+    uprobe_flags: u32,
+    pid: u32,
+}
+
+// since kernel 6.6
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn bpf_link_create_uprobe_multi(
+    prog_fd: RawFd,
+    path: &CStr,
+    offsets: &[u64],
+    ref_ctr_offsets: Option<&[u64]>,
+    cookies: Option<&[u64]>,
+    pid: Option<pid_t>,
+    flags: u32,
+) -> SysResult {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    const _: () =
+        assert!(mem::size_of::<bpf_link_create_uprobe_multi>() <= mem::size_of::<bpf_attr>());
+    let uprobe_multi =
+        unsafe { &mut *(&mut attr as *mut bpf_attr as *mut bpf_link_create_uprobe_multi) };
+    uprobe_multi.prog_fd = prog_fd as u32;
+    uprobe_multi.attach_type = BPF_TRACE_UPROBE_MULTI;
+    uprobe_multi.path = path.as_ptr() as u64;
+    uprobe_multi.offsets = offsets.as_ptr() as u64;
+    if let Some(ref_ctr_offsets) = ref_ctr_offsets {
+        uprobe_multi.ref_ctr_offsets = ref_ctr_offsets.as_ptr() as u64;
+    }
+    if let Some(cookies) = cookies {
+        uprobe_multi.cookies = cookies.as_ptr() as u64;
+    }
+    uprobe_multi.cnt = offsets.len() as u32;
+    uprobe_multi.uprobe_flags = flags;
+    uprobe_multi.pid = pid.unwrap_or(0) as u32;
+
+    sys_bpf(bpf_cmd::BPF_LINK_CREATE, &attr)
+}
+
+// The `BPF_STRUCT_OPS` attach type, missing from the generated bindings.
+const BPF_STRUCT_OPS: u32 = 44;
+
+// since kernel 6.4
+pub(crate) fn bpf_struct_ops_link_create(map_fd: RawFd) -> SysResult {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    // the map fd is passed in place of the program fd
+    attr.link_create.prog_fd = map_fd as u32;
+    attr.link_create.attach_type = BPF_STRUCT_OPS;
+
+    sys_bpf(bpf_cmd::BPF_LINK_CREATE, &attr)
+}
+
+// since kernel 5.9
+pub(crate) fn bpf_link_detach(link_fd: RawFd) -> SysResult {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    attr.link_detach.link_fd = link_fd as u32;
+
+    sys_bpf(bpf_cmd::BPF_LINK_DETACH, &attr)
+}
+
 // since kernel 5.7
 pub(crate) fn bpf_link_update(
     link_fd: RawFd,
@@ -479,6 +824,17 @@ pub(crate) fn bpf_prog_get_info_by_fd(prog_fd: RawFd) -> Result<bpf_prog_info, i
     }
 }
 
+pub(crate) fn bpf_map_get_fd_by_id(map_id: u32) -> Result<RawFd, io::Error> {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    attr.__bindgen_anon_6.__bindgen_anon_1.map_id = map_id;
+
+    match sys_bpf(bpf_cmd::BPF_MAP_GET_FD_BY_ID, &attr) {
+        Ok(v) => Ok(v as RawFd),
+        Err((_, err)) => Err(err),
+    }
+}
+
 pub(crate) fn bpf_map_get_info_by_fd(prog_fd: RawFd) -> Result<bpf_map_info, io::Error> {
     let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
     // info gets entirely populated by the kernel
@@ -494,6 +850,14 @@ pub(crate) fn bpf_map_get_info_by_fd(prog_fd: RawFd) -> Result<bpf_map_info, io:
     }
 }
 
+pub(crate) fn bpf_link_get_fd_by_id(link_id: u32) -> SysResult {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+
+    attr.__bindgen_anon_6.__bindgen_anon_1.link_id = link_id;
+
+    sys_bpf(bpf_cmd::BPF_LINK_GET_FD_BY_ID, &attr)
+}
+
 pub(crate) fn bpf_link_get_info_by_fd(link_fd: RawFd) -> Result<bpf_link_info, io::Error> {
     let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
     // info gets entirely populated by the kernel
@@ -512,13 +876,24 @@ pub(crate) fn bpf_link_get_info_by_fd(link_fd: RawFd) -> Result<bpf_link_info, i
 pub(crate) fn btf_obj_get_info_by_fd(
     prog_fd: RawFd,
     buf: &mut [u8],
+) -> Result<bpf_btf_info, io::Error> {
+    bpf_btf_get_info_by_fd(prog_fd, buf, &mut [])
+}
+
+pub(crate) fn bpf_btf_get_info_by_fd(
+    btf_fd: RawFd,
+    buf: &mut [u8],
+    name: &mut [u8],
 ) -> Result<bpf_btf_info, io::Error> {
     let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
     let mut info = unsafe { mem::zeroed::<bpf_btf_info>() };
-    let buf_size = buf.len() as u32;
     info.btf = buf.as_ptr() as u64;
-    info.btf_size = buf_size;
-    attr.info.bpf_fd = prog_fd as u32;
+    info.btf_size = buf.len() as u32;
+    if !name.is_empty() {
+        info.name = name.as_ptr() as u64;
+        info.name_len = name.len() as u32;
+    }
+    attr.info.bpf_fd = btf_fd as u32;
     attr.info.info = &info as *const bpf_btf_info as u64;
     attr.info.info_len = mem::size_of::<bpf_btf_info>() as u32;
 
@@ -630,6 +1005,60 @@ pub(crate) fn is_perf_link_supported() -> bool {
     false
 }
 
+pub(crate) fn is_uprobe_multi_link_supported() -> bool {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+    let u = unsafe { &mut attr.__bindgen_anon_3 };
+
+    let prog: &[u8] = &[
+        0xb7, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // mov64 r0 = 0
+        0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // exit
+    ];
+
+    let gpl = b"GPL\0";
+    u.license = gpl.as_ptr() as u64;
+
+    let insns = copy_instructions(prog).unwrap();
+    u.insn_cnt = insns.len() as u32;
+    u.insns = insns.as_ptr() as u64;
+    u.prog_type = bpf_prog_type::BPF_PROG_TYPE_KPROBE as u32;
+    u.expected_attach_type = BPF_TRACE_UPROBE_MULTI;
+
+    if let Ok(fd) = sys_bpf(bpf_cmd::BPF_PROG_LOAD, &attr) {
+        let path = CString::new("/").unwrap();
+        // Uses a directory as the target so we get EBADF if supported.
+        let res = match bpf_link_create_uprobe_multi(fd as i32, &path, &[0], None, None, None, 0) {
+            Ok(link_fd) => {
+                unsafe { libc::close(link_fd as i32) };
+                false
+            }
+            // Returns EINVAL if unsupported. EBADF if supported.
+            Err((_, e)) => e.raw_os_error() == Some(libc::EBADF),
+        };
+        unsafe { libc::close(fd as i32) };
+        return res;
+    }
+    false
+}
+
+pub(crate) fn is_map_mmap_supported() -> bool {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+    let u = unsafe { &mut attr.__bindgen_anon_1 };
+    u.map_type = bpf_map_type::BPF_MAP_TYPE_ARRAY as u32;
+    u.key_size = 4;
+    u.value_size = 4;
+    u.max_entries = 1;
+    u.map_flags = BPF_F_MMAPABLE;
+
+    match sys_bpf(bpf_cmd::BPF_MAP_CREATE, &attr) {
+        Ok(v) => {
+            let fd = v as RawFd;
+            unsafe { close(fd) };
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 pub(crate) fn is_btf_supported() -> bool {
     let mut btf = Btf::new();
     let name_offset = btf.add_string("int".to_string());
@@ -870,6 +1299,39 @@ pub(crate) fn bpf_prog_get_next_id(id: u32) -> Result<Option<u32>, (c_long, io::
     }
 }
 
+pub(crate) fn bpf_map_get_next_id(id: u32) -> Result<Option<u32>, (c_long, io::Error)> {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+    let u = unsafe { &mut attr.__bindgen_anon_6 };
+    u.__bindgen_anon_1.start_id = id;
+    match sys_bpf(bpf_cmd::BPF_MAP_GET_NEXT_ID, &attr) {
+        Ok(_) => Ok(Some(unsafe { attr.__bindgen_anon_6.next_id })),
+        Err((_, io_error)) if io_error.raw_os_error() == Some(ENOENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn bpf_link_get_next_id(id: u32) -> Result<Option<u32>, (c_long, io::Error)> {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+    let u = unsafe { &mut attr.__bindgen_anon_6 };
+    u.__bindgen_anon_1.start_id = id;
+    match sys_bpf(bpf_cmd::BPF_LINK_GET_NEXT_ID, &attr) {
+        Ok(_) => Ok(Some(unsafe { attr.__bindgen_anon_6.next_id })),
+        Err((_, io_error)) if io_error.raw_os_error() == Some(ENOENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn bpf_btf_get_next_id(id: u32) -> Result<Option<u32>, (c_long, io::Error)> {
+    let mut attr = unsafe { mem::zeroed::<bpf_attr>() };
+    let u = unsafe { &mut attr.__bindgen_anon_6 };
+    u.__bindgen_anon_1.start_id = id;
+    match sys_bpf(bpf_cmd::BPF_BTF_GET_NEXT_ID, &attr) {
+        Ok(_) => Ok(Some(unsafe { attr.__bindgen_anon_6.next_id })),
+        Err((_, io_error)) if io_error.raw_os_error() == Some(ENOENT) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 pub(crate) fn retry_with_verifier_logs<F>(
     max_retries: usize,
     log: &mut VerifierLog,
@@ -932,4 +1394,25 @@ mod tests {
         let supported = is_perf_link_supported();
         assert!(!supported);
     }
+
+    #[test]
+    fn test_uprobe_multi_link_supported() {
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_LINK_CREATE,
+                ..
+            } => Err((-1, io::Error::from_raw_os_error(EBADF))),
+            _ => Ok(42),
+        });
+        assert!(is_uprobe_multi_link_supported());
+
+        override_syscall(|call| match call {
+            Syscall::Bpf {
+                cmd: bpf_cmd::BPF_LINK_CREATE,
+                ..
+            } => Err((-1, io::Error::from_raw_os_error(EINVAL))),
+            _ => Ok(42),
+        });
+        assert!(!is_uprobe_multi_link_supported());
+    }
 }
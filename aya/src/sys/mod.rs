@@ -11,7 +11,7 @@ use std::{ffi::CString, mem};
 
 #[cfg(not(test))]
 use libc::utsname;
-use libc::{c_int, c_long, pid_t};
+use libc::{c_int, c_long, c_void, off_t, pid_t};
 
 pub(crate) use bpf::*;
 #[cfg(test)]
@@ -77,6 +77,38 @@ unsafe fn syscall_impl(call: Syscall) -> SysResult {
     Ok(ret)
 }
 
+#[cfg_attr(test, allow(unused_variables))]
+pub(crate) unsafe fn mmap(
+    addr: *mut c_void,
+    len: usize,
+    prot: c_int,
+    flags: c_int,
+    fd: i32,
+    offset: off_t,
+) -> *mut c_void {
+    #[cfg(not(test))]
+    return libc::mmap(addr, len, prot, flags, fd, offset);
+
+    // the fake mapping is the whole file, so that maps mapped in several parts, like ring
+    // buffers, see their pages at the right offsets
+    #[cfg(test)]
+    TEST_MMAP_RET.with(|ret| {
+        ret.borrow()
+            .cast::<u8>()
+            .wrapping_add(offset as usize)
+            .cast()
+    })
+}
+
+#[cfg_attr(test, allow(unused_variables))]
+pub(crate) unsafe fn munmap(addr: *mut c_void, len: usize) -> c_int {
+    #[cfg(not(test))]
+    return libc::munmap(addr, len);
+
+    #[cfg(test)]
+    0
+}
+
 #[cfg(test)]
 pub(crate) fn kernel_version() -> Result<(u32, u32, u32), ()> {
     Ok((0xff, 0xff, 0xff))
@@ -61,11 +61,15 @@ pub(crate) fn perf_event_open_bpf(cpu: c_int) -> SysResult {
     )
 }
 
+// The reference counter offset of uprobes is passed in the upper 32 bits of the config.
+const PERF_UPROBE_REF_CTR_OFFSET_SHIFT: u64 = 32;
+
 pub(crate) fn perf_event_open_probe(
     ty: u32,
     ret_bit: Option<u32>,
     name: &str,
     offset: u64,
+    ref_ctr_offset: Option<u64>,
     pid: Option<pid_t>,
 ) -> SysResult {
     let mut attr = unsafe { mem::zeroed::<perf_event_attr>() };
@@ -73,6 +77,9 @@ pub(crate) fn perf_event_open_probe(
     if let Some(ret_bit) = ret_bit {
         attr.config = 1 << ret_bit;
     }
+    if let Some(ref_ctr_offset) = ref_ctr_offset {
+        attr.config |= ref_ctr_offset << PERF_UPROBE_REF_CTR_OFFSET_SHIFT;
+    }
 
     let c_name = CString::new(name).unwrap();
 
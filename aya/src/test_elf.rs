@@ -0,0 +1,213 @@
+//! A minimal little endian ELF64 writer for building the objects used in tests.
+use object::elf::{
+    ELFCLASS64, ELFDATA2LSB, EV_CURRENT, PF_R, PF_X, PT_LOAD, SHT_NULL, SHT_STRTAB, SHT_SYMTAB,
+};
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+const SHDR_SIZE: usize = 64;
+const SYM_SIZE: usize = 24;
+
+/// A section, see `Elf64_Shdr`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Section {
+    pub(crate) name: String,
+    pub(crate) sh_type: u32,
+    pub(crate) flags: u64,
+    pub(crate) addr: u64,
+    pub(crate) data: Vec<u8>,
+    pub(crate) link: u32,
+    pub(crate) info: u32,
+    pub(crate) entsize: u64,
+}
+
+/// A symbol, see `Elf64_Sym`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Symbol<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) info: u8,
+    pub(crate) section: u16,
+    pub(crate) value: u64,
+    pub(crate) size: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct ElfBuilder {
+    e_type: u16,
+    machine: u16,
+    sections: Vec<Section>,
+    // the sections mapped by PT_LOAD segments
+    segments: Vec<usize>,
+}
+
+impl ElfBuilder {
+    pub(crate) fn new(e_type: u16, machine: u16) -> ElfBuilder {
+        ElfBuilder {
+            e_type,
+            machine,
+            sections: vec![Section {
+                sh_type: SHT_NULL,
+                ..Default::default()
+            }],
+            segments: Vec::new(),
+        }
+    }
+
+    /// Adds a section and returns its index.
+    pub(crate) fn section(&mut self, section: Section) -> usize {
+        self.sections.push(section);
+        self.sections.len() - 1
+    }
+
+    /// Maps a section at its address with a PT_LOAD segment.
+    pub(crate) fn segment(&mut self, section: usize) {
+        self.segments.push(section);
+    }
+
+    /// Adds a string to the string table of a symbol table, and returns its offset.
+    pub(crate) fn string(&mut self, symtab: usize, s: &str) -> u32 {
+        let strtab = self.sections[symtab].link as usize;
+        let strtab = &mut self.sections[strtab].data;
+        let offset = strtab.len() as u32;
+        strtab.extend_from_slice(s.as_bytes());
+        strtab.push(0);
+        offset
+    }
+
+    /// Adds a symbol table of type `sh_type` and its string table, and returns the index of the
+    /// symbol table. The null symbol is added first, and local symbols must come before the
+    /// others.
+    pub(crate) fn symbols(
+        &mut self,
+        name: &str,
+        strtab_name: &str,
+        sh_type: u32,
+        symbols: &[Symbol<'_>],
+    ) -> usize {
+        let mut strtab = vec![0];
+        let mut symtab = vec![0; SYM_SIZE];
+        let mut first_global = symbols.len() + 1;
+        for (i, sym) in symbols.iter().enumerate() {
+            if sym.info >> 4 != 0 && first_global > symbols.len() {
+                first_global = i + 1;
+            }
+            symtab.extend_from_slice(&(strtab.len() as u32).to_le_bytes());
+            strtab.extend_from_slice(sym.name.as_bytes());
+            strtab.push(0);
+            symtab.push(sym.info);
+            symtab.push(0);
+            symtab.extend_from_slice(&sym.section.to_le_bytes());
+            symtab.extend_from_slice(&sym.value.to_le_bytes());
+            symtab.extend_from_slice(&sym.size.to_le_bytes());
+        }
+        let strtab = self.section(Section {
+            name: strtab_name.to_owned(),
+            sh_type: SHT_STRTAB,
+            data: strtab,
+            ..Default::default()
+        });
+        self.section(Section {
+            name: name.to_owned(),
+            sh_type,
+            data: symtab,
+            link: strtab as u32,
+            info: first_global as u32,
+            entsize: SYM_SIZE as u64,
+            ..Default::default()
+        })
+    }
+
+    /// Adds a `.symtab` static symbol table.
+    pub(crate) fn symtab(&mut self, symbols: &[Symbol<'_>]) -> usize {
+        self.symbols(".symtab", ".strtab", SHT_SYMTAB, symbols)
+    }
+
+    pub(crate) fn build(&self) -> Vec<u8> {
+        let mut shstrtab = vec![0];
+        let mut names = Vec::new();
+        for section in &self.sections {
+            if section.name.is_empty() {
+                names.push(0);
+            } else {
+                names.push(shstrtab.len() as u32);
+                shstrtab.extend_from_slice(section.name.as_bytes());
+                shstrtab.push(0);
+            }
+        }
+        let shstrtab_name = shstrtab.len() as u32;
+        shstrtab.extend_from_slice(b".shstrtab\0");
+
+        let phoff = EHDR_SIZE;
+        let mut data = vec![0; phoff + self.segments.len() * PHDR_SIZE];
+        let mut offsets = Vec::new();
+        for section in self.sections.iter().map(|s| &s.data).chain([&shstrtab]) {
+            data.resize((data.len() + 7) & !7, 0);
+            offsets.push(data.len() as u64);
+            data.extend_from_slice(section);
+        }
+        data.resize((data.len() + 7) & !7, 0);
+        let shoff = data.len();
+        let shnum = self.sections.len() + 1;
+
+        data[..4].copy_from_slice(b"\x7fELF");
+        data[4] = ELFCLASS64;
+        data[5] = ELFDATA2LSB;
+        data[6] = EV_CURRENT;
+        put(&mut data, 16, &self.e_type.to_le_bytes());
+        put(&mut data, 18, &self.machine.to_le_bytes());
+        put(&mut data, 20, &(EV_CURRENT as u32).to_le_bytes());
+        if !self.segments.is_empty() {
+            put(&mut data, 32, &(phoff as u64).to_le_bytes());
+        }
+        put(&mut data, 40, &(shoff as u64).to_le_bytes());
+        put(&mut data, 52, &(EHDR_SIZE as u16).to_le_bytes());
+        put(&mut data, 54, &(PHDR_SIZE as u16).to_le_bytes());
+        put(&mut data, 56, &(self.segments.len() as u16).to_le_bytes());
+        put(&mut data, 58, &(SHDR_SIZE as u16).to_le_bytes());
+        put(&mut data, 60, &(shnum as u16).to_le_bytes());
+        put(&mut data, 62, &(shnum as u16 - 1).to_le_bytes());
+
+        for (i, &index) in self.segments.iter().enumerate() {
+            let section = &self.sections[index];
+            let size = section.data.len() as u64;
+            let at = phoff + i * PHDR_SIZE;
+            put(&mut data, at, &PT_LOAD.to_le_bytes());
+            put(&mut data, at + 4, &(PF_R | PF_X).to_le_bytes());
+            put(&mut data, at + 8, &offsets[index].to_le_bytes());
+            put(&mut data, at + 16, &section.addr.to_le_bytes());
+            put(&mut data, at + 24, &section.addr.to_le_bytes());
+            put(&mut data, at + 32, &size.to_le_bytes());
+            put(&mut data, at + 40, &size.to_le_bytes());
+            put(&mut data, at + 48, &8u64.to_le_bytes());
+        }
+
+        let shstrtab = Section {
+            sh_type: SHT_STRTAB,
+            data: shstrtab,
+            ..Default::default()
+        };
+        names.push(shstrtab_name);
+        for (i, section) in self.sections.iter().chain([&shstrtab]).enumerate() {
+            let mut shdr = [0; SHDR_SIZE];
+            put(&mut shdr, 0, &names[i].to_le_bytes());
+            put(&mut shdr, 4, &section.sh_type.to_le_bytes());
+            put(&mut shdr, 8, &section.flags.to_le_bytes());
+            put(&mut shdr, 16, &section.addr.to_le_bytes());
+            if i != 0 {
+                put(&mut shdr, 24, &offsets[i].to_le_bytes());
+                put(&mut shdr, 32, &(section.data.len() as u64).to_le_bytes());
+            }
+            put(&mut shdr, 40, &section.link.to_le_bytes());
+            put(&mut shdr, 44, &section.info.to_le_bytes());
+            put(&mut shdr, 48, &1u64.to_le_bytes());
+            put(&mut shdr, 56, &section.entsize.to_le_bytes());
+            data.extend_from_slice(&shdr);
+        }
+
+        data
+    }
+}
+
+fn put(data: &mut [u8], at: usize, bytes: &[u8]) {
+    data[at..at + bytes.len()].copy_from_slice(bytes);
+}
@@ -0,0 +1,87 @@
+use core::{cell::UnsafeCell, marker::PhantomData, mem};
+
+use aya_bpf_cty::c_void;
+
+use crate::{
+    bindings::{bpf_map_def, bpf_map_type::BPF_MAP_TYPE_ARRAY_OF_MAPS},
+    helpers::bpf_map_lookup_elem,
+    maps::{InnerMap, PinningType},
+};
+
+/// An array of maps.
+///
+/// The values of the array are maps of type `T`, which are set from user space. Looking up an
+/// index returns the inner map, which can then be used like any other map.
+///
+/// The map definition doesn't describe the inner maps, so the loader must be told which map to use
+/// as a template for them with
+/// [`BpfLoader::set_inner_map`](https://docs.rs/aya/latest/aya/struct.BpfLoader.html#method.set_inner_map).
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::map, maps::{Array, ArrayOfMaps}};
+///
+/// #[map]
+/// static POLICIES: ArrayOfMaps<Array<u32>> = ArrayOfMaps::with_max_entries(16, 0);
+///
+/// # fn try_test() -> Option<u32> {
+/// let tenant = 3;
+/// let policy = POLICIES.get(tenant)?;
+/// let action = policy.get(0)?;
+/// # Some(*action)
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct ArrayOfMaps<T: InnerMap> {
+    def: UnsafeCell<bpf_map_def>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<T: InnerMap> Sync for ArrayOfMaps<T> {}
+
+impl<T: InnerMap> ArrayOfMaps<T> {
+    pub const fn with_max_entries(max_entries: u32, flags: u32) -> ArrayOfMaps<T> {
+        ArrayOfMaps {
+            def: UnsafeCell::new(bpf_map_def {
+                type_: BPF_MAP_TYPE_ARRAY_OF_MAPS,
+                key_size: mem::size_of::<u32>() as u32,
+                value_size: mem::size_of::<u32>() as u32,
+                max_entries,
+                map_flags: flags,
+                id: 0,
+                pinning: PinningType::None as u32,
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(max_entries: u32, flags: u32) -> ArrayOfMaps<T> {
+        ArrayOfMaps {
+            def: UnsafeCell::new(bpf_map_def {
+                type_: BPF_MAP_TYPE_ARRAY_OF_MAPS,
+                key_size: mem::size_of::<u32>() as u32,
+                value_size: mem::size_of::<u32>() as u32,
+                max_entries,
+                map_flags: flags,
+                id: 0,
+                pinning: PinningType::ByName as u32,
+            }),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns the inner map at `index`, or `None` if no map is set at that index.
+    #[inline(always)]
+    pub fn get(&self, index: u32) -> Option<&T> {
+        unsafe {
+            let ptr = bpf_map_lookup_elem(
+                self.def.get() as *mut _,
+                &index as *const _ as *const c_void,
+            );
+            // the kernel returns a pointer to the inner map, which is what the methods of `T`
+            // expect as their map argument
+            (ptr as *const T).as_ref()
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use core::{cell::UnsafeCell, marker::PhantomData, mem};
+
+use crate::{
+    bindings::{
+        bpf_cgroup_storage_key, bpf_map_def,
+        bpf_map_type::{BPF_MAP_TYPE_CGROUP_STORAGE, BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE},
+    },
+    helpers::bpf_get_local_storage,
+    maps::PinningType,
+};
+
+/// A map holding a value of type `T` for each cgroup the program is attached to.
+///
+/// The value is created when the program is attached and freed when it's detached. It can be
+/// used by cgroup programs such as `cgroup_skb` and `cgroup_sock_addr`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::sync::atomic::{AtomicU64, Ordering};
+///
+/// use aya_bpf::{macros::{cgroup_skb, map}, maps::CgroupStorage, programs::SkBuffContext};
+///
+/// #[map]
+/// static EGRESS_BYTES: CgroupStorage<AtomicU64> = CgroupStorage::new(0);
+///
+/// #[cgroup_skb]
+/// pub fn count_egress(ctx: SkBuffContext) -> i32 {
+///     let bytes = EGRESS_BYTES.get();
+///     unsafe { (*bytes).fetch_add(ctx.len() as u64, Ordering::Relaxed) };
+///     1
+/// }
+/// ```
+#[repr(transparent)]
+pub struct CgroupStorage<T> {
+    def: UnsafeCell<bpf_map_def>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<T: Sync> Sync for CgroupStorage<T> {}
+
+impl<T> CgroupStorage<T> {
+    pub const fn new(flags: u32) -> CgroupStorage<T> {
+        CgroupStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_CGROUP_STORAGE,
+                flags,
+                PinningType::None,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(flags: u32) -> CgroupStorage<T> {
+        CgroupStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_CGROUP_STORAGE,
+                flags,
+                PinningType::ByName,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the value of the cgroup the program runs for.
+    ///
+    /// The pointer is never null. Other programs and CPUs can update the value concurrently, it's
+    /// up to the caller to decide whether it's safe to dereference the pointer or not.
+    #[inline]
+    pub fn get(&self) -> *mut T {
+        unsafe { bpf_get_local_storage(self.def.get() as *mut _, 0) as *mut T }
+    }
+}
+
+/// A map holding a value of type `T` for each CPU and cgroup the program is attached to.
+///
+/// Like [`CgroupStorage`], but `get` returns the value of the current CPU, which can be updated
+/// without synchronization.
+#[repr(transparent)]
+pub struct PerCpuCgroupStorage<T> {
+    def: UnsafeCell<bpf_map_def>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<T: Sync> Sync for PerCpuCgroupStorage<T> {}
+
+impl<T> PerCpuCgroupStorage<T> {
+    pub const fn new(flags: u32) -> PerCpuCgroupStorage<T> {
+        PerCpuCgroupStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE,
+                flags,
+                PinningType::None,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(flags: u32) -> PerCpuCgroupStorage<T> {
+        PerCpuCgroupStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_PERCPU_CGROUP_STORAGE,
+                flags,
+                PinningType::ByName,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the value of the cgroup the program runs for on the current CPU.
+    ///
+    /// The pointer is never null.
+    #[inline]
+    pub fn get(&self) -> *mut T {
+        unsafe { bpf_get_local_storage(self.def.get() as *mut _, 0) as *mut T }
+    }
+}
+
+const fn build_def<T>(ty: u32, flags: u32, pin: PinningType) -> bpf_map_def {
+    bpf_map_def {
+        type_: ty,
+        key_size: mem::size_of::<bpf_cgroup_storage_key>() as u32,
+        value_size: mem::size_of::<T>() as u32,
+        // the values are created by the kernel when attaching programs
+        max_entries: 0,
+        map_flags: flags,
+        id: 0,
+        pinning: pin as u32,
+    }
+}
@@ -0,0 +1,84 @@
+use core::{cell::UnsafeCell, marker::PhantomData, mem};
+
+use aya_bpf_cty::c_void;
+
+use crate::{
+    bindings::{bpf_map_def, bpf_map_type::BPF_MAP_TYPE_HASH_OF_MAPS},
+    helpers::bpf_map_lookup_elem,
+    maps::{InnerMap, PinningType},
+};
+
+/// A hash map of maps.
+///
+/// The values of the hash map are maps of type `T`, which are inserted from user space. Looking
+/// up a key returns the inner map, which can then be used like any other map.
+///
+/// The map definition doesn't describe the inner maps, so the loader must be told which map to use
+/// as a template for them with
+/// [`BpfLoader::set_inner_map`](https://docs.rs/aya/latest/aya/struct.BpfLoader.html#method.set_inner_map).
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::map, maps::{HashMap, HashOfMaps}};
+///
+/// #[map]
+/// static POLICIES: HashOfMaps<u32, HashMap<u32, u32>> = HashOfMaps::with_max_entries(1024, 0);
+///
+/// # unsafe fn try_test() -> Option<u32> {
+/// let tenant = 42;
+/// let policy = POLICIES.get(&tenant)?;
+/// let action = policy.get(&80)?;
+/// # Some(*action)
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct HashOfMaps<K, T: InnerMap> {
+    def: UnsafeCell<bpf_map_def>,
+    _k: PhantomData<K>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<K: Sync, T: InnerMap> Sync for HashOfMaps<K, T> {}
+
+impl<K, T: InnerMap> HashOfMaps<K, T> {
+    pub const fn with_max_entries(max_entries: u32, flags: u32) -> HashOfMaps<K, T> {
+        HashOfMaps {
+            def: UnsafeCell::new(build_def::<K>(max_entries, flags, PinningType::None)),
+            _k: PhantomData,
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(max_entries: u32, flags: u32) -> HashOfMaps<K, T> {
+        HashOfMaps {
+            def: UnsafeCell::new(build_def::<K>(max_entries, flags, PinningType::ByName)),
+            _k: PhantomData,
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns the inner map stored at `key`, or `None` if the key is not in the map.
+    #[inline]
+    pub fn get(&self, key: &K) -> Option<&T> {
+        unsafe {
+            let ptr =
+                bpf_map_lookup_elem(self.def.get() as *mut _, key as *const _ as *const c_void);
+            // the kernel returns a pointer to the inner map, which is what the methods of `T`
+            // expect as their map argument
+            (ptr as *const T).as_ref()
+        }
+    }
+}
+
+const fn build_def<K>(max_entries: u32, flags: u32, pin: PinningType) -> bpf_map_def {
+    bpf_map_def {
+        type_: BPF_MAP_TYPE_HASH_OF_MAPS,
+        key_size: mem::size_of::<K>() as u32,
+        value_size: mem::size_of::<u32>() as u32,
+        max_entries,
+        map_flags: flags,
+        id: 0,
+        pinning: pin as u32,
+    }
+}
@@ -0,0 +1,378 @@
+use core::{cell::UnsafeCell, marker::PhantomData, mem, ptr};
+
+use aya_bpf_cty::{c_long, c_void};
+
+use crate::{
+    bindings::{
+        bpf_map_def,
+        bpf_map_type::{
+            BPF_MAP_TYPE_CGRP_STORAGE, BPF_MAP_TYPE_INODE_STORAGE, BPF_MAP_TYPE_SK_STORAGE,
+            BPF_MAP_TYPE_TASK_STORAGE,
+        },
+        cgroup, task_struct, BPF_F_NO_PREALLOC, BPF_LOCAL_STORAGE_GET_F_CREATE,
+    },
+    helpers::{
+        bpf_cgrp_storage_delete, bpf_cgrp_storage_get, bpf_inode_storage_delete,
+        bpf_inode_storage_get, bpf_sk_storage_delete, bpf_sk_storage_get, bpf_task_storage_delete,
+        bpf_task_storage_get,
+    },
+    maps::PinningType,
+};
+
+/// A map attaching a value of type `T` to each socket.
+///
+/// The value is freed when the socket is closed. `sk` can be any socket pointer the program has,
+/// such as `bpf_sock_ops::sk` or a `struct sock *` argument of a tracing program.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::{map, sock_ops}, maps::SkStorage, programs::SockOpsContext};
+///
+/// #[map]
+/// static SOCKET_BYTES: SkStorage<u64> = SkStorage::new(0);
+///
+/// #[sock_ops]
+/// pub fn count_bytes(ctx: SockOpsContext) -> u32 {
+///     let sk = unsafe { (*ctx.ops).__bindgen_anon_2.sk } as *mut _;
+///     if let Some(bytes) = SOCKET_BYTES.get_or_create(sk, None) {
+///         unsafe { *bytes += 1 };
+///     }
+///     0
+/// }
+/// ```
+#[repr(transparent)]
+pub struct SkStorage<T> {
+    def: UnsafeCell<bpf_map_def>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<T: Sync> Sync for SkStorage<T> {}
+
+impl<T> SkStorage<T> {
+    pub const fn new(flags: u32) -> SkStorage<T> {
+        SkStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_SK_STORAGE,
+                flags,
+                PinningType::None,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(flags: u32) -> SkStorage<T> {
+        SkStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_SK_STORAGE,
+                flags,
+                PinningType::ByName,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the value stored for the socket, or `None` if there's none.
+    ///
+    /// Other programs can update the value concurrently, it's up to the caller to decide whether
+    /// it's safe to dereference the pointer or not.
+    #[inline]
+    pub fn get(&self, sk: *mut c_void) -> Option<*mut T> {
+        let ptr = unsafe { bpf_sk_storage_get(self.def.get() as *mut _, sk, ptr::null_mut(), 0) };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Returns a pointer to the value stored for the socket, creating it if there's none.
+    ///
+    /// The new value is initialized with `value`, or zeroed if `value` is `None`. The same caveat
+    /// as `get` applies.
+    #[inline]
+    pub fn get_or_create(&self, sk: *mut c_void, value: Option<&T>) -> Option<*mut T> {
+        let value = value.map_or(ptr::null_mut(), |v| v as *const T as *mut c_void);
+        let ptr = unsafe {
+            bpf_sk_storage_get(
+                self.def.get() as *mut _,
+                sk,
+                value,
+                BPF_LOCAL_STORAGE_GET_F_CREATE as u64,
+            )
+        };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Deletes the value stored for the socket.
+    #[inline]
+    pub fn delete(&self, sk: *mut c_void) -> Result<(), c_long> {
+        let ret = unsafe { bpf_sk_storage_delete(self.def.get() as *mut _, sk) };
+        (ret == 0).then_some(()).ok_or(ret as c_long)
+    }
+}
+
+/// A map attaching a value of type `T` to each task.
+///
+/// The value is freed when the task exits.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{helpers::bpf_get_current_task_btf, macros::map, maps::TaskStorage};
+///
+/// #[map]
+/// static SYSCALLS: TaskStorage<u64> = TaskStorage::new(0);
+///
+/// # fn try_test() -> Option<()> {
+/// let task = unsafe { bpf_get_current_task_btf() };
+/// let count = SYSCALLS.get_or_create(task, None)?;
+/// unsafe { *count += 1 };
+/// # Some(())
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct TaskStorage<T> {
+    def: UnsafeCell<bpf_map_def>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<T: Sync> Sync for TaskStorage<T> {}
+
+impl<T> TaskStorage<T> {
+    pub const fn new(flags: u32) -> TaskStorage<T> {
+        TaskStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_TASK_STORAGE,
+                flags,
+                PinningType::None,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(flags: u32) -> TaskStorage<T> {
+        TaskStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_TASK_STORAGE,
+                flags,
+                PinningType::ByName,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the value stored for the task, or `None` if there's none.
+    ///
+    /// Other programs can update the value concurrently, it's up to the caller to decide whether
+    /// it's safe to dereference the pointer or not.
+    #[inline]
+    pub fn get(&self, task: *mut task_struct) -> Option<*mut T> {
+        let ptr =
+            unsafe { bpf_task_storage_get(self.def.get() as *mut _, task, ptr::null_mut(), 0) };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Returns a pointer to the value stored for the task, creating it if there's none.
+    ///
+    /// The new value is initialized with `value`, or zeroed if `value` is `None`. The same caveat
+    /// as `get` applies.
+    #[inline]
+    pub fn get_or_create(&self, task: *mut task_struct, value: Option<&T>) -> Option<*mut T> {
+        let value = value.map_or(ptr::null_mut(), |v| v as *const T as *mut c_void);
+        let ptr = unsafe {
+            bpf_task_storage_get(
+                self.def.get() as *mut _,
+                task,
+                value,
+                BPF_LOCAL_STORAGE_GET_F_CREATE as u64,
+            )
+        };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Deletes the value stored for the task.
+    #[inline]
+    pub fn delete(&self, task: *mut task_struct) -> Result<(), c_long> {
+        let ret = unsafe { bpf_task_storage_delete(self.def.get() as *mut _, task) };
+        (ret == 0).then_some(()).ok_or(ret as c_long)
+    }
+}
+
+/// A map attaching a value of type `T` to each inode.
+///
+/// The value is freed with the inode. Only LSM programs can use inode storage.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{macros::map, maps::InodeStorage};
+///
+/// #[map]
+/// static PROTECTED: InodeStorage<u32> = InodeStorage::new(0);
+///
+/// # unsafe fn is_protected(inode: *mut core::ffi::c_void) -> bool {
+/// PROTECTED.get(inode.cast()).is_some()
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct InodeStorage<T> {
+    def: UnsafeCell<bpf_map_def>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<T: Sync> Sync for InodeStorage<T> {}
+
+impl<T> InodeStorage<T> {
+    pub const fn new(flags: u32) -> InodeStorage<T> {
+        InodeStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_INODE_STORAGE,
+                flags,
+                PinningType::None,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(flags: u32) -> InodeStorage<T> {
+        InodeStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_INODE_STORAGE,
+                flags,
+                PinningType::ByName,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the value stored for the inode, or `None` if there's none.
+    ///
+    /// Other programs can update the value concurrently, it's up to the caller to decide whether
+    /// it's safe to dereference the pointer or not.
+    #[inline]
+    pub fn get(&self, inode: *mut c_void) -> Option<*mut T> {
+        let ptr =
+            unsafe { bpf_inode_storage_get(self.def.get() as *mut _, inode, ptr::null_mut(), 0) };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Returns a pointer to the value stored for the inode, creating it if there's none.
+    ///
+    /// The new value is initialized with `value`, or zeroed if `value` is `None`. The same caveat
+    /// as `get` applies.
+    #[inline]
+    pub fn get_or_create(&self, inode: *mut c_void, value: Option<&T>) -> Option<*mut T> {
+        let value = value.map_or(ptr::null_mut(), |v| v as *const T as *mut c_void);
+        let ptr = unsafe {
+            bpf_inode_storage_get(
+                self.def.get() as *mut _,
+                inode,
+                value,
+                BPF_LOCAL_STORAGE_GET_F_CREATE as u64,
+            )
+        };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Deletes the value stored for the inode.
+    #[inline]
+    pub fn delete(&self, inode: *mut c_void) -> Result<(), c_long> {
+        let ret = unsafe { bpf_inode_storage_delete(self.def.get() as *mut _, inode) };
+        (ret == 0).then_some(()).ok_or(ret as c_long)
+    }
+}
+
+/// A map attaching a value of type `T` to each cgroup.
+///
+/// The value is freed with the cgroup.
+///
+/// # Examples
+///
+/// ```no_run
+/// use aya_bpf::{bindings::cgroup, macros::map, maps::CgrpStorage};
+///
+/// #[map]
+/// static CGROUP_QUOTA: CgrpStorage<u64> = CgrpStorage::new(0);
+///
+/// # fn quota(cgroup: *mut cgroup) -> u64 {
+/// CGROUP_QUOTA.get(cgroup).map(|q| unsafe { *q }).unwrap_or(0)
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct CgrpStorage<T> {
+    def: UnsafeCell<bpf_map_def>,
+    _t: PhantomData<T>,
+}
+
+unsafe impl<T: Sync> Sync for CgrpStorage<T> {}
+
+impl<T> CgrpStorage<T> {
+    pub const fn new(flags: u32) -> CgrpStorage<T> {
+        CgrpStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_CGRP_STORAGE,
+                flags,
+                PinningType::None,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    pub const fn pinned(flags: u32) -> CgrpStorage<T> {
+        CgrpStorage {
+            def: UnsafeCell::new(build_def::<T>(
+                BPF_MAP_TYPE_CGRP_STORAGE,
+                flags,
+                PinningType::ByName,
+            )),
+            _t: PhantomData,
+        }
+    }
+
+    /// Returns a pointer to the value stored for the cgroup, or `None` if there's none.
+    ///
+    /// Other programs can update the value concurrently, it's up to the caller to decide whether
+    /// it's safe to dereference the pointer or not.
+    #[inline]
+    pub fn get(&self, cgroup: *mut cgroup) -> Option<*mut T> {
+        let ptr =
+            unsafe { bpf_cgrp_storage_get(self.def.get() as *mut _, cgroup, ptr::null_mut(), 0) };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Returns a pointer to the value stored for the cgroup, creating it if there's none.
+    ///
+    /// The new value is initialized with `value`, or zeroed if `value` is `None`. The same caveat
+    /// as `get` applies.
+    #[inline]
+    pub fn get_or_create(&self, cgroup: *mut cgroup, value: Option<&T>) -> Option<*mut T> {
+        let value = value.map_or(ptr::null_mut(), |v| v as *const T as *mut c_void);
+        let ptr = unsafe {
+            bpf_cgrp_storage_get(
+                self.def.get() as *mut _,
+                cgroup,
+                value,
+                BPF_LOCAL_STORAGE_GET_F_CREATE as u64,
+            )
+        };
+        (!ptr.is_null()).then_some(ptr as *mut T)
+    }
+
+    /// Deletes the value stored for the cgroup.
+    #[inline]
+    pub fn delete(&self, cgroup: *mut cgroup) -> Result<(), c_long> {
+        let ret = unsafe { bpf_cgrp_storage_delete(self.def.get() as *mut _, cgroup) };
+        (ret == 0).then_some(()).ok_or(ret as c_long)
+    }
+}
+
+const fn build_def<T>(ty: u32, flags: u32, pin: PinningType) -> bpf_map_def {
+    bpf_map_def {
+        type_: ty,
+        key_size: mem::size_of::<i32>() as u32,
+        value_size: mem::size_of::<T>() as u32,
+        // the storage grows with the objects it's attached to
+        max_entries: 0,
+        // required by the kernel
+        map_flags: flags | BPF_F_NO_PREALLOC,
+        id: 0,
+        pinning: pin as u32,
+    }
+}
@@ -6,27 +6,61 @@ pub(crate) enum PinningType {
 }
 
 pub mod array;
+pub mod array_of_maps;
 pub mod bloom_filter;
+pub mod cgroup_storage;
 pub mod hash_map;
+pub mod hash_of_maps;
+pub mod local_storage;
 pub mod lpm_trie;
 pub mod per_cpu_array;
 pub mod perf;
 pub mod program_array;
 pub mod queue;
+pub mod ring_buf;
 pub mod sock_hash;
 pub mod sock_map;
 pub mod stack;
 pub mod stack_trace;
+pub mod user_ring_buf;
+pub mod xdp;
 
 pub use array::Array;
+pub use array_of_maps::ArrayOfMaps;
 pub use bloom_filter::BloomFilter;
+pub use cgroup_storage::{CgroupStorage, PerCpuCgroupStorage};
 pub use hash_map::{HashMap, LruHashMap, LruPerCpuHashMap, PerCpuHashMap};
+pub use hash_of_maps::HashOfMaps;
+pub use local_storage::{CgrpStorage, InodeStorage, SkStorage, TaskStorage};
 pub use lpm_trie::LpmTrie;
 pub use per_cpu_array::PerCpuArray;
 pub use perf::{PerfEventArray, PerfEventByteArray};
 pub use program_array::ProgramArray;
 pub use queue::Queue;
+pub use ring_buf::RingBuf;
 pub use sock_hash::SockHash;
 pub use sock_map::SockMap;
 pub use stack::Stack;
 pub use stack_trace::StackTrace;
+pub use user_ring_buf::UserRingBuf;
+pub use xdp::{CpuMap, DevMap, DevMapHash, XskMap};
+
+/// Marker trait for the maps that can be stored in an [`ArrayOfMaps`] or a [`HashOfMaps`].
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(transparent)]` wrappers around a `bpf_map_def`, so that the inner
+/// map pointer returned by the kernel can be used as a reference to the map type.
+pub unsafe trait InnerMap {}
+
+unsafe impl<T> InnerMap for Array<T> {}
+unsafe impl<T> InnerMap for BloomFilter<T> {}
+unsafe impl<K, V> InnerMap for HashMap<K, V> {}
+unsafe impl<K, V> InnerMap for LruHashMap<K, V> {}
+unsafe impl<K, V> InnerMap for LruPerCpuHashMap<K, V> {}
+unsafe impl<K, V> InnerMap for PerCpuHashMap<K, V> {}
+unsafe impl<K, V> InnerMap for LpmTrie<K, V> {}
+unsafe impl<T> InnerMap for PerCpuArray<T> {}
+unsafe impl<T> InnerMap for Queue<T> {}
+unsafe impl InnerMap for RingBuf {}
+unsafe impl<T> InnerMap for Stack<T> {}
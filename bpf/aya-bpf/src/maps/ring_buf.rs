@@ -0,0 +1,160 @@
+use core::{
+    cell::UnsafeCell,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    bindings::{bpf_map_def, bpf_map_type::BPF_MAP_TYPE_RINGBUF},
+    helpers::{
+        bpf_ringbuf_discard, bpf_ringbuf_output, bpf_ringbuf_query, bpf_ringbuf_reserve,
+        bpf_ringbuf_submit,
+    },
+    maps::PinningType,
+};
+
+/// A BPF ring buffer that can be used to send events to user space.
+///
+/// Unlike [`PerfEventArray`](crate::maps::PerfEventArray), a single ring buffer is shared by all
+/// the CPUs, so events are received in the order in which they were submitted.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.8.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #![allow(dead_code)]
+/// use aya_bpf::{macros::map, maps::RingBuf};
+/// # use aya_bpf::programs::ProbeContext;
+///
+/// #[repr(C)]
+/// struct Event {
+///     pid: u32,
+///     len: u32,
+/// }
+///
+/// #[map]
+/// static EVENTS: RingBuf = RingBuf::with_byte_size(256 * 1024, 0);
+///
+/// # fn try_test(ctx: &ProbeContext) -> Result<(), i64> {
+/// if let Some(mut entry) = EVENTS.reserve::<Event>(0) {
+///     entry.write(Event { pid: 42, len: 0 });
+///     entry.submit(0);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct RingBuf {
+    def: UnsafeCell<bpf_map_def>,
+}
+
+unsafe impl Sync for RingBuf {}
+
+/// A ring buffer entry, returned from [`RingBuf::reserve`].
+///
+/// The entry must be either committed with [`submit`](RingBufEntry::submit) or released with
+/// [`discard`](RingBufEntry::discard), otherwise the verifier will reject the program.
+pub struct RingBufEntry<T: 'static>(&'static mut MaybeUninit<T>);
+
+impl<T> Deref for RingBufEntry<T> {
+    type Target = MaybeUninit<T>;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<T> DerefMut for RingBufEntry<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.0
+    }
+}
+
+impl<T> RingBufEntry<T> {
+    /// Discard this ring buffer entry. The entry will be skipped by the user space reader.
+    pub fn discard(self, flags: u64) {
+        unsafe { bpf_ringbuf_discard(self.0.as_mut_ptr() as *mut _, flags) };
+    }
+
+    /// Commit this ring buffer entry. The entry will be made visible to the user space reader.
+    pub fn submit(self, flags: u64) {
+        unsafe { bpf_ringbuf_submit(self.0.as_mut_ptr() as *mut _, flags) };
+    }
+}
+
+impl RingBuf {
+    /// Declare a BPF ring buffer.
+    ///
+    /// `byte_size` must be a power of two multiple of the page size.
+    pub const fn with_byte_size(byte_size: u32, flags: u32) -> RingBuf {
+        RingBuf::new(byte_size, flags, PinningType::None)
+    }
+
+    /// Declare a pinned BPF ring buffer.
+    ///
+    /// `byte_size` must be a power of two multiple of the page size.
+    pub const fn pinned(byte_size: u32, flags: u32) -> RingBuf {
+        RingBuf::new(byte_size, flags, PinningType::ByName)
+    }
+
+    const fn new(byte_size: u32, flags: u32, pinning: PinningType) -> RingBuf {
+        RingBuf {
+            def: UnsafeCell::new(bpf_map_def {
+                type_: BPF_MAP_TYPE_RINGBUF,
+                key_size: 0,
+                value_size: 0,
+                max_entries: byte_size,
+                map_flags: flags,
+                id: 0,
+                pinning: pinning as u32,
+            }),
+        }
+    }
+
+    /// Reserve memory in the ring buffer that can fit `T`.
+    ///
+    /// Returns `None` if the ring buffer is full.
+    ///
+    /// Note that `T` must be aligned to no more than 8 bytes; the kernel only guarantees 8 byte
+    /// alignment for ring buffer entries.
+    pub fn reserve<T: 'static>(&self, flags: u64) -> Option<RingBufEntry<T>> {
+        assert!(8 % mem::align_of::<T>() == 0);
+        let ptr = unsafe {
+            bpf_ringbuf_reserve(self.def.get() as *mut _, mem::size_of::<T>() as _, flags)
+        } as *mut MaybeUninit<T>;
+        unsafe { ptr.as_mut() }.map(RingBufEntry)
+    }
+
+    /// Copy `data` to the ring buffer output.
+    ///
+    /// Consider using [`reserve`](RingBuf::reserve) and [`submit`](RingBufEntry::submit) if
+    /// `T` is statically sized and you want to save a copy from either a map or the stack.
+    ///
+    /// On failure, the negative error returned by `bpf_ringbuf_output` is returned wrapped in
+    /// `Err()`.
+    pub fn output(&self, data: &[u8], flags: u64) -> Result<(), i64> {
+        let ret = unsafe {
+            bpf_ringbuf_output(
+                self.def.get() as *mut _,
+                data.as_ptr() as *mut _,
+                data.len() as _,
+                flags,
+            )
+        };
+        if ret < 0 {
+            Err(ret)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Query various information about the ring buffer.
+    ///
+    /// Consult `bpf_ringbuf_query` documentation for a list of allowed flags.
+    pub fn query(&self, flags: u64) -> u64 {
+        unsafe { bpf_ringbuf_query(self.def.get() as *mut _, flags) }
+    }
+}
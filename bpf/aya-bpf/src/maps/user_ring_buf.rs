@@ -0,0 +1,171 @@
+use core::{
+    cell::UnsafeCell,
+    mem::{self, MaybeUninit},
+};
+
+use aya_bpf_cty::{c_long, c_void};
+
+use crate::{
+    bindings::{bpf_dynptr, bpf_map_def, bpf_map_type::BPF_MAP_TYPE_USER_RINGBUF},
+    helpers::{bpf_dynptr_read, bpf_user_ringbuf_drain},
+    maps::PinningType,
+};
+
+/// A BPF ring buffer that can be used to receive messages from user space.
+///
+/// User space produces samples into the ring buffer, and eBPF programs consume them by calling
+/// [`UserRingBuf::drain`] with a callback that is invoked once per sample.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 6.1.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #![allow(dead_code)]
+/// use aya_bpf::{
+///     bindings::bpf_dynptr,
+///     cty::c_long,
+///     macros::map,
+///     maps::{user_ring_buf::UserRingBufSample, UserRingBuf},
+/// };
+///
+/// #[map]
+/// static COMMANDS: UserRingBuf = UserRingBuf::with_byte_size(4096, 0);
+///
+/// unsafe extern "C" fn handle_command(sample: *mut bpf_dynptr, total: *mut u64) -> c_long {
+///     let sample = UserRingBufSample::from_ptr(sample);
+///     match sample.read::<u64>(0) {
+///         Ok(command) => {
+///             *total += command;
+///             0
+///         }
+///         // stop draining
+///         Err(_) => 1,
+///     }
+/// }
+///
+/// # fn try_test() -> Result<(), c_long> {
+/// let mut total = 0u64;
+/// let drained = COMMANDS.drain(handle_command, &mut total, 0)?;
+/// # Ok(())
+/// # }
+/// ```
+#[repr(transparent)]
+pub struct UserRingBuf {
+    def: UnsafeCell<bpf_map_def>,
+}
+
+unsafe impl Sync for UserRingBuf {}
+
+impl UserRingBuf {
+    /// Declare a BPF user ring buffer.
+    ///
+    /// `byte_size` must be a power of two multiple of the page size.
+    pub const fn with_byte_size(byte_size: u32, flags: u32) -> UserRingBuf {
+        UserRingBuf::new(byte_size, flags, PinningType::None)
+    }
+
+    /// Declare a pinned BPF user ring buffer.
+    ///
+    /// `byte_size` must be a power of two multiple of the page size.
+    pub const fn pinned(byte_size: u32, flags: u32) -> UserRingBuf {
+        UserRingBuf::new(byte_size, flags, PinningType::ByName)
+    }
+
+    const fn new(byte_size: u32, flags: u32, pinning: PinningType) -> UserRingBuf {
+        UserRingBuf {
+            def: UnsafeCell::new(bpf_map_def {
+                type_: BPF_MAP_TYPE_USER_RINGBUF,
+                key_size: 0,
+                value_size: 0,
+                max_entries: byte_size,
+                map_flags: flags,
+                id: 0,
+                pinning: pinning as u32,
+            }),
+        }
+    }
+
+    /// Drains the samples produced by user space, calling `callback` once for each of them.
+    ///
+    /// `ctx` is passed to every invocation of `callback`. The callback returns `0` to continue
+    /// draining, or `1` to stop after the current sample.
+    ///
+    /// Returns the number of samples drained. On failure, the negative error returned by
+    /// `bpf_user_ringbuf_drain` is returned wrapped in `Err()`.
+    pub fn drain<C>(
+        &self,
+        callback: unsafe extern "C" fn(*mut bpf_dynptr, *mut C) -> c_long,
+        ctx: &mut C,
+        flags: u64,
+    ) -> Result<u32, c_long> {
+        let ret = unsafe {
+            bpf_user_ringbuf_drain(
+                self.def.get() as *mut _,
+                callback as *mut c_void,
+                ctx as *mut C as *mut c_void,
+                flags,
+            )
+        };
+        if ret < 0 {
+            Err(ret)
+        } else {
+            Ok(ret as u32)
+        }
+    }
+}
+
+/// A sample passed to a [`UserRingBuf::drain`] callback.
+#[repr(transparent)]
+pub struct UserRingBufSample(bpf_dynptr);
+
+impl UserRingBufSample {
+    /// Wraps the dynptr passed to a drain callback.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be the dynptr passed by the kernel to a [`UserRingBuf::drain`] callback, and the
+    /// returned reference must not outlive the callback invocation.
+    pub unsafe fn from_ptr<'a>(ptr: *mut bpf_dynptr) -> &'a UserRingBufSample {
+        &*(ptr as *const UserRingBufSample)
+    }
+
+    /// Reads a `T` at `offset` bytes into the sample.
+    pub fn read<T>(&self, offset: u32) -> Result<T, c_long> {
+        let mut v: MaybeUninit<T> = MaybeUninit::uninit();
+        let ret = unsafe {
+            bpf_dynptr_read(
+                v.as_mut_ptr() as *mut c_void,
+                mem::size_of::<T>() as u32,
+                &self.0,
+                offset,
+                0,
+            )
+        };
+        if ret == 0 {
+            Ok(unsafe { v.assume_init() })
+        } else {
+            Err(ret)
+        }
+    }
+
+    /// Copies `dst.len()` bytes at `offset` bytes into the sample to `dst`.
+    pub fn read_bytes(&self, offset: u32, dst: &mut [u8]) -> Result<(), c_long> {
+        let ret = unsafe {
+            bpf_dynptr_read(
+                dst.as_mut_ptr() as *mut c_void,
+                dst.len() as u32,
+                &self.0,
+                offset,
+                0,
+            )
+        };
+        if ret == 0 {
+            Ok(())
+        } else {
+            Err(ret)
+        }
+    }
+}
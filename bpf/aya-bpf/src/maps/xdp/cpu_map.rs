@@ -0,0 +1,85 @@
+use core::{cell::UnsafeCell, mem};
+
+use crate::{
+    bindings::{bpf_cpumap_val, bpf_map_def, bpf_map_type::BPF_MAP_TYPE_CPUMAP},
+    helpers::bpf_redirect_map,
+    maps::PinningType,
+};
+
+use super::try_redirect_map;
+
+/// An array of available CPUs.
+///
+/// XDP programs can use this map to redirect packets to a target CPU for processing.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.9, as the map values carry an
+/// optional chained program.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use aya_bpf::{bindings::xdp_action, macros::{map, xdp}, maps::CpuMap, programs::XdpContext};
+///
+/// #[map]
+/// static MAP: CpuMap = CpuMap::with_max_entries(8, 0);
+///
+/// #[xdp]
+/// fn xdp(_ctx: XdpContext) -> u32 {
+///     // Redirect to CPU 7 or drop packet if no entry found.
+///     MAP.redirect(7, xdp_action::XDP_DROP as u64).unwrap_or(xdp_action::XDP_DROP)
+/// }
+/// ```
+#[repr(transparent)]
+pub struct CpuMap {
+    def: UnsafeCell<bpf_map_def>,
+}
+
+unsafe impl Sync for CpuMap {}
+
+impl CpuMap {
+    /// Creates a [`CpuMap`] with a set maximum number of elements.
+    ///
+    /// In a CPU map, an entry represents a CPU core. Thus there should be as many entries as
+    /// there are CPU cores on the system.
+    pub const fn with_max_entries(max_entries: u32, flags: u32) -> CpuMap {
+        CpuMap::new(max_entries, flags, PinningType::None)
+    }
+
+    /// Creates a [`CpuMap`] with a set maximum number of elements that can be pinned to the BPF
+    /// File System (bpffs).
+    ///
+    /// In a CPU map, an entry represents a CPU core. Thus there should be as many entries as
+    /// there are CPU cores on the system.
+    pub const fn pinned(max_entries: u32, flags: u32) -> CpuMap {
+        CpuMap::new(max_entries, flags, PinningType::ByName)
+    }
+
+    const fn new(max_entries: u32, flags: u32, pinning: PinningType) -> CpuMap {
+        CpuMap {
+            def: UnsafeCell::new(bpf_map_def {
+                type_: BPF_MAP_TYPE_CPUMAP,
+                key_size: mem::size_of::<u32>() as u32,
+                value_size: mem::size_of::<bpf_cpumap_val>() as u32,
+                max_entries,
+                map_flags: flags,
+                id: 0,
+                pinning: pinning as u32,
+            }),
+        }
+    }
+
+    /// Redirects the current packet to the CPU at `index`.
+    ///
+    /// The lower two bits of `flags` are used for the return code if the map lookup fails, which
+    /// can be used as the XDP program's return code if a CPU cannot be found.
+    ///
+    /// Returns `Ok(XDP_REDIRECT)` on success, and the fallback action wrapped in `Err()` if the
+    /// lookup fails.
+    #[inline(always)]
+    pub fn redirect(&self, index: u32, flags: u64) -> Result<u32, u32> {
+        let ret = unsafe { bpf_redirect_map(self.def.get() as *mut _, index as u64, flags) };
+        try_redirect_map(ret)
+    }
+}
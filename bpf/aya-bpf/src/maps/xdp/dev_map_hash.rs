@@ -0,0 +1,80 @@
+use core::{cell::UnsafeCell, mem};
+
+use crate::{
+    bindings::{bpf_devmap_val, bpf_map_def, bpf_map_type::BPF_MAP_TYPE_DEVMAP_HASH},
+    helpers::bpf_redirect_map,
+    maps::PinningType,
+};
+
+use super::try_redirect_map;
+
+/// A map of network devices.
+///
+/// XDP programs can use this map to redirect packets to other network devices. Unlike
+/// [`DevMap`](super::DevMap), the keys don't need to be contiguous, so the map is usually keyed
+/// by interface index.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 5.8, as the map values carry an
+/// optional chained program.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use aya_bpf::{bindings::xdp_action, macros::{map, xdp}, maps::DevMapHash, programs::XdpContext};
+///
+/// #[map]
+/// static MAP: DevMapHash = DevMapHash::with_max_entries(1, 0);
+///
+/// #[xdp]
+/// fn xdp(_ctx: XdpContext) -> u32 {
+///     MAP.redirect(42, xdp_action::XDP_PASS as u64).unwrap_or(xdp_action::XDP_DROP)
+/// }
+/// ```
+#[repr(transparent)]
+pub struct DevMapHash {
+    def: UnsafeCell<bpf_map_def>,
+}
+
+unsafe impl Sync for DevMapHash {}
+
+impl DevMapHash {
+    /// Creates a [`DevMapHash`] with a set maximum number of elements.
+    pub const fn with_max_entries(max_entries: u32, flags: u32) -> DevMapHash {
+        DevMapHash::new(max_entries, flags, PinningType::None)
+    }
+
+    /// Creates a [`DevMapHash`] with a set maximum number of elements that can be pinned to the
+    /// BPF File System (bpffs).
+    pub const fn pinned(max_entries: u32, flags: u32) -> DevMapHash {
+        DevMapHash::new(max_entries, flags, PinningType::ByName)
+    }
+
+    const fn new(max_entries: u32, flags: u32, pinning: PinningType) -> DevMapHash {
+        DevMapHash {
+            def: UnsafeCell::new(bpf_map_def {
+                type_: BPF_MAP_TYPE_DEVMAP_HASH,
+                key_size: mem::size_of::<u32>() as u32,
+                value_size: mem::size_of::<bpf_devmap_val>() as u32,
+                max_entries,
+                map_flags: flags,
+                id: 0,
+                pinning: pinning as u32,
+            }),
+        }
+    }
+
+    /// Redirects the current packet on the interface at `key`.
+    ///
+    /// The lower two bits of `flags` are used for the return code if the map lookup fails, which
+    /// can be used as the XDP program's return code if a matching device cannot be found.
+    ///
+    /// Returns `Ok(XDP_REDIRECT)` on success, and the fallback action wrapped in `Err()` if the
+    /// lookup fails.
+    #[inline(always)]
+    pub fn redirect(&self, key: u32, flags: u64) -> Result<u32, u32> {
+        let ret = unsafe { bpf_redirect_map(self.def.get() as *mut _, key as u64, flags) };
+        try_redirect_map(ret)
+    }
+}
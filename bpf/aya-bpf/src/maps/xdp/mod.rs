@@ -0,0 +1,21 @@
+mod cpu_map;
+mod dev_map;
+mod dev_map_hash;
+mod xsk_map;
+
+pub use cpu_map::CpuMap;
+pub use dev_map::DevMap;
+pub use dev_map_hash::DevMapHash;
+pub use xsk_map::XskMap;
+
+use crate::bindings::xdp_action::XDP_REDIRECT;
+
+// `bpf_redirect_map` returns `XDP_REDIRECT` on success, and the lower two bits of `flags`, which
+// hold the fallback action, on failure.
+#[inline(always)]
+fn try_redirect_map(ret: i64) -> Result<u32, u32> {
+    match ret as u32 {
+        XDP_REDIRECT => Ok(XDP_REDIRECT),
+        ret => Err(ret),
+    }
+}
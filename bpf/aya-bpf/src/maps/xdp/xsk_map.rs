@@ -0,0 +1,78 @@
+use core::{cell::UnsafeCell, mem};
+
+use crate::{
+    bindings::{bpf_map_def, bpf_map_type::BPF_MAP_TYPE_XSKMAP},
+    helpers::bpf_redirect_map,
+    maps::PinningType,
+};
+
+use super::try_redirect_map;
+
+/// An array of AF_XDP sockets.
+///
+/// XDP programs can use this map to redirect packets to a target AF_XDP socket.
+///
+/// # Minimum kernel version
+///
+/// The minimum kernel version required to use this feature is 4.18.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use aya_bpf::{bindings::xdp_action, macros::{map, xdp}, maps::XskMap, programs::XdpContext};
+///
+/// #[map]
+/// static SOCKS: XskMap = XskMap::with_max_entries(8, 0);
+///
+/// #[xdp]
+/// fn xdp(ctx: XdpContext) -> u32 {
+///     let queue_id = unsafe { (*ctx.ctx).rx_queue_index };
+///     SOCKS.redirect(queue_id, xdp_action::XDP_PASS as u64).unwrap_or(xdp_action::XDP_PASS)
+/// }
+/// ```
+#[repr(transparent)]
+pub struct XskMap {
+    def: UnsafeCell<bpf_map_def>,
+}
+
+unsafe impl Sync for XskMap {}
+
+impl XskMap {
+    /// Creates a [`XskMap`] with a set maximum number of elements.
+    pub const fn with_max_entries(max_entries: u32, flags: u32) -> XskMap {
+        XskMap::new(max_entries, flags, PinningType::None)
+    }
+
+    /// Creates a [`XskMap`] with a set maximum number of elements that can be pinned to the BPF
+    /// File System (bpffs).
+    pub const fn pinned(max_entries: u32, flags: u32) -> XskMap {
+        XskMap::new(max_entries, flags, PinningType::ByName)
+    }
+
+    const fn new(max_entries: u32, flags: u32, pinning: PinningType) -> XskMap {
+        XskMap {
+            def: UnsafeCell::new(bpf_map_def {
+                type_: BPF_MAP_TYPE_XSKMAP,
+                key_size: mem::size_of::<u32>() as u32,
+                value_size: mem::size_of::<u32>() as u32,
+                max_entries,
+                map_flags: flags,
+                id: 0,
+                pinning: pinning as u32,
+            }),
+        }
+    }
+
+    /// Redirects the current packet to the AF_XDP socket at `index`.
+    ///
+    /// The lower two bits of `flags` are used for the return code if the map lookup fails, which
+    /// can be used as the XDP program's return code if a matching socket cannot be found.
+    ///
+    /// Returns `Ok(XDP_REDIRECT)` on success, and the fallback action wrapped in `Err()` if the
+    /// lookup fails.
+    #[inline(always)]
+    pub fn redirect(&self, index: u32, flags: u64) -> Result<u32, u32> {
+        let ret = unsafe { bpf_redirect_map(self.def.get() as *mut _, index as u64, flags) };
+        try_redirect_map(ret)
+    }
+}
@@ -12,10 +12,12 @@ pub mod sock;
 pub mod sock_addr;
 pub mod sock_ops;
 pub mod sockopt;
+pub mod struct_ops;
 pub mod sysctl;
 pub mod tc;
 pub mod tp_btf;
 pub mod tracepoint;
+pub mod usdt;
 pub mod xdp;
 
 pub use device::DeviceContext;
@@ -32,8 +34,10 @@ pub use sock::SockContext;
 pub use sock_addr::SockAddrContext;
 pub use sock_ops::SockOpsContext;
 pub use sockopt::SockoptContext;
+pub use struct_ops::StructOpsContext;
 pub use sysctl::SysctlContext;
 pub use tc::TcContext;
 pub use tp_btf::BtfTracePointContext;
 pub use tracepoint::TracePointContext;
+pub use usdt::UsdtContext;
 pub use xdp::XdpContext;
@@ -0,0 +1,41 @@
+use core::ffi::c_void;
+
+use crate::{args::FromBtfArgument, BpfContext};
+
+pub struct StructOpsContext {
+    ctx: *mut c_void,
+}
+
+impl StructOpsContext {
+    pub fn new(ctx: *mut c_void) -> StructOpsContext {
+        StructOpsContext { ctx }
+    }
+
+    /// Returns the `n`th argument passed to the implemented struct member, starting from 0.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #![allow(non_camel_case_types)]
+    /// # #![allow(dead_code)]
+    /// # use aya_bpf::programs::StructOpsContext;
+    /// # struct sock {}
+    /// unsafe fn try_cong_avoid(ctx: StructOpsContext) -> Result<(), ()> {
+    ///     let sk: *const sock = ctx.arg(0);
+    ///     let acked: u32 = ctx.arg(2);
+    ///
+    ///     // Do something with sk and acked
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub unsafe fn arg<T: FromBtfArgument>(&self, n: usize) -> T {
+        T::from_argument(self.ctx as *const _, n)
+    }
+}
+
+impl BpfContext for StructOpsContext {
+    fn as_ptr(&self) -> *mut c_void {
+        self.ctx
+    }
+}
@@ -0,0 +1,140 @@
+use core::ffi::c_void;
+
+use crate::{
+    cty::c_long,
+    helpers::{bpf_get_attach_cookie, bpf_probe_read_kernel, bpf_probe_read_user},
+    maps::HashMap,
+    BpfContext,
+};
+
+// aarch64 uses user_pt_regs instead of pt_regs
+#[cfg(not(bpf_target_arch = "aarch64"))]
+use crate::bindings::pt_regs;
+#[cfg(bpf_target_arch = "aarch64")]
+use crate::bindings::user_pt_regs as pt_regs;
+
+/// The maximum number of arguments of a USDT probe.
+pub const USDT_MAX_ARGS: usize = 12;
+
+// The maximum number of USDT probe locations that can be attached to at the same time.
+const USDT_MAX_SPECS: u32 = 256;
+
+const ENOENT: c_long = 2;
+const EINVAL: c_long = 22;
+
+// The layout of the argument specs must be kept in sync with `aya::programs::usdt`.
+const USDT_ARG_CONST: u32 = 0;
+const USDT_ARG_REG: u32 = 1;
+const USDT_ARG_REG_DEREF: u32 = 2;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct UsdtArgSpec {
+    val_off: u64,
+    arg_type: u32,
+    reg_off: i16,
+    arg_signed: u8,
+    arg_bitshift: u8,
+}
+
+/// How to read the arguments of a USDT probe location, written by user space when attaching.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct UsdtSpec {
+    args: [UsdtArgSpec; USDT_MAX_ARGS],
+    cookie: u64,
+    arg_count: u64,
+}
+
+#[link_section = "maps"]
+#[export_name = "AYA_USDT_SPECS"]
+static AYA_USDT_SPECS: HashMap<u64, UsdtSpec> = HashMap::with_max_entries(USDT_MAX_SPECS, 0);
+
+pub struct UsdtContext {
+    pub regs: *mut pt_regs,
+}
+
+impl UsdtContext {
+    pub fn new(ctx: *mut c_void) -> UsdtContext {
+        UsdtContext {
+            regs: ctx as *mut pt_regs,
+        }
+    }
+
+    /// Returns the number of arguments of the probe.
+    pub fn arg_count(&self) -> Result<usize, c_long> {
+        Ok(self.spec()?.arg_count as usize)
+    }
+
+    /// Returns the `n`th argument of the probe, starting from 0.
+    ///
+    /// The argument is read as described by the probe `.note.stapsdt` ELF note, and widened to
+    /// 64 bits. Signed arguments are sign-extended, so they can be cast back to `i64`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #![allow(dead_code)]
+    /// # use aya_bpf::{cty::c_long, programs::UsdtContext};
+    /// fn try_query_start(ctx: UsdtContext) -> Result<u32, c_long> {
+    ///     let query = ctx.arg(0)? as *const u8;
+    ///
+    ///     // Do something with query
+    ///
+    ///     Ok(0)
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `-ENOENT` if the probe has no `n`th argument, or the error returned by the helper
+    /// reading it.
+    pub fn arg(&self, n: usize) -> Result<u64, c_long> {
+        let spec = self.spec()?;
+        if n >= USDT_MAX_ARGS || n >= spec.arg_count as usize {
+            return Err(-ENOENT);
+        }
+        let arg = &spec.args[n];
+
+        let val = match arg.arg_type {
+            USDT_ARG_CONST => arg.val_off,
+            USDT_ARG_REG => self.read_reg(arg.reg_off)?,
+            USDT_ARG_REG_DEREF => {
+                let addr = self.read_reg(arg.reg_off)?.wrapping_add(arg.val_off);
+                unsafe { bpf_probe_read_user(addr as *const u64)? }
+            }
+            _ => return Err(-EINVAL),
+        };
+
+        // keep the low bits of the value, sign-extending it if needed
+        let val = val << arg.arg_bitshift;
+        if arg.arg_signed != 0 {
+            Ok(((val as i64) >> arg.arg_bitshift) as u64)
+        } else {
+            Ok(val >> arg.arg_bitshift)
+        }
+    }
+
+    /// Returns the cookie passed by user space when attaching the program to the probe.
+    pub fn cookie(&self) -> Result<u64, c_long> {
+        Ok(self.spec()?.cookie)
+    }
+
+    fn spec(&self) -> Result<&UsdtSpec, c_long> {
+        let spec_id = unsafe { bpf_get_attach_cookie(self.as_ptr()) };
+        unsafe { AYA_USDT_SPECS.get(&spec_id) }.ok_or(-ENOENT)
+    }
+
+    fn read_reg(&self, reg_off: i16) -> Result<u64, c_long> {
+        // the verifier rejects variable offset accesses to the context
+        unsafe {
+            bpf_probe_read_kernel((self.regs as *const u8).offset(reg_off as isize) as *const u64)
+        }
+    }
+}
+
+impl BpfContext for UsdtContext {
+    fn as_ptr(&self) -> *mut c_void {
+        self.regs as *mut c_void
+    }
+}
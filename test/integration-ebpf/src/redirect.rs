@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+use aya_bpf::{
+    bindings::xdp_action,
+    macros::{map, xdp},
+    maps::XskMap,
+    programs::XdpContext,
+};
+
+#[map]
+static SOCKS: XskMap = XskMap::with_max_entries(1, 0);
+
+#[xdp(name = "redirect_sock")]
+pub fn redirect_sock(ctx: XdpContext) -> u32 {
+    let queue_id = unsafe { (*ctx.ctx).rx_queue_index };
+    SOCKS
+        .redirect(queue_id, xdp_action::XDP_ABORTED as u64)
+        .unwrap_or(xdp_action::XDP_ABORTED)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
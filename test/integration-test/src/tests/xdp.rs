@@ -0,0 +1,131 @@
+use std::{ffi::CString, mem, process::Command, thread, time::Duration};
+
+use aya::{
+    af_xdp::{Umem, UmemConfig, XdpDesc, XskBindFlags, XskSocket, XskSocketConfig},
+    include_bytes_aligned,
+    maps::XskMap,
+    programs::{Xdp, XdpFlags},
+    Bpf,
+};
+use log::info;
+
+use super::{integration_test, kernel_version, IntegrationTest};
+
+const MAX_RETRIES: u32 = 100;
+const RETRY_DURATION_MS: u64 = 10;
+
+// A veth pair, deleted on drop.
+struct Veth;
+
+impl Veth {
+    fn new() -> Veth {
+        ip(&[
+            "link", "add", "xsk0", "type", "veth", "peer", "name", "xsk1",
+        ]);
+        ip(&["link", "set", "xsk0", "up"]);
+        ip(&["link", "set", "xsk1", "up"]);
+        Veth
+    }
+}
+
+impl Drop for Veth {
+    fn drop(&mut self) {
+        ip(&["link", "del", "xsk0"]);
+    }
+}
+
+fn ip(args: &[&str]) {
+    let status = Command::new("ip").args(args).status().unwrap();
+    assert!(status.success(), "ip {args:?} failed");
+}
+
+// Sends a raw ethernet frame on `interface`.
+fn send_frame(interface: &str, frame: &[u8]) {
+    let c_interface = CString::new(interface).unwrap();
+    let if_index = unsafe { libc::if_nametoindex(c_interface.as_ptr()) };
+    assert_ne!(if_index, 0);
+
+    let fd = unsafe { libc::socket(libc::AF_PACKET, libc::SOCK_RAW, 0) };
+    assert!(fd >= 0, "socket failed");
+    let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+    addr.sll_family = libc::AF_PACKET as u16;
+    addr.sll_ifindex = if_index as i32;
+    addr.sll_halen = 6;
+    addr.sll_addr[..6].copy_from_slice(&frame[..6]);
+    let ret = unsafe {
+        libc::sendto(
+            fd,
+            frame.as_ptr() as *const _,
+            frame.len(),
+            0,
+            &addr as *const _ as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+        )
+    };
+    unsafe { libc::close(fd) };
+    assert_eq!(ret, frame.len() as isize, "sendto failed");
+}
+
+#[integration_test]
+fn af_xdp() {
+    let (major, minor, _) = kernel_version().unwrap();
+    if (major, minor) < (5, 4) {
+        info!(
+            "skipping as {}.{} does not meet version requirement of 5.4",
+            major, minor
+        );
+        return;
+    }
+
+    let _veth = Veth::new();
+
+    let bytes = include_bytes_aligned!("../../../../target/bpfel-unknown-none/debug/redirect");
+    let mut bpf = Bpf::load(bytes).unwrap();
+    let mut socks = XskMap::try_from(bpf.take_map("SOCKS").unwrap()).unwrap();
+    let prog: &mut Xdp = bpf
+        .program_mut("redirect_sock")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    prog.load().unwrap();
+
+    let umem = Umem::new(UmemConfig {
+        frame_count: 64,
+        fill_size: 64,
+        completion_size: 64,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut socket = XskSocket::new(
+        umem,
+        "xsk0",
+        0,
+        XskSocketConfig {
+            rx_size: 64,
+            tx_size: 64,
+            bind_flags: XskBindFlags::COPY,
+        },
+    )
+    .unwrap();
+    prog.attach_to_socket(&socket, &mut socks, XdpFlags::SKB_MODE)
+        .unwrap();
+
+    let frames = socket.umem().frame_addrs().collect::<Vec<_>>();
+    assert_eq!(socket.fill_queue().produce(&frames), frames.len());
+
+    // broadcast frame with a local experimental ethertype
+    let mut frame = vec![0xff; 6];
+    frame.extend_from_slice(&[0x02, 0, 0, 0, 0, 1]);
+    frame.extend_from_slice(&[0x88, 0xb5]);
+    frame.extend_from_slice(b"hello AF_XDP");
+    send_frame("xsk1", &frame);
+
+    let mut descs = [XdpDesc::default(); 1];
+    let mut retries = 0;
+    while socket.rx_queue().consume(&mut descs) == 0 {
+        assert!(retries < MAX_RETRIES, "no packet received");
+        retries += 1;
+        thread::sleep(Duration::from_millis(RETRY_DURATION_MS));
+    }
+    assert_eq!(socket.umem().data(&descs[0]), frame.as_slice());
+}